@@ -0,0 +1,216 @@
+//! `cidr_discover`: scan a CIDR, diff two scans, or enrich saved records.
+//!
+//! A thin wrapper over the library crates; every subcommand parses its
+//! arguments, calls one library entry point and writes the result.
+
+use clap::{Parser, Subcommand, ValueEnum};
+use discovery::{Discover, LiveArpDiscover};
+use enrich::{EnrichPipeline, HostnameVendorEnricher, OuiEnricher, ReverseDnsEnricher};
+use formats::DiscoveryRecord;
+use ipnetwork::Ipv4Network;
+use netutils::cidrsniffer;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+#[derive(Parser)]
+#[command(name = "cidr_discover", version, about = "Local network discovery")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// ARP-sweep a CIDR, optionally port-scanning the hosts found
+    Scan(ScanArgs),
+    /// Compare two saved scans and report added, removed and changed records
+    Diff(DiffArgs),
+    /// Fill vendor/hostname fields of saved records
+    Enrich(EnrichArgs),
+}
+
+#[derive(clap::Args)]
+struct ScanArgs {
+    /// Network to sweep, e.g. 192.168.1.0/24
+    #[arg(long)]
+    cidr: String,
+    /// Parallel ARP lookups (also the per-host port concurrency)
+    #[arg(long, default_value_t = 64)]
+    workers: usize,
+    /// Per-lookup and per-port timeout in seconds
+    #[arg(long, default_value_t = 1)]
+    timeout: u64,
+    /// Ports to scan on each host, e.g. `22,80,8000-8100`; no port scan without it
+    #[arg(long)]
+    ports: Option<String>,
+    /// Actively probe hosts missing from the ARP table (may need privileges)
+    #[arg(long)]
+    probe: bool,
+    /// Refuse CIDRs with more hosts than this
+    #[arg(long, default_value_t = cidrsniffer::DEFAULT_MAX_HOSTS)]
+    max_hosts: usize,
+    /// Write to this file instead of stdout
+    #[arg(long)]
+    output: Option<PathBuf>,
+    #[arg(long, value_enum, default_value_t = OutputFormat::Csv)]
+    format: OutputFormat,
+}
+
+#[derive(clap::Args)]
+struct DiffArgs {
+    /// Earlier scan (JSON records, or netscan CSV when the name ends in .csv)
+    #[arg(long)]
+    baseline: PathBuf,
+    /// Later scan, same formats as --baseline
+    #[arg(long)]
+    current: PathBuf,
+    /// Write the JSON diff to this file instead of stdout
+    #[arg(long)]
+    output: Option<PathBuf>,
+}
+
+#[derive(clap::Args)]
+struct EnrichArgs {
+    /// Records to enrich (JSON records, or netscan CSV when the name ends in .csv)
+    #[arg(long)]
+    input: PathBuf,
+    /// Write JSON records to this file instead of stdout
+    #[arg(long)]
+    output: Option<PathBuf>,
+    /// Fill empty hostnames from reverse DNS
+    #[arg(long)]
+    rdns: bool,
+    /// Fill vendors from the OUI database and hostname heuristics (the
+    /// default when neither --oui nor --rdns is given)
+    #[arg(long)]
+    oui: bool,
+}
+
+/// Output formats for `scan --format`; each maps onto an `io::OutputFormat`.
+#[derive(Clone, Copy, ValueEnum)]
+enum OutputFormat {
+    /// Fixed-column CSV (io::write_records_csv)
+    Csv,
+    /// Pretty JSON array of DiscoveryRecord
+    Json,
+    /// One JSON record per line (io::to_ndjson)
+    Ndjson,
+    /// Legacy netscan JSON (io::to_legacy_json)
+    Legacy,
+    /// Target-compatible JSON (io::to_target_json)
+    Target,
+    /// Graphviz digraph of hosts and open ports (io::to_dot_graph)
+    Dot,
+    /// Prometheus text exposition (io::to_prometheus)
+    Prometheus,
+}
+
+impl From<OutputFormat> for io::OutputFormat {
+    fn from(format: OutputFormat) -> Self {
+        match format {
+            OutputFormat::Csv => io::OutputFormat::Csv,
+            OutputFormat::Json => io::OutputFormat::Json,
+            OutputFormat::Ndjson => io::OutputFormat::Ndjson,
+            OutputFormat::Legacy => io::OutputFormat::LegacyJson,
+            OutputFormat::Target => io::OutputFormat::TargetJson,
+            OutputFormat::Dot => io::OutputFormat::Dot,
+            OutputFormat::Prometheus => io::OutputFormat::Prometheus,
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let result = match Cli::parse().command {
+        Command::Scan(args) => scan(args),
+        Command::Diff(args) => diff(args),
+        Command::Enrich(args) => enrich(args),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn scan(args: ScanArgs) -> Result<(), Box<dyn Error>> {
+    // LiveArpDiscover reports a bad CIDR as an empty scan; fail loudly instead
+    args.cidr
+        .parse::<Ipv4Network>()
+        .map_err(|e| format!("invalid cidr {:?}: {}", args.cidr, e))?;
+    cidrsniffer::check_host_limit(&args.cidr, args.max_hosts)?;
+    let ports = match args.ports.as_deref() {
+        Some(s) => {
+            let ports = discovery::ports::parse_port_list(s);
+            if ports.is_empty() {
+                return Err(format!("no valid ports in {:?}", s).into());
+            }
+            Some(ports)
+        }
+        None => None,
+    };
+    let records = LiveArpDiscover::new(args.cidr)
+        .with_workers(args.workers)
+        .with_max_hosts(args.max_hosts)
+        .with_timeout_secs(args.timeout)
+        .with_probe(args.probe)
+        .with_portscan(ports.is_some())
+        .with_ports(ports)
+        .with_port_concurrency(args.workers)
+        .with_port_timeout_secs(args.timeout)
+        .discover();
+    let mut bytes = Vec::new();
+    io::OutputFormat::from(args.format).write(&mut bytes, &records, io::scan_method(&records))?;
+    write_output(args.output.as_deref(), &bytes)
+}
+
+fn diff(args: DiffArgs) -> Result<(), Box<dyn Error>> {
+    let baseline = read_records(&args.baseline)?;
+    let current = read_records(&args.current)?;
+    let diff = formats::diff_records(&baseline, &current);
+    let mut bytes = serde_json::to_vec_pretty(&diff)?;
+    bytes.push(b'\n');
+    write_output(args.output.as_deref(), &bytes)
+}
+
+fn enrich(args: EnrichArgs) -> Result<(), Box<dyn Error>> {
+    let mut records = read_records(&args.input)?;
+    let mut pipeline = EnrichPipeline::new();
+    if args.oui || !args.rdns {
+        pipeline = pipeline.with(OuiEnricher).with(HostnameVendorEnricher);
+    }
+    if args.rdns {
+        pipeline = pipeline.with(ReverseDnsEnricher::new());
+    }
+    let report = pipeline.run(&mut records);
+    for (name, filled) in &report.filled {
+        eprintln!("{}: filled {} field(s)", name, filled);
+    }
+    let mut bytes = serde_json::to_vec_pretty(&records)?;
+    bytes.push(b'\n');
+    write_output(args.output.as_deref(), &bytes)
+}
+
+/// Load records: netscan CSV for `.csv` files, else a JSON record array.
+fn read_records(path: &Path) -> Result<Vec<DiscoveryRecord>, Box<dyn Error>> {
+    let name = path.to_str().ok_or("invalid path")?;
+    let records = if path
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("csv"))
+    {
+        io::read_netscan_csv(name)
+    } else {
+        io::read_records_json(name)
+    };
+    records.map_err(|e| format!("{}: {}", path.display(), e).into())
+}
+
+fn write_output(path: Option<&Path>, bytes: &[u8]) -> Result<(), Box<dyn Error>> {
+    match path {
+        Some(p) => std::fs::write(p, bytes).map_err(|e| format!("{}: {}", p.display(), e))?,
+        None => std::io::Write::write_all(&mut std::io::stdout().lock(), bytes)?,
+    }
+    Ok(())
+}
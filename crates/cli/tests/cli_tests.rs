@@ -0,0 +1,118 @@
+use std::path::Path;
+use std::process::{Command, Output};
+
+fn cidr_discover(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_cidr_discover"))
+        .args(args)
+        .output()
+        .expect("run cidr_discover")
+}
+
+fn fixture(name: &str) -> String {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name)
+        .display()
+        .to_string()
+}
+
+#[test]
+fn help_lists_subcommands() {
+    let out = cidr_discover(&["--help"]);
+    assert!(out.status.success());
+    let text = String::from_utf8_lossy(&out.stdout);
+    for sub in ["scan", "diff", "enrich"] {
+        assert!(text.contains(sub), "missing {} in help:\n{}", sub, text);
+    }
+    // no subcommand is a usage error
+    assert_eq!(cidr_discover(&[]).status.code(), Some(2));
+}
+
+#[test]
+fn diff_reports_added_removed_and_changed() {
+    let (baseline, current) = (fixture("baseline.json"), fixture("current.json"));
+    let out = cidr_discover(&["diff", "--baseline", &baseline, "--current", &current]);
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    let v: serde_json::Value = serde_json::from_slice(&out.stdout).expect("diff json");
+    assert_eq!(v["added"][0]["port"], 443);
+    assert_eq!(v["removed"][0]["ip"], "192.0.2.20");
+    let changed = v["changed"].as_array().expect("changed");
+    assert_eq!(changed.len(), 1);
+    assert_eq!(changed[0]["baseline"]["banner"], "SSH-2.0-OpenSSH_9.3");
+    assert_eq!(changed[0]["current"]["banner"], "SSH-2.0-OpenSSH_9.6");
+}
+
+#[test]
+fn diff_fails_on_missing_input() {
+    let out = cidr_discover(&[
+        "diff",
+        "--baseline",
+        "/nonexistent/baseline.json",
+        "--current",
+        &fixture("current.json"),
+    ]);
+    assert_eq!(out.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&out.stderr).contains("baseline.json"));
+}
+
+#[test]
+fn enrich_writes_records_with_oui_vendor() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("enriched.json");
+    let out = cidr_discover(&[
+        "enrich",
+        "--input",
+        &fixture("baseline.json"),
+        "--output",
+        path.to_str().unwrap(),
+        "--oui",
+    ]);
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    assert!(out.stdout.is_empty());
+    let recs = io::read_records_json(path.to_str().unwrap()).expect("records");
+    assert_eq!(recs.len(), 3);
+    assert_eq!(recs[0].vendor.as_deref(), Some("VMware"));
+    assert_eq!(recs[0].provenance("vendor").unwrap().source, "oui");
+}
+
+#[test]
+fn scan_rejects_invalid_cidr_and_ports() {
+    let out = cidr_discover(&["scan", "--cidr", "10.0.0.0/33"]);
+    assert_eq!(out.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&out.stderr).contains("invalid cidr"));
+
+    let out = cidr_discover(&["scan", "--cidr", "10.0.0.0/8"]);
+    assert_eq!(out.status.code(), Some(1));
+    assert!(
+        String::from_utf8_lossy(&out.stderr).contains("16777214 hosts, over the limit of 65536")
+    );
+
+    let out = cidr_discover(&["scan", "--cidr", "10.0.0.0/24", "--ports", "http"]);
+    assert_eq!(out.status.code(), Some(1));
+
+    let out = cidr_discover(&["scan", "--cidr", "10.0.0.0/24", "--format", "yaml"]);
+    assert_eq!(out.status.code(), Some(2));
+}
+
+#[test]
+fn scan_of_loopback_host_emits_csv_header() {
+    let out = cidr_discover(&["scan", "--cidr", "127.0.0.1/32", "--timeout", "1"]);
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    let text = String::from_utf8_lossy(&out.stdout);
+    assert_eq!(
+        text.lines().next(),
+        Some(io::CSV_COLUMNS.join(",").as_str())
+    );
+}
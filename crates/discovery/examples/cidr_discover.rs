@@ -1,4 +1,4 @@
-use discovery::{Discover, SimpleDiscover};
+use discovery::{Discover, SimpleDiscover, SimpleItem};
 
 fn ip_range_from_cidr(cidr: &str) -> Vec<String> {
     // Only support /24 CIDR like 192.168.1.0/24 for this example
@@ -22,14 +22,7 @@ fn ip_range_from_cidr(cidr: &str) -> Vec<String> {
 fn main() {
     let cidr = "192.168.1.0/24";
     let ips = ip_range_from_cidr(cidr);
-    let items: Vec<(
-        String,
-        Option<u16>,
-        Option<String>,
-        Option<String>,
-        Option<String>,
-        Option<String>,
-    )> = ips
+    let items: Vec<SimpleItem> = ips
         .into_iter()
         .map(|ip| (ip, None, None, None, None, None))
         .collect();
@@ -1,21 +1,64 @@
+use discovery::ports::{apply_exclusions, builtin_ports, parse_port_list};
 use discovery::Discover;
 use discovery::LiveArpDiscover;
+use discovery::{ScanPhase, ScanProgress};
 use formats::DiscoveryRecord;
+use io::OutputFormat;
+use netutils::portscan::{HostPortReport, ProbeStrategy};
+use netutils::CancelToken;
 use std::env;
-use std::fs::File;
-use std::io::Write;
 use std::net::Ipv4Addr;
 use std::path::PathBuf;
+use std::sync::mpsc;
+use std::thread;
 use std::time::Duration;
 
 fn usage(prog: &str) {
-    eprintln!("Usage: {} <cidr> [--probe] [--portscan] [--out file.csv] [--json] [--concurrency N] [--timeout secs]", prog);
+    eprintln!("Usage: {} <cidr> [--probe] [--portscan] [--output-format FMT[,FMT...]] [-o|--out BASE] [--concurrency N] [--rate PPS] [--exclude-ports LIST] [--port-report FILE] [--timeout secs] [--progress]", prog);
+    eprintln!();
+    eprintln!("Output formats (--output-format, comma-separated, default csv):");
+    eprintln!("  csv     fixed-column CSV (io::write_records_csv)");
+    eprintln!("  json    pretty JSON array of DiscoveryRecord");
+    eprintln!("  ndjson  one JSON record per line (io::to_ndjson)");
+    eprintln!("  legacy  legacy netscan JSON (io::to_legacy_json)");
+    eprintln!("  target  target-compatible JSON (io::to_target_json)");
+    eprintln!("  dot     Graphviz digraph of hosts and open ports (io::to_dot_graph)");
+    eprintln!("  prometheus  Prometheus text exposition (io::to_prometheus)");
+    eprintln!("legacy/target set Method to \"portscan\" when a port was found, else \"arp\".");
+    eprintln!("-o/--out writes BASE.<ext> per format (scan.csv, scan.target.json, ...);");
+    eprintln!("without it output goes to stdout.");
+    eprintln!("--json is kept as shorthand for --output-format json.");
+    eprintln!("--rate caps port connect attempts per second (default: unpaced).");
+    eprintln!("--exclude-ports never scans the listed ports, e.g. \"515,9100\" or \"8000-8100\".");
+    eprintln!(
+        "--port-report also writes every port result (closed/filtered, RTTs) per host as JSON."
+    );
+    eprintln!("Ctrl-C stops the scan and writes the results gathered so far.");
+}
+
+/// Parse one `--output-format` name.
+fn parse_format(s: &str) -> Option<OutputFormat> {
+    Some(match s.trim().to_ascii_lowercase().as_str() {
+        "csv" => OutputFormat::Csv,
+        "json" => OutputFormat::Json,
+        "ndjson" => OutputFormat::Ndjson,
+        "legacy" => OutputFormat::LegacyJson,
+        "target" => OutputFormat::TargetJson,
+        "dot" => OutputFormat::Dot,
+        "prometheus" => OutputFormat::Prometheus,
+        _ => return None,
+    })
+}
+
+/// Parse a comma-separated `--output-format` list; None if any name is unknown.
+fn parse_formats(s: &str) -> Option<Vec<OutputFormat>> {
+    s.split(',').map(parse_format).collect()
 }
 
 fn main() {
-    let mut args: Vec<String> = env::args().collect();
+    let args: Vec<String> = env::args().collect();
     let prog = args
-        .get(0)
+        .first()
         .cloned()
         .unwrap_or_else(|| "live_arpscan".into());
     if args.len() < 2 {
@@ -26,10 +69,14 @@ fn main() {
     let cidr = args[1].clone();
     let mut perform_probe = false;
     let mut do_portscan = false;
-    let mut out_csv: PathBuf = PathBuf::from("discovery_results.csv");
-    let mut write_json = false;
+    let mut out_path: Option<PathBuf> = None;
+    let mut formats = vec![OutputFormat::Csv];
     let mut concurrency = 64usize;
+    let mut rate: Option<u32> = None;
+    let mut exclude_ports: Vec<u16> = Vec::new();
+    let mut port_report: Option<String> = None;
     let mut timeout_secs = 1u64;
+    let mut show_progress = false;
 
     let mut i = 2;
     while i < args.len() {
@@ -42,17 +89,27 @@ fn main() {
                 do_portscan = true;
                 i += 1;
             }
-            "--out" => {
+            "-o" | "--out" => {
                 if i + 1 < args.len() {
-                    out_csv = PathBuf::from(&args[i + 1]);
+                    out_path = Some(PathBuf::from(&args[i + 1]));
                     i += 2;
                 } else {
                     usage(&prog);
                     return;
                 }
             }
+            "--output-format" => match args.get(i + 1).and_then(|f| parse_formats(f)) {
+                Some(f) => {
+                    formats = f;
+                    i += 2;
+                }
+                None => {
+                    usage(&prog);
+                    return;
+                }
+            },
             "--json" => {
-                write_json = true;
+                formats = vec![OutputFormat::Json];
                 i += 1;
             }
             "--concurrency" => {
@@ -64,6 +121,36 @@ fn main() {
                     return;
                 }
             }
+            "--rate" => match args.get(i + 1).and_then(|r| r.parse().ok()) {
+                Some(r) => {
+                    rate = Some(r);
+                    i += 2;
+                }
+                None => {
+                    usage(&prog);
+                    return;
+                }
+            },
+            "--exclude-ports" => match args.get(i + 1) {
+                Some(list) => {
+                    exclude_ports = parse_port_list(list);
+                    i += 2;
+                }
+                None => {
+                    usage(&prog);
+                    return;
+                }
+            },
+            "--port-report" => match args.get(i + 1) {
+                Some(path) => {
+                    port_report = Some(path.clone());
+                    i += 2;
+                }
+                None => {
+                    usage(&prog);
+                    return;
+                }
+            },
             "--timeout" => {
                 if i + 1 < args.len() {
                     timeout_secs = args[i + 1].parse().unwrap_or(timeout_secs);
@@ -73,6 +160,10 @@ fn main() {
                     return;
                 }
             }
+            "--progress" => {
+                show_progress = true;
+                i += 1;
+            }
             _ => {
                 eprintln!("Unknown arg: {}", args[i]);
                 usage(&prog);
@@ -81,30 +172,69 @@ fn main() {
         }
     }
 
-    let mut discover = LiveArpDiscover::new(cidr)
+    let cancel = cancel_on_ctrl_c();
+    let discover = LiveArpDiscover::new(cidr)
         .with_workers(concurrency)
         .with_probe(perform_probe)
-        .with_timeout_secs(timeout_secs);
+        .with_timeout_secs(timeout_secs)
+        .with_cancel_token(cancel.clone());
+
+    if show_progress {
+        // Let the library portscan so the bar can follow it host by host
+        let mut discover = discover
+            .with_portscan(do_portscan)
+            .with_port_concurrency(concurrency)
+            .with_port_timeout_secs(timeout_secs)
+            .with_excluded_ports(exclude_ports);
+        if let Some(pps) = rate {
+            discover = discover.with_scan_rate(pps);
+        }
+        let (report_tx, report_rx) = mpsc::channel();
+        if port_report.is_some() {
+            discover = discover.with_port_reports(report_tx);
+        }
+        let records = run_with_progress(discover);
+        write_output(&records, &formats, out_path.as_ref());
+        if let Some(path) = &port_report {
+            write_port_report(path, &report_rx.try_iter().collect::<Vec<_>>());
+        }
+        return;
+    }
 
     let records: Vec<DiscoveryRecord> = discover.discover();
 
     // Optionally run portscan per host (opt-in). Default built-in ports are 1..=1024
     let mut final_records = Vec::new();
+    let mut reports = Vec::new();
     if do_portscan {
         eprintln!("Performing portscan on discovered hosts (this may take a while)...");
         for r in records.iter() {
             let ip: Ipv4Addr = r.ip.parse().unwrap_or(Ipv4Addr::UNSPECIFIED);
-            if ip == Ipv4Addr::UNSPECIFIED {
+            // After Ctrl-C the remaining hosts are reported unscanned
+            if ip == Ipv4Addr::UNSPECIFIED || cancel.is_cancelled() {
+                final_records.push(r.clone());
                 continue;
             }
-            // ports 1..=1024
-            let ports: Vec<u16> = (1u16..=1024u16).collect();
-            let port_results = netutils::portscan::scan_host_ports(
+            let ports = apply_exclusions(builtin_ports(), &exclude_ports);
+            let port_results = match netutils::portscan::scan_host_ports_with_cancel(
                 ip,
                 ports,
                 Duration::from_secs(timeout_secs),
                 concurrency,
-            );
+                ProbeStrategy::default().with_rate_limit(rate),
+                Some(port_counter()),
+                cancel.clone(),
+            ) {
+                Ok(partial) => partial.results,
+                Err(e) => {
+                    eprintln!("Portscan of {} failed: {}", ip, e);
+                    Vec::new()
+                }
+            };
+            eprintln!();
+            if port_report.is_some() {
+                reports.push(HostPortReport::new(ip, port_results.clone()));
+            }
             if port_results.is_empty() {
                 final_records.push(r.clone());
             } else {
@@ -120,27 +250,95 @@ fn main() {
         final_records = records;
     }
 
-    // Write CSV by default
-    if let Ok(mut w) = File::create(&out_csv) {
-        let mut wtr = csv::Writer::from_writer(Vec::new());
-        for r in final_records.iter() {
-            let _ = wtr.serialize(r);
+    write_output(&final_records, &formats, out_path.as_ref());
+    if let Some(path) = &port_report {
+        write_port_report(path, &reports);
+    }
+}
+
+/// Write the `--port-report` file.
+fn write_port_report(path: &str, reports: &[HostPortReport]) {
+    match io::write_port_report_json(path, reports) {
+        Ok(()) => eprintln!("Wrote port report for {} hosts to {}", reports.len(), path),
+        Err(e) => eprintln!("Failed to write port report: {}", e),
+    }
+}
+
+/// Token cancelled by the first Ctrl-C, so the scan stops and the partial
+/// results are still written; a second Ctrl-C exits straight away.
+fn cancel_on_ctrl_c() -> CancelToken {
+    let cancel = CancelToken::new();
+    let token = cancel.clone();
+    let installed = ctrlc::set_handler(move || {
+        if token.is_cancelled() {
+            std::process::exit(130);
         }
-        let _ = wtr.flush();
-        if let Ok(bytes) = wtr.into_inner() {
-            let _ = w.write_all(&bytes);
-            println!("Wrote CSV to {}", out_csv.display());
+        eprintln!("\nInterrupted: finishing in-flight probes, then writing partial results (Ctrl-C again to quit)");
+        token.cancel();
+    });
+    if let Err(e) = installed {
+        eprintln!("Ctrl-C handler not installed: {}", e);
+    }
+    cancel
+}
+
+/// Progress hook printing an `ip: done/total` counter on one stderr line.
+fn port_counter() -> netutils::portscan::ProgressFn {
+    std::sync::Arc::new(|p: netutils::portscan::ScanProgress| {
+        eprint!("\r{}: {}/{} ports", p.ip, p.done, p.total);
+    })
+}
+
+/// Run `discover` on a worker thread and drive an indicatif bar from its
+/// `ScanProgress` updates. The bar is cleared before returning.
+fn run_with_progress(discover: LiveArpDiscover) -> Vec<DiscoveryRecord> {
+    let (tx, rx) = mpsc::channel::<ScanProgress>();
+    let worker = thread::spawn(move || discover.with_progress(tx).discover());
+
+    let bar = indicatif::ProgressBar::new(0);
+    bar.set_style(
+        indicatif::ProgressStyle::with_template(
+            "[{eta}] {pos}/{len} hosts scanned ({msg} open ports) {prefix}",
+        )
+        .unwrap_or_else(|_| indicatif::ProgressStyle::default_bar()),
+    );
+    let mut phase = None;
+    for p in rx {
+        if phase != Some(p.phase) {
+            phase = Some(p.phase);
+            bar.reset();
+            bar.set_prefix(match p.phase {
+                ScanPhase::Arp => "arp",
+                ScanPhase::PortScan => "portscan",
+                ScanPhase::Done => "done",
+            });
         }
-    } else {
-        eprintln!("Failed to open output file {}", out_csv.display());
+        bar.set_length(p.hosts_total as u64);
+        bar.set_position(p.hosts_done as u64);
+        bar.set_message(p.open_ports.to_string());
     }
+    bar.finish_and_clear();
+
+    worker.join().unwrap_or_default()
+}
 
-    if write_json {
-        let json_out = out_csv.with_extension("json");
-        if let Ok(mut f) = File::create(&json_out) {
-            if let Ok(s) = serde_json::to_string(&final_records) {
-                let _ = f.write_all(s.as_bytes());
-                println!("Wrote JSON to {}", json_out.display());
+/// Write every format to `out_path`.<ext>, or one after another to stdout.
+fn write_output(records: &[DiscoveryRecord], formats: &[OutputFormat], out_path: Option<&PathBuf>) {
+    match out_path {
+        Some(base) => match io::write_discovery_outputs(base, records, formats) {
+            Ok(paths) => {
+                for path in paths {
+                    eprintln!("Wrote {} records to {}", records.len(), path.display());
+                }
+            }
+            Err(e) => eprintln!("Failed to write output: {}", e),
+        },
+        None => {
+            let method = io::scan_method(records);
+            for format in formats {
+                if let Err(e) = format.write(std::io::stdout().lock(), records, method) {
+                    eprintln!("Failed to render output: {}", e);
+                }
             }
         }
     }
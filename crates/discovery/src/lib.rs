@@ -6,12 +6,27 @@
 //! `formats::DiscoveryRecord` objects used across the workspace.
 
 #[cfg(feature = "enrich")]
-use enrich::vendor_from_hostname;
-use formats::DiscoveryRecord;
+use enrich::EnrichPipeline;
+use formats::{DiscoveryRecord, PORT_SERVICE_CONFIDENCE};
 use io::{read_netscan_csv, read_netscan_json};
+use netutils::cidrsniffer::{CidrScanOptions, HostOrder};
+use netutils::portscan::{HostPortReport, ScanOrder};
+use netutils::CancelToken;
+use std::collections::{BTreeMap, VecDeque};
 use std::error::Error;
+use std::net::Ipv4Addr;
 use std::path::Path;
+use std::sync::mpsc::{Receiver, Sender};
+#[cfg(feature = "mdns")]
+pub mod mdns;
 pub mod ports;
+mod targets;
+pub mod tcpping;
+
+#[cfg(feature = "mdns")]
+pub use mdns::MdnsDiscover;
+pub use targets::{parse_targets, targets_from_file};
+pub use tcpping::TcpPingDiscover;
 
 /// A minimal discovery trait.
 ///
@@ -20,9 +35,53 @@ pub mod ports;
 pub trait Discover {
     /// Perform discovery and return canonical records.
     fn discover(&self) -> Vec<DiscoveryRecord>;
+
+    /// Discover and keep only records matching `pred`. The predicate is taken
+    /// as a trait object so this stays callable on `dyn Discover`.
+    fn discover_filtered(&self, pred: &dyn Fn(&DiscoveryRecord) -> bool) -> Vec<DiscoveryRecord> {
+        self.discover().into_iter().filter(|r| pred(r)).collect()
+    }
+
+    /// Discover and return at most `n` records.
+    fn discover_take(&self, n: usize) -> Vec<DiscoveryRecord> {
+        self.discover().into_iter().take(n).collect()
+    }
+
+    /// Yield records as they become available instead of all at once. The
+    /// default runs `discover` and iterates its result; implementors that
+    /// finish hosts incrementally override it.
+    fn discover_stream(&self) -> Box<dyn Iterator<Item = DiscoveryRecord>> {
+        Box::new(self.discover().into_iter())
+    }
 }
 
-/// Live ARP-based discoverer. Uses `netutils::cidrsniffer::scan_cidr` internally.
+/// Which stage of a `LiveArpDiscover` scan a progress update belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScanPhase {
+    /// ARP sweep over every address in the CIDR
+    Arp,
+    /// Port scans of the swept hosts (only with `with_portscan(true)`)
+    PortScan,
+    /// Scan finished; counters hold the final totals
+    Done,
+}
+
+/// Progress snapshot sent on the channel given to `LiveArpDiscover::with_progress`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScanProgress {
+    pub phase: ScanPhase,
+    /// Hosts finished in the current phase
+    pub hosts_done: usize,
+    /// Hosts in the current phase
+    pub hosts_total: usize,
+    /// Open ports found so far
+    pub open_ports: usize,
+}
+
+/// Predicate deciding which ARP-resolved hosts `LiveArpDiscover` port-scans.
+pub type PortscanFilter = Box<dyn Fn(&DiscoveryRecord) -> bool + Send + Sync>;
+
+/// Live ARP-based discoverer. Uses `netutils::cidrsniffer::scan_cidr_ordered` internally.
 pub struct LiveArpDiscover {
     pub cidr: String,
     pub workers: usize,
@@ -33,10 +92,61 @@ pub struct LiveArpDiscover {
     pub portscan: bool,
     /// optional explicit port list; when None the builtin 1..=1024 is used
     pub ports: Option<Vec<u16>>,
-    /// concurrency for port scanning
+    /// ports never scanned, whatever `ports` says
+    pub exclude_ports: Vec<u16>,
+    /// concurrent port probes, shared across all scanned hosts
     pub port_concurrency: usize,
     /// per-port timeout
     pub port_timeout_secs: u64,
+    /// extra connect attempts for ports whose connect timed out
+    pub port_retries: u8,
+    /// order port probes are started in; results are reported in port
+    /// order either way
+    pub port_order: ScanOrder,
+    /// how long each open port gets to send a banner
+    pub banner_timeout: std::time::Duration,
+    /// read banners from open ports; when false ports are only checked for
+    /// open/closed and closed right after connect
+    pub banner_grab: bool,
+    /// before port scanning, drop hosts that answer on none of
+    /// `LIVENESS_PORTS` (stale ARP entries); they are emitted with
+    /// `is_up = Some(false)` and not port-scanned
+    pub liveness_check: bool,
+    /// per-host liveness timeout, separate from `port_timeout_secs`
+    pub liveness_timeout_ms: u64,
+    /// cap on port connect attempts per second across all hosts; `None`
+    /// leaves only `port_concurrency` as a bound
+    pub scan_rate: Option<u32>,
+    /// source address the port scan and liveness check connect from, on
+    /// hosts with more than one network (VPN + LAN); `None` follows the
+    /// routing table
+    pub source_ip: Option<Ipv4Addr>,
+    /// read the certificate of open TLS ports while port scanning; a SAN
+    /// hostname fills the record's hostname when empty (needs the `tls` feature)
+    pub tls_probe: bool,
+    /// when port scanning, still emit the host record for hosts with no open
+    /// ports (marked `port_scanned = Some(true)`, `port = None`)
+    pub emit_closed_hosts: bool,
+    /// order in which CIDR hosts are visited (sequential by default)
+    pub host_order: HostOrder,
+    /// CIDRs with more hosts than this are rejected (a /16 by default)
+    pub max_hosts: usize,
+    /// return records in ascending IP order instead of lookup completion order
+    pub sorted_output: bool,
+    /// optional progress channel; send errors (receiver gone) are ignored
+    pub progress: Option<Sender<ScanProgress>>,
+    /// optional channel receiving every port-scanned host's raw port
+    /// results (closed and filtered ports included); send errors are ignored
+    pub port_reports: Option<Sender<HostPortReport>>,
+    /// when set, only hosts passing the filter are port-scanned; the rest are
+    /// emitted as plain discovery records
+    pub portscan_filter: Option<PortscanFilter>,
+    /// when fired, `discover` stops issuing lookups/probes and returns what
+    /// it has so far
+    pub cancel: Option<CancelToken>,
+    /// enrichment applied to host records; `None` disables enrichment
+    #[cfg(feature = "enrich")]
+    pub enrich: Option<EnrichPipeline>,
 }
 
 impl LiveArpDiscover {
@@ -48,8 +158,28 @@ impl LiveArpDiscover {
             timeout_secs: 1,
             portscan: false,
             ports: None,
+            exclude_ports: Vec::new(),
             port_concurrency: 64,
             port_timeout_secs: 1,
+            port_retries: 0,
+            port_order: ScanOrder::Sequential,
+            banner_timeout: netutils::portscan::DEFAULT_BANNER_TIMEOUT,
+            banner_grab: true,
+            liveness_check: false,
+            liveness_timeout_ms: 300,
+            scan_rate: None,
+            source_ip: None,
+            tls_probe: false,
+            emit_closed_hosts: true,
+            host_order: HostOrder::Sequential,
+            max_hosts: netutils::cidrsniffer::DEFAULT_MAX_HOSTS,
+            sorted_output: false,
+            progress: None,
+            port_reports: None,
+            portscan_filter: None,
+            cancel: None,
+            #[cfg(feature = "enrich")]
+            enrich: Some(EnrichPipeline::standard()),
         }
     }
 
@@ -80,6 +210,13 @@ impl LiveArpDiscover {
         self
     }
 
+    /// Never scan `ports`, even when the explicit port list names them
+    /// (e.g. 515/9100 on networks with fragile printers).
+    pub fn with_excluded_ports(mut self, ports: Vec<u16>) -> Self {
+        self.exclude_ports = ports;
+        self
+    }
+
     pub fn with_port_concurrency(mut self, c: usize) -> Self {
         self.port_concurrency = c;
         self
@@ -89,118 +226,642 @@ impl LiveArpDiscover {
         self.port_timeout_secs = secs;
         self
     }
+
+    /// Retry timed-out port connects up to `retries` times (lossy Wi-Fi can
+    /// drop a single SYN and make an open port look filtered).
+    pub fn with_port_retries(mut self, retries: u8) -> Self {
+        self.port_retries = retries;
+        self
+    }
+
+    /// Probe ports (and hosts) in shuffled order so the sweep does not walk
+    /// 1..1024 in sequence; `Some(seed)` repeats the same order every run.
+    pub fn with_randomized_ports(mut self, seed: Option<u64>) -> Self {
+        self.port_order = ScanOrder::Random { seed };
+        self
+    }
+
+    /// Same as `with_randomized_ports`; for a shuffled port list outside a
+    /// scan see `ports::generate_random_port_order`.
+    pub fn with_random_port_order(self, seed: Option<u64>) -> Self {
+        self.with_randomized_ports(seed)
+    }
+
+    /// Wait up to `timeout` for each open port's banner (default 300 ms).
+    pub fn with_banner_timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.banner_timeout = timeout;
+        self
+    }
+
+    /// `false` skips banner reads and probes entirely, which speeds up large
+    /// sweeps that only need open ports.
+    pub fn with_banner_grab(mut self, enabled: bool) -> Self {
+        self.banner_grab = enabled;
+        self
+    }
+
+    /// Confirm each host is still there (a connect accepted or refused on
+    /// one of `netutils::portscan::LIVENESS_PORTS`) before its full port
+    /// sweep. Hosts that stay silent are emitted as stale (`is_up =
+    /// Some(false)`) without being scanned. Firewalls dropping everything
+    /// make a live host look stale too.
+    pub fn with_liveness_check(mut self, enabled: bool) -> Self {
+        self.liveness_check = enabled;
+        self
+    }
+
+    /// Timeout for the liveness check (default 300 ms); the port sweep keeps
+    /// using `port_timeout_secs`.
+    pub fn with_liveness_timeout_ms(mut self, ms: u64) -> Self {
+        self.liveness_timeout_ms = ms;
+        self
+    }
+
+    /// Start at most `pps` port connect attempts per second. Concurrency
+    /// alone does not bound the attempt rate, which is what switches and IDS
+    /// boxes react to.
+    pub fn with_scan_rate(mut self, pps: u32) -> Self {
+        self.scan_rate = Some(pps);
+        self
+    }
+
+    /// Bind port-scan connects to `ip` so they leave through that address's
+    /// network rather than the default route. An address this host cannot
+    /// bind leaves every host unscanned (see
+    /// `netutils::portscan::check_local_addr`).
+    pub fn with_source_ip(mut self, ip: Ipv4Addr) -> Self {
+        self.source_ip = Some(ip);
+        self
+    }
+
+    /// `with_source_ip` with the IPv4 address of interface `name` (`"eth0"`,
+    /// `"wlan0"`, ...). Fails when there is no such interface or it has no
+    /// IPv4 address.
+    pub fn with_source_interface(self, name: &str) -> Result<Self, netutils::iface::IfaceError> {
+        let ip = netutils::iface::get_ipv4_by_interface_name(name)?;
+        Ok(self.with_source_ip(ip))
+    }
+
+    pub fn with_tls_probe(mut self, enabled: bool) -> Self {
+        self.tls_probe = enabled;
+        self
+    }
+
+    /// Only port-scan hosts for which `filter` returns true. The filter sees
+    /// the enriched host record (MAC, vendor, ...) after ARP resolution.
+    pub fn with_portscan_filter(mut self, filter: PortscanFilter) -> Self {
+        self.portscan_filter = Some(filter);
+        self
+    }
+
+    /// Visit hosts striped or shuffled instead of sweeping the range in order.
+    pub fn with_host_order(mut self, order: HostOrder) -> Self {
+        self.host_order = order;
+        self
+    }
+
+    /// Reject CIDRs with more than `max_hosts` hosts (an empty scan).
+    pub fn with_max_hosts(mut self, max_hosts: usize) -> Self {
+        self.max_hosts = max_hosts;
+        self
+    }
+
+    /// Stop the scan cleanly when `token` is cancelled (e.g. from a Ctrl-C
+    /// handler): in-flight ARP lookups and port probes finish, nothing new
+    /// starts, and `discover` returns the records gathered so far. Hosts the
+    /// port scan never reached are emitted unscanned (`port_scanned = None`).
+    pub fn with_cancel_token(mut self, token: CancelToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    /// Return records sorted by numeric IP (a host's port records stay
+    /// together, in port-list order), so successive scans diff cleanly.
+    pub fn with_sorted_output(mut self, sorted: bool) -> Self {
+        self.sorted_output = sorted;
+        self
+    }
+
+    /// Report `ScanProgress` snapshots on `tx` while `discover` runs.
+    pub fn with_progress(mut self, tx: Sender<ScanProgress>) -> Self {
+        self.progress = Some(tx);
+        self
+    }
+
+    /// Send a `HostPortReport` on `tx` for each host as its port scan
+    /// finishes, with every `PortResult` rather than just the open ports.
+    pub fn with_port_reports(mut self, tx: Sender<HostPortReport>) -> Self {
+        self.port_reports = Some(tx);
+        self
+    }
+
+    fn report(&self, phase: ScanPhase, hosts_done: usize, hosts_total: usize, open_ports: usize) {
+        if let Some(tx) = &self.progress {
+            let _ = tx.send(ScanProgress {
+                phase,
+                hosts_done,
+                hosts_total,
+                open_ports,
+            });
+        }
+    }
+
+    /// Emit (or drop) host records for port-scanned hosts with nothing open.
+    pub fn with_emit_closed_hosts(mut self, emit: bool) -> Self {
+        self.emit_closed_hosts = emit;
+        self
+    }
+
+    /// Replace the enrichment pipeline (defaults to `EnrichPipeline::standard()`).
+    /// Passing None disables enrichment.
+    #[cfg(feature = "enrich")]
+    pub fn with_enrich_pipeline(mut self, pipeline: Option<EnrichPipeline>) -> Self {
+        self.enrich = pipeline;
+        self
+    }
+}
+
+/// Host record for one ARP scan entry (MAC formatted lowercase, colon separated).
+fn arp_host_record(ip: std::net::Ipv4Addr, mac: Option<[u8; 6]>) -> DiscoveryRecord {
+    let mac_str = mac.map(|m| {
+        format!(
+            "{:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x}",
+            m[0], m[1], m[2], m[3], m[4], m[5]
+        )
+    });
+    DiscoveryRecord::new(&ip.to_string(), None, None, mac_str.as_deref(), None, None)
 }
 
+/// Scan several (possibly overlapping) CIDRs concurrently, one `scan_cidr`
+/// call per CIDR, and return one record per responding host sorted by IP.
+///
+/// Unlike `LiveArpDiscover`, hosts whose MAC could not be resolved are
+/// omitted, so an unreachable subnet contributes nothing. Invalid CIDRs are
+/// skipped. With the `enrich` feature the standard pipeline is applied.
+pub fn discover_parallel_cidrs(
+    cidrs: &[&str],
+    workers_per_cidr: usize,
+    perform_probe: bool,
+    timeout: std::time::Duration,
+) -> Vec<DiscoveryRecord> {
+    let per_cidr: Vec<Vec<netutils::cidrsniffer::ArpScanEntry>> = std::thread::scope(|s| {
+        let handles: Vec<_> = cidrs
+            .iter()
+            .map(|cidr| {
+                s.spawn(move || {
+                    netutils::cidrsniffer::scan_cidr(cidr, workers_per_cidr, perform_probe, timeout)
+                        .unwrap_or_default()
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|h| h.join().unwrap_or_default())
+            .collect()
+    });
+
+    #[allow(unused_mut)]
+    let mut recs = merge_responders(per_cidr);
+    #[cfg(feature = "enrich")]
+    EnrichPipeline::standard().run(&mut recs);
+    recs
+}
+
+/// Merge per-CIDR scan results: responders only, deduplicated by IP, sorted.
+fn merge_responders(
+    per_cidr: Vec<Vec<netutils::cidrsniffer::ArpScanEntry>>,
+) -> Vec<DiscoveryRecord> {
+    let mut seen = std::collections::BTreeMap::new();
+    for (ip, mac) in per_cidr.into_iter().flatten() {
+        if let Some(mac) = mac {
+            seen.entry(ip).or_insert(mac);
+        }
+    }
+    seen.into_iter()
+        .map(|(ip, mac)| arp_host_record(ip, Some(mac)))
+        .collect()
+}
+
+/// Confidence recorded for hostnames read from a TLS certificate.
+pub const TLS_HOSTNAME_CONFIDENCE: u8 = 70;
+
+/// Combine ARP `hosts` with the port scan of those whose `scan_ips` entry is
+/// set (results in the same order). Stale hosts are marked down. With no
+/// scan at all (`None`: the runtime could not start) every host is kept
+/// unscanned, `port_scanned` unset, rather than reported as "nothing open";
+/// so is a host a cancelled scan never reached.
+fn merge_port_scan(
+    hosts: Vec<DiscoveryRecord>,
+    scan_ips: &[Option<Ipv4Addr>],
+    stale: &[bool],
+    scanned: Option<netutils::portscan::PartialScan<netutils::portscan::HostPortResults>>,
+    emit_closed: bool,
+) -> Vec<DiscoveryRecord> {
+    let cancelled = scanned.as_ref().is_some_and(|s| s.cancelled);
+    let mut scanned = scanned.map(|s| s.results.into_iter());
+
+    let mut out = Vec::new();
+    for ((mut r, ip), &stale) in hosts.into_iter().zip(scan_ips).zip(stale) {
+        if stale {
+            r.is_up = Some(false);
+            out.push(r);
+            continue;
+        }
+        let (Some(_), Some(scanned)) = (ip, scanned.as_mut()) else {
+            out.push(r);
+            continue;
+        };
+        let port_results = scanned.next().map(|(_, res)| res).unwrap_or_default();
+        if cancelled && port_results.is_empty() {
+            // Cancelled before any of its ports was tried
+            out.push(r);
+            continue;
+        }
+        out.extend(expand_port_results(r, port_results, emit_closed));
+    }
+    out
+}
+
+/// Expand a port-scanned host into one record per open port. Every returned
+/// record is marked `port_scanned = Some(true)` and gets the port table's
+/// service name, if any; a TLS certificate hostname fills an empty
+/// `hostname`. With nothing open the bare host record is kept (port `None`)
+/// when `emit_closed` is set.
+fn expand_port_results(
+    host: DiscoveryRecord,
+    results: Vec<netutils::portscan::PortResult>,
+    emit_closed: bool,
+) -> Vec<DiscoveryRecord> {
+    let mut host = host;
+    host.port_scanned = Some(true);
+    let out: Vec<DiscoveryRecord> = results
+        .into_iter()
+        .filter(|p| p.open)
+        .map(|p| {
+            let mut rec = host.clone();
+            rec.port = Some(p.port);
+            rec.banner = p.banner;
+            if rec.hostname.is_none() {
+                if let Some(name) = p.tls.as_ref().and_then(|t| t.hostname()) {
+                    rec.hostname = Some(name.to_string());
+                    rec.set_provenance("hostname", "tls", TLS_HOSTNAME_CONFIDENCE);
+                }
+            }
+            if let Some(name) = p.service_name {
+                rec.service = Some(name.into_owned());
+                rec.set_provenance("service", "port", PORT_SERVICE_CONFIDENCE);
+            }
+            rec
+        })
+        .collect();
+    if !out.is_empty() {
+        return out;
+    }
+    if emit_closed {
+        host.port = None;
+        vec![host]
+    } else {
+        Vec::new()
+    }
+}
+
+/// Tuple form accepted by `SimpleDiscover`: (ip, port, banner, mac, vendor, timestamp).
+pub type SimpleItem = (
+    String,
+    Option<u16>,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+);
+
 /// A simple, deterministic discoverer built from an explicit list of
 /// tuples (ip, port, banner, mac, vendor, timestamp). Useful for unit tests.
 pub struct SimpleDiscover {
-    items: Vec<(
-        String,
-        Option<u16>,
-        Option<String>,
-        Option<String>,
-        Option<String>,
-        Option<String>,
-    )>,
+    items: Vec<SimpleItem>,
 }
 
 impl SimpleDiscover {
     /// Create a new SimpleDiscover from an iterator of tuples.
     pub fn new<I>(items: I) -> Self
     where
-        I: Into<
-            Vec<(
-                String,
-                Option<u16>,
-                Option<String>,
-                Option<String>,
-                Option<String>,
-                Option<String>,
-            )>,
-        >,
+        I: Into<Vec<SimpleItem>>,
     {
         Self {
             items: items.into(),
         }
     }
+
+    /// One bare record (no port, MAC or vendor) per target in a target
+    /// file; see `targets_from_file` for the format.
+    pub fn from_target_file<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        let items: Vec<SimpleItem> = targets_from_file(path)?
+            .into_iter()
+            .map(|t| (t, None, None, None, None, None))
+            .collect();
+        Ok(Self::new(items))
+    }
 }
 
-impl Discover for LiveArpDiscover {
-    fn discover(&self) -> Vec<DiscoveryRecord> {
+/// Phases shared by `discover` and `discover_stream`.
+impl LiveArpDiscover {
+    /// ARP-resolve the CIDR into host records (sorted and enriched as
+    /// configured); None when the CIDR is rejected.
+    fn arp_hosts(&self, cancel: &CancelToken) -> Option<Vec<DiscoveryRecord>> {
         let timeout = std::time::Duration::from_secs(self.timeout_secs);
-        match netutils::cidrsniffer::scan_cidr(
+        let options = CidrScanOptions::default()
+            .with_order(self.host_order)
+            .with_max_hosts(self.max_hosts);
+        let mut partial = netutils::cidrsniffer::scan_cidr_with_cancel(
             &self.cidr,
             self.workers,
             self.perform_probe,
             timeout,
-        ) {
-            Ok(results) => results
-                .into_iter()
-                .map(|(ip, mac)| {
-                    let mac_str = mac.map(|m| {
-                        format!(
-                            "{:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x}",
-                            m[0], m[1], m[2], m[3], m[4], m[5]
-                        )
-                    });
-                    DiscoveryRecord::new(
-                        &ip.to_string(),
-                        None,
-                        None,
-                        mac_str.as_deref(),
-                        None,
-                        None,
-                    )
-                })
-                .collect::<Vec<_>>()
-                .into_iter()
-                .flat_map(|r| {
-                    // If portscan disabled, just return the host record
-                    if !self.portscan {
-                        return vec![r].into_iter();
-                    }
+            options,
+            cancel,
+            |_, done, total| self.report(ScanPhase::Arp, done, total, 0),
+        )
+        .ok()?;
+        if self.sorted_output {
+            netutils::cidrsniffer::sort_by_ip(&mut partial.results);
+        }
 
-                    // Portscan enabled: run scan_host_ports and expand per-open-port records
-                    let ip_addr = match r.ip.parse::<std::net::Ipv4Addr>() {
-                        Ok(a) => a,
-                        Err(_) => return vec![r].into_iter(),
-                    };
+        #[allow(unused_mut)]
+        let mut hosts = partial
+            .results
+            .into_iter()
+            .map(|(ip, mac)| arp_host_record(ip, mac))
+            .collect::<Vec<_>>();
+        #[cfg(feature = "enrich")]
+        if let Some(pipeline) = &self.enrich {
+            pipeline.run(&mut hosts);
+        }
+        Some(hosts)
+    }
 
-                    // Determine ports to scan: explicit list or builtin 1..=1024
-                    let ports_vec = match &self.ports {
-                        Some(v) => v.clone(),
-                        None => ports::builtin_ports(),
-                    };
+    /// Per host, the address to port-scan (None: emit unscanned) and whether
+    /// the liveness check found it stale. Hosts failing the filter (or
+    /// without an IPv4 address) are not scanned.
+    fn port_scan_plan(&self, hosts: &[DiscoveryRecord]) -> (Vec<Option<Ipv4Addr>>, Vec<bool>) {
+        let scan_ip = |r: &DiscoveryRecord| {
+            r.ip.parse::<Ipv4Addr>()
+                .ok()
+                .filter(|_| self.portscan_filter.as_ref().is_none_or(|f| f(r)))
+        };
+        let mut scan_ips: Vec<_> = hosts.iter().map(scan_ip).collect();
+        let mut stale = vec![false; hosts.len()];
+        if self.liveness_check {
+            let candidates: Vec<usize> = (0..hosts.len())
+                .filter(|&i| scan_ips[i].is_some())
+                .collect();
+            let ips: Vec<_> = candidates.iter().filter_map(|&i| scan_ips[i]).collect();
+            // No runtime means no check; every host is treated as alive
+            let alive = netutils::portscan::tcp_alive_many(
+                &ips,
+                netutils::portscan::LIVENESS_PORTS,
+                std::time::Duration::from_millis(self.liveness_timeout_ms),
+                self.port_concurrency,
+                self.source_ip,
+            )
+            .unwrap_or_else(|_| vec![true; ips.len()]);
+            for (&i, alive) in candidates.iter().zip(alive) {
+                if !alive {
+                    stale[i] = true;
+                    scan_ips[i] = None;
+                }
+            }
+        }
+        (scan_ips, stale)
+    }
+
+    fn port_list(&self) -> Vec<u16> {
+        let ports = match &self.ports {
+            Some(v) => v.clone(),
+            None => ports::builtin_ports(),
+        };
+        ports::apply_exclusions(ports, &self.exclude_ports)
+    }
+
+    fn probe_strategy(&self) -> netutils::portscan::ProbeStrategy {
+        netutils::portscan::ProbeStrategy::default()
+            .with_tls_probe(self.tls_probe)
+            .with_retries(self.port_retries)
+            .with_banner_timeout(self.banner_timeout)
+            .with_banner_grab(self.banner_grab)
+            .with_order(self.port_order)
+            .with_local_addr(self.source_ip)
+            .with_rate_limit(self.scan_rate)
+    }
+
+    /// Port scanning is on and its source address, if any, can be bound.
+    fn port_scan_enabled(&self) -> bool {
+        self.portscan
+            && self
+                .source_ip
+                .is_none_or(|ip| netutils::portscan::check_local_addr(ip).is_ok())
+    }
+}
+
+impl Discover for LiveArpDiscover {
+    fn discover(&self) -> Vec<DiscoveryRecord> {
+        // A token nobody holds never fires, so uncancellable scans share the path
+        let cancel = self.cancel.clone().unwrap_or_default();
+        let Some(hosts) = self.arp_hosts(&cancel) else {
+            self.report(ScanPhase::Done, 0, 0, 0);
+            return Vec::new();
+        };
+
+        // If portscan disabled (or the scan was cancelled), just return the host records
+        if !self.port_scan_enabled() || cancel.is_cancelled() {
+            self.report(ScanPhase::Done, hosts.len(), hosts.len(), 0);
+            return hosts;
+        }
+
+        // Scanned hosts share one port-scan concurrency budget
+        let total = hosts.len();
+        let ports_vec = self.port_list();
+        let (scan_ips, stale) = self.port_scan_plan(&hosts);
+        let targets: Vec<_> = scan_ips
+            .iter()
+            .flatten()
+            .map(|ip| (*ip, ports_vec.clone()))
+            .collect();
+        let mut done = total - targets.len();
+        let mut open_ports = 0;
+        self.report(ScanPhase::PortScan, done, total, open_ports);
+
+        let timeout = std::time::Duration::from_secs(self.port_timeout_secs);
+        let scanned = netutils::portscan::scan_many_with_cancel(
+            targets,
+            timeout,
+            self.port_concurrency,
+            self.probe_strategy(),
+            cancel.clone(),
+            |(ip, results)| {
+                done += 1;
+                open_ports += results.iter().filter(|p| p.open).count();
+                self.report(ScanPhase::PortScan, done, total, open_ports);
+                if let Some(tx) = &self.port_reports {
+                    let _ = tx.send(HostPortReport::new(*ip, results.clone()));
+                }
+            },
+        )
+        .ok();
+        let out = merge_port_scan(hosts, &scan_ips, &stale, scanned, self.emit_closed_hosts);
+        self.report(ScanPhase::Done, total, total, open_ports);
+        out
+    }
+
+    /// The ARP sweep runs before the first record comes back; after that,
+    /// hosts that are not port-scanned are yielded at once and each scanned
+    /// host's records as its last port finishes (completion order). With
+    /// `with_sorted_output(true)` this waits for the whole scan, like
+    /// `discover`. Dropping the iterator early leaves the port scan running
+    /// in the background; cancel the token to stop it.
+    fn discover_stream(&self) -> Box<dyn Iterator<Item = DiscoveryRecord>> {
+        if self.sorted_output {
+            return Box::new(self.discover().into_iter());
+        }
+        let cancel = self.cancel.clone().unwrap_or_default();
+        let Some(hosts) = self.arp_hosts(&cancel) else {
+            self.report(ScanPhase::Done, 0, 0, 0);
+            return Box::new(std::iter::empty());
+        };
+        if !self.port_scan_enabled() || cancel.is_cancelled() {
+            self.report(ScanPhase::Done, hosts.len(), hosts.len(), 0);
+            return Box::new(hosts.into_iter());
+        }
+
+        let total = hosts.len();
+        let (scan_ips, stale) = self.port_scan_plan(&hosts);
+        let mut stream = PortScanStream {
+            buffered: VecDeque::new(),
+            finished: None,
+            pending: BTreeMap::new(),
+            cancel: cancel.clone(),
+            emit_closed: self.emit_closed_hosts,
+            progress: self.progress.clone(),
+            done: 0,
+            total,
+            open_ports: 0,
+        };
+        let mut targets = Vec::new();
+        for ((mut r, ip), stale) in hosts.into_iter().zip(scan_ips).zip(stale) {
+            match ip {
+                Some(ip) => {
+                    targets.push((ip, self.port_list()));
+                    stream.pending.insert(ip, r);
+                }
+                None => {
+                    if stale {
+                        r.is_up = Some(false);
+                    }
+                    stream.done += 1;
+                    stream.buffered.push_back(r);
+                }
+            }
+        }
+        stream.report(ScanPhase::PortScan);
 
-                    let timeout = std::time::Duration::from_secs(self.port_timeout_secs);
-                    let port_results = netutils::portscan::scan_host_ports(
-                        ip_addr,
-                        ports_vec,
-                        timeout,
-                        self.port_concurrency,
-                    );
-
-                    let mut out = Vec::new();
-                    let mut any_open = false;
-                    for p in port_results.into_iter() {
-                        if p.open {
-                            any_open = true;
-                            let mut rec = r.clone();
-                            rec.port = Some(p.port);
-                            rec.banner = p.banner.clone();
-                            out.push(rec);
-                        }
+        let (tx, rx) = std::sync::mpsc::channel();
+        let timeout = std::time::Duration::from_secs(self.port_timeout_secs);
+        let (concurrency, strategy) = (self.port_concurrency, self.probe_strategy());
+        let port_reports = self.port_reports.clone();
+        std::thread::spawn(move || {
+            // A failed scan just closes the channel; the stream handles that
+            let _ = netutils::portscan::scan_many_with_cancel(
+                targets,
+                timeout,
+                concurrency,
+                strategy,
+                cancel,
+                |host| {
+                    if let Some(reports) = &port_reports {
+                        let _ = reports.send(host.clone().into());
                     }
+                    let _ = tx.send(host.clone());
+                },
+            );
+        });
+        stream.finished = Some(rx);
+        Box::new(stream)
+    }
+}
+
+/// `LiveArpDiscover::discover_stream` output while the port scan runs on its
+/// own thread.
+struct PortScanStream {
+    buffered: VecDeque<DiscoveryRecord>,
+    finished: Option<Receiver<netutils::portscan::HostPortResults>>,
+    /// scanned hosts whose results have not arrived yet
+    pending: BTreeMap<Ipv4Addr, DiscoveryRecord>,
+    cancel: CancelToken,
+    emit_closed: bool,
+    progress: Option<Sender<ScanProgress>>,
+    done: usize,
+    total: usize,
+    open_ports: usize,
+}
+
+impl PortScanStream {
+    fn report(&self, phase: ScanPhase) {
+        if let Some(tx) = &self.progress {
+            let _ = tx.send(ScanProgress {
+                phase,
+                hosts_done: self.done,
+                hosts_total: self.total,
+                open_ports: self.open_ports,
+            });
+        }
+    }
 
-                    if any_open {
-                        out.into_iter()
-                    } else {
-                        // no open ports; return original host record
-                        vec![r].into_iter()
+    fn host_records(
+        &self,
+        host: DiscoveryRecord,
+        results: Vec<netutils::portscan::PortResult>,
+    ) -> Vec<DiscoveryRecord> {
+        if results.is_empty() && self.cancel.is_cancelled() {
+            // Cancelled before any of its ports was tried
+            return vec![host];
+        }
+        expand_port_results(host, results, self.emit_closed)
+    }
+}
+
+impl Iterator for PortScanStream {
+    type Item = DiscoveryRecord;
+
+    fn next(&mut self) -> Option<DiscoveryRecord> {
+        loop {
+            if let Some(r) = self.buffered.pop_front() {
+                return Some(r);
+            }
+            match self.finished.as_ref().map(|rx| rx.recv()) {
+                Some(Ok((ip, results))) => {
+                    let Some(host) = self.pending.remove(&ip) else {
+                        continue;
+                    };
+                    self.done += 1;
+                    self.open_ports += results.iter().filter(|p| p.open).count();
+                    self.report(ScanPhase::PortScan);
+                    let records = self.host_records(host, results);
+                    self.buffered.extend(records);
+                }
+                // Scan over; hosts it never reported (no runtime) were not
+                // scanned, so they keep `port_scanned` unset
+                _ => {
+                    self.finished = None;
+                    if self.pending.is_empty() {
+                        self.report(ScanPhase::Done);
+                        self.progress = None;
+                        return None;
                     }
-                })
-                .collect(),
-            Err(_) => Vec::new(),
+                    for host in std::mem::take(&mut self.pending).into_values() {
+                        self.done += 1;
+                        self.buffered.push_back(host);
+                    }
+                }
+            }
         }
     }
 }
@@ -221,48 +882,212 @@ impl Discover for SimpleDiscover {
     }
 }
 
+/// How much `ArpSimDiscover` reports while loading a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum Verbosity {
+    /// Nothing
+    #[default]
+    Quiet,
+    /// Record counts, read errors and fields filled per enrichment step
+    Info,
+    /// `Info` plus one line per loaded record
+    Debug,
+}
+
+/// Receives `ArpSimDiscover` log lines.
+pub type Logger = Box<dyn Fn(&str) + Send + Sync>;
+
 /// ArpSimDiscover: load legacy netscan outputs (CSV/JSON) and map them into canonical DiscoveryRecord
-pub struct ArpSimDiscover {}
+///
+/// The `from_*` functions load silently; build an instance with
+/// `with_verbosity` to have `load_csv` / `load_json` report what happened.
+#[derive(Default)]
+pub struct ArpSimDiscover {
+    pub verbosity: Verbosity,
+    /// where log lines go; stderr when None
+    pub logger: Option<Logger>,
+}
 
 impl ArpSimDiscover {
-    /// Load from a CSV file path (netscan-style) and return canonical DiscoveryRecord list.
-    pub fn from_csv<P: AsRef<Path>>(p: P) -> Result<Vec<DiscoveryRecord>, Box<dyn Error>> {
-        let mut recs = read_netscan_csv(p.as_ref().to_str().ok_or("invalid path")?)?;
-        // Enrich with heuristics when enabled
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_verbosity(mut self, v: Verbosity) -> Self {
+        self.verbosity = v;
+        self
+    }
+
+    /// Send log lines to `f` instead of stderr.
+    pub fn with_logger(mut self, f: impl Fn(&str) + Send + Sync + 'static) -> Self {
+        self.logger = Some(Box::new(f));
+        self
+    }
+
+    fn log(&self, level: Verbosity, msg: &str) {
+        if level == Verbosity::Quiet || level > self.verbosity {
+            return;
+        }
+        match &self.logger {
+            Some(f) => f(msg),
+            None => eprintln!("{}", msg),
+        }
+    }
+
+    /// Instance form of `from_csv`, logging at the configured verbosity.
+    pub fn load_csv<P: AsRef<Path>>(&self, p: P) -> Result<Vec<DiscoveryRecord>, Box<dyn Error>> {
+        let path = p.as_ref().to_str().ok_or("invalid path")?;
+        #[allow(unused_mut)]
+        let mut recs = self.read(path, read_netscan_csv(path))?;
+        #[cfg(feature = "enrich")]
+        self.enrich(&mut recs);
+        self.log_records(&recs);
+        Ok(recs)
+    }
+
+    /// Instance form of `from_json`, logging at the configured verbosity.
+    pub fn load_json<P: AsRef<Path>>(&self, p: P) -> Result<Vec<DiscoveryRecord>, Box<dyn Error>> {
+        let path = p.as_ref().to_str().ok_or("invalid path")?;
+        #[allow(unused_mut)]
+        let mut recs = self.read(path, read_netscan_json(path))?;
         #[cfg(feature = "enrich")]
         {
+            self.enrich(&mut recs);
+            let before = recs.iter().filter(|r| r.os.is_some()).count();
             for r in recs.iter_mut() {
-                if r.vendor.is_none() {
-                    if let Some(b) = r.banner.as_deref() {
-                        if let Some(v) = vendor_from_hostname(b) {
-                            r.vendor = Some(v);
-                        }
-                    }
-                }
+                enrich::annotate_os_from_banner(r);
             }
+            let after = recs.iter().filter(|r| r.os.is_some()).count();
+            self.log(
+                Verbosity::Info,
+                &format!("enrich os-from-banner: filled {} fields", after - before),
+            );
         }
+        self.log_records(&recs);
         Ok(recs)
     }
 
+    fn read(
+        &self,
+        path: &str,
+        result: Result<Vec<DiscoveryRecord>, Box<dyn Error>>,
+    ) -> Result<Vec<DiscoveryRecord>, Box<dyn Error>> {
+        match &result {
+            Ok(recs) => self.log(
+                Verbosity::Info,
+                &format!("read {} records from {}", recs.len(), path),
+            ),
+            Err(e) => self.log(Verbosity::Info, &format!("failed to read {}: {}", path, e)),
+        }
+        result
+    }
+
+    #[cfg(feature = "enrich")]
+    fn enrich(&self, recs: &mut [DiscoveryRecord]) {
+        let report = EnrichPipeline::standard().run(recs);
+        for (step, filled) in &report.filled {
+            self.log(
+                Verbosity::Info,
+                &format!("enrich {}: filled {} fields", step, filled),
+            );
+        }
+    }
+
+    fn log_records(&self, recs: &[DiscoveryRecord]) {
+        if self.verbosity < Verbosity::Debug {
+            return;
+        }
+        for r in recs {
+            self.log(
+                Verbosity::Debug,
+                &format!(
+                    "record {} port={:?} mac={:?} vendor={:?} os={:?}",
+                    r.ip, r.port, r.mac, r.vendor, r.os
+                ),
+            );
+        }
+    }
+
+    /// Load from a CSV file path (netscan-style) and return canonical DiscoveryRecord list.
+    /// With the `enrich` feature the standard enrichment pipeline is applied.
+    pub fn from_csv<P: AsRef<Path>>(p: P) -> Result<Vec<DiscoveryRecord>, Box<dyn Error>> {
+        Self::new().load_csv(p)
+    }
+
     /// Load from a JSON file path (netscan-style) and return canonical DiscoveryRecord list.
+    /// With the `enrich` feature the standard enrichment pipeline is applied
+    /// and records without an `os` get one from OS names in their banner.
     pub fn from_json<P: AsRef<Path>>(p: P) -> Result<Vec<DiscoveryRecord>, Box<dyn Error>> {
+        Self::new().load_json(p)
+    }
+
+    /// Like `from_csv` but with an explicit pipeline (`None` skips enrichment).
+    #[cfg(feature = "enrich")]
+    pub fn from_csv_with<P: AsRef<Path>>(
+        p: P,
+        pipeline: Option<&EnrichPipeline>,
+    ) -> Result<Vec<DiscoveryRecord>, Box<dyn Error>> {
+        let mut recs = read_netscan_csv(p.as_ref().to_str().ok_or("invalid path")?)?;
+        if let Some(pipeline) = pipeline {
+            pipeline.run(&mut recs);
+        }
+        Ok(recs)
+    }
+
+    /// Like `from_json` but with an explicit pipeline (`None` skips enrichment).
+    #[cfg(feature = "enrich")]
+    pub fn from_json_with<P: AsRef<Path>>(
+        p: P,
+        pipeline: Option<&EnrichPipeline>,
+    ) -> Result<Vec<DiscoveryRecord>, Box<dyn Error>> {
         let mut recs = read_netscan_json(p.as_ref().to_str().ok_or("invalid path")?)?;
-        #[cfg(feature = "enrich")]
-        {
-            for r in recs.iter_mut() {
-                if r.vendor.is_none() {
-                    if let Some(b) = r.banner.as_deref() {
-                        if let Some(v) = vendor_from_hostname(b) {
-                            r.vendor = Some(v);
-                        }
-                    }
-                }
-            }
+        if let Some(pipeline) = pipeline {
+            pipeline.run(&mut recs);
         }
         Ok(recs)
     }
 }
 
+/// Discover WireGuard peers from `wg show all endpoints` output.
+///
+/// Each line is `<interface> <peer public key> <endpoint>`; peers without a
+/// known endpoint (`(none)`) are skipped. Records carry the endpoint IP and
+/// UDP port with vendor set to `"WireGuard Peer"`.
+#[cfg(feature = "wireguard")]
+pub struct WireGuardPeerDiscover {
+    records: Vec<DiscoveryRecord>,
+}
+
+#[cfg(feature = "wireguard")]
+impl WireGuardPeerDiscover {
+    /// Parse captured `wg show all endpoints` output.
+    pub fn from_wg_output(s: &str) -> Self {
+        let records = s
+            .lines()
+            .filter_map(|line| line.split_whitespace().last())
+            .filter_map(|endpoint| endpoint.parse::<std::net::SocketAddr>().ok())
+            .map(|addr| {
+                DiscoveryRecord::new(
+                    &addr.ip().to_string(),
+                    Some(addr.port()),
+                    None,
+                    None,
+                    Some("WireGuard Peer"),
+                    None,
+                )
+            })
+            .collect();
+        Self { records }
+    }
+}
+
+#[cfg(feature = "wireguard")]
+impl Discover for WireGuardPeerDiscover {
+    fn discover(&self) -> Vec<DiscoveryRecord> {
+        self.records.clone()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -289,4 +1114,255 @@ mod tests {
         assert_eq!(recs[1].ip, "198.51.100.5");
         assert_eq!(recs[1].port, None);
     }
+
+    #[test]
+    fn simple_discover_from_target_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("targets.txt");
+        std::fs::write(&path, "# core\n192.0.2.1\nnas.lan\n").unwrap();
+        let recs = SimpleDiscover::from_target_file(&path).unwrap().discover();
+        let ips: Vec<_> = recs.iter().map(|r| r.ip.as_str()).collect();
+        assert_eq!(ips, ["192.0.2.1", "nas.lan"]);
+        assert!(recs.iter().all(|r| r.port.is_none() && r.mac.is_none()));
+    }
+
+    #[test]
+    fn default_filter_and_take_work_through_dyn() {
+        let items: Vec<SimpleItem> = (1..=5)
+            .map(|i| {
+                let port = if i % 2 == 0 { Some(22) } else { None };
+                (format!("192.0.2.{}", i), port, None, None, None, None)
+            })
+            .collect();
+        let d: Box<dyn Discover> = Box::new(SimpleDiscover::new(items));
+
+        let with_port = d.discover_filtered(&|r| r.port.is_some());
+        assert_eq!(
+            with_port.iter().map(|r| r.ip.as_str()).collect::<Vec<_>>(),
+            vec!["192.0.2.2", "192.0.2.4"]
+        );
+        let pred: Box<dyn Fn(&DiscoveryRecord) -> bool> = Box::new(|r| r.ip.ends_with(".5"));
+        assert_eq!(d.discover_filtered(pred.as_ref()).len(), 1);
+
+        assert_eq!(d.discover_take(3).len(), 3);
+        assert_eq!(d.discover_take(3)[0].ip, "192.0.2.1");
+        assert_eq!(d.discover_take(100).len(), 5);
+        assert!(d.discover_take(0).is_empty());
+    }
+
+    #[test]
+    fn default_stream_yields_discover_output() {
+        let items: Vec<SimpleItem> = (1..=3)
+            .map(|i| (format!("192.0.2.{}", i), None, None, None, None, None))
+            .collect();
+        let d: Box<dyn Discover> = Box::new(SimpleDiscover::new(items));
+        assert_eq!(d.discover_stream().collect::<Vec<_>>(), d.discover());
+    }
+
+    #[cfg(feature = "enrich")]
+    #[test]
+    fn standard_pipeline_labels_randomized_macs() {
+        let mut recs = vec![
+            DiscoveryRecord::new(
+                "192.0.2.10",
+                None,
+                None,
+                Some("da:a1:19:00:11:22"),
+                None,
+                None,
+            ),
+            DiscoveryRecord::new(
+                "192.0.2.11",
+                None,
+                Some("CR1000A.mynetworksettings.com"),
+                Some("02:00:00:00:00:01"),
+                Some("Existing"),
+                None,
+            ),
+        ];
+        EnrichPipeline::standard().run(&mut recs);
+        assert_eq!(
+            recs[0].vendor.as_deref(),
+            Some(enrich::RANDOMIZED_MAC_VENDOR)
+        );
+        // existing vendors are never overwritten
+        assert_eq!(recs[1].vendor.as_deref(), Some("Existing"));
+    }
+
+    fn port(port: u16, open: bool) -> netutils::portscan::PortResult {
+        netutils::portscan::PortResult {
+            port,
+            proto: "tcp".into(),
+            open,
+            state: if open {
+                netutils::portscan::PortState::Open
+            } else {
+                netutils::portscan::PortState::Closed
+            },
+            banner: open.then(|| format!("banner-{port}")),
+            banner_hex: None,
+            rtt_ms: None,
+            service_name: None,
+            tls: None,
+            attempts: 1,
+        }
+    }
+
+    #[test]
+    fn port_expansion_takes_hostname_from_certificate() {
+        let host = DiscoveryRecord::new("192.0.2.20", None, None, None, None, None);
+        let mut named = host.clone();
+        named.hostname = Some("nas.corp".into());
+        let mut https = port(443, true);
+        https.banner = None;
+        https.tls = Some(netutils::portscan::TlsInfo {
+            subject_cn: Some("nas.lan".into()),
+            san: vec!["*.nas.lan".into(), "nas".into()],
+            issuer: "CN=nas.lan".into(),
+            not_after: "2036-01-01T00:00:00Z".into(),
+            self_signed: true,
+        });
+        let mut ssh = port(22, true);
+        ssh.tls = https.tls.clone();
+        let recs = expand_port_results(host, vec![https.clone(), ssh], true);
+        assert_eq!(recs[0].hostname.as_deref(), Some("nas"));
+        assert_eq!(recs[0].banner, None);
+        assert_eq!(recs[0].provenance("hostname").unwrap().source, "tls");
+        // the banner is kept next to the certificate name
+        assert_eq!(recs[1].hostname.as_deref(), Some("nas"));
+        assert_eq!(recs[1].banner.as_deref(), Some("banner-22"));
+        // and a known hostname is not replaced
+        let recs = expand_port_results(named, vec![https], true);
+        assert_eq!(recs[0].hostname.as_deref(), Some("nas.corp"));
+    }
+
+    #[test]
+    fn port_expansion_carries_table_service_name() {
+        let host = DiscoveryRecord::new("192.0.2.20", None, None, None, None, None);
+        let mut ssh = port(22, true);
+        ssh.service_name = Some("ssh".into());
+        let recs = expand_port_results(host, vec![ssh, port(9999, true)], true);
+        assert_eq!(recs[0].service.as_deref(), Some("ssh"));
+        assert_eq!(recs[0].provenance("service").unwrap().source, "port");
+        assert!(recs[1].service.is_none());
+    }
+
+    #[test]
+    fn port_expansion_marks_records_as_scanned() {
+        let host = DiscoveryRecord::new("192.0.2.20", None, None, None, None, None);
+        let recs = expand_port_results(host.clone(), vec![port(22, true), port(23, false)], true);
+        assert_eq!(recs.len(), 1);
+        assert_eq!(recs[0].port, Some(22));
+        assert_eq!(recs[0].banner.as_deref(), Some("banner-22"));
+        assert_eq!(recs[0].port_scanned, Some(true));
+
+        // scanned but closed: distinguishable from a host that was never scanned
+        let closed = expand_port_results(host.clone(), vec![port(23, false)], true);
+        assert_eq!(closed.len(), 1);
+        assert_eq!(closed[0].port, None);
+        assert_eq!(closed[0].port_scanned, Some(true));
+        assert_eq!(host.port_scanned, None);
+
+        assert!(expand_port_results(host, vec![port(23, false)], false).is_empty());
+    }
+
+    #[test]
+    fn hosts_stay_unscanned_when_the_port_scan_never_ran() {
+        let hosts = vec![
+            DiscoveryRecord::new("192.0.2.20", None, None, None, None, None),
+            DiscoveryRecord::new("192.0.2.21", None, None, None, None, None),
+        ];
+        let ips = [Some(Ipv4Addr::new(192, 0, 2, 20)), None];
+        let recs = merge_port_scan(hosts.clone(), &ips, &[false, false], None, true);
+        assert_eq!(recs.len(), 2);
+        assert!(recs.iter().all(|r| r.port_scanned.is_none()));
+
+        let scan = netutils::portscan::PartialScan {
+            results: vec![(Ipv4Addr::new(192, 0, 2, 20), vec![port(23, false)])],
+            timed_out: false,
+            cancelled: false,
+        };
+        let recs = merge_port_scan(hosts, &ips, &[false, false], Some(scan), true);
+        assert_eq!(recs[0].port_scanned, Some(true));
+        assert_eq!(recs[1].port_scanned, None);
+    }
+
+    #[cfg(feature = "wireguard")]
+    #[test]
+    fn wireguard_peers_parsed_from_wg_show() {
+        let out = "wg0\tuH6pF2Y7tq3cBvBhyTqVMbE7JkXXqNFQ4T8pJ0pZc0Y=\t203.0.113.7:51820\n\
+                   wg0\tq0Z9rTQxj1Qm3pZ1k6n1Xb7Gd3sRz0cW2yBf9v1Lh2E=\t(none)\n\
+                   wg1\t3lY8cS0Vx8N2v1kQ7hGfJ2pTz9bWm4aRt6uE5yDq1Hs=\t[2001:db8::5]:41000\n";
+        let recs = WireGuardPeerDiscover::from_wg_output(out).discover();
+        assert_eq!(recs.len(), 2);
+        assert_eq!(recs[0].ip, "203.0.113.7");
+        assert_eq!(recs[0].port, Some(51820));
+        assert_eq!(recs[1].ip, "2001:db8::5");
+        assert_eq!(recs[1].port, Some(41000));
+        for r in &recs {
+            assert!(r.ip.parse::<std::net::IpAddr>().is_ok());
+            assert_eq!(r.vendor.as_deref(), Some("WireGuard Peer"));
+        }
+    }
+
+    #[test]
+    fn overlapping_cidrs_are_deduplicated() {
+        let ip = |last: u8| std::net::Ipv4Addr::new(10, 0, 0, last);
+        let mac = Some([0x00, 0x1b, 0x21, 0x00, 0x00, 0x01]);
+        // 10.0.0.0/29 and 10.0.0.0/30 overlap on .1 and .2
+        let per_cidr = vec![
+            vec![(ip(1), mac), (ip(2), mac), (ip(5), None), (ip(6), mac)],
+            vec![(ip(2), mac), (ip(1), mac)],
+        ];
+        let recs = merge_responders(per_cidr);
+        let ips: Vec<&str> = recs.iter().map(|r| r.ip.as_str()).collect();
+        assert_eq!(ips, ["10.0.0.1", "10.0.0.2", "10.0.0.6"]);
+        assert_eq!(recs[0].mac.as_deref(), Some("00:1b:21:00:00:01"));
+    }
+
+    #[test]
+    fn unreachable_and_invalid_cidrs_yield_nothing() {
+        let recs = discover_parallel_cidrs(
+            &["198.51.100.0/30", "203.0.113.252/30", "not-a-cidr"],
+            4,
+            false,
+            std::time::Duration::from_millis(50),
+        );
+        assert!(recs.is_empty());
+    }
+
+    #[test]
+    fn live_discover_reports_progress() {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let d = LiveArpDiscover::new("192.168.254.0/30")
+            .with_workers(2)
+            .with_progress(tx);
+        let recs = d.discover();
+        drop(d);
+        let updates: Vec<ScanProgress> = rx.iter().collect();
+        let arp: Vec<_> = updates
+            .iter()
+            .filter(|p| p.phase == ScanPhase::Arp)
+            .map(|p| (p.hosts_done, p.hosts_total))
+            .collect();
+        assert_eq!(arp, [(1, 2), (2, 2)]);
+        let last = updates.last().unwrap();
+        assert_eq!(last.phase, ScanPhase::Done);
+        assert_eq!(last.hosts_done, recs.len());
+    }
+
+    #[test]
+    fn sorted_output_is_ascending_by_ip() {
+        let recs = LiveArpDiscover::new("192.168.254.0/28")
+            .with_workers(7)
+            .with_host_order(HostOrder::Shuffled)
+            .with_sorted_output(true)
+            .discover();
+        let ips: Vec<u32> = recs
+            .iter()
+            .map(|r| u32::from(r.ip.parse::<std::net::Ipv4Addr>().unwrap()))
+            .collect();
+        assert_eq!(ips.len(), 14);
+        assert!(ips.windows(2).all(|w| w[0] < w[1]));
+    }
 }
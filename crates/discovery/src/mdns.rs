@@ -0,0 +1,347 @@
+//! mDNS / DNS-SD discovery (`mdns` feature).
+//!
+//! `MdnsDiscover` multicasts the DNS-SD meta-query
+//! (`_services._dns-sd._udp.local`), asks for the instances of every service
+//! type that answers, and turns each instance into a `DiscoveryRecord`. Only
+//! the PTR, SRV and A records needed for that are parsed.
+
+use crate::Discover;
+use formats::DiscoveryRecord;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr, UdpSocket};
+use std::time::{Duration, Instant};
+
+const MDNS_MULTICAST: (Ipv4Addr, u16) = (Ipv4Addr::new(224, 0, 0, 251), 5353);
+/// DNS-SD meta-query name: its PTR records list the service types present.
+pub const SERVICES_META_QUERY: &str = "_services._dns-sd._udp.local";
+
+const TYPE_A: u16 = 1;
+const TYPE_PTR: u16 = 12;
+const TYPE_SRV: u16 = 33;
+const CLASS_IN: u16 = 1;
+/// Ask for a unicast reply (RFC 6762 section 5.4)
+const QU_BIT: u16 = 0x8000;
+/// Compression pointers followed per name before giving up
+const MAX_POINTERS: usize = 16;
+
+/// Resource records `MdnsDiscover` reads from a response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MdnsRecord {
+    /// `name` (a service type, or the meta-query) points at `target`
+    Ptr { name: String, target: String },
+    /// Instance `name` is served on `target:port`
+    Srv {
+        name: String,
+        port: u16,
+        target: String,
+    },
+    /// Host `name` has address `addr`
+    A { name: String, addr: Ipv4Addr },
+}
+
+/// Build a PTR query for `name` asking for a unicast reply.
+pub fn build_ptr_query(name: &str) -> Vec<u8> {
+    // id 0, no flags, one question
+    let mut q = vec![0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0];
+    for label in name.trim_end_matches('.').split('.') {
+        q.push(label.len() as u8);
+        q.extend_from_slice(label.as_bytes());
+    }
+    q.push(0);
+    q.extend_from_slice(&TYPE_PTR.to_be_bytes());
+    q.extend_from_slice(&(CLASS_IN | QU_BIT).to_be_bytes());
+    q
+}
+
+/// Parse the answer, authority and additional sections of an mDNS response,
+/// keeping PTR, SRV and A records. Returns None for queries and malformed packets.
+pub fn parse_mdns_response(packet: &[u8]) -> Option<Vec<MdnsRecord>> {
+    let header = packet.get(..12)?;
+    let field = |i: usize| u16::from_be_bytes([header[i], header[i + 1]]);
+    // QR bit: responses only
+    if field(2) & 0x8000 == 0 {
+        return None;
+    }
+    let questions = field(4);
+    let records = usize::from(field(6)) + usize::from(field(8)) + usize::from(field(10));
+
+    let mut pos = 12;
+    for _ in 0..questions {
+        pos = read_name(packet, pos)?.1 + 4;
+    }
+    let mut out = Vec::new();
+    for _ in 0..records {
+        let (name, after) = read_name(packet, pos)?;
+        let fixed = packet.get(after..after + 10)?;
+        let rtype = u16::from_be_bytes([fixed[0], fixed[1]]);
+        let rdlen = usize::from(u16::from_be_bytes([fixed[8], fixed[9]]));
+        let rdata = after + 10;
+        let data = packet.get(rdata..rdata + rdlen)?;
+        match rtype {
+            TYPE_PTR => out.push(MdnsRecord::Ptr {
+                name,
+                target: read_name(packet, rdata)?.0,
+            }),
+            TYPE_SRV if rdlen >= 7 => out.push(MdnsRecord::Srv {
+                name,
+                port: u16::from_be_bytes([data[4], data[5]]),
+                target: read_name(packet, rdata + 6)?.0,
+            }),
+            TYPE_A if rdlen == 4 => out.push(MdnsRecord::A {
+                name,
+                addr: Ipv4Addr::new(data[0], data[1], data[2], data[3]),
+            }),
+            _ => {}
+        }
+        pos = rdata + rdlen;
+    }
+    Some(out)
+}
+
+/// Read a (possibly compressed) name at `pos`; returns the dotted name and
+/// the offset just past it in the original position.
+fn read_name(packet: &[u8], mut pos: usize) -> Option<(String, usize)> {
+    let mut labels = Vec::new();
+    let mut end = None;
+    let mut pointers = 0;
+    loop {
+        let len = *packet.get(pos)?;
+        match len {
+            0 => break,
+            l if l & 0xc0 == 0xc0 => {
+                pointers += 1;
+                if pointers > MAX_POINTERS {
+                    return None;
+                }
+                let target = usize::from(u16::from_be_bytes([l & 0x3f, *packet.get(pos + 1)?]));
+                end.get_or_insert(pos + 2);
+                pos = target;
+            }
+            l => {
+                let label = packet.get(pos + 1..pos + 1 + usize::from(l))?;
+                labels.push(String::from_utf8_lossy(label).into_owned());
+                pos += 1 + usize::from(l);
+            }
+        }
+    }
+    Some((labels.join("."), end.unwrap_or(pos + 1)))
+}
+
+/// Group the instances described by `packets` (source address, records) by
+/// service type. Types listed by the meta-query appear even when none of
+/// their instances resolved. An instance needs an SRV record; its address
+/// comes from the A record of the SRV target, else the packet's source.
+pub fn group_services(
+    packets: &[(Ipv4Addr, Vec<MdnsRecord>)],
+) -> Vec<(String, Vec<DiscoveryRecord>)> {
+    let key = |s: &str| s.trim_end_matches('.').to_ascii_lowercase();
+    let mut types: BTreeMap<String, Vec<DiscoveryRecord>> = BTreeMap::new();
+    let mut srv: HashMap<String, (Ipv4Addr, u16, String)> = HashMap::new();
+    let mut hosts: HashMap<String, Ipv4Addr> = HashMap::new();
+    let mut instances: Vec<(String, String)> = Vec::new();
+    for (src, records) in packets {
+        for rec in records {
+            match rec {
+                MdnsRecord::Ptr { name, target } if key(name) == SERVICES_META_QUERY => {
+                    types.entry(service_type(target)).or_default();
+                }
+                MdnsRecord::Ptr { name, target } => {
+                    instances.push((service_type(name), target.clone()))
+                }
+                MdnsRecord::Srv { name, port, target } => {
+                    srv.insert(key(name), (*src, *port, key(target)));
+                }
+                MdnsRecord::A { name, addr } => {
+                    hosts.insert(key(name), *addr);
+                }
+            }
+        }
+    }
+
+    let mut seen = HashSet::new();
+    for (stype, instance) in instances {
+        let Some((src, port, target)) = srv.get(&key(&instance)) else {
+            continue;
+        };
+        if !seen.insert(key(&instance)) {
+            continue;
+        }
+        let ip = hosts.get(target).unwrap_or(src);
+        let label = instance
+            .strip_suffix(&format!(".{}.local", stype))
+            .unwrap_or(&instance);
+        let mut r =
+            DiscoveryRecord::new(&ip.to_string(), Some(*port), Some(label), None, None, None);
+        r.service = Some(service_label(&stype).to_string());
+        types.entry(stype).or_default().push(r);
+    }
+    types.into_iter().collect()
+}
+
+/// `_http._tcp.local` -> `_http._tcp`
+fn service_type(name: &str) -> String {
+    let name = name.trim_end_matches('.');
+    name.strip_suffix(".local").unwrap_or(name).to_string()
+}
+
+/// `_http._tcp` -> `http`
+fn service_label(stype: &str) -> &str {
+    stype
+        .split('.')
+        .next()
+        .unwrap_or(stype)
+        .trim_start_matches('_')
+}
+
+/// DNS-SD browser over multicast DNS.
+pub struct MdnsDiscover {
+    /// how long to listen for responses
+    pub timeout: Duration,
+}
+
+impl MdnsDiscover {
+    pub fn new(timeout: Duration) -> Self {
+        Self { timeout }
+    }
+
+    /// Browse for `timeout` and return the service types on the network
+    /// (`_http._tcp`, `_airplay._tcp`, ...), sorted, each with its instances
+    /// (port from SRV, instance name as banner, `service` set to the bare
+    /// type such as `http`). Socket errors yield an empty list.
+    pub fn enumerate_services(timeout: Duration) -> Vec<(String, Vec<DiscoveryRecord>)> {
+        group_services(&browse(timeout))
+    }
+}
+
+impl Discover for MdnsDiscover {
+    fn discover(&self) -> Vec<DiscoveryRecord> {
+        Self::enumerate_services(self.timeout)
+            .into_iter()
+            .flat_map(|(_, records)| records)
+            .collect()
+    }
+}
+
+/// Send the meta-query, follow up with a PTR query for each service type
+/// announced, and collect every response until `timeout` elapses.
+fn browse(timeout: Duration) -> Vec<(Ipv4Addr, Vec<MdnsRecord>)> {
+    let Ok(socket) = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)) else {
+        return Vec::new();
+    };
+    if socket
+        .send_to(&build_ptr_query(SERVICES_META_QUERY), MDNS_MULTICAST)
+        .is_err()
+    {
+        return Vec::new();
+    }
+
+    let deadline = Instant::now() + timeout;
+    let mut queried = HashSet::new();
+    let mut out = Vec::new();
+    let mut buf = [0u8; 9000];
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() || socket.set_read_timeout(Some(remaining)).is_err() {
+            break;
+        }
+        let (n, src) = match socket.recv_from(&mut buf) {
+            Ok((n, SocketAddr::V4(src))) => (n, src),
+            Ok(_) => continue,
+            Err(e)
+                if e.kind() == io::ErrorKind::WouldBlock || e.kind() == io::ErrorKind::TimedOut =>
+            {
+                break
+            }
+            Err(_) => continue,
+        };
+        let Some(records) = parse_mdns_response(&buf[..n]) else {
+            continue;
+        };
+        for rec in &records {
+            if let MdnsRecord::Ptr { name, target } = rec {
+                if name.eq_ignore_ascii_case(SERVICES_META_QUERY)
+                    && queried.insert(target.to_ascii_lowercase())
+                {
+                    let _ = socket.send_to(&build_ptr_query(target), MDNS_MULTICAST);
+                }
+            }
+        }
+        out.push((*src.ip(), records));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SERVICES: &[u8] = include_bytes!("../tests/fixtures/mdns_services.bin");
+    const AIRPLAY: &[u8] = include_bytes!("../tests/fixtures/mdns_airplay.bin");
+    const HTTP: &[u8] = include_bytes!("../tests/fixtures/mdns_http.bin");
+
+    #[test]
+    fn parses_compressed_ptr_srv_and_a_records() {
+        let records = parse_mdns_response(AIRPLAY).expect("response");
+        assert_eq!(
+            records,
+            [
+                MdnsRecord::Ptr {
+                    name: "_airplay._tcp.local".into(),
+                    target: "Living Room._airplay._tcp.local".into(),
+                },
+                MdnsRecord::Srv {
+                    name: "Living Room._airplay._tcp.local".into(),
+                    port: 7000,
+                    target: "living-room.local".into(),
+                },
+                MdnsRecord::A {
+                    name: "living-room.local".into(),
+                    addr: Ipv4Addr::new(192, 168, 1, 60),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_queries_truncation_and_pointer_loops() {
+        assert_eq!(
+            parse_mdns_response(&build_ptr_query(SERVICES_META_QUERY)),
+            None
+        );
+        assert_eq!(parse_mdns_response(&AIRPLAY[..AIRPLAY.len() - 3]), None);
+        // answer whose name points at itself
+        let mut looped = vec![0, 0, 0x84, 0, 0, 0, 0, 1, 0, 0, 0, 0];
+        looped.extend_from_slice(&[0xc0, 12]);
+        assert_eq!(parse_mdns_response(&looped), None);
+    }
+
+    #[test]
+    fn groups_instances_by_service_type() {
+        let src_tv = Ipv4Addr::new(192, 168, 1, 60);
+        let src_printer = Ipv4Addr::new(192, 168, 1, 70);
+        let packets = vec![
+            (src_tv, parse_mdns_response(SERVICES).unwrap()),
+            (src_tv, parse_mdns_response(AIRPLAY).unwrap()),
+            // the same announcement twice is one instance
+            (src_tv, parse_mdns_response(AIRPLAY).unwrap()),
+            (src_printer, parse_mdns_response(HTTP).unwrap()),
+        ];
+        let services = group_services(&packets);
+        let types: Vec<&str> = services.iter().map(|(t, _)| t.as_str()).collect();
+        assert_eq!(types, ["_airplay._tcp", "_http._tcp", "_raop._tcp"]);
+
+        let tv = &services[0].1;
+        assert_eq!(tv.len(), 1);
+        assert_eq!(tv[0].ip, "192.168.1.60");
+        assert_eq!(tv[0].port, Some(7000));
+        assert_eq!(tv[0].banner.as_deref(), Some("Living Room"));
+        assert_eq!(tv[0].service.as_deref(), Some("airplay"));
+
+        // no A record: the responder's own address is used
+        let printer = &services[1].1;
+        assert_eq!(printer[0].ip, "192.168.1.70");
+        assert_eq!(printer[0].banner.as_deref(), Some("Office Printer"));
+        assert!(services[2].1.is_empty());
+    }
+}
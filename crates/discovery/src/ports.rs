@@ -1,3 +1,7 @@
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+
 /// Return the builtin thorough ports list (1..=1024).
 pub fn builtin_ports() -> Vec<u16> {
     (1u16..=1024u16).collect()
@@ -82,6 +86,32 @@ pub fn parse_port_list(s: &str) -> Vec<u16> {
     out
 }
 
+/// Drop every port in `exclusions` from `ports`, keeping the order of the
+/// rest. Apply it after the include list is resolved, so "1-1024 except
+/// 515,9100" works whatever the include list came from.
+pub fn apply_exclusions(ports: Vec<u16>, exclusions: &[u16]) -> Vec<u16> {
+    if exclusions.is_empty() {
+        return ports;
+    }
+    ports
+        .into_iter()
+        .filter(|p| !exclusions.contains(p))
+        .collect()
+}
+
+/// `ports` in shuffled order (Fisher-Yates over a `StdRng`), so a sweep
+/// does not walk the list in sequence. `Some(seed)` always gives the same
+/// permutation; `None` seeds from `rand::thread_rng`.
+pub fn generate_random_port_order(ports: &[u16], seed: Option<u64>) -> Vec<u16> {
+    let mut rng = match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_rng(rand::thread_rng()).expect("thread_rng never fails"),
+    };
+    let mut out = ports.to_vec();
+    out.shuffle(&mut rng);
+    out
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -116,6 +146,36 @@ mod tests {
         assert!(v.contains(&65535));
     }
 
+    #[test]
+    fn exclusions_remove_ports_from_any_include_list() {
+        let excluded = parse_port_list("515,9100");
+        let builtin = apply_exclusions(builtin_ports(), &excluded);
+        assert_eq!(builtin.len(), 1023);
+        assert!(!builtin.contains(&515));
+        assert_eq!(
+            apply_exclusions(vec![9100, 22, 515, 80], &excluded),
+            vec![22, 80]
+        );
+        assert_eq!(apply_exclusions(vec![22, 80], &[]), vec![22, 80]);
+    }
+
+    #[test]
+    fn random_port_order_is_reproducible_with_a_seed() {
+        let ports = [1, 2, 3, 4, 5];
+        let first = generate_random_port_order(&ports, Some(2024));
+        for _ in 0..10 {
+            assert_eq!(generate_random_port_order(&ports, Some(2024)), first);
+        }
+        let mut sorted = first.clone();
+        sorted.sort_unstable();
+        assert_eq!(sorted, ports);
+
+        let unseeded: std::collections::HashSet<Vec<u16>> = (0..20)
+            .map(|_| generate_random_port_order(&ports, None))
+            .collect();
+        assert!(unseeded.len() > 1, "{:?}", unseeded);
+    }
+
     #[test]
     fn empty_or_all_invalid_returns_empty() {
         let v = parse_port_list("");
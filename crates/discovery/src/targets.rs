@@ -0,0 +1,42 @@
+//! Target lists: a flat file of IPs or hostnames, one per line, for the
+//! "scan exactly these hosts" workflow where there is no CIDR.
+
+use std::io;
+use std::path::Path;
+
+/// Read targets from `path`: one IP or hostname per line, trimmed. Blank
+/// lines and `#` comments (whole-line or trailing) are skipped; order and
+/// duplicates are kept as written.
+pub fn targets_from_file<P: AsRef<Path>>(path: P) -> io::Result<Vec<String>> {
+    Ok(parse_targets(&std::fs::read_to_string(path)?))
+}
+
+/// `targets_from_file` on text already in memory.
+pub fn parse_targets(text: &str) -> Vec<String> {
+    text.lines()
+        .map(|line| line.split('#').next().unwrap_or("").trim())
+        .filter(|t| !t.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn comments_and_blanks_are_skipped() {
+        let text =
+            "# lab hosts\n192.0.2.1\n\n  printer.lan  \n192.0.2.9 # switch\n\t\n#192.0.2.50\n";
+        assert_eq!(
+            parse_targets(text),
+            ["192.0.2.1", "printer.lan", "192.0.2.9"]
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("targets.txt");
+        std::fs::write(&path, text).unwrap();
+        assert_eq!(targets_from_file(&path).unwrap().len(), 3);
+        assert!(targets_from_file(dir.path().join("missing.txt")).is_err());
+    }
+}
@@ -0,0 +1,127 @@
+//! TCP-ping host discovery for ranges ARP cannot reach.
+//!
+//! `TcpPingDiscover` sweeps a CIDR with `netutils::portscan::tcp_ping_sweep`:
+//! a host is up when any probe port accepts or refuses a connect. Works
+//! across routers and needs no privileges, but hosts that drop every probe
+//! are indistinguishable from empty addresses.
+
+use crate::Discover;
+use formats::DiscoveryRecord;
+use netutils::cidrsniffer::CidrScanOptions;
+use netutils::portscan::TCP_PING_PORTS;
+use std::time::Duration;
+
+/// Host discovery over a CIDR by TCP connect, without ARP.
+pub struct TcpPingDiscover {
+    pub cidr: String,
+    /// ports tried on every host; the first answer marks it up
+    pub probe_ports: Vec<u16>,
+    /// per-host timeout
+    pub timeout: Duration,
+    /// hosts probed at once
+    pub concurrency: usize,
+    /// also emit hosts that did not answer (`is_up = Some(false)`)
+    pub emit_down: bool,
+}
+
+impl TcpPingDiscover {
+    pub fn new<S: Into<String>>(cidr: S) -> Self {
+        Self {
+            cidr: cidr.into(),
+            probe_ports: TCP_PING_PORTS.to_vec(),
+            timeout: Duration::from_millis(500),
+            concurrency: 64,
+            emit_down: false,
+        }
+    }
+
+    /// Replace the default probe ports (80, 443, 22, 445).
+    pub fn with_probe_ports(mut self, ports: Vec<u16>) -> Self {
+        self.probe_ports = ports;
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_concurrency(mut self, c: usize) -> Self {
+        self.concurrency = c;
+        self
+    }
+
+    pub fn with_emit_down(mut self, emit: bool) -> Self {
+        self.emit_down = emit;
+        self
+    }
+}
+
+impl Discover for TcpPingDiscover {
+    /// One host record per answering address (`is_up = Some(true)`), in
+    /// address order. An invalid or oversized CIDR yields no records.
+    fn discover(&self) -> Vec<DiscoveryRecord> {
+        let Ok(ips) = netutils::cidrsniffer::cidr_hosts(&self.cidr, CidrScanOptions::default())
+        else {
+            return Vec::new();
+        };
+        let swept = netutils::portscan::tcp_ping_sweep(
+            ips,
+            &self.probe_ports,
+            self.timeout,
+            self.concurrency,
+        )
+        .unwrap_or_default();
+        swept
+            .into_iter()
+            .filter(|(_, up, _)| *up || self.emit_down)
+            .map(|(ip, up, _)| {
+                let mut r = DiscoveryRecord::new(&ip.to_string(), None, None, None, None, None);
+                r.is_up = Some(up);
+                r
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{Ipv4Addr, TcpListener};
+
+    fn local_discover(ports: Vec<u16>) -> TcpPingDiscover {
+        TcpPingDiscover::new("127.0.0.1/32")
+            .with_probe_ports(ports)
+            .with_timeout(Duration::from_millis(300))
+    }
+
+    #[test]
+    fn listening_port_marks_host_up() {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).expect("bind");
+        let port = listener.local_addr().unwrap().port();
+        let records = local_discover(vec![port]).discover();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].ip, "127.0.0.1");
+        assert_eq!(records[0].is_up, Some(true));
+    }
+
+    #[test]
+    fn refused_port_marks_host_up() {
+        let port = {
+            let l = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).expect("bind");
+            l.local_addr().unwrap().port()
+        };
+        let records = local_discover(vec![port]).discover();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].is_up, Some(true));
+    }
+
+    #[test]
+    fn silent_hosts_are_dropped_unless_requested() {
+        assert!(local_discover(vec![]).discover().is_empty());
+        let down = local_discover(vec![]).with_emit_down(true).discover();
+        assert_eq!(down.len(), 1);
+        assert_eq!(down[0].is_up, Some(false));
+        assert!(TcpPingDiscover::new("not-a-cidr").discover().is_empty());
+    }
+}
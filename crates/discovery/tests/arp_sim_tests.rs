@@ -18,3 +18,96 @@ fn load_golden_csv_via_arp_sim() {
     let recs = ArpSimDiscover::from_json(json_path).expect("read json golden");
     assert!(!recs.is_empty());
 }
+
+#[cfg(feature = "enrich")]
+#[test]
+fn arp_sim_uses_supplied_enrich_pipeline() {
+    use enrich::{EnrichPipeline, HostnameVendorEnricher};
+
+    let dir = std::env::temp_dir().join(format!("arp-sim-pipeline-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let csv = dir.join("hosts.csv");
+    std::fs::write(
+        &csv,
+        "Timestamp,IP,MAC,Hostname,Vendor,OS\n\
+         2025-11-02T12:00:00Z,192.0.2.5,00:0c:29:aa:bb:cc,netgear-router,,\n",
+    )
+    .unwrap();
+
+    let plain = ArpSimDiscover::from_csv_with(&csv, None).expect("read csv");
+    assert!(plain[0].vendor.is_none());
+
+    let hostname_only = EnrichPipeline::new().with(HostnameVendorEnricher);
+    let recs = ArpSimDiscover::from_csv_with(&csv, Some(&hostname_only)).expect("read csv");
+    assert_eq!(recs[0].vendor.as_deref(), Some("Netgear"));
+
+    // default pipeline prefers the OUI vendor over hostname heuristics
+    let recs = ArpSimDiscover::from_csv(&csv).expect("read csv");
+    assert!(recs[0].vendor.as_deref().unwrap().contains("VMware"));
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[cfg(feature = "enrich")]
+#[test]
+fn arp_sim_json_fills_os_from_banners() {
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let json = tmp.path().join("hosts.json");
+    std::fs::write(
+        &json,
+        r#"[
+  {"IP": "192.0.2.5", "ports": [22], "banners": ["SSH-2.0-OpenSSH_9.2p1 Debian-2+deb12u2"]},
+  {"IP": "192.0.2.6", "ports": [80], "banners": ["Server: Microsoft-IIS/10.0"]},
+  {"IP": "192.0.2.7", "Hostname": "printer.lan"}
+]"#,
+    )
+    .unwrap();
+
+    let recs = ArpSimDiscover::from_json(&json).expect("read json");
+    assert_eq!(recs[0].os.as_deref(), Some("Debian"));
+    assert_eq!(recs[0].provenance("os").unwrap().source, "banner");
+    assert_eq!(recs[1].os.as_deref(), Some("Windows"));
+    assert!(recs[2].os.is_none());
+}
+
+#[test]
+fn arp_sim_logs_to_supplied_logger() {
+    use discovery::Verbosity;
+    use std::sync::{Arc, Mutex};
+
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let csv = tmp.path().join("hosts.csv");
+    std::fs::write(
+        &csv,
+        "Timestamp,IP,MAC,Hostname,Vendor,OS\n\
+         2025-11-02T12:00:00Z,192.0.2.5,00:0c:29:aa:bb:cc,,,\n\
+         2025-11-02T12:00:01Z,192.0.2.6,,,,\n",
+    )
+    .unwrap();
+
+    let lines = Arc::new(Mutex::new(Vec::<String>::new()));
+    let sink = lines.clone();
+    let loader = ArpSimDiscover::new()
+        .with_verbosity(Verbosity::Debug)
+        .with_logger(move |msg| sink.lock().unwrap().push(msg.to_string()));
+    let recs = loader.load_csv(&csv).expect("read csv");
+    assert_eq!(recs.len(), 2);
+    let logged = lines.lock().unwrap().clone();
+    assert!(logged[0].starts_with("read 2 records from"), "{:?}", logged);
+    assert_eq!(
+        logged.iter().filter(|l| l.starts_with("record ")).count(),
+        2
+    );
+
+    lines.lock().unwrap().clear();
+    assert!(loader.load_csv(tmp.path().join("missing.csv")).is_err());
+    assert!(lines.lock().unwrap()[0].starts_with("failed to read"));
+
+    // Quiet (the default) never calls the logger
+    let quiet_sink = lines.clone();
+    lines.lock().unwrap().clear();
+    ArpSimDiscover::new()
+        .with_logger(move |msg| quiet_sink.lock().unwrap().push(msg.to_string()))
+        .load_csv(&csv)
+        .expect("read csv");
+    assert!(lines.lock().unwrap().is_empty());
+}
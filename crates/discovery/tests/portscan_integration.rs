@@ -22,7 +22,8 @@ fn detect_local_tcp_listener() {
         _ => panic!("expected ipv4 local addr"),
     };
     let ports = vec![addr.port()];
-    let res = portscan::scan_host_ports(ip, ports, Duration::from_secs(2), 2);
+    let res =
+        portscan::scan_host_ports(ip, ports, Duration::from_secs(2), 2, None).expect("runtime");
     assert_eq!(res.len(), 1);
     assert!(res[0].open);
     assert_eq!(res[0].port, addr.port());
@@ -32,3 +33,252 @@ fn detect_local_tcp_listener() {
         .map(|s| s.contains("CLI-TEST"))
         .unwrap_or(false));
 }
+
+#[test]
+fn portscan_filter_skips_rejected_hosts() {
+    use discovery::{Discover, LiveArpDiscover};
+
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).expect("bind");
+    let port = listener.local_addr().unwrap().port();
+    thread::spawn(move || for _ in listener.incoming() {});
+
+    let discover = |accept: bool| {
+        LiveArpDiscover::new("127.0.0.1/32")
+            .with_portscan(true)
+            .with_ports(Some(vec![port]))
+            .with_portscan_filter(Box::new(move |r| accept && r.ip == "127.0.0.1"))
+            .discover()
+    };
+
+    let scanned = discover(true);
+    assert_eq!(scanned.len(), 1);
+    assert_eq!(scanned[0].port, Some(port));
+
+    let skipped = discover(false);
+    assert_eq!(skipped.len(), 1);
+    assert_eq!(skipped[0].port, None);
+    assert_eq!(skipped[0].port_scanned, None);
+}
+
+#[test]
+fn cancel_token_stops_port_scan_with_partial_records() {
+    use discovery::{Discover, LiveArpDiscover};
+    use netutils::CancelToken;
+
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).expect("bind");
+    let port = listener.local_addr().unwrap().port();
+    thread::spawn(move || for _ in listener.incoming() {});
+
+    let cancel = CancelToken::new();
+    let trigger = cancel.clone();
+    thread::spawn(move || {
+        thread::sleep(Duration::from_millis(300));
+        trigger.cancel();
+    });
+    // 30 probes at 20/s would take 1.5 s
+    let records = LiveArpDiscover::new("127.0.0.1/32")
+        .with_portscan(true)
+        .with_ports(Some(vec![port; 30]))
+        .with_scan_rate(20)
+        .with_cancel_token(cancel)
+        .discover();
+    assert!(
+        !records.is_empty() && records.len() < 30,
+        "{}",
+        records.len()
+    );
+    assert!(records.iter().all(|r| r.port == Some(port)));
+}
+
+#[test]
+fn liveness_check_passes_answering_host_to_port_scan() {
+    use discovery::{Discover, LiveArpDiscover};
+
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).expect("bind");
+    let port = listener.local_addr().unwrap().port();
+    thread::spawn(move || for _ in listener.incoming() {});
+
+    // Loopback refuses the liveness ports it doesn't listen on, which counts
+    let records = LiveArpDiscover::new("127.0.0.1/32")
+        .with_portscan(true)
+        .with_ports(Some(vec![port]))
+        .with_liveness_check(true)
+        .with_liveness_timeout_ms(200)
+        .discover();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].port, Some(port));
+    assert_ne!(records[0].is_up, Some(false));
+}
+
+#[test]
+fn stream_yields_port_records_and_final_progress() {
+    use discovery::{Discover, LiveArpDiscover, ScanPhase};
+
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).expect("bind");
+    let port = listener.local_addr().unwrap().port();
+    thread::spawn(move || for _ in listener.incoming() {});
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let discover = LiveArpDiscover::new("127.0.0.1/32")
+        .with_portscan(true)
+        .with_ports(Some(vec![port, port]))
+        .with_progress(tx);
+    let streamed: Vec<_> = discover.discover_stream().collect();
+    assert_eq!(streamed.len(), 2);
+    assert!(streamed
+        .iter()
+        .all(|r| r.port == Some(port) && r.port_scanned == Some(true)));
+
+    let last = rx.try_iter().last().expect("progress");
+    assert_eq!(last.phase, ScanPhase::Done);
+    assert_eq!((last.hosts_done, last.hosts_total), (1, 1));
+    assert_eq!(discover.discover(), streamed);
+}
+
+#[test]
+fn banner_grab_can_be_turned_off() {
+    use discovery::{Discover, LiveArpDiscover};
+
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).expect("bind");
+    let port = listener.local_addr().unwrap().port();
+    thread::spawn(move || {
+        for mut s in listener.incoming().flatten() {
+            use std::io::Write;
+            let _ = s.write_all(b"HELLO\n");
+        }
+    });
+
+    let scan = |grab: bool| {
+        LiveArpDiscover::new("127.0.0.1/32")
+            .with_portscan(true)
+            .with_ports(Some(vec![port]))
+            .with_banner_timeout(Duration::from_secs(1))
+            .with_banner_grab(grab)
+            .discover()
+    };
+    assert_eq!(scan(true)[0].banner.as_deref(), Some("HELLO"));
+    let bare = scan(false);
+    assert_eq!(bare[0].port, Some(port));
+    assert_eq!(bare[0].banner, None);
+}
+
+#[test]
+fn excluded_ports_never_show_up() {
+    use discovery::{Discover, LiveArpDiscover};
+
+    let open = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).expect("bind");
+    let excluded = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).expect("bind");
+    let (port, skip) = (
+        open.local_addr().unwrap().port(),
+        excluded.local_addr().unwrap().port(),
+    );
+    thread::spawn(move || for _ in open.incoming() {});
+    thread::spawn(move || for _ in excluded.incoming() {});
+
+    let records = LiveArpDiscover::new("127.0.0.1/32")
+        .with_portscan(true)
+        .with_ports(Some(vec![skip, port]))
+        .with_excluded_ports(vec![skip])
+        .discover();
+    assert_eq!(
+        records.iter().map(|r| r.port).collect::<Vec<_>>(),
+        [Some(port)]
+    );
+}
+
+#[test]
+fn randomized_port_order_reports_ports_sorted() {
+    use discovery::{Discover, LiveArpDiscover};
+
+    let listeners: Vec<TcpListener> = (0..4)
+        .map(|_| TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).expect("bind"))
+        .collect();
+    let mut ports: Vec<u16> = listeners
+        .iter()
+        .map(|l| l.local_addr().unwrap().port())
+        .collect();
+    ports.reverse();
+
+    let records = LiveArpDiscover::new("127.0.0.1/32")
+        .with_portscan(true)
+        .with_ports(Some(ports.clone()))
+        .with_banner_grab(false)
+        .with_randomized_ports(Some(99))
+        .discover();
+    ports.sort_unstable();
+    assert_eq!(
+        records.iter().map(|r| r.port).collect::<Vec<_>>(),
+        ports.into_iter().map(Some).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn port_reports_keep_closed_ports() {
+    use discovery::{Discover, LiveArpDiscover};
+    use netutils::portscan::PortState;
+
+    let open = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).expect("bind");
+    let port = open.local_addr().unwrap().port();
+    let closed = {
+        let l = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).expect("bind");
+        l.local_addr().unwrap().port()
+    };
+    thread::spawn(move || for _ in open.incoming() {});
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let records = LiveArpDiscover::new("127.0.0.1/32")
+        .with_portscan(true)
+        .with_ports(Some(vec![port, closed]))
+        .with_banner_grab(false)
+        .with_port_reports(tx)
+        .discover();
+    assert_eq!(records.len(), 1);
+
+    let reports: Vec<_> = rx.iter().collect();
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0].ip, Ipv4Addr::LOCALHOST);
+    let states: Vec<_> = reports[0]
+        .results
+        .iter()
+        .map(|r| (r.port, r.state))
+        .collect();
+    assert_eq!(
+        states,
+        [(port, PortState::Open), (closed, PortState::Closed)]
+    );
+}
+
+#[test]
+fn port_scan_connects_from_the_source_ip() {
+    use discovery::{Discover, LiveArpDiscover};
+
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).expect("bind");
+    let port = listener.local_addr().unwrap().port();
+    let (tx, rx) = std::sync::mpsc::channel();
+    thread::spawn(move || {
+        for s in listener.incoming().flatten() {
+            let _ = tx.send(s.peer_addr().unwrap().ip());
+        }
+    });
+    let scan = |source: Ipv4Addr| {
+        LiveArpDiscover::new("127.0.0.1/32")
+            .with_portscan(true)
+            .with_ports(Some(vec![port]))
+            .with_banner_grab(false)
+            .with_source_ip(source)
+            .discover()
+    };
+
+    let records = scan(Ipv4Addr::new(127, 0, 0, 2));
+    assert_eq!(records[0].port, Some(port));
+    let peer = rx.recv_timeout(Duration::from_secs(2)).expect("connect");
+    assert_eq!(peer, std::net::IpAddr::from([127, 0, 0, 2]));
+
+    // A source address this host does not have: nothing is scanned
+    let records = scan(Ipv4Addr::new(192, 0, 2, 1));
+    assert_eq!(records.len(), 1);
+    assert_eq!((records[0].port, records[0].port_scanned), (None, None));
+    assert!(LiveArpDiscover::new("127.0.0.1/32")
+        .with_source_interface("definitely_not_a_real_interface_name_12345")
+        .is_err());
+}
@@ -0,0 +1,361 @@
+//! Rule-based device classification from vendor strings and open ports.
+//!
+//! Rules are plain data (`DeviceRule`) evaluated in order; the first rule
+//! whose constraints all hold wins. Callers can extend the builtin table by
+//! building their own slice (e.g. custom rules followed by
+//! `DEFAULT_DEVICE_RULES`) and passing it to `classify_device_with`.
+
+use crate::pipeline::DEVICE_CLASS_CONFIDENCE;
+use formats::DiscoveryRecord;
+use once_cell::sync::Lazy;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Coarse device category assigned by the classifier.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DeviceClass {
+    Router,
+    Printer,
+    Camera,
+    Nas,
+    MediaPlayer,
+    VirtualMachine,
+}
+
+impl DeviceClass {
+    /// Stable lowercase label stored in `DiscoveryRecord::device_type`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeviceClass::Router => "router",
+            DeviceClass::Printer => "printer",
+            DeviceClass::Camera => "camera",
+            DeviceClass::Nas => "nas",
+            DeviceClass::MediaPlayer => "media",
+            DeviceClass::VirtualMachine => "vm",
+        }
+    }
+}
+
+impl fmt::Display for DeviceClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A single classification rule, built with `DeviceRule::new` and the
+/// `with_*` methods. Empty lists mean "no constraint"; a rule with no
+/// constraints at all never matches.
+#[derive(Debug, Clone)]
+pub struct DeviceRule {
+    class: DeviceClass,
+    /// Lowercased substrings; at least one must appear in the vendor.
+    vendor_contains: Vec<String>,
+    /// At least one of these ports must be open.
+    any_ports: Vec<u16>,
+    /// All of these ports must be open.
+    all_ports: Vec<u16>,
+    /// Only match when the host is the network gateway.
+    gateway_only: bool,
+}
+
+impl DeviceRule {
+    /// A rule assigning `class`, with no constraints yet.
+    pub fn new(class: DeviceClass) -> Self {
+        Self {
+            class,
+            vendor_contains: Vec::new(),
+            any_ports: Vec::new(),
+            all_ports: Vec::new(),
+            gateway_only: false,
+        }
+    }
+
+    /// Vendor substrings, matched case-insensitively (lowercased here, once).
+    pub fn with_vendors(mut self, vendors: &[&str]) -> Self {
+        self.vendor_contains = vendors.iter().map(|v| v.to_ascii_lowercase()).collect();
+        self
+    }
+
+    pub fn with_any_ports(mut self, ports: &[u16]) -> Self {
+        self.any_ports = ports.to_vec();
+        self
+    }
+
+    pub fn with_all_ports(mut self, ports: &[u16]) -> Self {
+        self.all_ports = ports.to_vec();
+        self
+    }
+
+    pub fn with_gateway_only(mut self, gateway_only: bool) -> Self {
+        self.gateway_only = gateway_only;
+        self
+    }
+
+    /// The class this rule assigns.
+    pub fn class(&self) -> DeviceClass {
+        self.class
+    }
+}
+
+const PRINTER_VENDORS: &[&str] = &[
+    "hewlett packard",
+    "hp inc",
+    "brother",
+    "seiko epson",
+    "canon",
+    "lexmark",
+    "xerox",
+    "kyocera",
+    "ricoh",
+];
+const PRINTER_PORTS: &[u16] = &[515, 631, 9100];
+const ROUTER_VENDORS: &[&str] = &[
+    "netgear", "tp-link", "asustek", "arris", "ubiquiti", "mikrotik", "eero", "linksys",
+];
+
+/// Builtin rule table, ordered from most to least specific.
+pub static DEFAULT_DEVICE_RULES: Lazy<Vec<DeviceRule>> = Lazy::new(|| {
+    vec![
+        DeviceRule::new(DeviceClass::Printer)
+            .with_vendors(PRINTER_VENDORS)
+            .with_any_ports(PRINTER_PORTS),
+        DeviceRule::new(DeviceClass::Router)
+            .with_all_ports(&[53, 80, 443])
+            .with_gateway_only(true),
+        DeviceRule::new(DeviceClass::Router)
+            .with_vendors(ROUTER_VENDORS)
+            .with_gateway_only(true),
+        DeviceRule::new(DeviceClass::Camera).with_vendors(&[
+            "hikvision",
+            "dahua",
+            "axis communications",
+            "amcrest",
+            "reolink",
+        ]),
+        DeviceRule::new(DeviceClass::Nas).with_vendors(&[
+            "synology",
+            "qnap",
+            "asustor",
+            "buffalo",
+            "western digital",
+        ]),
+        DeviceRule::new(DeviceClass::MediaPlayer).with_vendors(&["roku", "sonos"]),
+        DeviceRule::new(DeviceClass::VirtualMachine).with_vendors(&[
+            "vmware",
+            "xensource",
+            "pcs systemtechnik",
+            "qemu",
+        ]),
+        DeviceRule::new(DeviceClass::Printer).with_any_ports(PRINTER_PORTS),
+    ]
+});
+
+/// Aggregated observations about a single host used as classifier input.
+#[derive(Debug, Clone, Default)]
+pub struct HostFacts<'a> {
+    pub vendor: Option<&'a str>,
+    pub open_ports: Vec<u16>,
+    pub is_gateway: bool,
+}
+
+impl DeviceRule {
+    fn matches(&self, facts: &HostFacts<'_>) -> bool {
+        if self.vendor_contains.is_empty()
+            && self.any_ports.is_empty()
+            && self.all_ports.is_empty()
+            && !self.gateway_only
+        {
+            return false;
+        }
+        if self.gateway_only && !facts.is_gateway {
+            return false;
+        }
+        if !self.vendor_contains.is_empty() {
+            let vendor = match facts.vendor {
+                Some(v) => v.to_ascii_lowercase(),
+                None => return false,
+            };
+            if !self
+                .vendor_contains
+                .iter()
+                .any(|s| vendor.contains(s.as_str()))
+            {
+                return false;
+            }
+        }
+        if !self.any_ports.is_empty()
+            && !self.any_ports.iter().any(|p| facts.open_ports.contains(p))
+        {
+            return false;
+        }
+        self.all_ports.iter().all(|p| facts.open_ports.contains(p))
+    }
+}
+
+/// Classify host facts against an explicit rule table (first match wins).
+pub fn classify_facts(rules: &[DeviceRule], facts: &HostFacts<'_>) -> Option<DeviceClass> {
+    rules.iter().find(|r| r.matches(facts)).map(|r| r.class)
+}
+
+/// Classify a single record against an explicit rule table. Only the
+/// record's own port is considered and the host is assumed not to be the
+/// gateway; use `classify_records` for multi-port/gateway-aware grouping.
+pub fn classify_device_with(rules: &[DeviceRule], record: &DiscoveryRecord) -> Option<DeviceClass> {
+    let facts = HostFacts {
+        vendor: record.vendor.as_deref(),
+        open_ports: record.port.into_iter().collect(),
+        is_gateway: false,
+    };
+    classify_facts(rules, &facts)
+}
+
+/// Classify a single record using the builtin rule table.
+pub fn classify_device(record: &DiscoveryRecord) -> Option<DeviceClass> {
+    classify_device_with(&DEFAULT_DEVICE_RULES, record)
+}
+
+/// Classify all records host-by-host (ports from every record sharing an IP
+/// are combined) and store the label in `device_type` for each classified
+/// host. `gateway` is the gateway IP, if known. Returns the number of records
+/// that received a label.
+pub fn classify_records(
+    records: &mut [DiscoveryRecord],
+    rules: &[DeviceRule],
+    gateway: Option<&str>,
+) -> usize {
+    let mut hosts: BTreeMap<String, (Option<String>, Vec<u16>)> = BTreeMap::new();
+    for r in records.iter() {
+        let entry = hosts.entry(r.ip.clone()).or_default();
+        if entry.0.is_none() {
+            entry.0 = r.vendor.clone();
+        }
+        if let Some(p) = r.port {
+            entry.1.push(p);
+        }
+    }
+
+    let classes: BTreeMap<String, DeviceClass> = hosts
+        .iter()
+        .filter_map(|(ip, (vendor, ports))| {
+            let facts = HostFacts {
+                vendor: vendor.as_deref(),
+                open_ports: ports.clone(),
+                is_gateway: gateway == Some(ip.as_str()),
+            };
+            classify_facts(rules, &facts).map(|c| (ip.clone(), c))
+        })
+        .collect();
+
+    let mut labelled = 0;
+    for r in records.iter_mut() {
+        if let Some(c) = classes.get(&r.ip) {
+            r.device_type = Some(c.as_str().to_string());
+            r.set_provenance("device_type", "device_class", DEVICE_CLASS_CONFIDENCE);
+            labelled += 1;
+        }
+    }
+    labelled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rec(ip: &str, port: Option<u16>, vendor: Option<&str>) -> DiscoveryRecord {
+        DiscoveryRecord::new(ip, port, None, None, vendor, None)
+    }
+
+    #[test]
+    fn hp_with_jetdirect_is_printer() {
+        let r = rec("192.0.2.20", Some(9100), Some("Hewlett Packard"));
+        assert_eq!(classify_device(&r), Some(DeviceClass::Printer));
+    }
+
+    #[test]
+    fn ipp_port_alone_is_printer() {
+        let r = rec("192.0.2.21", Some(631), None);
+        assert_eq!(classify_device(&r), Some(DeviceClass::Printer));
+    }
+
+    #[test]
+    fn hikvision_is_camera() {
+        let r = rec(
+            "192.0.2.30",
+            None,
+            Some("Hangzhou Hikvision Digital Technology"),
+        );
+        assert_eq!(classify_device(&r), Some(DeviceClass::Camera));
+    }
+
+    #[test]
+    fn synology_is_nas() {
+        let r = rec("192.0.2.40", Some(5001), Some("Synology Incorporated"));
+        assert_eq!(classify_device(&r), Some(DeviceClass::Nas));
+    }
+
+    #[test]
+    fn vmware_is_virtual_machine() {
+        let r = rec("192.0.2.50", Some(22), Some("VMware, Inc."));
+        assert_eq!(classify_device(&r), Some(DeviceClass::VirtualMachine));
+    }
+
+    #[test]
+    fn sonos_is_media_player() {
+        let r = rec("192.0.2.60", None, Some("Sonos, Inc."));
+        assert_eq!(classify_device(&r), Some(DeviceClass::MediaPlayer));
+    }
+
+    #[test]
+    fn gateway_with_dns_and_web_is_router() {
+        let mut recs = vec![
+            rec("192.0.2.1", Some(53), None),
+            rec("192.0.2.1", Some(80), None),
+            rec("192.0.2.1", Some(443), None),
+            rec("192.0.2.9", Some(80), None),
+        ];
+        let n = classify_records(&mut recs, &DEFAULT_DEVICE_RULES, Some("192.0.2.1"));
+        assert_eq!(n, 3);
+        assert!(recs[..3]
+            .iter()
+            .all(|r| r.device_type.as_deref() == Some("router")));
+        assert!(recs[3].device_type.is_none());
+    }
+
+    #[test]
+    fn same_ports_off_gateway_are_not_router() {
+        let mut recs = vec![
+            rec("192.0.2.7", Some(53), None),
+            rec("192.0.2.7", Some(80), None),
+            rec("192.0.2.7", Some(443), None),
+        ];
+        assert_eq!(
+            classify_records(&mut recs, &DEFAULT_DEVICE_RULES, Some("192.0.2.1")),
+            0
+        );
+    }
+
+    #[test]
+    fn unclassifiable_host_returns_none() {
+        let r = rec("192.0.2.99", Some(22), Some("Some Unknown Maker"));
+        assert_eq!(classify_device(&r), None);
+    }
+
+    #[test]
+    fn custom_rules_take_precedence_in_order() {
+        let mut rules = vec![DeviceRule::new(DeviceClass::Camera).with_any_ports(&[9100])];
+        rules.extend_from_slice(&DEFAULT_DEVICE_RULES);
+        let r = rec("192.0.2.20", Some(9100), Some("Hewlett Packard"));
+        assert_eq!(classify_device_with(&rules, &r), Some(DeviceClass::Camera));
+        // deterministic: repeated calls yield the same answer
+        assert_eq!(classify_device_with(&rules, &r), Some(DeviceClass::Camera));
+    }
+
+    #[test]
+    fn custom_vendor_rules_ignore_case() {
+        let rules = [DeviceRule::new(DeviceClass::Camera).with_vendors(&["Acme CAM"])];
+        let r = rec("192.0.2.70", None, Some("ACME Cam Systems"));
+        assert_eq!(classify_device_with(&rules, &r), Some(DeviceClass::Camera));
+        let r = rec("192.0.2.71", None, Some("acme cam systems"));
+        assert_eq!(classify_device_with(&rules, &r), Some(DeviceClass::Camera));
+    }
+}
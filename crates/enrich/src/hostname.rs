@@ -0,0 +1,357 @@
+//! Data-driven hostname -> vendor heuristics.
+//!
+//! Rules are loaded from a small CSV (`pattern,vendor,match_type`) where
+//! `match_type` is one of `substring`, `prefix` or `regex`. A default rule set
+//! is embedded in the crate; `load_hostname_rules` replaces the active set at
+//! runtime without a rebuild.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::sync::RwLock;
+
+// Embedded default rules shipped with this crate.
+static EMBEDDED_HOSTNAME_RULES: &str = include_str!("../data/hostname_rules.csv");
+static HOSTNAME_RULES: Lazy<RwLock<Vec<HostnameRule>>> =
+    Lazy::new(|| RwLock::new(parse_hostname_rules(EMBEDDED_HOSTNAME_RULES).unwrap_or_default()));
+
+/// How a rule pattern is compared against a (lowercased) hostname.
+#[derive(Debug, Clone)]
+pub enum MatchType {
+    Substring,
+    Prefix,
+    Regex(Regex),
+}
+
+/// A single hostname heuristic: hostnames matching `pattern` map to `vendor`.
+#[derive(Debug, Clone)]
+pub struct HostnameRule {
+    pub pattern: String,
+    pub vendor: String,
+    pub match_type: MatchType,
+}
+
+impl HostnameRule {
+    /// Build a rule; `match_type` is `substring`, `prefix` or `regex`
+    /// (case-insensitive). Substring and prefix patterns are lowercased;
+    /// regex patterns are kept as written and compiled case-insensitively,
+    /// since lowercasing would turn classes like `\D` into `\d`.
+    pub fn new(pattern: &str, vendor: &str, match_type: &str) -> Result<Self, Box<dyn Error>> {
+        let pattern = pattern.trim();
+        if pattern.is_empty() {
+            return Err("empty hostname rule pattern".into());
+        }
+        let (pattern, match_type) = match match_type.trim().to_ascii_lowercase().as_str() {
+            "" | "substring" => (pattern.to_ascii_lowercase(), MatchType::Substring),
+            "prefix" => (pattern.to_ascii_lowercase(), MatchType::Prefix),
+            "regex" => (
+                pattern.to_string(),
+                MatchType::Regex(Regex::new(&format!("(?i){}", pattern))?),
+            ),
+            other => return Err(format!("unknown match_type: {}", other).into()),
+        };
+        Ok(Self {
+            pattern,
+            vendor: vendor.trim().to_string(),
+            match_type,
+        })
+    }
+
+    /// True if the (already lowercased) hostname matches this rule.
+    pub fn matches(&self, hostname_lower: &str) -> bool {
+        match &self.match_type {
+            MatchType::Substring => hostname_lower.contains(&self.pattern),
+            MatchType::Prefix => hostname_lower.starts_with(&self.pattern),
+            MatchType::Regex(re) => re.is_match(hostname_lower),
+        }
+    }
+}
+
+/// Parse rules from a CSV-like string. Blank lines, `#` comments and an
+/// optional `pattern,vendor,match_type` header row are skipped; a missing
+/// third column defaults to `substring`.
+pub fn parse_hostname_rules(s: &str) -> Result<Vec<HostnameRule>, Box<dyn Error>> {
+    let mut rdr = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .comment(Some(b'#'))
+        .from_reader(s.as_bytes());
+    let mut out = Vec::new();
+    for result in rdr.records() {
+        let rec = result?;
+        let pattern = rec.get(0).unwrap_or("").trim();
+        if pattern.is_empty() || pattern.eq_ignore_ascii_case("pattern") {
+            continue;
+        }
+        let vendor = rec.get(1).unwrap_or("").trim();
+        if vendor.is_empty() {
+            return Err(format!("hostname rule '{}' has no vendor", pattern).into());
+        }
+        out.push(HostnameRule::new(
+            pattern,
+            vendor,
+            rec.get(2).unwrap_or(""),
+        )?);
+    }
+    Ok(out)
+}
+
+/// Replace the active global rule set with rules read from `path`.
+/// Returns the number of rules loaded; on error the current rules are kept.
+pub fn load_hostname_rules<P: AsRef<Path>>(path: P) -> Result<usize, Box<dyn Error>> {
+    let s = fs::read_to_string(path.as_ref())?;
+    let rules = parse_hostname_rules(&s)?;
+    let n = rules.len();
+    *HOSTNAME_RULES
+        .write()
+        .map_err(|_| "hostname rules lock poisoned")? = rules;
+    Ok(n)
+}
+
+/// Restore the embedded default rule set.
+pub fn reset_hostname_rules() {
+    if let Ok(mut guard) = HOSTNAME_RULES.write() {
+        *guard = parse_hostname_rules(EMBEDDED_HOSTNAME_RULES).unwrap_or_default();
+    }
+}
+
+/// Match a hostname against an explicit rule list (first match wins).
+pub fn vendor_from_hostname_with(rules: &[HostnameRule], hostname: &str) -> Option<String> {
+    let hn = hostname.to_ascii_lowercase();
+    rules
+        .iter()
+        .find(|r| r.matches(&hn))
+        .map(|r| r.vendor.clone())
+}
+
+/// Given a hostname, attempt to derive a user-friendly vendor string.
+/// This is heuristic-only and intended for display; it should not overwrite
+/// manufacturer/vendor fields derived from OUI unless explicitly requested.
+pub fn vendor_from_hostname(hostname: &str) -> Option<String> {
+    let rules = HOSTNAME_RULES.read().ok()?;
+    vendor_from_hostname_with(&rules, hostname)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn defaults() -> Vec<HostnameRule> {
+        parse_hostname_rules(EMBEDDED_HOSTNAME_RULES).expect("embedded rules parse")
+    }
+
+    fn vendor(h: &str) -> Option<String> {
+        vendor_from_hostname_with(&defaults(), h)
+    }
+
+    #[test]
+    fn embedded_rules_parse() {
+        assert!(defaults().len() >= 15);
+    }
+
+    #[test]
+    fn detects_netgear() {
+        assert_eq!(vendor("NETGEAR42.local").as_deref(), Some("Netgear"));
+        assert_eq!(vendor("RBR750.lan").as_deref(), Some("Netgear"));
+    }
+
+    #[test]
+    fn detects_tplink() {
+        assert_eq!(
+            vendor("TL-WR841N.tplinkwifi.net").as_deref(),
+            Some("TP-Link")
+        );
+        assert_eq!(vendor("Archer_C7").as_deref(), Some("TP-Link"));
+    }
+
+    #[test]
+    fn detects_ubiquiti() {
+        assert_eq!(vendor("UniFi-AP-Lite").as_deref(), Some("Ubiquiti"));
+        assert_eq!(vendor("ubnt.home").as_deref(), Some("Ubiquiti"));
+    }
+
+    #[test]
+    fn detects_eero() {
+        assert_eq!(vendor("eero-gateway.lan").as_deref(), Some("eero"));
+    }
+
+    #[test]
+    fn detects_asus() {
+        assert_eq!(vendor("RT-AX88U-1A2B").as_deref(), Some("ASUS"));
+        assert_eq!(vendor("asusrouter.com").as_deref(), Some("ASUS"));
+    }
+
+    #[test]
+    fn detects_amazon_and_ring() {
+        assert_eq!(vendor("amazon-3f2a1b").as_deref(), Some("Amazon"));
+        assert_eq!(vendor("Ring-Doorbell-12").as_deref(), Some("Ring (Amazon)"));
+        assert_eq!(vendor("kindle-paperwhite").as_deref(), Some("Amazon"));
+    }
+
+    #[test]
+    fn detects_home_iot() {
+        assert_eq!(vendor("Roomba-3F2A").as_deref(), Some("iRobot"));
+        assert_eq!(vendor("iRobot-5C1D.lan").as_deref(), Some("iRobot"));
+        assert_eq!(vendor("Philips-hue").as_deref(), Some("Philips Hue"));
+        assert_eq!(vendor("hue-bridge.lan").as_deref(), Some("Philips Hue"));
+        assert_eq!(vendor("Sonos-48A6B8C1D2E3").as_deref(), Some("Sonos"));
+        assert_eq!(vendor("Chromecast-Ultra").as_deref(), Some("Google"));
+        assert_eq!(vendor("Nest-Thermostat").as_deref(), Some("Google"));
+        assert_eq!(vendor("google-home-mini").as_deref(), Some("Google"));
+        assert_eq!(vendor("Orbi-Satellite").as_deref(), Some("Netgear"));
+    }
+
+    #[test]
+    fn detects_printers() {
+        assert_eq!(vendor("BRN3C2AF41B7E90").as_deref(), Some("Brother"));
+        assert_eq!(vendor("brw008092aabbcc").as_deref(), Some("Brother"));
+        assert_eq!(vendor("Brother-HL-L2350").as_deref(), Some("Brother"));
+        assert_eq!(vendor("NPI8E3A21").as_deref(), Some("HP"));
+        assert_eq!(vendor("HP3C4A92.lan").as_deref(), Some("HP"));
+        assert_eq!(
+            vendor("office-printer.lan").as_deref(),
+            Some("Printer (detected)")
+        );
+        // vendor-specific names win over the generic rule
+        assert_eq!(vendor("brother-printer").as_deref(), Some("Brother"));
+    }
+
+    #[test]
+    fn detects_synology() {
+        assert_eq!(vendor("DS918").as_deref(), Some("Synology"));
+        assert_eq!(vendor("ds220plus").as_deref(), Some("Synology"));
+        assert_eq!(vendor("ds1821+.local").as_deref(), Some("Synology"));
+        assert_eq!(vendor("SynologyNAS").as_deref(), Some("Synology"));
+    }
+
+    #[test]
+    fn short_patterns_need_word_boundaries() {
+        assert!(vendor("honest-laptop").is_none());
+        assert!(vendor("nestor-pc").is_none());
+        assert!(vendor("bluehues").is_none());
+        assert!(vendor("chuey-desktop").is_none());
+        assert!(vendor("hpc-node1").is_none());
+        assert!(vendor("dsl-modem").is_none());
+        assert!(vendor("orbital-db").is_none());
+        assert!(vendor("my-orbi").is_none());
+        assert_eq!(vendor("ORBI75").as_deref(), Some("Netgear"));
+    }
+
+    #[test]
+    fn every_embedded_rule_is_covered() {
+        let samples = [
+            "cr1000a.mynetworksettings.com",
+            "CR1000A",
+            "fios-router",
+            "google-home",
+            "chromecast",
+            "nest-cam",
+            "netgear42",
+            "orbi",
+            "rbr750",
+            "tplinkwifi",
+            "tp-link-ap",
+            "archer-c7",
+            "ubnt",
+            "unifi-ap",
+            "eero",
+            "rt-ax88u",
+            "asusrouter",
+            "ring-doorbell",
+            "amazon-3f2a1b",
+            "kindle",
+            "roomba",
+            "irobot-1",
+            "hue",
+            "sonos",
+            "brother",
+            "brn3c2af41b7e90",
+            "npi8e3a21",
+            "hp3c4a92",
+            "printer",
+            "synology",
+            "ds918",
+        ];
+        for rule in defaults() {
+            assert!(
+                samples.iter().any(|s| rule.matches(s)),
+                "no sample hostname for rule {:?}",
+                rule.pattern
+            );
+        }
+    }
+
+    #[test]
+    fn prefix_rules_only_match_at_start() {
+        assert!(vendor("my-amazon-thing").is_none());
+    }
+
+    #[test]
+    fn custom_rules_are_injected() {
+        let rules = parse_hostname_rules(
+            "pattern,vendor,match_type\n\
+             # comment line\n\
+             lab-,Lab Gear,prefix\n\
+             \"^sw\\d+$\",Switch Co,regex\n\
+             printer,PrintCo\n",
+        )
+        .expect("parse");
+        assert_eq!(rules.len(), 3);
+        assert_eq!(
+            vendor_from_hostname_with(&rules, "LAB-node1").as_deref(),
+            Some("Lab Gear")
+        );
+        assert_eq!(
+            vendor_from_hostname_with(&rules, "SW12").as_deref(),
+            Some("Switch Co")
+        );
+        assert_eq!(
+            vendor_from_hostname_with(&rules, "office-printer").as_deref(),
+            Some("PrintCo")
+        );
+        assert!(vendor_from_hostname_with(&rules, "sw12x").is_none());
+    }
+
+    #[test]
+    fn regex_patterns_keep_their_case() {
+        let rules = parse_hostname_rules("\"^cam\\D+$\",Cam Co,regex\n").expect("parse");
+        assert_eq!(rules[0].pattern, "^cam\\D+$");
+        assert_eq!(
+            vendor_from_hostname_with(&rules, "CAM-lobby").as_deref(),
+            Some("Cam Co")
+        );
+        assert!(vendor_from_hostname_with(&rules, "cam42").is_none());
+    }
+
+    #[test]
+    fn invalid_rules_are_rejected() {
+        assert!(parse_hostname_rules("foo,Bar,glob").is_err());
+        assert!(parse_hostname_rules("(unclosed,Bar,regex").is_err());
+        assert!(parse_hostname_rules("foo,,substring").is_err());
+    }
+
+    #[test]
+    fn load_and_reset_global_rules() {
+        let dir = std::env::temp_dir().join(format!("enrich-rules-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("rules.csv");
+        // keep the defaults in the file so concurrently running tests still see them
+        let contents = format!("{}\nzzlabhost,ZZ Labs,prefix\n", EMBEDDED_HOSTNAME_RULES);
+        fs::write(&path, contents).unwrap();
+
+        assert_eq!(
+            load_hostname_rules(&path).expect("load"),
+            defaults().len() + 1
+        );
+        assert_eq!(
+            vendor_from_hostname("zzlabhost-01").as_deref(),
+            Some("ZZ Labs")
+        );
+
+        reset_hostname_rules();
+        assert!(vendor_from_hostname("zzlabhost-01").is_none());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
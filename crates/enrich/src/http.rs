@@ -0,0 +1,465 @@
+//! HTTP `<title>` and `Server` header enrichment for web ports.
+//!
+//! `http_probe` sends `GET /` and reads at most `MAX_HTTP_RESPONSE_BYTES` of
+//! the reply. Plain HTTP is always available; HTTPS needs the `https` feature
+//! (rustls, certificates are not verified since we only read metadata).
+
+use formats::DiscoveryRecord;
+use netutils::portscan::{block_on, PortScanError};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+/// Largest response (headers + body) read from a server.
+pub const MAX_HTTP_RESPONSE_BYTES: usize = 64 * 1024;
+/// Ports probed with plain HTTP by `enrich_records_http`.
+pub const HTTP_PORTS: &[u16] = &[80, 8000, 8008, 8080, 8081, 8888];
+/// Ports probed with HTTPS by `enrich_records_http` (needs the `https` feature).
+pub const HTTPS_PORTS: &[u16] = &[443, 8443];
+
+/// Provenance confidence for fields filled from an HTTP response.
+const HTTP_CONFIDENCE: u8 = 80;
+
+/// What a web server said about itself.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpInfo {
+    /// Text of the page `<title>`, whitespace collapsed
+    pub title: Option<String>,
+    /// `Server` response header
+    pub server: Option<String>,
+    /// HTTP status code
+    pub status: u16,
+}
+
+static TITLE_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?is)<title[^>]*>(.*?)</title\s*>").expect("valid title regex"));
+
+/// Extract the page title from (possibly malformed, non-UTF-8) HTML.
+pub fn extract_title(body: &[u8]) -> Option<String> {
+    let html = String::from_utf8_lossy(body);
+    let raw = TITLE_RE.captures(&html)?.get(1)?.as_str();
+    let text = raw
+        .replace("&amp;", "&")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'");
+    let title = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!title.is_empty()).then_some(title)
+}
+
+/// Parse a raw HTTP/1.x response (headers plus whatever body was read).
+pub fn parse_http_response(raw: &[u8]) -> Option<HttpInfo> {
+    let head_end = raw
+        .windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .map(|i| (i, i + 4))
+        .or_else(|| {
+            raw.windows(2)
+                .position(|w| w == b"\n\n")
+                .map(|i| (i, i + 2))
+        });
+    let (head, body) = match head_end {
+        Some((end, body_start)) => (&raw[..end], &raw[body_start..]),
+        None => (raw, &raw[raw.len()..]),
+    };
+    let head = String::from_utf8_lossy(head);
+    let mut lines = head.lines();
+    let status_line = lines.next()?;
+    if !status_line.starts_with("HTTP/") {
+        return None;
+    }
+    let status = status_line.split_whitespace().nth(1)?.parse().ok()?;
+    let server = lines
+        .filter_map(|l| l.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("server"))
+        .map(|(_, v)| v.trim().to_string())
+        .filter(|v| !v.is_empty());
+    Some(HttpInfo {
+        title: extract_title(body),
+        server,
+        status,
+    })
+}
+
+async fn exchange<S>(stream: &mut S, host: &str) -> Option<Vec<u8>>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let request = format!(
+        "GET / HTTP/1.0\r\nHost: {}\r\nUser-Agent: enrich-http-probe\r\nAccept: text/html\r\nConnection: close\r\n\r\n",
+        host
+    );
+    stream.write_all(request.as_bytes()).await.ok()?;
+    let mut raw = Vec::new();
+    // anything past the cap is dropped, not an error
+    let _ = (&mut *stream)
+        .take(MAX_HTTP_RESPONSE_BYTES as u64)
+        .read_to_end(&mut raw)
+        .await;
+    (!raw.is_empty()).then_some(raw)
+}
+
+#[cfg(feature = "https")]
+mod tls {
+    use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+    use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+    use rustls::{DigitallySignedStruct, SignatureScheme};
+    use std::sync::Arc;
+
+    /// Accepts any certificate: the probe only reads the title and headers.
+    #[derive(Debug)]
+    struct NoVerify(Arc<rustls::crypto::CryptoProvider>);
+
+    impl ServerCertVerifier for NoVerify {
+        fn verify_server_cert(
+            &self,
+            _end_entity: &CertificateDer<'_>,
+            _intermediates: &[CertificateDer<'_>],
+            _server_name: &ServerName<'_>,
+            _ocsp_response: &[u8],
+            _now: UnixTime,
+        ) -> Result<ServerCertVerified, rustls::Error> {
+            Ok(ServerCertVerified::assertion())
+        }
+
+        fn verify_tls12_signature(
+            &self,
+            message: &[u8],
+            cert: &CertificateDer<'_>,
+            dss: &DigitallySignedStruct,
+        ) -> Result<HandshakeSignatureValid, rustls::Error> {
+            rustls::crypto::verify_tls12_signature(
+                message,
+                cert,
+                dss,
+                &self.0.signature_verification_algorithms,
+            )
+        }
+
+        fn verify_tls13_signature(
+            &self,
+            message: &[u8],
+            cert: &CertificateDer<'_>,
+            dss: &DigitallySignedStruct,
+        ) -> Result<HandshakeSignatureValid, rustls::Error> {
+            rustls::crypto::verify_tls13_signature(
+                message,
+                cert,
+                dss,
+                &self.0.signature_verification_algorithms,
+            )
+        }
+
+        fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+            self.0.signature_verification_algorithms.supported_schemes()
+        }
+    }
+
+    pub(super) fn connector() -> tokio_rustls::TlsConnector {
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let config = rustls::ClientConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()
+            .expect("ring supports the default protocol versions")
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(NoVerify(provider)))
+            .with_no_client_auth();
+        tokio_rustls::TlsConnector::from(Arc::new(config))
+    }
+}
+
+/// Async form of `http_probe`.
+pub async fn http_probe_async(
+    ip: IpAddr,
+    port: u16,
+    tls: bool,
+    timeout: Duration,
+) -> Option<HttpInfo> {
+    let addr = SocketAddr::new(ip, port);
+    let host = addr.to_string();
+    let raw = tokio::time::timeout(timeout, async {
+        let mut tcp = TcpStream::connect(addr).await.ok()?;
+        if !tls {
+            return exchange(&mut tcp, &host).await;
+        }
+        #[cfg(feature = "https")]
+        {
+            let name = rustls::pki_types::ServerName::IpAddress(ip.into());
+            let mut stream = tls::connector().connect(name, tcp).await.ok()?;
+            exchange(&mut stream, &host).await
+        }
+        #[cfg(not(feature = "https"))]
+        None
+    })
+    .await
+    .ok()??;
+    parse_http_response(&raw)
+}
+
+/// Fetch `/` from `ip:port` and return its status, `Server` header and
+/// title. `tls` selects HTTPS, which returns None without the `https`
+/// feature. `timeout` bounds the whole exchange. Runs on netutils' shared
+/// runtime, so it may be called from inside a tokio runtime; errors only
+/// when that runtime cannot be started.
+pub fn http_probe(
+    ip: IpAddr,
+    port: u16,
+    tls: bool,
+    timeout: Duration,
+) -> Result<Option<HttpInfo>, PortScanError> {
+    block_on(http_probe_async(ip, port, tls, timeout))
+}
+
+/// Probe every record on a web port (`HTTP_PORTS`, plus `HTTPS_PORTS` with
+/// the `https` feature), `concurrency` at a time, and fill banner (page
+/// title), product (`Server` header) and service where empty. Returns the
+/// number of records changed.
+pub fn enrich_records_http(
+    records: &mut [DiscoveryRecord],
+    timeout: Duration,
+    concurrency: usize,
+) -> Result<usize, PortScanError> {
+    let mut targets: Vec<(IpAddr, u16, bool)> = records
+        .iter()
+        .filter_map(|r| {
+            let port = r.port?;
+            let tls = if HTTP_PORTS.contains(&port) {
+                false
+            } else if cfg!(feature = "https") && HTTPS_PORTS.contains(&port) {
+                true
+            } else {
+                return None;
+            };
+            Some((r.ip.parse().ok()?, port, tls))
+        })
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    if targets.is_empty() {
+        return Ok(0);
+    }
+    targets.sort();
+
+    let results: HashMap<(String, u16), HttpInfo> = block_on(async {
+        let sem = Arc::new(tokio::sync::Semaphore::new(concurrency.max(1)));
+        let mut set = tokio::task::JoinSet::new();
+        for (ip, port, tls) in targets {
+            let sem = sem.clone();
+            set.spawn(async move {
+                let _permit = sem.acquire_owned().await.ok()?;
+                let info = http_probe_async(ip, port, tls, timeout).await?;
+                Some(((ip.to_string(), port), info))
+            });
+        }
+        let mut out = HashMap::new();
+        while let Some(res) = set.join_next().await {
+            if let Ok(Some((key, info))) = res {
+                out.insert(key, info);
+            }
+        }
+        out
+    })?;
+    Ok(apply_http_info(records, &results))
+}
+
+/// Apply already collected results keyed by (IP string, port).
+pub fn apply_http_info(
+    records: &mut [DiscoveryRecord],
+    results: &HashMap<(String, u16), HttpInfo>,
+) -> usize {
+    let mut changed = 0;
+    for r in records.iter_mut() {
+        let Some(port) = r.port else { continue };
+        let Some(info) = results.get(&(r.ip.clone(), port)) else {
+            continue;
+        };
+        let mut touched = false;
+        if r.service.is_none() {
+            let tls = HTTPS_PORTS.contains(&port);
+            r.service = Some(if tls { "https" } else { "http" }.to_string());
+            r.set_provenance("service", "http", HTTP_CONFIDENCE);
+            touched = true;
+        }
+        if r.product.is_none() {
+            if let Some(server) = &info.server {
+                r.product = Some(server.clone());
+                r.set_provenance("product", "http", HTTP_CONFIDENCE);
+                touched = true;
+            }
+        }
+        if r.banner.is_none() {
+            if let Some(title) = &info.title {
+                r.banner = Some(title.clone());
+                r.set_provenance("banner", "http", HTTP_CONFIDENCE);
+                touched = true;
+            }
+        }
+        if touched {
+            changed += 1;
+        }
+    }
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::{Ipv4Addr, TcpListener};
+    use std::thread;
+
+    #[test]
+    fn title_from_messy_html() {
+        assert_eq!(
+            extract_title(b"<html><head><TITLE>Router Login</TITLE></head>").as_deref(),
+            Some("Router Login")
+        );
+        assert_eq!(
+            extract_title(b"<title lang=\"en\">\n   HP LaserJet\n\tM404dn\r\n</title>").as_deref(),
+            Some("HP LaserJet M404dn")
+        );
+        assert_eq!(
+            extract_title(b"<title>Caf\xe9 &amp; Bar</title>").as_deref(),
+            Some("Caf\u{fffd} & Bar")
+        );
+        assert!(extract_title(b"<html><body>no title here</body></html>").is_none());
+        assert!(extract_title(b"<title>   </title>").is_none());
+        assert!(extract_title(b"<title>unterminated").is_none());
+        assert!(extract_title(&[0xff, 0xfe, 0x00]).is_none());
+    }
+
+    #[test]
+    fn parses_status_server_and_title() {
+        let raw = b"HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nserver: lighttpd/1.4.59\r\n\r\n<title>Synology DiskStation</title>";
+        let info = parse_http_response(raw).unwrap();
+        assert_eq!(info.status, 200);
+        assert_eq!(info.server.as_deref(), Some("lighttpd/1.4.59"));
+        assert_eq!(info.title.as_deref(), Some("Synology DiskStation"));
+
+        let redirect = parse_http_response(b"HTTP/1.0 302 Found\nLocation: /login\n\n").unwrap();
+        assert_eq!(
+            (redirect.status, redirect.server, redirect.title),
+            (302, None, None)
+        );
+
+        assert!(parse_http_response(b"SSH-2.0-OpenSSH_9.6\r\n").is_none());
+    }
+
+    fn serve_once(response: Vec<u8>) -> u16 {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let port = listener.local_addr().unwrap().port();
+        thread::spawn(move || {
+            if let Ok((mut s, _)) = listener.accept() {
+                let mut req = [0u8; 512];
+                let _ = s.read(&mut req);
+                let _ = s.write_all(&response);
+            }
+        });
+        port
+    }
+
+    #[test]
+    fn probes_plain_http_and_caps_size() {
+        let port =
+            serve_once(b"HTTP/1.1 200 OK\r\nServer: test/1.0\r\n\r\n<title>Hello</title>".to_vec());
+        let info = http_probe(
+            Ipv4Addr::LOCALHOST.into(),
+            port,
+            false,
+            Duration::from_secs(2),
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(info.title.as_deref(), Some("Hello"));
+
+        // title beyond the 64 KiB cap is never seen
+        let mut big = b"HTTP/1.1 200 OK\r\n\r\n".to_vec();
+        big.extend(std::iter::repeat_n(b' ', MAX_HTTP_RESPONSE_BYTES));
+        big.extend_from_slice(b"<title>late</title>");
+        let port = serve_once(big);
+        let info = http_probe(
+            Ipv4Addr::LOCALHOST.into(),
+            port,
+            false,
+            Duration::from_secs(2),
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!((info.status, info.title), (200, None));
+    }
+
+    #[test]
+    fn tls_probe_of_plain_http_server_fails_quietly() {
+        let port = serve_once(b"HTTP/1.1 200 OK\r\n\r\n<title>plain</title>".to_vec());
+        assert!(http_probe(
+            Ipv4Addr::LOCALHOST.into(),
+            port,
+            true,
+            Duration::from_secs(2)
+        )
+        .unwrap()
+        .is_none());
+    }
+
+    #[test]
+    fn applies_http_info_without_clobbering() {
+        let mut recs = vec![
+            DiscoveryRecord::new("192.0.2.1", Some(80), None, None, None, None),
+            DiscoveryRecord::new("192.0.2.1", Some(22), None, None, None, None),
+            DiscoveryRecord::new("192.0.2.2", Some(8080), Some("keep"), None, None, None),
+        ];
+        let info = HttpInfo {
+            title: Some("Printer".into()),
+            server: Some("HP HTTP Server".into()),
+            status: 200,
+        };
+        let mut results = HashMap::new();
+        results.insert(("192.0.2.1".to_string(), 80), info.clone());
+        results.insert(("192.0.2.2".to_string(), 8080), info);
+        assert_eq!(apply_http_info(&mut recs, &results), 2);
+        assert_eq!(recs[0].banner.as_deref(), Some("Printer"));
+        assert_eq!(recs[0].product.as_deref(), Some("HP HTTP Server"));
+        assert_eq!(recs[0].service.as_deref(), Some("http"));
+        assert!(recs[1].service.is_none());
+        assert_eq!(recs[2].banner.as_deref(), Some("keep"));
+    }
+
+    #[test]
+    fn non_web_ports_are_not_probed() {
+        let mut recs = vec![DiscoveryRecord::new(
+            "127.0.0.1",
+            Some(22),
+            None,
+            None,
+            None,
+            None,
+        )];
+        assert_eq!(
+            enrich_records_http(&mut recs, Duration::from_millis(100), 4).unwrap(),
+            0
+        );
+    }
+
+    #[test]
+    fn probe_runs_inside_a_tokio_runtime() {
+        let port = serve_once(b"HTTP/1.1 204 No Content\r\n\r\n".to_vec());
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let info = rt.block_on(async {
+            http_probe(
+                Ipv4Addr::LOCALHOST.into(),
+                port,
+                false,
+                Duration::from_secs(2),
+            )
+        });
+        assert_eq!(info.unwrap().unwrap().status, 204);
+    }
+}
@@ -1,18 +1,56 @@
-/// Small enrichment utilities (hostname-based heuristics)
+//! Small enrichment utilities: hostname/MAC vendor heuristics, vendor name
+//! normalization, device classification, banner-based service
+//! identification, OS guesses, SNMP system info, HTTP titles and SSH host
+//! keys.
 
-/// Given a hostname, attempt to derive a user-friendly vendor string.
-/// This is heuristic-only and intended for display; it should not overwrite
-/// manufacturer/vendor fields derived from OUI unless explicitly requested.
-pub fn vendor_from_hostname(hostname: &str) -> Option<String> {
-    let hn = hostname.to_ascii_lowercase();
-    if hn.contains("mynetworksettings.com") || hn.starts_with("cr1000a") || hn.contains("fios") {
-        return Some("Verizon Fios (detected)".to_string());
-    }
-    if hn.contains("google") || hn.contains("nest") {
-        return Some("Google".to_string());
-    }
-    None
-}
+mod classify;
+mod hostname;
+mod http;
+mod mac;
+mod os;
+mod pipeline;
+mod service;
+mod snmp;
+#[cfg(feature = "ssdp")]
+pub mod ssdp;
+mod ssh;
+mod vendor;
+pub use classify::{
+    classify_device, classify_device_with, classify_facts, classify_records, DeviceClass,
+    DeviceRule, HostFacts, DEFAULT_DEVICE_RULES,
+};
+pub use hostname::{
+    load_hostname_rules, parse_hostname_rules, reset_hostname_rules, vendor_from_hostname,
+    vendor_from_hostname_with, HostnameRule, MatchType,
+};
+pub use http::{
+    apply_http_info, enrich_records_http, extract_title, http_probe, http_probe_async,
+    parse_http_response, HttpInfo, HTTPS_PORTS, HTTP_PORTS, MAX_HTTP_RESPONSE_BYTES,
+};
+pub use mac::{is_locally_administered, vendor_from_mac, RANDOMIZED_MAC_VENDOR};
+pub use os::{
+    annotate_os, annotate_os_from_banner, fingerprint_os_from_banner, guess_os, OsFamily, OsGuess,
+    OsHint, BANNER_OS_CONFIDENCE,
+};
+pub use pipeline::{
+    DeviceClassEnricher, EnrichPipeline, EnrichReport, Enricher, HostnameVendorEnricher,
+    OsEnricher, OuiEnricher, ReverseDnsEnricher, ServiceEnricher, DEVICE_CLASS_CONFIDENCE,
+    HOSTNAME_CONFIDENCE, OUI_CONFIDENCE, REVERSE_DNS_CONFIDENCE,
+};
+pub use service::{
+    enrich_records_services, identify_service, ServiceInfo, BANNER_SERVICE_CONFIDENCE,
+    PORT_SERVICE_CONFIDENCE,
+};
+pub use snmp::{
+    apply_snmp_sysinfo, decode_get_response, encode_get_request, enrich_records_snmp, snmp_sysinfo,
+    SnmpSysInfo,
+};
+pub use ssh::{
+    apply_ssh_hostkeys, enrich_records_ssh, frame_packet, hostkey_fingerprint,
+    kexinit_kex_algorithms, packet_payload, parse_ecdh_reply, ssh_hostkey, ssh_hostkey_async,
+    SshKeyInfo,
+};
+pub use vendor::normalize_vendor_name;
 
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,65 @@
+//! MAC-address based vendor enrichment.
+
+/// Vendor label used for locally-administered (typically randomized) MACs.
+pub const RANDOMIZED_MAC_VENDOR: &str = "Randomized (private)";
+
+/// True if the MAC has the U/L bit set (second-least-significant bit of the
+/// first octet), i.e. it was locally assigned rather than burned in. Modern
+/// phones and laptops use such addresses for per-network privacy. Accepts
+/// the usual separators (`:`, `-`, `.`); unparseable input returns false.
+pub fn is_locally_administered(mac: &str) -> bool {
+    let hex: String = mac.chars().filter(|c| c.is_ascii_hexdigit()).collect();
+    if hex.len() < 12 {
+        return false;
+    }
+    match u8::from_str_radix(&hex[..2], 16) {
+        Ok(first) => first & 0x02 != 0,
+        Err(_) => false,
+    }
+}
+
+/// Derive a vendor from a MAC address. Locally-administered MACs are labelled
+/// `RANDOMIZED_MAC_VENDOR` instead of being looked up, since their OUI bits
+/// carry no manufacturer information; others go through the OUI database.
+pub fn vendor_from_mac(mac: &str) -> Option<String> {
+    if is_locally_administered(mac) {
+        return Some(RANDOMIZED_MAC_VENDOR.to_string());
+    }
+    io::lookup_vendor_from_oui(mac)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_locally_administered_bit() {
+        assert!(is_locally_administered("02:00:5e:10:00:01"));
+        assert!(is_locally_administered("DA-A1-19-00-11-22"));
+        assert!(is_locally_administered("a6:83:e7:12:34:56"));
+        assert!(!is_locally_administered("00:0c:29:aa:bb:cc"));
+        assert!(!is_locally_administered("3c:22:fb:00:00:01"));
+    }
+
+    #[test]
+    fn malformed_macs_are_not_local() {
+        assert!(!is_locally_administered(""));
+        assert!(!is_locally_administered("02:00"));
+        assert!(!is_locally_administered("not-a-mac"));
+    }
+
+    #[test]
+    fn randomized_macs_skip_oui_lookup() {
+        // 00:0C:29 is VMware in the OUI data; flip the U/L bit and it must not resolve.
+        assert_eq!(
+            vendor_from_mac("02:0c:29:aa:bb:cc").as_deref(),
+            Some(RANDOMIZED_MAC_VENDOR)
+        );
+    }
+
+    #[test]
+    fn universal_macs_use_oui_database() {
+        let v = vendor_from_mac("00:0c:29:aa:bb:cc").expect("vmware oui");
+        assert!(v.contains("VMware"));
+    }
+}
@@ -0,0 +1,389 @@
+//! Rough OS family guess from IP TTL, banner and vendor hints.
+//!
+//! Each source votes for a family with a fixed weight (TTL 40, vendor 50,
+//! banner 80); the family with the highest total wins and its total, capped
+//! at 100, is the confidence. A banner hint therefore overrides a TTL bucket.
+
+use formats::DiscoveryRecord;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::fmt;
+
+/// Coarse operating-system family.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OsFamily {
+    /// Linux and other Unix-likes (initial TTL 64)
+    Linux,
+    /// Windows (initial TTL 128)
+    Windows,
+    /// Routers, switches and other network gear (initial TTL 255)
+    NetworkDevice,
+}
+
+impl OsFamily {
+    /// Stable lowercase label stored in `DiscoveryRecord::os`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OsFamily::Linux => "linux",
+            OsFamily::Windows => "windows",
+            OsFamily::NetworkDevice => "network",
+        }
+    }
+}
+
+impl fmt::Display for OsFamily {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// An OS guess with a 0-100 confidence score.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OsGuess {
+    pub family: OsFamily,
+    pub confidence: u8,
+}
+
+const TTL_WEIGHT: u8 = 40;
+const VENDOR_WEIGHT: u8 = 50;
+const BANNER_WEIGHT: u8 = 80;
+
+// Case-insensitive substrings, checked against the lowercased text.
+const BANNER_HINTS: &[(&str, OsFamily)] = &[
+    ("ubuntu", OsFamily::Linux),
+    ("debian", OsFamily::Linux),
+    ("centos", OsFamily::Linux),
+    ("red hat", OsFamily::Linux),
+    ("fedora", OsFamily::Linux),
+    ("raspbian", OsFamily::Linux),
+    ("freebsd", OsFamily::Linux),
+    ("linux", OsFamily::Linux),
+    ("microsoft-iis", OsFamily::Windows),
+    ("microsoft-httpapi", OsFamily::Windows),
+    ("microsoft ftp", OsFamily::Windows),
+    ("microsoft esmtp", OsFamily::Windows),
+    ("windows", OsFamily::Windows),
+    ("routeros", OsFamily::NetworkDevice),
+    ("mikrotik", OsFamily::NetworkDevice),
+    ("cisco", OsFamily::NetworkDevice),
+    ("junos", OsFamily::NetworkDevice),
+    ("fortigate", OsFamily::NetworkDevice),
+];
+
+const VENDOR_HINTS: &[(&str, OsFamily)] = &[
+    ("raspberry pi", OsFamily::Linux),
+    ("microsoft", OsFamily::Windows),
+    ("cisco", OsFamily::NetworkDevice),
+    ("juniper", OsFamily::NetworkDevice),
+    ("mikrotik", OsFamily::NetworkDevice),
+    ("routerboard", OsFamily::NetworkDevice),
+    ("ubiquiti", OsFamily::NetworkDevice),
+    ("aruba", OsFamily::NetworkDevice),
+    ("fortinet", OsFamily::NetworkDevice),
+];
+
+/// Map an observed TTL to the family whose default initial TTL it falls under.
+fn ttl_family(ttl: u8) -> OsFamily {
+    match ttl {
+        0..=64 => OsFamily::Linux,
+        65..=128 => OsFamily::Windows,
+        _ => OsFamily::NetworkDevice,
+    }
+}
+
+fn hint(text: Option<&str>, hints: &[(&str, OsFamily)]) -> Option<OsFamily> {
+    let t = text?.to_ascii_lowercase();
+    hints
+        .iter()
+        .find(|(needle, _)| t.contains(needle))
+        .map(|(_, f)| *f)
+}
+
+/// Family named by a banner-like string (sysDescr, Server header, ...).
+pub(crate) fn family_from_banner(text: &str) -> Option<OsFamily> {
+    hint(Some(text), BANNER_HINTS)
+}
+
+/// A named OS found in a service banner, finer grained than `OsFamily`
+/// ("Ubuntu" rather than "linux").
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OsHint {
+    pub os_family: String,
+    pub version_hint: Option<String>,
+}
+
+/// Provenance confidence for an OS named in a banner.
+pub const BANNER_OS_CONFIDENCE: u8 = 80;
+
+/// Banner OS rule: `re` must match; capture group `version`, if present and
+/// matched, becomes the version hint.
+struct OsRule {
+    os_family: &'static str,
+    re: Regex,
+}
+
+fn os_rule(os_family: &'static str, pattern: &str) -> OsRule {
+    OsRule {
+        os_family,
+        re: Regex::new(pattern).expect("valid os rule"),
+    }
+}
+
+// Ordered from most to least specific; the first match wins. Raspbian comes
+// before Debian because its OpenSSH builds also carry a `+debNNuN` suffix.
+static OS_RULES: Lazy<Vec<OsRule>> = Lazy::new(|| {
+    vec![
+        os_rule(
+            "Raspbian",
+            r"(?i)raspbian(?:.*?\bdeb(?P<version>\d{1,2})u\d)?",
+        ),
+        os_rule(
+            "Ubuntu",
+            r"(?i)ubuntu(?:[ /-]?(?P<version>\d{2}\.\d{2}(?:\.\d+)?))?",
+        ),
+        // OpenSSH packages: "OpenSSH_9.2p1 Debian-2+deb12u2"
+        os_rule("Debian", r"(?i)\bdeb(?P<version>\d{1,2})u\d"),
+        os_rule(
+            "Debian",
+            r"(?i)debian(?:[ /-]?(?P<version>\d{1,2}(?:\.\d+)?)\b)?",
+        ),
+        os_rule("CentOS", r"(?i)centos(?:[ /-]?(?P<version>\d+(?:\.\d+)*))?"),
+        os_rule(
+            "Red Hat",
+            r"(?i)red ?hat(?: enterprise linux)?(?:[ /-]?(?P<version>\d+(?:\.\d+)*))?",
+        ),
+        // The OpenSSH port suffix is a date ("FreeBSD-20230719"), not a release
+        os_rule(
+            "FreeBSD",
+            r"(?i)freebsd(?:[ /-]?(?P<version>\d{1,2}\.\d+))?",
+        ),
+        os_rule(
+            "Cisco IOS",
+            r"(?i)cisco ios.*?version (?P<version>\d[\w.()]*\w\)?)",
+        ),
+        os_rule("Cisco IOS", r"(?i)cisco[ _-]ios|^SSH-\d\.\d+-Cisco-"),
+        os_rule(
+            "Ubiquiti",
+            r"(?i)edge ?os v?(?P<version>\d+\.\d+(?:\.\d+)?)",
+        ),
+        os_rule("Ubiquiti", r"(?i)ubiquiti|\bubnt\b|edge ?os|unifi"),
+        os_rule(
+            "Windows",
+            r"(?i)windows(?: nt)?[ /-]?(?P<version>server \d{4}(?: r2)?|\d+(?:\.\d+)*)",
+        ),
+        os_rule(
+            "Windows",
+            r"(?i)windows|microsoft-(?:iis|httpapi)|microsoft (?:ftp|esmtp)",
+        ),
+    ]
+});
+
+/// Find an OS named in a service banner (SSH, FTP, HTTP `Server`, SNMP
+/// sysDescr, ...) using a priority-ordered rule list.
+pub fn fingerprint_os_from_banner(banner: &str) -> Option<OsHint> {
+    OS_RULES.iter().find_map(|rule| {
+        let caps = rule.re.captures(banner)?;
+        Some(OsHint {
+            os_family: rule.os_family.to_string(),
+            version_hint: caps.name("version").map(|m| m.as_str().to_string()),
+        })
+    })
+}
+
+/// Fill an empty `record.os` with the OS named in its banner, e.g.
+/// "Ubuntu", with "banner" provenance. Returns true when set.
+pub fn annotate_os_from_banner(record: &mut DiscoveryRecord) -> bool {
+    if record.os.is_some() {
+        return false;
+    }
+    let Some(hint) = record
+        .banner
+        .as_deref()
+        .and_then(fingerprint_os_from_banner)
+    else {
+        return false;
+    };
+    record.os = Some(hint.os_family);
+    record.set_provenance("os", "banner", BANNER_OS_CONFIDENCE);
+    true
+}
+
+/// Guess the OS family of the host behind `record`, optionally using an
+/// observed IP TTL. Returns None when there is no evidence at all.
+pub fn guess_os(record: &DiscoveryRecord, ttl: Option<u8>) -> Option<OsGuess> {
+    let votes = [
+        (ttl.map(ttl_family), TTL_WEIGHT),
+        (hint(record.vendor.as_deref(), VENDOR_HINTS), VENDOR_WEIGHT),
+        (
+            hint(record.banner.as_deref(), BANNER_HINTS)
+                .or_else(|| hint(record.product.as_deref(), BANNER_HINTS)),
+            BANNER_WEIGHT,
+        ),
+    ];
+
+    let mut best: Option<OsGuess> = None;
+    for family in [OsFamily::Linux, OsFamily::Windows, OsFamily::NetworkDevice] {
+        let score: u16 = votes
+            .iter()
+            .filter(|(f, _)| *f == Some(family))
+            .map(|(_, w)| *w as u16)
+            .sum();
+        if score > 0 && best.is_none_or(|b| score > b.confidence as u16) {
+            best = Some(OsGuess {
+                family,
+                confidence: score.min(100) as u8,
+            });
+        }
+    }
+    best
+}
+
+/// Store a guess in `record.os` if it is empty, with the guess confidence
+/// as provenance. Returns true when set.
+pub fn annotate_os(record: &mut DiscoveryRecord, ttl: Option<u8>) -> bool {
+    if record.os.is_some() {
+        return false;
+    }
+    match guess_os(record, ttl) {
+        Some(g) => {
+            record.os = Some(g.family.as_str().to_string());
+            record.set_provenance("os", "os_guess", g.confidence);
+            true
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fp(banner: &str) -> Option<(String, Option<String>)> {
+        fingerprint_os_from_banner(banner).map(|h| (h.os_family, h.version_hint))
+    }
+
+    fn hint(family: &str, version: Option<&str>) -> Option<(String, Option<String>)> {
+        Some((family.to_string(), version.map(|v| v.to_string())))
+    }
+
+    #[test]
+    fn fingerprints_os_families_from_banners() {
+        assert_eq!(
+            fp("SSH-2.0-OpenSSH_8.9p1 Ubuntu-3ubuntu0.6"),
+            hint("Ubuntu", None)
+        );
+        assert_eq!(
+            fp("220 ProFTPD Server (Ubuntu 22.04) ready"),
+            hint("Ubuntu", Some("22.04"))
+        );
+        assert_eq!(
+            fp("SSH-2.0-OpenSSH_9.2p1 Debian-2+deb12u2"),
+            hint("Debian", Some("12"))
+        );
+        assert_eq!(fp("Server: Apache/2.4.57 (Debian)"), hint("Debian", None));
+        assert_eq!(
+            fp("SSH-2.0-OpenSSH_7.9p1 Raspbian-10+deb10u2"),
+            hint("Raspbian", Some("10"))
+        );
+        assert_eq!(
+            fp("SSH-2.0-OpenSSH_9.3 FreeBSD-20230719"),
+            hint("FreeBSD", None)
+        );
+        assert_eq!(fp("FreeBSD 13.2-RELEASE"), hint("FreeBSD", Some("13.2")));
+        assert_eq!(
+            fp("Cisco IOS Software, C2960 Software (C2960-LANBASEK9-M), Version 15.0(2)SE11, RELEASE SOFTWARE (fc3)"),
+            hint("Cisco IOS", Some("15.0(2)SE11"))
+        );
+        assert_eq!(fp("SSH-2.0-Cisco-1.25"), hint("Cisco IOS", None));
+        assert_eq!(
+            fp("EdgeOS v2.0.9-hotfix.7"),
+            hint("Ubiquiti", Some("2.0.9"))
+        );
+        assert_eq!(fp("UBNT UniFi AP"), hint("Ubiquiti", None));
+        assert_eq!(
+            fp("Hardware: x86 Family 6 - Software: Windows Version 6.3 (Build 17763)"),
+            hint("Windows", None)
+        );
+        assert_eq!(
+            fp("Microsoft Windows Server 2019"),
+            hint("Windows", Some("Server 2019"))
+        );
+        assert_eq!(fp("Server: Microsoft-IIS/10.0"), hint("Windows", None));
+        assert_eq!(fp("SSH-2.0-dropbear_2022.83"), None);
+    }
+
+    #[test]
+    fn annotate_from_banner_only_fills_empty_os() {
+        let mut r = rec(Some("SSH-2.0-OpenSSH_8.9p1 Ubuntu-3ubuntu0.6"), None);
+        assert!(annotate_os_from_banner(&mut r));
+        assert_eq!(r.os.as_deref(), Some("Ubuntu"));
+        assert_eq!(r.provenance("os").unwrap().source, "banner");
+        r.banner = Some("Microsoft-IIS/10.0".into());
+        assert!(!annotate_os_from_banner(&mut r));
+        assert_eq!(r.os.as_deref(), Some("Ubuntu"));
+    }
+
+    fn rec(banner: Option<&str>, vendor: Option<&str>) -> DiscoveryRecord {
+        DiscoveryRecord::new("192.0.2.1", Some(22), banner, None, vendor, None)
+    }
+
+    #[test]
+    fn ttl_buckets() {
+        let r = rec(None, None);
+        let g = guess_os(&r, Some(64)).unwrap();
+        assert_eq!((g.family, g.confidence), (OsFamily::Linux, TTL_WEIGHT));
+        assert_eq!(guess_os(&r, Some(57)).unwrap().family, OsFamily::Linux);
+        assert_eq!(guess_os(&r, Some(128)).unwrap().family, OsFamily::Windows);
+        assert_eq!(guess_os(&r, Some(113)).unwrap().family, OsFamily::Windows);
+        assert_eq!(
+            guess_os(&r, Some(255)).unwrap().family,
+            OsFamily::NetworkDevice
+        );
+        assert!(guess_os(&r, None).is_none());
+    }
+
+    #[test]
+    fn banner_overrides_ttl() {
+        let r = rec(Some("SSH-2.0-OpenSSH_8.9p1 Ubuntu-3ubuntu0.6"), None);
+        let g = guess_os(&r, Some(128)).unwrap();
+        assert_eq!(g.family, OsFamily::Linux);
+        assert_eq!(g.confidence, BANNER_WEIGHT);
+
+        let iis = rec(Some("HTTP/1.1 200 OK Server: Microsoft-IIS/10.0"), None);
+        assert_eq!(guess_os(&iis, Some(64)).unwrap().family, OsFamily::Windows);
+
+        let mikrotik = rec(Some("220 MikroTik FTP server (MikroTik 6.49) ready"), None);
+        assert_eq!(
+            guess_os(&mikrotik, None).unwrap().family,
+            OsFamily::NetworkDevice
+        );
+    }
+
+    #[test]
+    fn agreeing_sources_raise_confidence() {
+        let r = rec(Some("RouterOS"), Some("Routerboard.com"));
+        let g = guess_os(&r, Some(255)).unwrap();
+        assert_eq!(g.family, OsFamily::NetworkDevice);
+        assert_eq!(g.confidence, 100);
+
+        let cisco = rec(None, Some("Cisco Systems, Inc"));
+        let g = guess_os(&cisco, Some(64)).unwrap();
+        // vendor (50) outweighs the TTL bucket (40)
+        assert_eq!(g.family, OsFamily::NetworkDevice);
+        assert_eq!(g.confidence, VENDOR_WEIGHT);
+    }
+
+    #[test]
+    fn annotate_only_fills_empty_os() {
+        let mut r = rec(None, None);
+        assert!(annotate_os(&mut r, Some(128)));
+        assert_eq!(r.os.as_deref(), Some("windows"));
+        assert_eq!(r.confidence("os"), 40);
+        assert!(!annotate_os(&mut r, Some(64)));
+        assert_eq!(r.os.as_deref(), Some("windows"));
+
+        let mut unknown = rec(None, None);
+        assert!(!annotate_os(&mut unknown, None));
+        assert!(unknown.os.is_none());
+    }
+}
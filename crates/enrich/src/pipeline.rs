@@ -0,0 +1,372 @@
+//! Composable enrichment: an `Enricher` trait plus an ordered `EnrichPipeline`.
+//!
+//! Enrichers only fill fields that are still empty, so the order of the
+//! pipeline decides which source wins (e.g. OUI before hostname heuristics).
+
+use crate::vendor::fill_vendor;
+use crate::{
+    annotate_os, classify_device, enrich_records_services, vendor_from_hostname, vendor_from_mac,
+};
+use formats::DiscoveryRecord;
+use std::process::Command;
+
+/// Provenance confidence recorded by the builtin enrichers. OS guesses and
+/// services carry their own scores (see `annotate_os`, `enrich_records_services`).
+pub const OUI_CONFIDENCE: u8 = 90;
+pub const HOSTNAME_CONFIDENCE: u8 = 50;
+pub const REVERSE_DNS_CONFIDENCE: u8 = 80;
+pub const DEVICE_CLASS_CONFIDENCE: u8 = 60;
+
+/// A single enrichment step applied to records in place.
+pub trait Enricher {
+    /// Short identifier used in pipeline reports.
+    fn name(&self) -> &str;
+
+    /// Fill in whatever this enricher knows about `record`. Implementations
+    /// must not overwrite fields that are already set.
+    fn enrich(&self, record: &mut DiscoveryRecord);
+
+    /// Enrich a batch of records; override when a batch lookup is cheaper.
+    fn enrich_all(&self, records: &mut [DiscoveryRecord]) {
+        for r in records.iter_mut() {
+            self.enrich(r);
+        }
+    }
+}
+
+/// Vendor from MAC via the OUI database (randomized MACs are labelled),
+/// spelled by `normalize_vendor_name`.
+pub struct OuiEnricher;
+
+impl Enricher for OuiEnricher {
+    fn name(&self) -> &str {
+        "oui"
+    }
+
+    fn enrich(&self, record: &mut DiscoveryRecord) {
+        if record.vendor.is_none() {
+            let vendor = record.mac.as_deref().and_then(vendor_from_mac);
+            fill_vendor(record, vendor.as_deref(), "oui", OUI_CONFIDENCE);
+        }
+    }
+}
+
+/// Vendor from the hostname heuristics table, using the record hostname
+/// (or the banner when no hostname is known), spelled by
+/// `normalize_vendor_name`.
+pub struct HostnameVendorEnricher;
+
+impl Enricher for HostnameVendorEnricher {
+    fn name(&self) -> &str {
+        "hostname"
+    }
+
+    fn enrich(&self, record: &mut DiscoveryRecord) {
+        if record.vendor.is_none() {
+            let vendor = record
+                .hostname
+                .as_deref()
+                .or(record.banner.as_deref())
+                .and_then(vendor_from_hostname);
+            fill_vendor(record, vendor.as_deref(), "hostname", HOSTNAME_CONFIDENCE);
+        }
+    }
+}
+
+type Resolver = Box<dyn Fn(&str) -> Option<String> + Send + Sync>;
+
+/// Reverse DNS (PTR) name stored in `hostname` when none is known yet.
+/// The default resolver shells out to `getent hosts <ip>`.
+pub struct ReverseDnsEnricher {
+    resolver: Resolver,
+}
+
+impl ReverseDnsEnricher {
+    pub fn new() -> Self {
+        Self {
+            resolver: Box::new(getent_reverse_lookup),
+        }
+    }
+
+    /// Use a custom resolver (ip -> name); handy for tests and custom DNS.
+    pub fn with_resolver<F>(resolver: F) -> Self
+    where
+        F: Fn(&str) -> Option<String> + Send + Sync + 'static,
+    {
+        Self {
+            resolver: Box::new(resolver),
+        }
+    }
+}
+
+impl Default for ReverseDnsEnricher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Enricher for ReverseDnsEnricher {
+    fn name(&self) -> &str {
+        "reverse_dns"
+    }
+
+    fn enrich(&self, record: &mut DiscoveryRecord) {
+        if record.hostname.is_none() {
+            record.hostname = (self.resolver)(&record.ip);
+            if record.hostname.is_some() {
+                record.set_provenance("hostname", "reverse_dns", REVERSE_DNS_CONFIDENCE);
+            }
+        }
+    }
+}
+
+/// Parse `getent hosts` output ("<ip> <name> [aliases...]") for the first name.
+fn getent_reverse_lookup(ip: &str) -> Option<String> {
+    let output = Command::new("getent").args(["hosts", ip]).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    stdout
+        .lines()
+        .next()
+        .and_then(|l| l.split_whitespace().nth(1))
+        .map(|s| s.trim_end_matches('.').to_string())
+}
+
+/// Device class label from the builtin classification rules.
+pub struct DeviceClassEnricher;
+
+impl Enricher for DeviceClassEnricher {
+    fn name(&self) -> &str {
+        "device_class"
+    }
+
+    fn enrich(&self, record: &mut DiscoveryRecord) {
+        if record.device_type.is_none() {
+            record.device_type = classify_device(record).map(|c| c.as_str().to_string());
+            if record.device_type.is_some() {
+                record.set_provenance("device_type", "device_class", DEVICE_CLASS_CONFIDENCE);
+            }
+        }
+    }
+}
+
+/// Service name and product from the port's banner (port fallback otherwise).
+pub struct ServiceEnricher;
+
+impl Enricher for ServiceEnricher {
+    fn name(&self) -> &str {
+        "service"
+    }
+
+    fn enrich(&self, record: &mut DiscoveryRecord) {
+        enrich_records_services(std::slice::from_mut(record));
+    }
+}
+
+/// OS family guess from banner/vendor hints (no TTL is available here).
+pub struct OsEnricher;
+
+impl Enricher for OsEnricher {
+    fn name(&self) -> &str {
+        "os"
+    }
+
+    fn enrich(&self, record: &mut DiscoveryRecord) {
+        annotate_os(record, None);
+    }
+}
+
+/// Per-enricher count of fields filled during a pipeline run, in run order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EnrichReport {
+    pub filled: Vec<(String, usize)>,
+}
+
+impl EnrichReport {
+    /// Fields filled by the enricher called `name` (0 if it didn't run).
+    pub fn filled_by(&self, name: &str) -> usize {
+        self.filled
+            .iter()
+            .filter(|(n, _)| n == name)
+            .map(|(_, c)| *c)
+            .sum()
+    }
+}
+
+/// An ordered sequence of enrichers.
+#[derive(Default)]
+pub struct EnrichPipeline {
+    steps: Vec<Box<dyn Enricher + Send + Sync>>,
+}
+
+impl EnrichPipeline {
+    /// An empty pipeline (no-op).
+    pub fn new() -> Self {
+        Self { steps: Vec::new() }
+    }
+
+    /// The builtin offline pipeline: OUI vendor, then hostname heuristics.
+    pub fn standard() -> Self {
+        Self::new().with(OuiEnricher).with(HostnameVendorEnricher)
+    }
+
+    /// Append an enricher (builder style).
+    pub fn with<E: Enricher + Send + Sync + 'static>(mut self, enricher: E) -> Self {
+        self.steps.push(Box::new(enricher));
+        self
+    }
+
+    /// Names of the configured enrichers, in run order.
+    pub fn names(&self) -> Vec<&str> {
+        self.steps.iter().map(|e| e.name()).collect()
+    }
+
+    /// Run every enricher over `records` in order and report how many fields
+    /// each one filled.
+    pub fn run(&self, records: &mut [DiscoveryRecord]) -> EnrichReport {
+        let mut report = EnrichReport::default();
+        for step in &self.steps {
+            let before: usize = records.iter().map(filled_fields).sum();
+            step.enrich_all(records);
+            let after: usize = records.iter().map(filled_fields).sum();
+            report
+                .filled
+                .push((step.name().to_string(), after.saturating_sub(before)));
+        }
+        report
+    }
+}
+
+fn filled_fields(r: &DiscoveryRecord) -> usize {
+    [
+        r.port.is_some(),
+        r.banner.is_some(),
+        r.hostname.is_some(),
+        r.mac.is_some(),
+        r.vendor.is_some(),
+        r.timestamp.is_some(),
+        r.device_type.is_some(),
+        r.service.is_some(),
+        r.product.is_some(),
+        r.os.is_some(),
+    ]
+    .iter()
+    .filter(|b| **b)
+    .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vmware_netgear() -> DiscoveryRecord {
+        DiscoveryRecord::new(
+            "192.0.2.5",
+            None,
+            Some("netgear-router.lan"),
+            Some("00:0c:29:aa:bb:cc"),
+            None,
+            None,
+        )
+    }
+
+    #[test]
+    fn oui_before_hostname_prefers_oui_vendor() {
+        let mut recs = vec![vmware_netgear()];
+        let report = EnrichPipeline::standard().run(&mut recs);
+        assert!(recs[0].vendor.as_deref().unwrap().contains("VMware"));
+        assert_eq!(report.filled_by("oui"), 1);
+        assert_eq!(report.filled_by("hostname"), 0);
+    }
+
+    #[test]
+    fn oui_vendor_uses_the_normalized_name() {
+        let mut recs = vec![vmware_netgear()];
+        EnrichPipeline::new().with(OuiEnricher).run(&mut recs);
+        // the registry spells it "VMware, Inc."
+        assert_eq!(recs[0].vendor.as_deref(), Some("VMware"));
+    }
+
+    #[test]
+    fn hostname_before_oui_prefers_hostname_vendor() {
+        let mut recs = vec![vmware_netgear()];
+        let pipeline = EnrichPipeline::new()
+            .with(HostnameVendorEnricher)
+            .with(OuiEnricher);
+        let report = pipeline.run(&mut recs);
+        assert_eq!(recs[0].vendor.as_deref(), Some("Netgear"));
+        assert_eq!(report.filled_by("hostname"), 1);
+        assert_eq!(report.filled_by("oui"), 0);
+    }
+
+    #[test]
+    fn merge_keeps_oui_vendor_over_hostname_guess() {
+        let mut guessed = vec![vmware_netgear()];
+        EnrichPipeline::new()
+            .with(HostnameVendorEnricher)
+            .run(&mut guessed);
+        assert_eq!(guessed[0].confidence("vendor"), HOSTNAME_CONFIDENCE);
+
+        let mut from_oui = vec![vmware_netgear()];
+        EnrichPipeline::new().with(OuiEnricher).run(&mut from_oui);
+        assert_eq!(from_oui[0].confidence("vendor"), OUI_CONFIDENCE);
+
+        let mut merged = guessed[0].clone();
+        merged.merge(&from_oui[0]);
+        assert!(merged.vendor.as_deref().unwrap().contains("VMware"));
+        assert_eq!(merged.provenance("vendor").unwrap().source, "oui");
+    }
+
+    #[test]
+    fn later_enrichers_do_not_clobber() {
+        let mut recs = vec![DiscoveryRecord::new(
+            "192.0.2.6",
+            None,
+            None,
+            Some("00:0c:29:aa:bb:cc"),
+            Some("Preset Vendor"),
+            None,
+        )];
+        recs[0].hostname = Some("printer.lan".into());
+        let pipeline = EnrichPipeline::standard().with(ReverseDnsEnricher::with_resolver(|_| {
+            Some("other.lan".into())
+        }));
+        let report = pipeline.run(&mut recs);
+        assert_eq!(recs[0].vendor.as_deref(), Some("Preset Vendor"));
+        assert_eq!(recs[0].hostname.as_deref(), Some("printer.lan"));
+        assert!(report.filled.iter().all(|(_, c)| *c == 0));
+    }
+
+    #[test]
+    fn reverse_dns_feeds_hostname_heuristics() {
+        let mut recs = vec![
+            DiscoveryRecord::new("192.0.2.7", None, None, None, None, None),
+            DiscoveryRecord::new("192.0.2.8", None, None, None, None, None),
+        ];
+        let pipeline = EnrichPipeline::new()
+            .with(ReverseDnsEnricher::with_resolver(|ip| {
+                (ip == "192.0.2.7").then(|| "UniFi-AP.lan".to_string())
+            }))
+            .with(HostnameVendorEnricher)
+            .with(DeviceClassEnricher);
+        assert_eq!(
+            pipeline.names(),
+            vec!["reverse_dns", "hostname", "device_class"]
+        );
+        let report = pipeline.run(&mut recs);
+        assert_eq!(recs[0].hostname.as_deref(), Some("UniFi-AP.lan"));
+        assert!(recs[0].banner.is_none());
+        assert_eq!(recs[0].vendor.as_deref(), Some("Ubiquiti"));
+        assert!(recs[1].hostname.is_none());
+        assert_eq!(
+            report.filled,
+            vec![
+                ("reverse_dns".to_string(), 1),
+                ("hostname".to_string(), 1),
+                ("device_class".to_string(), 0)
+            ]
+        );
+    }
+}
@@ -0,0 +1,257 @@
+//! Service/product/version identification from captured banners.
+//!
+//! Banners are matched by `netutils::portscan::fingerprint_service`; when
+//! there is no banner (or nothing matches) a port-number fallback supplies
+//! just the service name.
+
+use formats::DiscoveryRecord;
+pub use formats::PORT_SERVICE_CONFIDENCE;
+pub use netutils::portscan::ServiceInfo;
+use netutils::portscan::{fingerprint_service, port_to_service};
+
+/// The conventional service for `port` from netutils' port table, used when
+/// the banner says nothing.
+fn port_fallback(port: u16) -> Option<ServiceInfo> {
+    port_to_service(port, "tcp").map(ServiceInfo::from_name)
+}
+
+/// Identify the service behind `port` from its banner; falls back to the
+/// conventional service for the port when the banner is empty or unknown.
+pub fn identify_service(port: u16, banner: Option<&str>) -> Option<ServiceInfo> {
+    fingerprint_service(port, banner).or_else(|| port_fallback(port))
+}
+
+/// Provenance confidence for a service identified from its banner.
+pub const BANNER_SERVICE_CONFIDENCE: u8 = 90;
+
+/// Annotate records that carry a port with `service` and `product`. Existing
+/// values are kept unless their recorded confidence is lower than the new
+/// match, so a banner signature replaces a service guessed from the port
+/// table. Provenance is "banner" for signature matches and "port" for the
+/// port-number fallback. Returns the number of records that gained or
+/// upgraded a service.
+pub fn enrich_records_services(records: &mut [DiscoveryRecord]) -> usize {
+    let mut n = 0;
+    for r in records.iter_mut() {
+        let Some(port) = r.port else { continue };
+        let (info, source, confidence) = match fingerprint_service(port, r.banner.as_deref()) {
+            Some(info) => (info, "banner", BANNER_SERVICE_CONFIDENCE),
+            None => match port_fallback(port) {
+                Some(info) => (info, "port", PORT_SERVICE_CONFIDENCE),
+                None => continue,
+            },
+        };
+        if r.service.is_some() && r.confidence("service") >= confidence {
+            continue;
+        }
+        if r.product.is_none() {
+            r.product = info.product_label();
+            if r.product.is_some() {
+                r.set_provenance("product", source, confidence);
+            }
+        }
+        r.service = Some(info.name);
+        r.set_provenance("service", source, confidence);
+        n += 1;
+    }
+    n
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id(port: u16, banner: &str) -> (String, Option<String>, Option<String>) {
+        let i = identify_service(port, Some(banner)).expect("identified");
+        (i.name, i.product, i.version)
+    }
+
+    fn t(
+        service: &str,
+        product: Option<&str>,
+        version: Option<&str>,
+    ) -> (String, Option<String>, Option<String>) {
+        (
+            service.to_string(),
+            product.map(|s| s.to_string()),
+            version.map(|s| s.to_string()),
+        )
+    }
+
+    #[test]
+    fn ssh_banners() {
+        assert_eq!(
+            id(22, "SSH-2.0-OpenSSH_9.3p1 Debian-1"),
+            t("ssh", Some("OpenSSH"), Some("9.3p1"))
+        );
+        assert_eq!(
+            id(2222, "SSH-2.0-dropbear_2022.83"),
+            t("ssh", Some("dropbear"), Some("2022.83"))
+        );
+        assert_eq!(
+            id(22, "SSH-2.0-Cisco-1.25"),
+            t("ssh", Some("Cisco"), Some("1.25"))
+        );
+    }
+
+    #[test]
+    fn ftp_banners() {
+        assert_eq!(
+            id(21, "220 (vsFTPd 3.0.5)"),
+            t("ftp", Some("vsFTPd"), Some("3.0.5"))
+        );
+        assert_eq!(
+            id(21, "220 ProFTPD 1.3.8 Server (Debian) [::ffff:192.0.2.4]"),
+            t("ftp", Some("ProFTPD"), Some("1.3.8"))
+        );
+        assert_eq!(
+            id(21, "220 Microsoft FTP Service"),
+            t("ftp", Some("Microsoft ftpd"), None)
+        );
+    }
+
+    #[test]
+    fn smtp_banners() {
+        assert_eq!(
+            id(25, "220 mail.example.com ESMTP Postfix (Ubuntu)"),
+            t("smtp", Some("Postfix"), None)
+        );
+        assert_eq!(
+            id(
+                25,
+                "220 mx.example.org ESMTP Exim 4.96 Mon, 03 Nov 2025 10:00:00 +0000"
+            ),
+            t("smtp", Some("Exim"), Some("4.96"))
+        );
+        assert_eq!(
+            id(
+                587,
+                "220 ex01.corp.local Microsoft ESMTP MAIL Service ready"
+            ),
+            t("smtp", Some("Microsoft Exchange smtpd"), None)
+        );
+    }
+
+    #[test]
+    fn http_banners_raw_and_normalized() {
+        assert_eq!(
+            id(
+                80,
+                "HTTP/1.1 400 Bad Request\r\nServer: nginx/1.24.0\r\nContent-Length: 0\r\n"
+            ),
+            t("http", Some("nginx"), Some("1.24.0"))
+        );
+        assert_eq!(
+            id(
+                8080,
+                "HTTP/1.0 200 OK Server: Apache/2.4.57 (Debian) Date: Mon"
+            ),
+            t("http", Some("Apache"), Some("2.4.57"))
+        );
+        assert_eq!(id(80, "HTTP/1.1 400 Bad Request"), t("http", None, None));
+        assert_eq!(
+            id(443, "HTTP/1.1 400 Bad Request Server: cloudflare"),
+            t("https", Some("cloudflare"), None)
+        );
+    }
+
+    #[test]
+    fn redis_and_mail_store_banners() {
+        assert_eq!(
+            id(6379, "-NOAUTH Authentication required."),
+            t("redis", Some("Redis"), None)
+        );
+        assert_eq!(
+            id(
+                6379,
+                "$3671 # Server redis_version:7.2.4 redis_git_sha1:00000000"
+            ),
+            t("redis", Some("Redis"), Some("7.2.4"))
+        );
+        assert_eq!(
+            id(110, "+OK Dovecot (Ubuntu) ready."),
+            t("pop3", Some("Dovecot"), None)
+        );
+        assert_eq!(
+            id(
+                143,
+                "* OK [CAPABILITY IMAP4rev1 SASL-IR LOGIN-REFERRALS] Dovecot ready."
+            ),
+            t("imap", Some("Dovecot"), None)
+        );
+    }
+
+    #[test]
+    fn mysql_greetings() {
+        // raw handshake bytes rendered lossily: length, seq, protocol 10, version\0 ...
+        let raw = "J\0\0\0\n8.0.36\0\u{1}\0\0\0abc\0caching_sha2_password\0";
+        assert_eq!(id(3306, raw), t("mysql", Some("MySQL"), Some("8.0.36")));
+        // normalized form (control characters stripped)
+        let norm = "n5.5.5-10.6.12-MariaDB-0ubuntu0.22.04.1xyz mysql_native_password";
+        assert_eq!(id(3306, norm), t("mysql", Some("MariaDB"), Some("10.6.12")));
+    }
+
+    #[test]
+    fn port_fallback_for_empty_or_unknown_banner() {
+        assert_eq!(identify_service(22, None).unwrap().name, "ssh");
+        assert_eq!(
+            identify_service(9100, Some("  ")).unwrap().name,
+            "jetdirect"
+        );
+        assert_eq!(
+            identify_service(3389, Some("\u{3}\0\0\u{13}"))
+                .unwrap()
+                .name,
+            "ms-wbt-server"
+        );
+        assert!(identify_service(40000, None).is_none());
+    }
+
+    #[test]
+    fn enrich_records_sets_service_and_product() {
+        let mut recs = vec![
+            DiscoveryRecord::new(
+                "192.0.2.1",
+                Some(22),
+                Some("SSH-2.0-OpenSSH_9.3p1"),
+                None,
+                None,
+                None,
+            ),
+            DiscoveryRecord::new("192.0.2.1", Some(80), None, None, None, None),
+            DiscoveryRecord::new("192.0.2.2", None, Some("host.lan"), None, None, None),
+        ];
+        assert_eq!(enrich_records_services(&mut recs), 2);
+        assert_eq!(recs[0].service.as_deref(), Some("ssh"));
+        assert_eq!(recs[0].product.as_deref(), Some("OpenSSH 9.3p1"));
+        assert_eq!(recs[1].service.as_deref(), Some("http"));
+        assert!(recs[1].product.is_none());
+        assert_eq!(recs[0].provenance("service").unwrap().source, "banner");
+        assert_eq!(recs[1].confidence("service"), PORT_SERVICE_CONFIDENCE);
+        // hosts without a port are left alone
+        assert!(recs[2].service.is_none());
+    }
+
+    #[test]
+    fn banner_match_replaces_port_table_service() {
+        let mut guessed = DiscoveryRecord::new(
+            "192.0.2.1",
+            Some(8080),
+            Some("SSH-2.0-OpenSSH_9.3p1"),
+            None,
+            None,
+            None,
+        );
+        guessed.service = Some("http-proxy".into());
+        guessed.set_provenance("service", "port", PORT_SERVICE_CONFIDENCE);
+        let mut given = DiscoveryRecord::new("192.0.2.1", Some(22), None, None, None, None);
+        given.service = Some("custom".into());
+
+        let mut recs = vec![guessed, given];
+        assert_eq!(enrich_records_services(&mut recs), 1);
+        assert_eq!(recs[0].service.as_deref(), Some("ssh"));
+        assert_eq!(recs[0].provenance("service").unwrap().source, "banner");
+        // values without provenance count as authoritative
+        assert_eq!(recs[1].service.as_deref(), Some("custom"));
+    }
+}
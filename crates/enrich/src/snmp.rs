@@ -0,0 +1,364 @@
+//! SNMPv2c sysDescr / sysName lookup.
+//!
+//! Builds a GetRequest for two OIDs with `netutils::ber` and decodes just
+//! enough BER to read back the GetResponse; no MIB handling. Unreachable
+//! hosts, wrong communities (v2c agents silently drop those) and malformed
+//! replies all come back as None.
+
+use crate::os::family_from_banner;
+use formats::DiscoveryRecord;
+use netutils::ber::{self, tlv, TAG_INTEGER, TAG_NULL, TAG_OCTET_STRING, TAG_OID, TAG_SEQUENCE};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr, UdpSocket};
+use std::sync::Mutex;
+use std::time::Duration;
+
+const SNMP_PORT: u16 = 161;
+const SNMP_V2C: i64 = 1;
+
+/// 1.3.6.1.2.1.1.1.0
+const SYS_DESCR: &[u32] = &[1, 3, 6, 1, 2, 1, 1, 1, 0];
+/// 1.3.6.1.2.1.1.5.0
+const SYS_NAME: &[u32] = &[1, 3, 6, 1, 2, 1, 1, 5, 0];
+
+// sysName is set by the device's administrator; the OS family is only a
+// keyword match on sysDescr.
+const SYS_NAME_CONFIDENCE: u8 = 90;
+const SYS_DESCR_OS_CONFIDENCE: u8 = 70;
+
+const TAG_GET_REQUEST: u8 = 0xa0;
+const TAG_GET_RESPONSE: u8 = 0xa2;
+
+/// System identity reported by an SNMP agent.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SnmpSysInfo {
+    /// sysDescr.0, usually OS / firmware and hardware
+    pub descr: Option<String>,
+    /// sysName.0, the administratively assigned name
+    pub name: Option<String>,
+}
+
+/// Encode an SNMPv2c GetRequest for sysDescr.0 and sysName.0.
+pub fn encode_get_request(community: &str, request_id: i32) -> Vec<u8> {
+    let varbinds: Vec<u8> = [SYS_DESCR, SYS_NAME]
+        .iter()
+        .flat_map(|oid| tlv(TAG_SEQUENCE, &[ber::oid(oid), tlv(TAG_NULL, &[])].concat()))
+        .collect();
+    let pdu = [
+        ber::integer(request_id as i64),
+        ber::integer(0),
+        ber::integer(0),
+        tlv(TAG_SEQUENCE, &varbinds),
+    ]
+    .concat();
+    let msg = [
+        ber::integer(SNMP_V2C),
+        tlv(TAG_OCTET_STRING, community.as_bytes()),
+        tlv(TAG_GET_REQUEST, &pdu),
+    ]
+    .concat();
+    tlv(TAG_SEQUENCE, &msg)
+}
+
+/// Split one TLV off the front of `buf`: (tag, value, rest).
+fn read_tlv(buf: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, rest) = buf.split_first()?;
+    let (&first, rest) = rest.split_first()?;
+    let (len, rest) = if first & 0x80 == 0 {
+        (first as usize, rest)
+    } else {
+        let n = (first & 0x7f) as usize;
+        if n == 0 || n > 4 || rest.len() < n {
+            return None;
+        }
+        let len = rest[..n]
+            .iter()
+            .fold(0usize, |acc, b| (acc << 8) | *b as usize);
+        (len, &rest[n..])
+    };
+    if rest.len() < len {
+        return None;
+    }
+    Some((tag, &rest[..len], &rest[len..]))
+}
+
+fn expect_tlv(buf: &[u8], tag: u8) -> Option<(&[u8], &[u8])> {
+    match read_tlv(buf)? {
+        (t, value, rest) if t == tag => Some((value, rest)),
+        _ => None,
+    }
+}
+
+fn decode_integer(value: &[u8]) -> Option<i64> {
+    if value.is_empty() || value.len() > 8 {
+        return None;
+    }
+    let init = if value[0] & 0x80 != 0 { -1i64 } else { 0 };
+    Some(value.iter().fold(init, |acc, b| (acc << 8) | *b as i64))
+}
+
+fn decode_oid(value: &[u8]) -> Option<Vec<u32>> {
+    let (&first, rest) = value.split_first()?;
+    let mut oid = vec![(first / 40) as u32, (first % 40) as u32];
+    let mut arc: u32 = 0;
+    for &b in rest {
+        arc = arc.checked_mul(128)? | (b & 0x7f) as u32;
+        if b & 0x80 == 0 {
+            oid.push(arc);
+            arc = 0;
+        }
+    }
+    Some(oid)
+}
+
+/// Decode a GetResponse for the request with `request_id`. Returns None for
+/// anything else: other PDUs, mismatched ids, error-status set, or garbage.
+/// Varbinds holding exceptions (noSuchObject etc.) are left as None.
+pub fn decode_get_response(buf: &[u8], request_id: i32) -> Option<SnmpSysInfo> {
+    let (msg, _) = expect_tlv(buf, TAG_SEQUENCE)?;
+    let (version, msg) = expect_tlv(msg, TAG_INTEGER)?;
+    if decode_integer(version)? != SNMP_V2C {
+        return None;
+    }
+    let (_community, msg) = expect_tlv(msg, TAG_OCTET_STRING)?;
+    let (pdu, _) = expect_tlv(msg, TAG_GET_RESPONSE)?;
+    let (id, pdu) = expect_tlv(pdu, TAG_INTEGER)?;
+    let (error_status, pdu) = expect_tlv(pdu, TAG_INTEGER)?;
+    let (_error_index, pdu) = expect_tlv(pdu, TAG_INTEGER)?;
+    if decode_integer(id)? != request_id as i64 || decode_integer(error_status)? != 0 {
+        return None;
+    }
+
+    let (mut varbinds, _) = expect_tlv(pdu, TAG_SEQUENCE)?;
+    let mut info = SnmpSysInfo::default();
+    while !varbinds.is_empty() {
+        let (vb, rest) = expect_tlv(varbinds, TAG_SEQUENCE)?;
+        varbinds = rest;
+        let (oid, vb) = expect_tlv(vb, TAG_OID)?;
+        let (tag, value, _) = read_tlv(vb)?;
+        if tag != TAG_OCTET_STRING {
+            continue;
+        }
+        let text = String::from_utf8_lossy(value).trim().to_string();
+        if text.is_empty() {
+            continue;
+        }
+        match decode_oid(oid)?.as_slice() {
+            SYS_DESCR => info.descr = Some(text),
+            SYS_NAME => info.name = Some(text),
+            _ => {}
+        }
+    }
+    Some(info)
+}
+
+/// Query `ip` for sysDescr and sysName with an SNMPv2c GET. Returns None on
+/// timeout, wrong community, error responses or when neither value is set.
+pub fn snmp_sysinfo(ip: IpAddr, community: &str, timeout: Duration) -> Option<SnmpSysInfo> {
+    let bind: SocketAddr = match ip {
+        IpAddr::V4(_) => "0.0.0.0:0".parse().ok()?,
+        IpAddr::V6(_) => "[::]:0".parse().ok()?,
+    };
+    let socket = UdpSocket::bind(bind).ok()?;
+    socket.set_read_timeout(Some(timeout)).ok()?;
+    socket.connect(SocketAddr::new(ip, SNMP_PORT)).ok()?;
+
+    let request_id = (std::process::id() as i32 ^ 0x5a5a) & 0x7fff_ffff;
+    socket
+        .send(&encode_get_request(community, request_id))
+        .ok()?;
+    let mut buf = [0u8; 4096];
+    let n = socket.recv(&mut buf).ok()?;
+    decode_get_response(&buf[..n], request_id).filter(|i| i.descr.is_some() || i.name.is_some())
+}
+
+/// Fill records from SNMP: `hostname` from sysName and os from the
+/// family recognised in sysDescr, only where empty. Each distinct IP is
+/// queried once, `concurrency` at a time. Returns the number of records changed.
+pub fn enrich_records_snmp(
+    records: &mut [DiscoveryRecord],
+    community: &str,
+    timeout: Duration,
+    concurrency: usize,
+) -> usize {
+    let mut ips: Vec<IpAddr> = records.iter().filter_map(|r| r.ip.parse().ok()).collect();
+    ips.sort();
+    ips.dedup();
+
+    let queue = Mutex::new(ips.into_iter());
+    let found: Mutex<HashMap<String, SnmpSysInfo>> = Mutex::new(HashMap::new());
+    std::thread::scope(|s| {
+        for _ in 0..concurrency.max(1) {
+            s.spawn(|| loop {
+                let Some(ip) = queue.lock().unwrap().next() else {
+                    break;
+                };
+                if let Some(info) = snmp_sysinfo(ip, community, timeout) {
+                    found.lock().unwrap().insert(ip.to_string(), info);
+                }
+            });
+        }
+    });
+    apply_snmp_sysinfo(records, &found.into_inner().unwrap())
+}
+
+/// Apply already collected results keyed by IP string (see `enrich_records_snmp`).
+pub fn apply_snmp_sysinfo(
+    records: &mut [DiscoveryRecord],
+    results: &HashMap<String, SnmpSysInfo>,
+) -> usize {
+    let mut changed = 0;
+    for r in records.iter_mut() {
+        let Some(info) = results.get(&r.ip) else {
+            continue;
+        };
+        let mut touched = false;
+        if r.hostname.is_none() {
+            if let Some(name) = &info.name {
+                r.hostname = Some(name.clone());
+                r.set_provenance("hostname", "snmp", SYS_NAME_CONFIDENCE);
+                touched = true;
+            }
+        }
+        if r.os.is_none() {
+            if let Some(family) = info.descr.as_deref().and_then(family_from_banner) {
+                r.os = Some(family.as_str().to_string());
+                r.set_provenance("os", "snmp", SYS_DESCR_OS_CONFIDENCE);
+                touched = true;
+            }
+        }
+        if touched {
+            changed += 1;
+        }
+    }
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REQUEST_ID: i32 = 0x1a2b_3c4d;
+
+    // GetRequest for sysDescr.0 + sysName.0, community "public".
+    const GET_REQUEST: &[u8] = &[
+        0x30, 0x37, 0x02, 0x01, 0x01, 0x04, 0x06, 0x70, 0x75, 0x62, 0x6c, 0x69, //
+        0x63, 0xa0, 0x2a, 0x02, 0x04, 0x1a, 0x2b, 0x3c, 0x4d, 0x02, 0x01, 0x00, //
+        0x02, 0x01, 0x00, 0x30, 0x1c, 0x30, 0x0c, 0x06, 0x08, 0x2b, 0x06, 0x01, //
+        0x02, 0x01, 0x01, 0x01, 0x00, 0x05, 0x00, 0x30, 0x0c, 0x06, 0x08, 0x2b, //
+        0x06, 0x01, 0x02, 0x01, 0x01, 0x05, 0x00, 0x05, 0x00,
+    ];
+
+    // Response from a Linux-based switch.
+    const LINUX_SWITCH_RESPONSE: &[u8] = &[
+        0x30, 0x66, 0x02, 0x01, 0x01, 0x04, 0x06, 0x70, 0x75, 0x62, 0x6c, 0x69, //
+        0x63, 0xa2, 0x59, 0x02, 0x04, 0x1a, 0x2b, 0x3c, 0x4d, 0x02, 0x01, 0x00, //
+        0x02, 0x01, 0x00, 0x30, 0x4b, 0x30, 0x31, 0x06, 0x08, 0x2b, 0x06, 0x01, //
+        0x02, 0x01, 0x01, 0x01, 0x00, 0x04, 0x25, 0x4c, 0x69, 0x6e, 0x75, 0x78, //
+        0x20, 0x73, 0x77, 0x2d, 0x63, 0x6f, 0x72, 0x65, 0x2d, 0x30, 0x31, 0x20, //
+        0x34, 0x2e, 0x31, 0x39, 0x2e, 0x30, 0x20, 0x23, 0x31, 0x20, 0x53, 0x4d, //
+        0x50, 0x20, 0x61, 0x72, 0x6d, 0x76, 0x37, 0x6c, 0x30, 0x16, 0x06, 0x08, //
+        0x2b, 0x06, 0x01, 0x02, 0x01, 0x01, 0x05, 0x00, 0x04, 0x0a, 0x73, 0x77, //
+        0x2d, 0x63, 0x6f, 0x72, 0x65, 0x2d, 0x30, 0x31,
+    ];
+
+    // Cisco IOS response where sysName came back as noSuchObject.
+    const NO_SUCH_NAME_RESPONSE: &[u8] = &[
+        0x30, 0x59, 0x02, 0x01, 0x01, 0x04, 0x06, 0x70, 0x75, 0x62, 0x6c, 0x69, //
+        0x63, 0xa2, 0x4c, 0x02, 0x04, 0x1a, 0x2b, 0x3c, 0x4d, 0x02, 0x01, 0x00, //
+        0x02, 0x01, 0x00, 0x30, 0x3e, 0x30, 0x2e, 0x06, 0x08, 0x2b, 0x06, 0x01, //
+        0x02, 0x01, 0x01, 0x01, 0x00, 0x04, 0x22, 0x43, 0x69, 0x73, 0x63, 0x6f, //
+        0x20, 0x49, 0x4f, 0x53, 0x20, 0x53, 0x6f, 0x66, 0x74, 0x77, 0x61, 0x72, //
+        0x65, 0x2c, 0x20, 0x43, 0x32, 0x39, 0x36, 0x30, 0x20, 0x53, 0x6f, 0x66, //
+        0x74, 0x77, 0x61, 0x72, 0x65, 0x30, 0x0c, 0x06, 0x08, 0x2b, 0x06, 0x01, //
+        0x02, 0x01, 0x01, 0x05, 0x00, 0x80, 0x00,
+    ];
+
+    #[test]
+    fn encodes_get_request() {
+        assert_eq!(encode_get_request("public", REQUEST_ID), GET_REQUEST);
+    }
+
+    #[test]
+    fn ber_primitives_roundtrip() {
+        for v in [0i64, 1, 127, 128, 255, 256, -1, -129, i32::MAX as i64] {
+            let enc = ber::integer(v);
+            let (tag, value, rest) = read_tlv(&enc).unwrap();
+            assert_eq!((tag, rest.len()), (TAG_INTEGER, 0));
+            assert_eq!(decode_integer(value), Some(v), "{v}");
+        }
+        let oid = [1, 3, 6, 1, 4, 1, 9, 300, 70000];
+        let enc = ber::oid(&oid);
+        let (_, value, _) = read_tlv(&enc).unwrap();
+        assert_eq!(decode_oid(value).unwrap(), oid);
+
+        let long = tlv(TAG_OCTET_STRING, &[b'x'; 300]);
+        assert_eq!(&long[..4], &[0x04, 0x82, 0x01, 0x2c]);
+        assert_eq!(read_tlv(&long).unwrap().1.len(), 300);
+    }
+
+    #[test]
+    fn decodes_get_response() {
+        let info = decode_get_response(LINUX_SWITCH_RESPONSE, REQUEST_ID).unwrap();
+        assert_eq!(
+            info.descr.as_deref(),
+            Some("Linux sw-core-01 4.19.0 #1 SMP armv7l")
+        );
+        assert_eq!(info.name.as_deref(), Some("sw-core-01"));
+
+        let partial = decode_get_response(NO_SUCH_NAME_RESPONSE, REQUEST_ID).unwrap();
+        assert!(partial.descr.unwrap().starts_with("Cisco IOS"));
+        assert!(partial.name.is_none());
+    }
+
+    #[test]
+    fn rejects_mismatched_or_malformed_responses() {
+        assert!(decode_get_response(LINUX_SWITCH_RESPONSE, REQUEST_ID + 1).is_none());
+        assert!(decode_get_response(GET_REQUEST, REQUEST_ID).is_none());
+        assert!(decode_get_response(&LINUX_SWITCH_RESPONSE[..40], REQUEST_ID).is_none());
+        assert!(decode_get_response(&[], REQUEST_ID).is_none());
+
+        // error-status = noSuchName(2)
+        let mut err = LINUX_SWITCH_RESPONSE.to_vec();
+        err[23] = 0x02;
+        assert!(decode_get_response(&err, REQUEST_ID).is_none());
+    }
+
+    #[test]
+    fn non_responder_yields_none() {
+        assert!(snmp_sysinfo(
+            "127.0.0.1".parse().unwrap(),
+            "wrong",
+            Duration::from_millis(100)
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn applies_sysinfo_without_clobbering() {
+        let mut recs = vec![
+            DiscoveryRecord::new("192.0.2.1", None, None, None, None, None),
+            DiscoveryRecord::new("192.0.2.2", None, None, None, None, None),
+            DiscoveryRecord::new("192.0.2.3", None, None, None, None, None),
+        ];
+        recs[1].hostname = Some("printer.lan".into());
+        let mut results = HashMap::new();
+        results.insert(
+            "192.0.2.1".to_string(),
+            decode_get_response(LINUX_SWITCH_RESPONSE, REQUEST_ID).unwrap(),
+        );
+        results.insert(
+            "192.0.2.2".to_string(),
+            decode_get_response(NO_SUCH_NAME_RESPONSE, REQUEST_ID).unwrap(),
+        );
+        assert_eq!(apply_snmp_sysinfo(&mut recs, &results), 2);
+        assert_eq!(recs[0].hostname.as_deref(), Some("sw-core-01"));
+        assert_eq!(recs[0].os.as_deref(), Some("linux"));
+        assert_eq!(recs[1].hostname.as_deref(), Some("printer.lan"));
+        assert_eq!(recs[1].os.as_deref(), Some("network"));
+        assert_eq!(
+            recs[2],
+            DiscoveryRecord::new("192.0.2.3", None, None, None, None, None)
+        );
+    }
+}
@@ -0,0 +1,429 @@
+//! SSDP/UPnP discovery and device-description enrichment (`ssdp` feature).
+//!
+//! `discover_ssdp` multicasts an M-SEARCH and collects the unicast replies;
+//! `fetch_description` downloads the UPnP device description XML (plain HTTP,
+//! short overall deadline, size cap, only from the device that answered)
+//! and extracts the root device's identity. Parsing is deliberately
+//! minimal: only the handful of fields we use are read.
+
+use crate::vendor::fill_vendor;
+use formats::DiscoveryRecord;
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::{Read, Write};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpStream, UdpSocket};
+use std::time::{Duration, Instant};
+
+const SSDP_MULTICAST: (Ipv4Addr, u16) = (Ipv4Addr::new(239, 255, 255, 250), 1900);
+/// Deadline for a whole description fetch: connect, request and response.
+pub const DESCRIPTION_TIMEOUT: Duration = Duration::from_secs(2);
+/// Largest description document we are willing to read.
+pub const MAX_DESCRIPTION_BYTES: usize = 64 * 1024;
+/// Provenance confidence for fields taken from a device description.
+const SSDP_CONFIDENCE: u8 = 80;
+
+/// A device that answered an SSDP M-SEARCH.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SsdpDevice {
+    /// Source IP of the response
+    pub ip: String,
+    /// URL of the device description XML
+    pub location: Option<String>,
+    pub server: Option<String>,
+    pub st: Option<String>,
+    pub usn: Option<String>,
+}
+
+/// Identity fields from a UPnP device description (root device).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeviceDescription {
+    pub friendly_name: Option<String>,
+    pub manufacturer: Option<String>,
+    pub model_name: Option<String>,
+    pub model_number: Option<String>,
+    pub device_type: Option<String>,
+}
+
+/// Parse one SSDP search response (or NOTIFY) received from `ip`. Returns
+/// None unless it is a `200 OK` response or a `NOTIFY` announcement.
+pub fn parse_ssdp_response(ip: &str, payload: &[u8]) -> Option<SsdpDevice> {
+    let text = String::from_utf8_lossy(payload);
+    let mut lines = text.lines();
+    let status = lines.next()?.trim();
+    let is_response =
+        status.starts_with("HTTP/") && status.split_whitespace().nth(1) == Some("200");
+    if !is_response && !status.starts_with("NOTIFY ") {
+        return None;
+    }
+
+    let mut headers: HashMap<String, String> = HashMap::new();
+    for line in lines {
+        if let Some((k, v)) = line.split_once(':') {
+            let v = v.trim();
+            if !v.is_empty() {
+                headers.insert(k.trim().to_ascii_lowercase(), v.to_string());
+            }
+        }
+    }
+    Some(SsdpDevice {
+        ip: ip.to_string(),
+        location: headers.remove("location"),
+        server: headers.remove("server"),
+        // NOTIFY uses NT where search responses use ST
+        st: headers.remove("st").or_else(|| headers.remove("nt")),
+        usn: headers.remove("usn"),
+    })
+}
+
+/// Multicast an M-SEARCH for all devices and collect responses until
+/// `timeout` elapses. Responses are de-duplicated by (ip, location).
+pub fn discover_ssdp(timeout: Duration) -> Vec<SsdpDevice> {
+    let socket = match UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)) {
+        Ok(s) => s,
+        Err(_) => return Vec::new(),
+    };
+    let mx = timeout.as_secs().clamp(1, 5);
+    let msearch = format!(
+        "M-SEARCH * HTTP/1.1\r\nHOST: 239.255.255.250:1900\r\nMAN: \"ssdp:discover\"\r\nMX: {}\r\nST: ssdp:all\r\n\r\n",
+        mx
+    );
+    if socket.send_to(msearch.as_bytes(), SSDP_MULTICAST).is_err() {
+        return Vec::new();
+    }
+
+    let deadline = Instant::now() + timeout;
+    let mut out: Vec<SsdpDevice> = Vec::new();
+    let mut buf = [0u8; 2048];
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() || socket.set_read_timeout(Some(remaining)).is_err() {
+            break;
+        }
+        match socket.recv_from(&mut buf) {
+            Ok((n, src)) => {
+                if let Some(dev) = parse_ssdp_response(&src.ip().to_string(), &buf[..n]) {
+                    if !out
+                        .iter()
+                        .any(|d| d.ip == dev.ip && d.location == dev.location)
+                    {
+                        out.push(dev);
+                    }
+                }
+            }
+            Err(_) => break,
+        }
+    }
+    out
+}
+
+/// Return the text of the first `<tag>...</tag>` element, entity-unescaped.
+fn xml_text(xml: &str, tag: &str) -> Option<String> {
+    let open = format!("<{}>", tag);
+    let close = format!("</{}>", tag);
+    let start = xml.find(&open)? + open.len();
+    let end = start + xml[start..].find(&close)?;
+    let text = xml[start..end]
+        .trim()
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&");
+    if text.is_empty() {
+        None
+    } else {
+        Some(text)
+    }
+}
+
+/// Parse a UPnP device description document. The root device's fields come
+/// before any embedded `deviceList`, so the first occurrence of each tag wins.
+pub fn parse_description(xml: &str) -> DeviceDescription {
+    DeviceDescription {
+        friendly_name: xml_text(xml, "friendlyName"),
+        manufacturer: xml_text(xml, "manufacturer"),
+        model_name: xml_text(xml, "modelName"),
+        model_number: xml_text(xml, "modelNumber"),
+        device_type: xml_text(xml, "deviceType"),
+    }
+}
+
+/// Split `http://host[:port]/path` into (host, port, path).
+fn split_http_url(url: &str) -> Result<(String, u16, String), Box<dyn Error>> {
+    let rest = url
+        .strip_prefix("http://")
+        .ok_or_else(|| format!("unsupported description url: {}", url))?;
+    let (authority, path) = match rest.find('/') {
+        Some(i) => (&rest[..i], &rest[i..]),
+        None => (rest, "/"),
+    };
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((h, p)) => (h, p.parse::<u16>()?),
+        None => (authority, 80),
+    };
+    if host.is_empty() {
+        return Err(format!("missing host in url: {}", url).into());
+    }
+    Ok((host.to_string(), port, path.to_string()))
+}
+
+/// Fetch and parse the description XML that `device_ip` advertised at
+/// `location_url` (plain HTTP only). The URL host must be `device_ip`
+/// itself, so a responder cannot point the scanner at another machine and
+/// no name is resolved. The whole fetch is bounded by `DESCRIPTION_TIMEOUT`
+/// and the body by `MAX_DESCRIPTION_BYTES`; oversized documents and slow
+/// servers are errors.
+pub fn fetch_description(
+    device_ip: IpAddr,
+    location_url: &str,
+) -> Result<DeviceDescription, Box<dyn Error>> {
+    let (host, port, path) = split_http_url(location_url)?;
+    let ip: IpAddr = host
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .parse()
+        .map_err(|_| format!("description host is not an address: {}", host))?;
+    if ip != device_ip {
+        return Err(format!("description host {} is not the device {}", ip, device_ip).into());
+    }
+    let deadline = Instant::now() + DESCRIPTION_TIMEOUT;
+    let remaining = || -> Result<Duration, Box<dyn Error>> {
+        match deadline.saturating_duration_since(Instant::now()) {
+            d if d.is_zero() => Err("description fetch timed out".into()),
+            d => Ok(d),
+        }
+    };
+    let mut stream = TcpStream::connect_timeout(&SocketAddr::new(ip, port), remaining()?)?;
+    stream.set_write_timeout(Some(remaining()?))?;
+    // HTTP/1.0 keeps the response un-chunked and closes the connection after
+    let request = format!(
+        "GET {} HTTP/1.0\r\nHost: {}:{}\r\nConnection: close\r\n\r\n",
+        path, host, port
+    );
+    stream.write_all(request.as_bytes())?;
+
+    // headers + body, capped; each read only gets what is left of the deadline
+    let limit = MAX_DESCRIPTION_BYTES + 4096;
+    let mut raw = Vec::new();
+    let mut buf = [0u8; 4096];
+    loop {
+        stream.set_read_timeout(Some(remaining()?))?;
+        let n = stream.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        raw.extend_from_slice(&buf[..n]);
+        if raw.len() > limit {
+            return Err("description document too large".into());
+        }
+    }
+    let text = String::from_utf8_lossy(&raw);
+    let (head, body) = text
+        .split_once("\r\n\r\n")
+        .or_else(|| text.split_once("\n\n"))
+        .ok_or("malformed http response")?;
+    let status = head.lines().next().unwrap_or("");
+    if status.split_whitespace().nth(1) != Some("200") {
+        return Err(format!("description fetch failed: {}", status).into());
+    }
+    Ok(parse_description(body))
+}
+
+/// Fill records from SSDP devices matched by IP: vendor from `manufacturer`
+/// (spelled by `normalize_vendor_name`) and `hostname` from `friendlyName`,
+/// only where empty. Returns the number of records changed.
+pub fn apply_ssdp_descriptions(
+    records: &mut [DiscoveryRecord],
+    descriptions: &HashMap<String, DeviceDescription>,
+) -> usize {
+    let mut changed = 0;
+    for r in records.iter_mut() {
+        let Some(desc) = descriptions.get(&r.ip) else {
+            continue;
+        };
+        let mut touched = fill_vendor(r, desc.manufacturer.as_deref(), "ssdp", SSDP_CONFIDENCE);
+        if r.hostname.is_none() {
+            if let Some(n) = &desc.friendly_name {
+                r.hostname = Some(n.clone());
+                r.set_provenance("hostname", "ssdp", SSDP_CONFIDENCE);
+                touched = true;
+            }
+        }
+        if touched {
+            changed += 1;
+        }
+    }
+    changed
+}
+
+/// Run SSDP discovery for `timeout`, fetch each responder's description and
+/// enrich matching records. Returns the number of records changed.
+pub fn enrich_records_ssdp(records: &mut [DiscoveryRecord], timeout: Duration) -> usize {
+    let mut descriptions: HashMap<String, DeviceDescription> = HashMap::new();
+    for dev in discover_ssdp(timeout) {
+        if descriptions.contains_key(&dev.ip) || !records.iter().any(|r| r.ip == dev.ip) {
+            continue;
+        }
+        let Ok(ip) = dev.ip.parse::<IpAddr>() else {
+            continue;
+        };
+        if let Some(desc) = dev
+            .location
+            .as_deref()
+            .and_then(|l| fetch_description(ip, l).ok())
+        {
+            descriptions.insert(dev.ip, desc);
+        }
+    }
+    apply_ssdp_descriptions(records, &descriptions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread;
+
+    const LOCALHOST: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+    const RESPONSE: &str = include_str!("../tests/fixtures/ssdp_response.txt");
+    const DESCRIPTION: &str = include_str!("../tests/fixtures/upnp_description.xml");
+
+    #[test]
+    fn parses_msearch_response() {
+        let dev = parse_ssdp_response("192.168.1.50", RESPONSE.as_bytes()).expect("parsed");
+        assert_eq!(dev.ip, "192.168.1.50");
+        assert_eq!(
+            dev.location.as_deref(),
+            Some("http://192.168.1.50:49152/description.xml")
+        );
+        assert!(dev.server.as_deref().unwrap().contains("Sonos"));
+        assert_eq!(
+            dev.st.as_deref(),
+            Some("urn:schemas-upnp-org:device:ZonePlayer:1")
+        );
+        assert!(dev.usn.as_deref().unwrap().starts_with("uuid:RINCON"));
+    }
+
+    #[test]
+    fn parses_notify_and_rejects_other_messages() {
+        let notify = "NOTIFY * HTTP/1.1\nHOST: 239.255.255.250:1900\nNT: upnp:rootdevice\nLOCATION: http://10.0.0.2/desc.xml\n\n";
+        let dev = parse_ssdp_response("10.0.0.2", notify.as_bytes()).expect("notify");
+        assert_eq!(dev.st.as_deref(), Some("upnp:rootdevice"));
+        assert!(parse_ssdp_response("10.0.0.3", b"M-SEARCH * HTTP/1.1\r\n\r\n").is_none());
+        assert!(parse_ssdp_response("10.0.0.3", b"HTTP/1.1 404 Not Found\r\n\r\n").is_none());
+    }
+
+    #[test]
+    fn parses_root_device_description() {
+        let d = parse_description(DESCRIPTION);
+        assert_eq!(d.friendly_name.as_deref(), Some("Living Room - Sonos One"));
+        assert_eq!(d.manufacturer.as_deref(), Some("Sonos, Inc."));
+        assert_eq!(d.model_name.as_deref(), Some("Sonos One & Voice"));
+        assert_eq!(d.model_number.as_deref(), Some("S18"));
+        assert_eq!(
+            d.device_type.as_deref(),
+            Some("urn:schemas-upnp-org:device:ZonePlayer:1")
+        );
+        assert_eq!(parse_description("<root/>"), DeviceDescription::default());
+    }
+
+    fn serve_once(response: Vec<u8>) -> String {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).expect("bind");
+        let port = listener.local_addr().unwrap().port();
+        thread::spawn(move || {
+            if let Ok((mut s, _)) = listener.accept() {
+                let mut req = [0u8; 1024];
+                let _ = s.read(&mut req);
+                let _ = s.write_all(&response);
+            }
+        });
+        format!("http://127.0.0.1:{}/description.xml", port)
+    }
+
+    #[test]
+    fn fetches_description_over_http() {
+        let body = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/xml\r\nContent-Length: {}\r\n\r\n{}",
+            DESCRIPTION.len(),
+            DESCRIPTION
+        );
+        let url = serve_once(body.into_bytes());
+        let d = fetch_description(LOCALHOST, &url).expect("fetch");
+        assert_eq!(d.manufacturer.as_deref(), Some("Sonos, Inc."));
+    }
+
+    #[test]
+    fn oversized_or_failed_fetches_are_errors() {
+        let mut big = b"HTTP/1.1 200 OK\r\n\r\n".to_vec();
+        big.extend(std::iter::repeat_n(b'x', MAX_DESCRIPTION_BYTES + 8192));
+        assert!(fetch_description(LOCALHOST, &serve_once(big)).is_err());
+
+        let not_found = b"HTTP/1.1 404 Not Found\r\n\r\n".to_vec();
+        assert!(fetch_description(LOCALHOST, &serve_once(not_found)).is_err());
+        let tls: IpAddr = Ipv4Addr::new(192, 0, 2, 1).into();
+        assert!(fetch_description(tls, "https://192.0.2.1/desc.xml").is_err());
+    }
+
+    #[test]
+    fn location_must_point_at_the_responding_device() {
+        let url = serve_once(b"HTTP/1.1 200 OK\r\n\r\n<root/>".to_vec());
+        let other: IpAddr = Ipv4Addr::new(192, 168, 1, 50).into();
+        let err = fetch_description(other, &url).unwrap_err();
+        assert!(err.to_string().contains("not the device"), "{err}");
+        assert!(fetch_description(LOCALHOST, "http://printer.example:80/d.xml").is_err());
+    }
+
+    #[test]
+    fn slow_servers_hit_the_overall_deadline() {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).expect("bind");
+        let port = listener.local_addr().unwrap().port();
+        thread::spawn(move || {
+            if let Ok((mut s, _)) = listener.accept() {
+                // one byte every 200ms never trips a per-read timeout
+                for _ in 0..50 {
+                    if s.write_all(b"x").is_err() {
+                        break;
+                    }
+                    thread::sleep(Duration::from_millis(200));
+                }
+            }
+        });
+        let started = Instant::now();
+        let url = format!("http://127.0.0.1:{}/description.xml", port);
+        assert!(fetch_description(LOCALHOST, &url).is_err());
+        assert!(started.elapsed() < DESCRIPTION_TIMEOUT + Duration::from_secs(1));
+    }
+
+    #[test]
+    fn applies_descriptions_by_ip_without_clobbering() {
+        let mut recs = vec![
+            DiscoveryRecord::new(
+                "192.168.1.50",
+                None,
+                None,
+                Some("48:a6:b8:00:00:01"),
+                None,
+                None,
+            ),
+            DiscoveryRecord::new("192.168.1.51", None, None, None, Some("LG"), None),
+            DiscoveryRecord::new("192.168.1.52", None, None, None, None, None),
+        ];
+        recs[1].hostname = Some("tv.lan".into());
+        let mut descs = HashMap::new();
+        descs.insert("192.168.1.50".to_string(), parse_description(DESCRIPTION));
+        descs.insert(
+            "192.168.1.51".to_string(),
+            DeviceDescription {
+                friendly_name: Some("Bedroom TV".into()),
+                manufacturer: Some("LG Electronics".into()),
+                ..Default::default()
+            },
+        );
+        assert_eq!(apply_ssdp_descriptions(&mut recs, &descs), 1);
+        assert_eq!(recs[0].vendor.as_deref(), Some("Sonos"));
+        assert_eq!(recs[0].hostname.as_deref(), Some("Living Room - Sonos One"));
+        assert_eq!(recs[1].vendor.as_deref(), Some("LG"));
+        assert_eq!(recs[1].hostname.as_deref(), Some("tv.lan"));
+        assert!(recs[2].vendor.is_none());
+    }
+}
@@ -0,0 +1,424 @@
+//! SSH host key fingerprints.
+//!
+//! Only the unencrypted start of the SSH transport is spoken: version
+//! exchange, KEXINIT, and a curve25519 KEX_ECDH_INIT. The server's
+//! KEX_ECDH_REPLY carries its public host key in the clear, so no shared
+//! secret is computed and the connection is dropped right after.
+
+use base64::engine::general_purpose::STANDARD_NO_PAD;
+use base64::Engine;
+use formats::DiscoveryRecord;
+use netutils::portscan::{block_on, PortScanError};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+
+/// Host key algorithm and OpenSSH-style fingerprint of a server's host key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SshKeyInfo {
+    /// Key type, e.g. "ssh-ed25519"
+    pub algo: String,
+    /// `SHA256:<base64>` as printed by `ssh-keygen -l`
+    pub sha256_fp: String,
+}
+
+const CLIENT_VERSION: &[u8] = b"SSH-2.0-netscan_0.1\r\n";
+/// Largest packet we accept (RFC 4253 section 6.1 minimum).
+const MAX_PACKET_LEN: usize = 35000;
+/// Lines a server may send before its identification string.
+const MAX_PREAMBLE_LINES: usize = 16;
+/// Non-kex messages (IGNORE/DEBUG) skipped while waiting for a reply.
+const MAX_SKIPPED_PACKETS: usize = 8;
+
+const MSG_IGNORE: u8 = 2;
+const MSG_DEBUG: u8 = 4;
+const MSG_KEXINIT: u8 = 20;
+const MSG_KEX_ECDH_INIT: u8 = 30;
+const MSG_KEX_ECDH_REPLY: u8 = 31;
+
+const KEX_ALGORITHMS: &[&str] = &["curve25519-sha256", "curve25519-sha256@libssh.org"];
+const HOSTKEY_ALGORITHMS: &str =
+    "ssh-ed25519,ecdsa-sha2-nistp256,ecdsa-sha2-nistp384,ecdsa-sha2-nistp521,rsa-sha2-512,rsa-sha2-256,ssh-rsa";
+
+/// Curve25519 base point, used as our ephemeral public key. Any valid point
+/// works since the exchange is abandoned before keys are derived.
+const CLIENT_EPHEMERAL: [u8; 32] = {
+    let mut k = [0u8; 32];
+    k[0] = 9;
+    k
+};
+
+fn put_string(out: &mut Vec<u8>, s: &[u8]) {
+    out.extend_from_slice(&(s.len() as u32).to_be_bytes());
+    out.extend_from_slice(s);
+}
+
+/// Read an SSH `string` (u32 length + bytes) at `*pos`, advancing it.
+fn take_string<'a>(buf: &'a [u8], pos: &mut usize) -> Option<&'a [u8]> {
+    let len = u32::from_be_bytes(buf.get(*pos..*pos + 4)?.try_into().ok()?) as usize;
+    let s = buf.get(*pos + 4..(*pos + 4).checked_add(len)?)?;
+    *pos += 4 + len;
+    Some(s)
+}
+
+/// Wrap `payload` in an unencrypted binary packet (RFC 4253 section 6).
+pub fn frame_packet(payload: &[u8]) -> Vec<u8> {
+    let mut pad = 8 - (5 + payload.len()) % 8;
+    if pad < 4 {
+        pad += 8;
+    }
+    let mut out = Vec::with_capacity(5 + payload.len() + pad);
+    out.extend_from_slice(&((1 + payload.len() + pad) as u32).to_be_bytes());
+    out.push(pad as u8);
+    out.extend_from_slice(payload);
+    out.resize(out.len() + pad, 0);
+    out
+}
+
+/// Split a packet body (everything after the length field) into its payload.
+pub fn packet_payload(body: &[u8]) -> Option<&[u8]> {
+    let pad = *body.first()? as usize;
+    let end = body.len().checked_sub(pad)?;
+    if pad < 4 || end < 2 {
+        return None;
+    }
+    Some(&body[1..end])
+}
+
+fn client_kexinit() -> Vec<u8> {
+    let mut p = vec![MSG_KEXINIT];
+    p.extend_from_slice(&[0x5a; 16]); // cookie
+    let kex = KEX_ALGORITHMS.join(",");
+    let lists: [&str; 10] = [
+        &kex,
+        HOSTKEY_ALGORITHMS,
+        "aes128-ctr,chacha20-poly1305@openssh.com",
+        "aes128-ctr,chacha20-poly1305@openssh.com",
+        "hmac-sha2-256",
+        "hmac-sha2-256",
+        "none",
+        "none",
+        "",
+        "",
+    ];
+    for l in lists {
+        put_string(&mut p, l.as_bytes());
+    }
+    p.push(0); // first_kex_packet_follows
+    p.extend_from_slice(&[0; 4]);
+    p
+}
+
+/// Key exchange methods offered in a KEXINIT payload.
+pub fn kexinit_kex_algorithms(payload: &[u8]) -> Option<Vec<String>> {
+    if payload.first() != Some(&MSG_KEXINIT) {
+        return None;
+    }
+    let mut pos = 17; // message byte + cookie
+    let list = std::str::from_utf8(take_string(payload, &mut pos)?).ok()?;
+    Some(list.split(',').map(str::to_string).collect())
+}
+
+/// Fingerprint of a host key blob, in `ssh-keygen -l` form.
+pub fn hostkey_fingerprint(blob: &[u8]) -> String {
+    format!("SHA256:{}", STANDARD_NO_PAD.encode(Sha256::digest(blob)))
+}
+
+/// Extract the host key from a KEX_ECDH_REPLY payload.
+pub fn parse_ecdh_reply(payload: &[u8]) -> Option<SshKeyInfo> {
+    if payload.first() != Some(&MSG_KEX_ECDH_REPLY) {
+        return None;
+    }
+    let mut pos = 1;
+    let blob = take_string(payload, &mut pos)?;
+    let algo = std::str::from_utf8(take_string(blob, &mut 0)?).ok()?;
+    if algo.is_empty() {
+        return None;
+    }
+    Some(SshKeyInfo {
+        algo: algo.to_string(),
+        sha256_fp: hostkey_fingerprint(blob),
+    })
+}
+
+async fn read_packet<R: AsyncRead + Unpin>(r: &mut R) -> Option<Vec<u8>> {
+    let mut len = [0u8; 4];
+    r.read_exact(&mut len).await.ok()?;
+    let len = u32::from_be_bytes(len) as usize;
+    if !(5..=MAX_PACKET_LEN).contains(&len) {
+        return None;
+    }
+    let mut body = vec![0u8; len];
+    r.read_exact(&mut body).await.ok()?;
+    packet_payload(&body).map(<[u8]>::to_vec)
+}
+
+/// Next packet that isn't IGNORE/DEBUG; None unless it has type `want`.
+async fn expect_packet<R: AsyncRead + Unpin>(r: &mut R, want: u8) -> Option<Vec<u8>> {
+    for _ in 0..=MAX_SKIPPED_PACKETS {
+        let payload = read_packet(r).await?;
+        match payload[0] {
+            MSG_IGNORE | MSG_DEBUG => continue,
+            t if t == want => return Some(payload),
+            _ => return None,
+        }
+    }
+    None
+}
+
+async fn exchange(addr: SocketAddr) -> Option<SshKeyInfo> {
+    let tcp = TcpStream::connect(addr).await.ok()?;
+    let mut stream = BufReader::new(tcp);
+    stream.write_all(CLIENT_VERSION).await.ok()?;
+
+    let mut line = Vec::new();
+    for _ in 0..MAX_PREAMBLE_LINES {
+        line.clear();
+        (&mut stream)
+            .take(256)
+            .read_until(b'\n', &mut line)
+            .await
+            .ok()?;
+        if line.is_empty() {
+            return None;
+        }
+        if line.starts_with(b"SSH-") {
+            break;
+        }
+    }
+    if !(line.starts_with(b"SSH-2.0-") || line.starts_with(b"SSH-1.99-")) {
+        return None;
+    }
+
+    let server_kexinit = expect_packet(&mut stream, MSG_KEXINIT).await?;
+    let offered = kexinit_kex_algorithms(&server_kexinit)?;
+    if !KEX_ALGORITHMS
+        .iter()
+        .any(|k| offered.iter().any(|o| o == k))
+    {
+        return None;
+    }
+
+    let mut init = vec![MSG_KEX_ECDH_INIT];
+    put_string(&mut init, &CLIENT_EPHEMERAL);
+    let mut out = frame_packet(&client_kexinit());
+    out.extend(frame_packet(&init));
+    stream.write_all(&out).await.ok()?;
+
+    let reply = expect_packet(&mut stream, MSG_KEX_ECDH_REPLY).await?;
+    parse_ecdh_reply(&reply)
+}
+
+/// Async form of `ssh_hostkey`.
+pub async fn ssh_hostkey_async(ip: IpAddr, port: u16, timeout: Duration) -> Option<SshKeyInfo> {
+    tokio::time::timeout(timeout, exchange(SocketAddr::new(ip, port)))
+        .await
+        .ok()?
+}
+
+/// Fetch the host key fingerprint of the SSH server at `ip:port`. Servers
+/// that only offer non-curve25519 key exchange, malformed replies and
+/// timeouts (covering the whole exchange) all give None. Like `http_probe`
+/// it runs on netutils' shared runtime, so async callers are fine.
+pub fn ssh_hostkey(
+    ip: IpAddr,
+    port: u16,
+    timeout: Duration,
+) -> Result<Option<SshKeyInfo>, PortScanError> {
+    block_on(ssh_hostkey_async(ip, port, timeout))
+}
+
+/// Fetch host keys for every record with port 22, `concurrency` at a time,
+/// and store them in `ssh_hostkey` (all records of the host, not only the
+/// port 22 one). Returns the number of records changed.
+pub fn enrich_records_ssh(
+    records: &mut [DiscoveryRecord],
+    timeout: Duration,
+    concurrency: usize,
+) -> Result<usize, PortScanError> {
+    let mut targets: Vec<IpAddr> = records
+        .iter()
+        .filter(|r| r.port == Some(22))
+        .filter_map(|r| r.ip.parse().ok())
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    if targets.is_empty() {
+        return Ok(0);
+    }
+    targets.sort();
+
+    let results: HashMap<String, SshKeyInfo> = block_on(async {
+        let sem = Arc::new(tokio::sync::Semaphore::new(concurrency.max(1)));
+        let mut set = tokio::task::JoinSet::new();
+        for ip in targets {
+            let sem = sem.clone();
+            set.spawn(async move {
+                let _permit = sem.acquire_owned().await.ok()?;
+                let info = ssh_hostkey_async(ip, 22, timeout).await?;
+                Some((ip.to_string(), info))
+            });
+        }
+        let mut out = HashMap::new();
+        while let Some(res) = set.join_next().await {
+            if let Ok(Some((ip, info))) = res {
+                out.insert(ip, info);
+            }
+        }
+        out
+    })?;
+    Ok(apply_ssh_hostkeys(records, &results))
+}
+
+/// Apply already collected host keys keyed by IP string. Existing values
+/// are kept.
+pub fn apply_ssh_hostkeys(
+    records: &mut [DiscoveryRecord],
+    results: &HashMap<String, SshKeyInfo>,
+) -> usize {
+    let mut changed = 0;
+    for r in records.iter_mut() {
+        if r.ssh_hostkey.is_some() {
+            continue;
+        }
+        if let Some(info) = results.get(&r.ip) {
+            r.ssh_hostkey = Some(format!("{} {}", info.algo, info.sha256_fp));
+            changed += 1;
+        }
+    }
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    const KEXINIT: &[u8] = include_bytes!("../tests/fixtures/ssh_kexinit.bin");
+    const ECDH_REPLY: &[u8] = include_bytes!("../tests/fixtures/ssh_ecdh_reply.bin");
+    // `ssh-keygen -lf` of the ed25519 key inside ECDH_REPLY
+    const FIXTURE_FP: &str = "SHA256:WfG2G/lyTkA1aVwdWR21oe8oh8iLkZpn/c/t56m6lUc";
+
+    #[test]
+    fn frames_are_block_aligned() {
+        for n in 1..40 {
+            let pkt = frame_packet(&vec![7u8; n]);
+            assert_eq!(pkt.len() % 8, 0);
+            assert!(pkt[4] >= 4);
+            assert_eq!(packet_payload(&pkt[4..]), Some(&vec![7u8; n][..]));
+        }
+    }
+
+    #[test]
+    fn parses_server_kexinit() {
+        let payload = packet_payload(&KEXINIT[4..]).unwrap();
+        let kex = kexinit_kex_algorithms(payload).unwrap();
+        assert!(kex.contains(&"curve25519-sha256".to_string()));
+        assert!(kexinit_kex_algorithms(&[MSG_KEXINIT, 0, 0]).is_none());
+    }
+
+    #[test]
+    fn extracts_host_key_from_ecdh_reply() {
+        let payload = packet_payload(&ECDH_REPLY[4..]).unwrap();
+        let info = parse_ecdh_reply(payload).unwrap();
+        assert_eq!(info.algo, "ssh-ed25519");
+        assert_eq!(info.sha256_fp, FIXTURE_FP);
+
+        // truncated key blob
+        assert!(parse_ecdh_reply(&payload[..20]).is_none());
+        assert!(parse_ecdh_reply(&[MSG_KEXINIT]).is_none());
+    }
+
+    fn serve_once(script: Vec<u8>, read_client: bool) -> u16 {
+        use std::io::{Read, Write};
+        let listener = std::net::TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let port = listener.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            let (mut s, _) = listener.accept().unwrap();
+            let mut buf = [0u8; 1024];
+            if read_client {
+                let _ = s.read(&mut buf);
+            }
+            let _ = s.write_all(&script);
+            // hold the connection open until the client gives up
+            while matches!(s.read(&mut buf), Ok(n) if n > 0) {}
+        });
+        port
+    }
+
+    #[test]
+    fn fetches_hostkey_from_fake_server() {
+        let mut script = b"SSH-2.0-OpenSSH_9.6p1 Ubuntu-3ubuntu13\r\n".to_vec();
+        script.extend_from_slice(KEXINIT);
+        script.extend_from_slice(&frame_packet(&[MSG_IGNORE, 0, 0, 0, 0]));
+        script.extend_from_slice(ECDH_REPLY);
+        let port = serve_once(script, true);
+        let info = ssh_hostkey(Ipv4Addr::LOCALHOST.into(), port, Duration::from_secs(2))
+            .unwrap()
+            .unwrap();
+        assert_eq!(info.sha256_fp, FIXTURE_FP);
+    }
+
+    #[test]
+    fn hostkey_fetch_runs_inside_a_tokio_runtime() {
+        let mut script = b"SSH-2.0-OpenSSH_9.6p1\r\n".to_vec();
+        script.extend_from_slice(KEXINIT);
+        script.extend_from_slice(ECDH_REPLY);
+        let port = serve_once(script, true);
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let info = rt.block_on(async {
+            ssh_hostkey(Ipv4Addr::LOCALHOST.into(), port, Duration::from_secs(2))
+        });
+        assert_eq!(info.unwrap().unwrap().sha256_fp, FIXTURE_FP);
+    }
+
+    #[test]
+    fn malformed_or_silent_servers_give_none() {
+        let ip: IpAddr = Ipv4Addr::LOCALHOST.into();
+        let timeout = Duration::from_millis(300);
+
+        let port = serve_once(b"HTTP/1.1 400 Bad Request\r\n\r\n".to_vec(), false);
+        assert!(ssh_hostkey(ip, port, timeout).unwrap().is_none());
+
+        let mut oversized = b"SSH-2.0-x\r\n".to_vec();
+        oversized.extend_from_slice(&u32::MAX.to_be_bytes());
+        let port = serve_once(oversized, false);
+        assert!(ssh_hostkey(ip, port, timeout).unwrap().is_none());
+
+        // version string, then nothing: must time out rather than hang
+        let port = serve_once(b"SSH-2.0-x\r\n".to_vec(), false);
+        let started = std::time::Instant::now();
+        assert!(ssh_hostkey(ip, port, timeout).unwrap().is_none());
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn hostkeys_applied_to_every_record_of_host() {
+        let mut recs = vec![
+            DiscoveryRecord::new("192.0.2.9", Some(22), None, None, None, None),
+            DiscoveryRecord::new("192.0.2.9", Some(80), None, None, None, None),
+            DiscoveryRecord::new("192.0.2.10", Some(22), None, None, None, None),
+        ];
+        let mut results = HashMap::new();
+        results.insert(
+            "192.0.2.9".to_string(),
+            SshKeyInfo {
+                algo: "ssh-ed25519".into(),
+                sha256_fp: FIXTURE_FP.into(),
+            },
+        );
+        assert_eq!(apply_ssh_hostkeys(&mut recs, &results), 2);
+        assert_eq!(
+            recs[1].ssh_hostkey.as_deref(),
+            Some("ssh-ed25519 SHA256:WfG2G/lyTkA1aVwdWR21oe8oh8iLkZpn/c/t56m6lUc")
+        );
+        assert!(recs[2].ssh_hostkey.is_none());
+    }
+}
@@ -0,0 +1,105 @@
+//! Vendor name normalization, so OUI registry names, hostname heuristics and
+//! imported data agree on one spelling per manufacturer.
+
+use formats::DiscoveryRecord;
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+/// Known vendors, keyed by lowercased name with corporate suffixes removed.
+const VENDOR_ALIASES: &[(&str, &str)] = &[
+    ("cisco", "Cisco"),
+    ("cisco systems", "Cisco"),
+    ("vmware", "VMware"),
+    ("apple", "Apple"),
+    ("hewlett packard", "HP"),
+    ("hewlett-packard", "HP"),
+    ("hp", "HP"),
+    ("intel corporate", "Intel"),
+    ("tp-link technologies", "TP-Link"),
+    ("netgear", "Netgear"),
+];
+
+/// A trailing `, Inc.` / `Ltd` / `LLC` / `Corp.` / `Co.` style suffix.
+static CORPORATE_SUFFIX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)[\s,]+(inc|ltd|llc|corp|corporation|co|limited)\.?$").expect("suffix regex")
+});
+
+/// Canonical spelling of a vendor name: whitespace trimmed, trailing
+/// corporate suffixes (`, Inc.`, `, Ltd.`, `, LLC`, `, Corp.`, stacked ones
+/// like `Co., Ltd.` too) removed, and known vendors mapped case-insensitively
+/// to one name (`"Cisco Systems, Inc."`, `"CISCO"` -> `"Cisco"`). Unknown
+/// vendors keep their casing.
+pub fn normalize_vendor_name(s: &str) -> String {
+    let mut name = s.trim();
+    while let Some(m) = CORPORATE_SUFFIX.find(name) {
+        if m.start() == 0 {
+            break;
+        }
+        name = name[..m.start()].trim_end();
+    }
+    let key = name.to_ascii_lowercase();
+    VENDOR_ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == key)
+        .map_or_else(|| name.to_string(), |(_, canonical)| canonical.to_string())
+}
+
+/// Store `raw` in an empty `record.vendor`, normalized, with its provenance.
+/// Every enricher that sets a vendor goes through here so all sources agree
+/// on the spelling. Returns true when the vendor was set.
+pub(crate) fn fill_vendor(
+    record: &mut DiscoveryRecord,
+    raw: Option<&str>,
+    source: &str,
+    confidence: u8,
+) -> bool {
+    let Some(raw) = raw.filter(|_| record.vendor.is_none()) else {
+        return false;
+    };
+    record.vendor = Some(normalize_vendor_name(raw));
+    record.set_provenance("vendor", source, confidence);
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn corporate_suffixes_are_stripped() {
+        assert_eq!(normalize_vendor_name("Sonos, Inc."), "Sonos");
+        assert_eq!(normalize_vendor_name("Sonos Inc"), "Sonos");
+        assert_eq!(
+            normalize_vendor_name("Raspberry Pi Trading Ltd"),
+            "Raspberry Pi Trading"
+        );
+        assert_eq!(
+            normalize_vendor_name("Ubiquiti Networks, LLC"),
+            "Ubiquiti Networks"
+        );
+        assert_eq!(
+            normalize_vendor_name("Juniper Networks, Corp."),
+            "Juniper Networks"
+        );
+        assert_eq!(normalize_vendor_name("Microsoft Corporation"), "Microsoft");
+        assert_eq!(
+            normalize_vendor_name("  Nokia Shanghai Bell Co., Ltd.  "),
+            "Nokia Shanghai Bell"
+        );
+        assert_eq!(normalize_vendor_name("ACME"), "ACME");
+        // A bare suffix is a name, not something to strip down to nothing
+        assert_eq!(normalize_vendor_name("Inc."), "Inc.");
+    }
+
+    #[test]
+    fn known_vendors_share_one_spelling() {
+        for raw in ["Cisco Systems, Inc.", "Cisco", "CISCO", "cisco systems"] {
+            assert_eq!(normalize_vendor_name(raw), "Cisco", "{raw}");
+        }
+        assert_eq!(normalize_vendor_name("VMware, Inc."), "VMware");
+        assert_eq!(normalize_vendor_name("VMWARE"), "VMware");
+        assert_eq!(normalize_vendor_name("Hewlett Packard"), "HP");
+        assert_eq!(normalize_vendor_name("Intel Corporate"), "Intel");
+        assert_eq!(normalize_vendor_name("NETGEAR"), "Netgear");
+    }
+}
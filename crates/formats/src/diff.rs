@@ -0,0 +1,102 @@
+//! Compare two scans of the same network.
+
+#[cfg(feature = "serde")]
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::DiscoveryRecord;
+
+/// A record present in both scans whose observed values differ.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct RecordChange {
+    pub baseline: DiscoveryRecord,
+    pub current: DiscoveryRecord,
+}
+
+/// Result of `diff_records`. Each list keeps the order of the scan it
+/// comes from (`removed` follows the baseline, the others the current scan).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct RecordDiff {
+    /// In the current scan only (a new host, or a newly open port)
+    pub added: Vec<DiscoveryRecord>,
+    /// In the baseline only
+    pub removed: Vec<DiscoveryRecord>,
+    pub changed: Vec<RecordChange>,
+}
+
+impl RecordDiff {
+    /// True when both scans saw the same records.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Diff two scans. Records are matched on `(ip, port)`; a matched pair is
+/// `changed` when any field other than `timestamp` and the provenance list
+/// differs, since those change on every scan without the host changing.
+pub fn diff_records(baseline: &[DiscoveryRecord], current: &[DiscoveryRecord]) -> RecordDiff {
+    let key = |r: &DiscoveryRecord| (r.ip.clone(), r.port);
+    let before: HashMap<_, _> = baseline.iter().map(|r| (key(r), r)).collect();
+    let after: HashMap<_, _> = current.iter().map(|r| (key(r), r)).collect();
+
+    let mut diff = RecordDiff::default();
+    for r in current {
+        match before.get(&key(r)) {
+            None => diff.added.push(r.clone()),
+            Some(old) if !same_observation(old, r) => diff.changed.push(RecordChange {
+                baseline: (*old).clone(),
+                current: r.clone(),
+            }),
+            Some(_) => {}
+        }
+    }
+    diff.removed = baseline
+        .iter()
+        .filter(|r| !after.contains_key(&key(r)))
+        .cloned()
+        .collect();
+    diff
+}
+
+fn same_observation(a: &DiscoveryRecord, b: &DiscoveryRecord) -> bool {
+    let strip = |r: &DiscoveryRecord| DiscoveryRecord {
+        timestamp: None,
+        ..r.without_enrichment()
+    };
+    strip(a) == strip(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rec(ip: &str, port: Option<u16>, vendor: Option<&str>, ts: &str) -> DiscoveryRecord {
+        DiscoveryRecord::new(ip, port, None, None, vendor, Some(ts))
+    }
+
+    #[test]
+    fn classifies_added_removed_and_changed() {
+        let baseline = vec![
+            rec("10.0.0.1", None, Some("Acme"), "t0"),
+            rec("10.0.0.2", Some(22), None, "t0"),
+            rec("10.0.0.3", None, None, "t0"),
+        ];
+        let mut current = vec![
+            // only the timestamp and provenance moved: unchanged
+            rec("10.0.0.1", None, Some("Acme"), "t1"),
+            rec("10.0.0.2", Some(22), Some("Raspberry Pi"), "t1"),
+            rec("10.0.0.2", Some(80), None, "t1"),
+        ];
+        current[0].set_provenance("vendor", "oui", 90);
+
+        let d = diff_records(&baseline, &current);
+        assert_eq!(d.added, [current[2].clone()]);
+        assert_eq!(d.removed, [baseline[2].clone()]);
+        assert_eq!(d.changed.len(), 1);
+        assert_eq!(d.changed[0].baseline, baseline[1]);
+        assert_eq!(d.changed[0].current, current[1]);
+        assert!(diff_records(&baseline, &baseline).is_empty());
+    }
+}
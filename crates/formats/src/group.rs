@@ -0,0 +1,185 @@
+//! Grouping helpers for reports (records per vendor, per subnet, and
+//! reachable vs. merely present hosts).
+
+use crate::DiscoveryRecord;
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+/// Key used for records without a vendor or with an unparseable IP.
+pub const UNKNOWN_GROUP: &str = "Unknown";
+
+/// Group records by vendor; records without one go under `"Unknown"`.
+pub fn group_by_vendor(records: &[DiscoveryRecord]) -> HashMap<String, Vec<DiscoveryRecord>> {
+    let mut groups: HashMap<String, Vec<DiscoveryRecord>> = HashMap::new();
+    for r in records {
+        let key = r.vendor.as_deref().unwrap_or(UNKNOWN_GROUP);
+        groups.entry(key.to_string()).or_default().push(r.clone());
+    }
+    groups
+}
+
+/// Group records by the network their IP falls in, keyed in CIDR form
+/// (prefix 24 puts 192.168.1.7 under `"192.168.1.0/24"`). `prefix_len` is
+/// clamped to the address width, so IPv6 records can share a call with
+/// IPv4 ones. Records whose IP does not parse go under `"Unknown"`.
+pub fn group_by_subnet(
+    records: &[DiscoveryRecord],
+    prefix_len: u8,
+) -> HashMap<String, Vec<DiscoveryRecord>> {
+    let mut groups: HashMap<String, Vec<DiscoveryRecord>> = HashMap::new();
+    for r in records {
+        let key = match r.ip.parse::<IpAddr>() {
+            Ok(ip) => subnet_key(ip, prefix_len),
+            Err(_) => UNKNOWN_GROUP.to_string(),
+        };
+        groups.entry(key).or_default().push(r.clone());
+    }
+    groups
+}
+
+/// Split a combined ARP + port scan into `(with_open_ports,
+/// without_open_ports)`: records with a `port` are confirmed reachable,
+/// the rest are only known to be present. Both lists keep input order.
+pub fn partition_by_reachability(
+    records: Vec<DiscoveryRecord>,
+) -> (Vec<DiscoveryRecord>, Vec<DiscoveryRecord>) {
+    records.into_iter().partition(|r| r.port.is_some())
+}
+
+/// Set `is_up = Some(false)` on every record without a port; records with
+/// one are left alone.
+pub fn mark_unreachable(records: &mut [DiscoveryRecord]) {
+    for r in records.iter_mut().filter(|r| r.port.is_none()) {
+        r.is_up = Some(false);
+    }
+}
+
+fn subnet_key(ip: IpAddr, prefix_len: u8) -> String {
+    match ip {
+        IpAddr::V4(v4) => {
+            let len = prefix_len.min(32);
+            let mask = u32::MAX.checked_shl(32 - len as u32).unwrap_or(0);
+            let net = std::net::Ipv4Addr::from(u32::from(v4) & mask);
+            format!("{}/{}", net, len)
+        }
+        IpAddr::V6(v6) => {
+            let len = prefix_len.min(128);
+            let mask = u128::MAX.checked_shl(128 - len as u32).unwrap_or(0);
+            let net = std::net::Ipv6Addr::from(u128::from(v6) & mask);
+            format!("{}/{}", net, len)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture() -> Vec<DiscoveryRecord> {
+        [
+            ("192.168.1.1", Some("Netgear")),
+            ("192.168.1.20", Some("Apple")),
+            ("192.168.1.21", Some("Apple")),
+            ("192.168.1.99", None),
+            ("192.168.2.5", Some("Apple")),
+            ("192.168.2.6", Some("Sonos")),
+            ("192.168.2.7", None),
+            ("10.0.0.1", Some("Netgear")),
+            ("10.0.0.2", Some("Apple")),
+            ("10.0.7.3", None),
+        ]
+        .iter()
+        .map(|(ip, vendor)| DiscoveryRecord::new(ip, None, None, None, *vendor, None))
+        .collect()
+    }
+
+    fn sizes(groups: &HashMap<String, Vec<DiscoveryRecord>>) -> Vec<(&str, usize)> {
+        let mut v: Vec<_> = groups.iter().map(|(k, g)| (k.as_str(), g.len())).collect();
+        v.sort();
+        v
+    }
+
+    #[test]
+    fn groups_by_vendor_with_unknown_bucket() {
+        let groups = group_by_vendor(&fixture());
+        assert_eq!(
+            sizes(&groups),
+            [("Apple", 4), ("Netgear", 2), ("Sonos", 1), ("Unknown", 3)]
+        );
+    }
+
+    #[test]
+    fn groups_by_subnet_prefix() {
+        let recs = fixture();
+        assert_eq!(
+            sizes(&group_by_subnet(&recs, 24)),
+            [
+                ("10.0.0.0/24", 2),
+                ("10.0.7.0/24", 1),
+                ("192.168.1.0/24", 4),
+                ("192.168.2.0/24", 3)
+            ]
+        );
+        assert_eq!(
+            sizes(&group_by_subnet(&recs, 16)),
+            [("10.0.0.0/16", 3), ("192.168.0.0/16", 7)]
+        );
+        assert_eq!(sizes(&group_by_subnet(&recs, 0)), [("0.0.0.0/0", 10)]);
+    }
+
+    fn scanned() -> Vec<DiscoveryRecord> {
+        [
+            ("192.168.1.1", Some(80)),
+            ("192.168.1.1", Some(443)),
+            ("192.168.1.20", None),
+            ("192.168.1.21", Some(22)),
+            ("192.168.1.99", None),
+        ]
+        .iter()
+        .map(|(ip, port)| DiscoveryRecord::new(ip, *port, None, None, None, None))
+        .collect()
+    }
+
+    #[test]
+    fn partitions_by_open_ports() {
+        let (open, silent) = partition_by_reachability(scanned());
+        let keys = |v: &[DiscoveryRecord]| -> Vec<(String, Option<u16>)> {
+            v.iter().map(|r| (r.ip.clone(), r.port)).collect()
+        };
+        assert_eq!(
+            keys(&open),
+            [
+                ("192.168.1.1".into(), Some(80)),
+                ("192.168.1.1".into(), Some(443)),
+                ("192.168.1.21".into(), Some(22))
+            ]
+        );
+        assert_eq!(
+            keys(&silent),
+            [("192.168.1.20".into(), None), ("192.168.1.99".into(), None)]
+        );
+        assert_eq!(
+            partition_by_reachability(Vec::new()),
+            (Vec::new(), Vec::new())
+        );
+    }
+
+    #[test]
+    fn marks_portless_records_down() {
+        let mut recs = scanned();
+        recs[1].is_up = Some(true);
+        mark_unreachable(&mut recs);
+        let up: Vec<_> = recs.iter().map(|r| r.is_up).collect();
+        assert_eq!(up, [None, Some(true), Some(false), None, Some(false)]);
+    }
+
+    #[test]
+    fn subnet_keys_for_v6_and_bad_ips() {
+        let recs = vec![
+            DiscoveryRecord::new("fe80::1:2", None, None, None, None, None),
+            DiscoveryRecord::new("not-an-ip", None, None, None, None, None),
+        ];
+        let groups = group_by_subnet(&recs, 64);
+        assert_eq!(sizes(&groups), [("Unknown", 1), ("fe80::/64", 1)]);
+    }
+}
@@ -2,32 +2,103 @@
 //!
 //! This crate contains the canonical Rust types for discovery records and
 //! provides serde-friendly mapping to JSON and CSV for golden-file tests.
+//! Serialization lives behind the default `serde` feature; with
+//! `default-features = false` the types and helpers build without serde.
 
+#[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
+mod diff;
+mod group;
+mod redact;
+mod stats;
+mod validate;
+pub use diff::{diff_records, RecordChange, RecordDiff};
+pub use group::{
+    group_by_subnet, group_by_vendor, mark_unreachable, partition_by_reachability, UNKNOWN_GROUP,
+};
+pub use redact::{
+    anonymize_records, redact, redact_record, IpRedaction, MacRedaction, RedactOptions, REDACTED,
+};
+pub use stats::ScanStats;
+pub use validate::{validate_record, validate_records, ValidationError};
+
+/// Where an enriched field's value came from and how far to trust it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct FieldProvenance {
+    /// Record field name (e.g. "vendor", "os")
+    pub field: String,
+    /// Enrichment source (e.g. "oui", "hostname")
+    pub source: String,
+    /// Percent, 0 (wild guess) to 100 (authoritative)
+    pub confidence: u8,
+}
+
+/// Confidence recorded for a service assumed from the port number alone.
+/// Shared by every crate that fills `service` from a port table, so a banner
+/// match (which records more) always replaces such a guess.
+pub const PORT_SERVICE_CONFIDENCE: u8 = 40;
+
 /// A single discovery record representing a host/service observation.
 ///
 /// Keep this struct minimal and stable: add new optional fields rather than
 /// changing existing names so golden-file compatibility is easier.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct DiscoveryRecord {
     /// IP address in string form (v4 or v6)
     pub ip: String,
     /// Optional observed service port
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
     pub port: Option<u16>,
     /// Free-form banner or probe result
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
     pub banner: Option<String>,
+    /// Optional host name (DNS/PTR, NetBIOS, SNMP sysName, certificate),
+    /// kept apart from `banner` so a host can have both
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub hostname: Option<String>,
     /// Optional MAC address if available
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
     pub mac: Option<String>,
     /// Optional vendor / manufacturer string
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
     pub vendor: Option<String>,
     /// Optional ISO timestamp string from source
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
     pub timestamp: Option<String>,
+    /// Optional device classification label (e.g. "router", "printer")
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub device_type: Option<String>,
+    /// Optional identified service name (e.g. "ssh", "http")
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub service: Option<String>,
+    /// Optional product and version behind the service (e.g. "OpenSSH 9.3p1")
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub product: Option<String>,
+    /// Optional operating-system family guess (e.g. "linux", "windows")
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub os: Option<String>,
+    /// `Some(true)` when the host was port-scanned, even if no port was open;
+    /// `None` when no port scan ran
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub port_scanned: Option<bool>,
+    /// Host up/down state reported by the source; `None` when unknown
+    /// (exporters treat unknown as up, since the host was discovered)
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub is_up: Option<bool>,
+    /// Optional SSH host key fingerprint (`<algo> SHA256:<base64>`); stable
+    /// across DHCP address changes
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub ssh_hostkey: Option<String>,
+    /// Provenance of enriched fields; values without an entry were observed
+    /// directly and count as fully trusted
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub enrichment: Vec<FieldProvenance>,
 }
 
 impl DiscoveryRecord {
@@ -44,14 +115,128 @@ impl DiscoveryRecord {
             ip: ip.to_string(),
             port,
             banner: banner.map(|s| s.to_string()),
+            hostname: None,
             mac: mac.map(|s| s.to_string()),
             vendor: vendor.map(|s| s.to_string()),
             timestamp: timestamp.map(|s| s.to_string()),
+            device_type: None,
+            service: None,
+            product: None,
+            os: None,
+            port_scanned: None,
+            is_up: None,
+            ssh_hostkey: None,
+            enrichment: Vec::new(),
+        }
+    }
+
+    /// Record for an observed `ip:port`, every other field empty.
+    pub fn from_socket_addr(addr: std::net::SocketAddr) -> Self {
+        Self::new(
+            &addr.ip().to_string(),
+            Some(addr.port()),
+            None,
+            None,
+            None,
+            None,
+        )
+    }
+
+    /// `ip:port` of this record; None when it has no port or `ip` does not
+    /// parse as an address. IPv6 flow info and scope id are not kept.
+    pub fn to_socket_addr(&self) -> Option<std::net::SocketAddr> {
+        let ip: std::net::IpAddr = self.ip.parse().ok()?;
+        Some(std::net::SocketAddr::new(ip, self.port?))
+    }
+
+    /// Record that `field` was filled by `source`, replacing any earlier entry.
+    pub fn set_provenance(&mut self, field: &str, source: &str, confidence: u8) {
+        self.enrichment.retain(|p| p.field != field);
+        self.enrichment.push(FieldProvenance {
+            field: field.to_string(),
+            source: source.to_string(),
+            confidence,
+        });
+    }
+
+    /// Provenance entry for `field`, if it was enriched.
+    pub fn provenance(&self, field: &str) -> Option<&FieldProvenance> {
+        self.enrichment.iter().find(|p| p.field == field)
+    }
+
+    /// Confidence in the current value of `field`: its provenance confidence,
+    /// or 100 for values without provenance (observed directly).
+    pub fn confidence(&self, field: &str) -> u8 {
+        self.provenance(field).map_or(100, |p| p.confidence)
+    }
+
+    /// Copy without the provenance list, for flat formats such as CSV that
+    /// cannot hold the nested `enrichment` block.
+    pub fn without_enrichment(&self) -> DiscoveryRecord {
+        DiscoveryRecord {
+            enrichment: Vec::new(),
+            ..self.clone()
+        }
+    }
+
+    /// Merge another observation of the same host into this record. Empty
+    /// fields are filled from `other`; when both are set, the value with the
+    /// higher confidence wins (ties keep the current value). Provenance moves
+    /// with the chosen value.
+    pub fn merge(&mut self, other: &DiscoveryRecord) {
+        macro_rules! merge_fields {
+            ($($field:ident),*) => {$(
+                self.merge_field(
+                    stringify!($field),
+                    other,
+                    |r| &mut r.$field,
+                    |r| r.$field.clone(),
+                );
+            )*};
+        }
+        merge_fields!(
+            port,
+            banner,
+            hostname,
+            mac,
+            vendor,
+            timestamp,
+            device_type,
+            service,
+            product,
+            os,
+            ssh_hostkey
+        );
+        if self.port_scanned.is_none() {
+            self.port_scanned = other.port_scanned;
+        }
+        if self.is_up.is_none() {
+            self.is_up = other.is_up;
+        }
+    }
+
+    fn merge_field<T>(
+        &mut self,
+        name: &str,
+        other: &DiscoveryRecord,
+        slot: impl Fn(&mut DiscoveryRecord) -> &mut Option<T>,
+        value: impl Fn(&DiscoveryRecord) -> Option<T>,
+    ) {
+        let Some(theirs) = value(other) else { return };
+        let take = slot(self).is_none() || other.confidence(name) > self.confidence(name);
+        if !take {
+            return;
+        }
+        *slot(self) = Some(theirs);
+        self.enrichment.retain(|p| p.field != name);
+        if let Some(p) = other.provenance(name) {
+            self.enrichment.push(p.clone());
         }
     }
 }
 
 /// Round-trip helpers: JSON (serde_json) and CSV (csv crate)
+#[cfg(feature = "serde")]
 pub mod serde_helpers {
     use super::DiscoveryRecord;
 
@@ -65,10 +250,11 @@ pub mod serde_helpers {
         Ok(serde_json::from_str(s)?)
     }
 
-    /// Serialize to CSV (single-record, header included)
+    /// Serialize to CSV (single-record, header included). Provenance is not
+    /// representable in CSV and is left out.
     pub fn to_csv(rec: &DiscoveryRecord) -> Result<String, Box<dyn std::error::Error>> {
         let mut wtr = csv::Writer::from_writer(vec![]);
-        wtr.serialize(rec)?;
+        wtr.serialize(rec.without_enrichment())?;
         wtr.flush()?;
         // into_inner returns a Vec<u8> on success
         let inner = wtr
@@ -96,6 +282,7 @@ pub mod serde_helpers {
 mod tests {
     use super::*;
 
+    #[cfg(feature = "serde")]
     #[test]
     fn json_roundtrip() {
         let r = DiscoveryRecord::new("192.0.2.1", Some(80), Some("example"), None, None, None);
@@ -104,6 +291,7 @@ mod tests {
         assert_eq!(r, parsed);
     }
 
+    #[cfg(feature = "serde")]
     #[test]
     fn csv_roundtrip() {
         let r = DiscoveryRecord::new(
@@ -123,4 +311,110 @@ mod tests {
         assert_eq!(r.port, parsed.port);
         assert_eq!(r.banner, parsed.banner);
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn port_scanned_marker_is_omitted_until_set() {
+        let mut r = DiscoveryRecord::new("192.0.2.7", None, None, None, None, None);
+        let j = serde_helpers::to_json(&r).expect("to_json");
+        assert!(!j.contains("port_scanned"));
+
+        r.port_scanned = Some(true);
+        let j = serde_helpers::to_json(&r).expect("to_json");
+        assert!(j.contains("\"port_scanned\":true"));
+        assert_eq!(serde_helpers::from_json(&j).expect("from_json"), r);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn hostname_is_kept_apart_from_banner() {
+        let mut r =
+            DiscoveryRecord::new("192.0.2.8", Some(22), Some("SSH-2.0-x"), None, None, None);
+        let j = serde_helpers::to_json(&r).expect("to_json");
+        assert!(!j.contains("hostname"));
+
+        r.hostname = Some("nas.lan".into());
+        let back = serde_helpers::from_json(&serde_helpers::to_json(&r).unwrap()).unwrap();
+        assert_eq!(back.hostname.as_deref(), Some("nas.lan"));
+        assert_eq!(back.banner.as_deref(), Some("SSH-2.0-x"));
+
+        let mut merged = DiscoveryRecord::new("192.0.2.8", None, None, None, None, None);
+        merged.merge(&r);
+        assert_eq!(merged.hostname.as_deref(), Some("nas.lan"));
+    }
+
+    #[test]
+    fn socket_addr_roundtrip_v4_and_v6() {
+        for addr in ["192.0.2.10:8080", "[2001:db8::1]:443"] {
+            let addr: std::net::SocketAddr = addr.parse().unwrap();
+            let r = DiscoveryRecord::from_socket_addr(addr);
+            assert_eq!(r.port, Some(addr.port()));
+            assert_eq!(r.mac, None);
+            assert_eq!(r.to_socket_addr(), Some(addr));
+        }
+        assert_eq!(
+            DiscoveryRecord::from_socket_addr("[2001:db8::1]:443".parse().unwrap()).ip,
+            "2001:db8::1"
+        );
+    }
+
+    #[test]
+    fn to_socket_addr_needs_port_and_valid_ip() {
+        let no_port = DiscoveryRecord::new("192.0.2.10", None, None, None, None, None);
+        assert_eq!(no_port.to_socket_addr(), None);
+        let bad_ip = DiscoveryRecord::new("printer.lan", Some(631), None, None, None, None);
+        assert_eq!(bad_ip.to_socket_addr(), None);
+    }
+
+    #[test]
+    fn merge_prefers_higher_confidence() {
+        let mut guessed =
+            DiscoveryRecord::new("192.0.2.9", None, None, None, Some("Netgear"), None);
+        guessed.set_provenance("vendor", "hostname", 50);
+        let mut oui =
+            DiscoveryRecord::new("192.0.2.9", None, None, None, Some("Apple, Inc."), None);
+        oui.set_provenance("vendor", "oui", 90);
+        oui.os = Some("linux".into());
+
+        let mut merged = guessed.clone();
+        merged.merge(&oui);
+        assert_eq!(merged.vendor.as_deref(), Some("Apple, Inc."));
+        assert_eq!(merged.provenance("vendor").unwrap().source, "oui");
+        assert_eq!(merged.os.as_deref(), Some("linux"));
+
+        // the other direction keeps the OUI value
+        let mut merged = oui.clone();
+        merged.merge(&guessed);
+        assert_eq!(merged.vendor.as_deref(), Some("Apple, Inc."));
+        assert_eq!(merged.enrichment.len(), 1);
+
+        // directly observed values beat any enrichment guess
+        let mut observed = DiscoveryRecord::new("192.0.2.9", None, None, None, Some("Cisco"), None);
+        observed.merge(&oui);
+        assert_eq!(observed.vendor.as_deref(), Some("Cisco"));
+        assert!(observed.provenance("vendor").is_none());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn csv_drops_enrichment_block() {
+        let mut r = DiscoveryRecord::new("192.0.2.9", None, None, None, Some("Acme"), None);
+        r.set_provenance("vendor", "oui", 90);
+        let csv = serde_helpers::to_csv(&r).expect("to_csv");
+        let parsed = serde_helpers::from_csv(&csv).expect("from_csv");
+        assert_eq!(parsed.vendor.as_deref(), Some("Acme"));
+        assert!(parsed.enrichment.is_empty());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn enrichment_block_skipped_when_empty() {
+        let mut r = DiscoveryRecord::new("192.0.2.9", None, None, None, Some("Acme"), None);
+        assert!(!serde_helpers::to_json(&r).unwrap().contains("enrichment"));
+        r.set_provenance("vendor", "oui", 90);
+        r.set_provenance("vendor", "oui", 90);
+        let j = serde_helpers::to_json(&r).unwrap();
+        assert!(j.contains(r#""enrichment":[{"field":"vendor","source":"oui","confidence":90}]"#));
+        assert_eq!(serde_helpers::from_json(&j).unwrap(), r);
+    }
 }
@@ -0,0 +1,340 @@
+//! Anonymize records before sharing them outside the network they describe.
+//!
+//! IPs keep their network prefix and lose (or hash) the host bits, MACs keep
+//! the OUI half so vendor counts survive, and free-text fields can be
+//! dropped. Hashing is FNV-1a over a caller-chosen salt plus the original
+//! value: the same host maps to the same output across exports that use the
+//! same salt, and a secret salt stops anyone from brute-forcing a /24 back.
+//!
+//! `anonymize_records` is the fixed, stronger preset for handing results to
+//! a third party: every address becomes an HMAC-SHA256 pseudonym in
+//! 10.0.0.0/8, so not even the network survives.
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::DiscoveryRecord;
+
+/// Replacement for IPs (or unparseable MACs) that cannot be redacted in place.
+pub const REDACTED: &str = "redacted";
+
+/// What to do with the host part of `DiscoveryRecord.ip`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IpRedaction {
+    #[default]
+    Keep,
+    /// Zero the host bits: 192.168.1.37/24 -> 192.168.1.0
+    ZeroHost,
+    /// Replace the host bits with salted hash bits: stable per host, and
+    /// the result stays an address in the original network
+    HashHost,
+}
+
+/// What to do with the device-specific (NIC) half of `DiscoveryRecord.mac`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MacRedaction {
+    #[default]
+    Keep,
+    /// aa:bb:cc:12:34:56 -> aa:bb:cc:00:00:00
+    MaskNic,
+    /// aa:bb:cc:12:34:56 -> aa:bb:cc:<3 salted hash octets>
+    HashNic,
+}
+
+/// Per-field settings for `redact`. The default changes nothing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RedactOptions {
+    pub ip: IpRedaction,
+    /// Network bits kept for IPv4 addresses
+    pub ipv4_prefix: u8,
+    /// Network bits kept for IPv6 addresses
+    pub ipv6_prefix: u8,
+    pub mac: MacRedaction,
+    /// Clear `banner` (may carry hostnames, serials, software versions)
+    pub drop_banners: bool,
+    /// Clear `hostname`
+    pub drop_hostnames: bool,
+    /// Clear `ssh_hostkey` (identifies the host across address changes)
+    pub drop_ssh_hostkeys: bool,
+    /// Mixed into every hash; keep it secret and reuse it for exports that
+    /// should stay comparable
+    pub salt: String,
+}
+
+impl Default for RedactOptions {
+    fn default() -> Self {
+        Self {
+            ip: IpRedaction::Keep,
+            ipv4_prefix: 24,
+            ipv6_prefix: 64,
+            mac: MacRedaction::Keep,
+            drop_banners: false,
+            drop_hostnames: false,
+            drop_ssh_hostkeys: false,
+            salt: String::new(),
+        }
+    }
+}
+
+impl RedactOptions {
+    pub fn with_ip(mut self, ip: IpRedaction) -> Self {
+        self.ip = ip;
+        self
+    }
+
+    /// Network bits kept by `IpRedaction::ZeroHost` / `HashHost`; values
+    /// beyond the address width are clamped.
+    pub fn with_prefixes(mut self, ipv4_prefix: u8, ipv6_prefix: u8) -> Self {
+        self.ipv4_prefix = ipv4_prefix;
+        self.ipv6_prefix = ipv6_prefix;
+        self
+    }
+
+    pub fn with_mac(mut self, mac: MacRedaction) -> Self {
+        self.mac = mac;
+        self
+    }
+
+    pub fn with_drop_banners(mut self, drop: bool) -> Self {
+        self.drop_banners = drop;
+        self
+    }
+
+    pub fn with_drop_hostnames(mut self, drop: bool) -> Self {
+        self.drop_hostnames = drop;
+        self
+    }
+
+    pub fn with_drop_ssh_hostkeys(mut self, drop: bool) -> Self {
+        self.drop_ssh_hostkeys = drop;
+        self
+    }
+
+    pub fn with_salt<S: Into<String>>(mut self, salt: S) -> Self {
+        self.salt = salt.into();
+        self
+    }
+}
+
+/// Redacted copies of `records`, in order (see `redact_record`).
+pub fn redact(records: &[DiscoveryRecord], options: &RedactOptions) -> Vec<DiscoveryRecord> {
+    records.iter().map(|r| redact_record(r, options)).collect()
+}
+
+/// Redacted copy of one record. IPs that do not parse become `REDACTED`
+/// (`ZeroHost`) or a hex hash (`HashHost`); MACs that are not six hex
+/// octets become `REDACTED`. Vendor, ports and the other fields are kept.
+pub fn redact_record(record: &DiscoveryRecord, options: &RedactOptions) -> DiscoveryRecord {
+    let mut out = record.clone();
+    out.ip = redact_ip(&record.ip, options);
+    out.mac = record.mac.as_deref().map(|m| redact_mac(m, options));
+    if options.drop_banners {
+        out.banner = None;
+    }
+    if options.drop_hostnames {
+        out.hostname = None;
+    }
+    if options.drop_ssh_hostkeys {
+        out.ssh_hostkey = None;
+    }
+    out
+}
+
+/// Pseudonymized copies of `records` for sharing outside the organisation.
+/// Each IP becomes `10.a.b.c`, where `a.b.c` are the first bytes of
+/// HMAC-SHA256(`salt`, ip): the same IP and salt always give the same
+/// pseudonym, another salt gives an unrelated one. MACs keep their OUI with
+/// the NIC bytes zeroed (`MacRedaction::MaskNic`), so `vendor` stays
+/// meaningful and is kept. `banner`, `hostname` and `ssh_hostkey` are
+/// cleared.
+pub fn anonymize_records(records: &[DiscoveryRecord], salt: &[u8]) -> Vec<DiscoveryRecord> {
+    let options = RedactOptions::default()
+        .with_mac(MacRedaction::MaskNic)
+        .with_drop_banners(true)
+        .with_drop_hostnames(true)
+        .with_drop_ssh_hostkeys(true);
+    records
+        .iter()
+        .map(|r| {
+            let mut out = redact_record(r, &options);
+            out.ip = ip_pseudonym(&r.ip, salt).to_string();
+            out
+        })
+        .collect()
+}
+
+fn ip_pseudonym(ip: &str, salt: &[u8]) -> Ipv4Addr {
+    let mut mac = Hmac::<Sha256>::new_from_slice(salt).expect("HMAC takes keys of any length");
+    mac.update(ip.as_bytes());
+    let digest = mac.finalize().into_bytes();
+    Ipv4Addr::new(10, digest[0], digest[1], digest[2])
+}
+
+fn redact_ip(ip: &str, options: &RedactOptions) -> String {
+    if options.ip == IpRedaction::Keep {
+        return ip.to_string();
+    }
+    let hash = options.ip == IpRedaction::HashHost;
+    match ip.parse::<IpAddr>() {
+        Ok(IpAddr::V4(v4)) => {
+            let host_bits = 32 - options.ipv4_prefix.min(32) as u32;
+            let host_mask = if host_bits == 0 {
+                0
+            } else {
+                u32::MAX >> (32 - host_bits)
+            };
+            let host = if hash {
+                salted_hash(&options.salt, ip) as u32 & host_mask
+            } else {
+                0
+            };
+            Ipv4Addr::from(u32::from(v4) & !host_mask | host).to_string()
+        }
+        Ok(IpAddr::V6(v6)) => {
+            let host_bits = 128 - options.ipv6_prefix.min(128) as u32;
+            let host_mask = if host_bits == 0 {
+                0
+            } else {
+                u128::MAX >> (128 - host_bits)
+            };
+            let host = if hash {
+                let hi = salted_hash(&options.salt, ip) as u128;
+                let lo = salted_hash(&options.salt, &format!("{}#", ip)) as u128;
+                (hi << 64 | lo) & host_mask
+            } else {
+                0
+            };
+            Ipv6Addr::from(u128::from(v6) & !host_mask | host).to_string()
+        }
+        Err(_) if hash => format!("{:016x}", salted_hash(&options.salt, ip)),
+        Err(_) => REDACTED.to_string(),
+    }
+}
+
+fn redact_mac(mac: &str, options: &RedactOptions) -> String {
+    if options.mac == MacRedaction::Keep {
+        return mac.to_string();
+    }
+    let octets: Vec<u8> = mac
+        .split([':', '-'])
+        .filter_map(|o| u8::from_str_radix(o, 16).ok())
+        .collect();
+    if octets.len() != 6 || mac.len() != 17 {
+        return REDACTED.to_string();
+    }
+    let nic = match options.mac {
+        MacRedaction::HashNic => {
+            let h = salted_hash(&options.salt, &mac.to_ascii_lowercase().replace('-', ":"));
+            [(h >> 16) as u8, (h >> 8) as u8, h as u8]
+        }
+        _ => [0, 0, 0],
+    };
+    format!(
+        "{:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x}",
+        octets[0], octets[1], octets[2], nic[0], nic[1], nic[2]
+    )
+}
+
+/// 64-bit FNV-1a of `salt`, a separator byte, then `value`.
+fn salted_hash(salt: &str, value: &str) -> u64 {
+    const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0100_0000_01b3;
+    salt.bytes()
+        .chain(std::iter::once(0))
+        .chain(value.bytes())
+        .fold(OFFSET, |h, b| (h ^ b as u64).wrapping_mul(PRIME))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn host(ip: &str, mac: Option<&str>) -> DiscoveryRecord {
+        let mut r = DiscoveryRecord::new(ip, Some(22), Some("SSH-2.0-x"), mac, Some("ACME"), None);
+        r.hostname = Some("build-07.corp.example".into());
+        r.ssh_hostkey = Some("ssh-ed25519 SHA256:abc".into());
+        r
+    }
+
+    #[test]
+    fn default_options_change_nothing() {
+        let r = host("192.168.1.37", Some("aa:bb:cc:12:34:56"));
+        assert_eq!(redact_record(&r, &RedactOptions::default()), r);
+    }
+
+    #[test]
+    fn zero_host_and_mask_nic_keep_network_and_oui() {
+        let opts = RedactOptions::default()
+            .with_ip(IpRedaction::ZeroHost)
+            .with_mac(MacRedaction::MaskNic)
+            .with_drop_banners(true)
+            .with_drop_ssh_hostkeys(true);
+        let r = redact_record(&host("192.168.1.37", Some("AA-BB-CC-12-34-56")), &opts);
+        assert_eq!(r.ip, "192.168.1.0");
+        assert_eq!(r.mac.as_deref(), Some("aa:bb:cc:00:00:00"));
+        assert_eq!((r.banner, r.ssh_hostkey), (None, None));
+        assert_eq!((r.vendor.as_deref(), r.port), (Some("ACME"), Some(22)));
+
+        let v6 = redact_record(&host("2001:db8::1234", None), &opts);
+        assert_eq!(v6.ip, "2001:db8::");
+        let bad = redact_record(&host("printer.lan", Some("not-a-mac")), &opts);
+        assert_eq!(
+            (bad.ip.as_str(), bad.mac.as_deref()),
+            (REDACTED, Some(REDACTED))
+        );
+    }
+
+    #[test]
+    fn hashing_is_deterministic_per_salt_and_stays_in_network() {
+        let opts = RedactOptions::default()
+            .with_ip(IpRedaction::HashHost)
+            .with_mac(MacRedaction::HashNic)
+            .with_salt("s3cret");
+        let a = host("10.0.5.20", Some("aa:bb:cc:12:34:56"));
+        let first = redact(&[a.clone(), host("10.0.5.21", None)], &opts);
+        let again = redact(std::slice::from_ref(&a), &opts);
+        assert_eq!(first[0].ip, again[0].ip);
+        assert_eq!(first[0].mac, again[0].mac);
+        assert_ne!(first[0].ip, first[1].ip);
+        assert!(first[0].ip.starts_with("10.0.5."));
+        assert!(first[0].mac.as_deref().unwrap().starts_with("aa:bb:cc:"));
+
+        let other_salt = redact_record(&a, &opts.clone().with_salt("other"));
+        assert_ne!(other_salt.ip, first[0].ip);
+    }
+
+    #[test]
+    fn anonymized_ips_are_stable_per_salt() {
+        let records = [
+            host("192.168.1.37", Some("aa:bb:cc:12:34:56")),
+            host("192.168.1.38", None),
+            host("192.168.1.37", None),
+        ];
+        let first = anonymize_records(&records, b"vendor-ticket-42");
+        let again = anonymize_records(&records, b"vendor-ticket-42");
+        assert_eq!(first, again);
+        assert_eq!(first[0].ip, first[2].ip);
+        assert_ne!(first[0].ip, first[1].ip);
+        assert!(first[0].ip.starts_with("10."));
+        assert!(first[0].ip.parse::<Ipv4Addr>().is_ok());
+
+        let other = anonymize_records(&records, b"another-salt");
+        assert_ne!(other[0].ip, first[0].ip);
+    }
+
+    #[test]
+    fn anonymize_keeps_oui_and_vendor_only() {
+        let r = &anonymize_records(&[host("2001:db8::7", Some("aa:bb:cc:12:34:56"))], b"s")[0];
+        assert!(r.ip.starts_with("10."));
+        assert_eq!(r.mac.as_deref(), Some("aa:bb:cc:00:00:00"));
+        assert_eq!(r.vendor.as_deref(), Some("ACME"));
+        assert_eq!(
+            (r.banner.as_deref(), r.ssh_hostkey.as_deref()),
+            (None, None)
+        );
+        assert_eq!(r.hostname, None);
+        assert_eq!(r.port, Some(22));
+    }
+}
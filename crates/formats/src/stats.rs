@@ -0,0 +1,126 @@
+//! Summary counts over a scan, for reports.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use crate::DiscoveryRecord;
+
+/// Ports listed by the `Display` summary.
+const DISPLAY_TOP_PORTS: usize = 5;
+
+/// Counts over one scan's records, from `ScanStats::from_records`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScanStats {
+    /// Records in the scan (one per open port, or one per host without any)
+    pub total_records: usize,
+    /// Distinct IPs
+    pub unique_hosts: usize,
+    /// Distinct IPs with a MAC on any of their records
+    pub hosts_with_mac: usize,
+    /// Distinct IPs with a vendor on any of their records
+    pub hosts_with_vendor: usize,
+    /// Records with a port
+    pub open_ports: usize,
+    /// `(port, records)` per port seen, most frequent first (ties by port)
+    pub port_frequency: Vec<(u16, usize)>,
+}
+
+impl ScanStats {
+    pub fn from_records(records: &[DiscoveryRecord]) -> Self {
+        let hosts = |f: fn(&DiscoveryRecord) -> bool| {
+            records
+                .iter()
+                .filter(|r| f(r))
+                .map(|r| r.ip.as_str())
+                .collect::<HashSet<_>>()
+                .len()
+        };
+        let mut ports: HashMap<u16, usize> = HashMap::new();
+        for port in records.iter().filter_map(|r| r.port) {
+            *ports.entry(port).or_default() += 1;
+        }
+        let mut port_frequency: Vec<_> = ports.into_iter().collect();
+        port_frequency.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        Self {
+            total_records: records.len(),
+            unique_hosts: hosts(|_| true),
+            hosts_with_mac: hosts(|r| r.mac.is_some()),
+            hosts_with_vendor: hosts(|r| r.vendor.is_some()),
+            open_ports: records.iter().filter(|r| r.port.is_some()).count(),
+            port_frequency,
+        }
+    }
+}
+
+impl fmt::Display for ScanStats {
+    /// One line: `"6 records, 4 hosts (2 with MAC, 1 with vendor), 5 open
+    /// ports; top ports: 22 (3), 80 (1)"`, listing at most five ports.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} records, {} hosts ({} with MAC, {} with vendor), {} open ports",
+            self.total_records,
+            self.unique_hosts,
+            self.hosts_with_mac,
+            self.hosts_with_vendor,
+            self.open_ports
+        )?;
+        if !self.port_frequency.is_empty() {
+            let top: Vec<String> = self
+                .port_frequency
+                .iter()
+                .take(DISPLAY_TOP_PORTS)
+                .map(|(port, n)| format!("{} ({})", port, n))
+                .collect();
+            write!(f, "; top ports: {}", top.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scan() -> Vec<DiscoveryRecord> {
+        let mac = Some("aa:bb:cc:dd:ee:01");
+        vec![
+            DiscoveryRecord::new("192.0.2.1", Some(22), None, mac, Some("ACME"), None),
+            DiscoveryRecord::new("192.0.2.1", Some(80), None, mac, Some("ACME"), None),
+            DiscoveryRecord::new("192.0.2.2", Some(22), None, mac, None, None),
+            DiscoveryRecord::new("192.0.2.2", Some(443), None, None, None, None),
+            DiscoveryRecord::new("192.0.2.3", None, None, None, None, None),
+            DiscoveryRecord::new("192.0.2.4", Some(22), None, None, None, None),
+        ]
+    }
+
+    #[test]
+    fn counts_records_hosts_and_ports() {
+        let stats = ScanStats::from_records(&scan());
+        assert_eq!(
+            stats,
+            ScanStats {
+                total_records: 6,
+                unique_hosts: 4,
+                hosts_with_mac: 2,
+                hosts_with_vendor: 1,
+                open_ports: 5,
+                port_frequency: vec![(22, 3), (80, 1), (443, 1)],
+            }
+        );
+        assert_eq!(ScanStats::from_records(&[]), ScanStats::default());
+    }
+
+    #[test]
+    fn display_is_a_one_line_summary() {
+        assert_eq!(
+            ScanStats::from_records(&scan()).to_string(),
+            "6 records, 4 hosts (2 with MAC, 1 with vendor), 5 open ports; \
+             top ports: 22 (3), 80 (1), 443 (1)"
+        );
+        assert_eq!(
+            ScanStats::default().to_string(),
+            "0 records, 0 hosts (0 with MAC, 0 with vendor), 0 open ports"
+        );
+    }
+}
@@ -0,0 +1,143 @@
+//! Sanity checks for records imported from external files.
+
+use std::fmt;
+use std::net::IpAddr;
+
+use crate::DiscoveryRecord;
+
+/// One problem found by `validate_record`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationError {
+    /// `ip` is not an IPv4 or IPv6 address
+    InvalidIp(String),
+    /// `mac` is not six hex octets separated by `:` or `-`
+    InvalidMac(String),
+    /// `ip` is empty or whitespace
+    EmptyIp,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationError::InvalidIp(ip) => write!(f, "invalid IP address: {:?}", ip),
+            ValidationError::InvalidMac(mac) => write!(f, "invalid MAC address: {:?}", mac),
+            ValidationError::EmptyIp => write!(f, "empty IP address"),
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// Check that `rec.ip` parses as an IP address and `rec.mac`, when present,
+/// is a MAC address. All problems are returned, not just the first.
+pub fn validate_record(rec: &DiscoveryRecord) -> Result<(), Vec<ValidationError>> {
+    let mut errors = Vec::new();
+    if rec.ip.trim().is_empty() {
+        errors.push(ValidationError::EmptyIp);
+    } else if rec.ip.parse::<IpAddr>().is_err() {
+        errors.push(ValidationError::InvalidIp(rec.ip.clone()));
+    }
+    if let Some(mac) = rec.mac.as_deref().filter(|m| !is_valid_mac(m)) {
+        errors.push(ValidationError::InvalidMac(mac.to_string()));
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// Split `records` into the valid ones and, for the rest, their index in
+/// `records` with the errors found.
+pub fn validate_records(
+    records: &[DiscoveryRecord],
+) -> (Vec<DiscoveryRecord>, Vec<(usize, Vec<ValidationError>)>) {
+    let mut valid = Vec::new();
+    let mut invalid = Vec::new();
+    for (i, rec) in records.iter().enumerate() {
+        match validate_record(rec) {
+            Ok(()) => valid.push(rec.clone()),
+            Err(errors) => invalid.push((i, errors)),
+        }
+    }
+    (valid, invalid)
+}
+
+fn is_valid_mac(mac: &str) -> bool {
+    let Some(sep) = mac.chars().find(|c| *c == ':' || *c == '-') else {
+        return false;
+    };
+    let octets: Vec<&str> = mac.split(sep).collect();
+    octets.len() == 6
+        && octets
+            .iter()
+            .all(|o| o.len() == 2 && o.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rec(ip: &str, mac: Option<&str>) -> DiscoveryRecord {
+        DiscoveryRecord::new(ip, None, None, mac, None, None)
+    }
+
+    #[test]
+    fn accepts_v4_v6_and_both_mac_styles() {
+        assert_eq!(
+            validate_record(&rec("192.0.2.1", Some("AA:bb:cc:00:11:22"))),
+            Ok(())
+        );
+        assert_eq!(
+            validate_record(&rec("2001:db8::1", Some("aa-bb-cc-00-11-22"))),
+            Ok(())
+        );
+        assert_eq!(validate_record(&rec("10.0.0.1", None)), Ok(()));
+    }
+
+    #[test]
+    fn reports_every_problem() {
+        assert_eq!(
+            validate_record(&rec("999.0.0.1", Some("aa:bb:cc:dd:ee"))),
+            Err(vec![
+                ValidationError::InvalidIp("999.0.0.1".into()),
+                ValidationError::InvalidMac("aa:bb:cc:dd:ee".into()),
+            ])
+        );
+        assert_eq!(
+            validate_record(&rec(" ", None)),
+            Err(vec![ValidationError::EmptyIp])
+        );
+        for mac in [
+            "aa:bb:cc-dd:ee:ff",
+            "aabbccddeeff",
+            "gg:bb:cc:dd:ee:ff",
+            "a:bb:cc:dd:ee:fff",
+        ] {
+            assert_eq!(
+                validate_record(&rec("10.0.0.1", Some(mac))),
+                Err(vec![ValidationError::InvalidMac(mac.into())]),
+                "{}",
+                mac
+            );
+        }
+    }
+
+    #[test]
+    fn partitions_valid_and_invalid() {
+        let records = vec![
+            rec("192.0.2.1", None),
+            rec("999.0.0.1", None),
+            rec("", Some("zz:zz:zz:zz:zz:zz")),
+            rec("192.0.2.2", Some("00:11:22:33:44:55")),
+        ];
+        let (valid, invalid) = validate_records(&records);
+        assert_eq!(valid, [records[0].clone(), records[3].clone()]);
+        assert_eq!(invalid.iter().map(|(i, _)| *i).collect::<Vec<_>>(), [1, 2]);
+        assert_eq!(invalid[1].1.len(), 2);
+        assert_eq!(
+            ValidationError::InvalidIp("999.0.0.1".into()).to_string(),
+            "invalid IP address: \"999.0.0.1\""
+        );
+    }
+}
@@ -0,0 +1,15 @@
+//! Built and run by `cargo test --no-default-features`: the record types and
+//! grouping helpers must not need serde.
+#![cfg(not(feature = "serde"))]
+
+use formats::{group_by_vendor, DiscoveryRecord};
+
+#[test]
+fn record_builds_without_serde() {
+    let mut r = DiscoveryRecord::new("192.0.2.1", Some(22), None, None, Some("Acme"), None);
+    r.set_provenance("vendor", "oui", 90);
+    let other = DiscoveryRecord::new("192.0.2.1", None, Some("host.lan"), None, None, None);
+    r.merge(&other);
+    assert_eq!(r.banner.as_deref(), Some("host.lan"));
+    assert_eq!(group_by_vendor(&[r])["Acme"].len(), 1);
+}
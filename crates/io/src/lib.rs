@@ -4,11 +4,31 @@ use std::error::Error;
 use std::fs::File;
 use std::io::Read;
 
-use formats::DiscoveryRecord;
-mod oui;
+use formats::{DiscoveryRecord, FieldProvenance, ScanStats};
+mod mapping;
+#[cfg(feature = "nmap")]
+mod nmap;
+pub mod oui;
+mod output;
+#[cfg(feature = "parquet")]
+mod parquet;
+#[cfg(feature = "port-report")]
+mod port_report;
+use mapping::find_column;
+pub use mapping::ColumnMapping;
+#[cfg(feature = "nmap")]
+pub use nmap::read_nmap_xml;
 pub use oui::lookup_vendor as lookup_vendor_from_oui;
+pub use oui::lookup_vendor_fuzzy as lookup_vendor_fuzzy_from_oui;
+pub use output::{scan_method, write_discovery_outputs, OutputFormat};
+#[cfg(feature = "parquet")]
+pub use parquet::{write_parquet, write_parquet_with_row_group_size, PARQUET_ROW_GROUP_SIZE};
+#[cfg(feature = "port-report")]
+pub use port_report::{read_port_report_json, to_port_report_json, write_port_report_json};
 
 /// Read a netscan-style JSON file and map to canonical DiscoveryRecord list.
+/// `Hostname` (or `hostname`) fills `hostname` and the first of `banners`
+/// fills `banner`, so an entry with both keeps both.
 pub fn read_netscan_json<P: AsRef<str>>(path: P) -> Result<Vec<DiscoveryRecord>, Box<dyn Error>> {
     let path = path.as_ref();
     let mut s = String::new();
@@ -16,7 +36,7 @@ pub fn read_netscan_json<P: AsRef<str>>(path: P) -> Result<Vec<DiscoveryRecord>,
     let v: serde_json::Value = serde_json::from_str(&s)?;
     let arr = v
         .as_array()
-        .ok_or_else(|| "expected top-level array in netscan json")?;
+        .ok_or("expected top-level array in netscan json")?;
     let mut out = Vec::with_capacity(arr.len());
     for item in arr {
         let ip = item
@@ -28,21 +48,18 @@ pub fn read_netscan_json<P: AsRef<str>>(path: P) -> Result<Vec<DiscoveryRecord>,
         let port = item
             .get("ports")
             .and_then(|p| p.as_array())
-            .and_then(|a| a.get(0))
+            .and_then(|a| a.first())
             .and_then(|n| n.as_u64())
             .map(|n| n as u16);
-        // prefer Hostname or first banner
-        let banner = item
+        let hostname = item
             .get("Hostname")
             .and_then(|x| x.as_str())
-            .map(|s| s.to_string())
-            .or_else(|| {
-                item.get("banners")
-                    .and_then(|b| b.as_array())
-                    .and_then(|arr| arr.get(0))
-                    .and_then(|v| v.as_str())
-                    .map(|s| s.to_string())
-            });
+            .or_else(|| item.get("hostname").and_then(|x| x.as_str()));
+        let banner = item
+            .get("banners")
+            .and_then(|b| b.as_array())
+            .and_then(|arr| arr.first())
+            .and_then(|v| v.as_str());
         // optional fields commonly present in netscan outputs
         let mac = item
             .get("MAC")
@@ -57,19 +74,26 @@ pub fn read_netscan_json<P: AsRef<str>>(path: P) -> Result<Vec<DiscoveryRecord>,
             .and_then(|x| x.as_str())
             .or_else(|| item.get("timestamp").and_then(|x| x.as_str()))
             .or_else(|| item.get("time").and_then(|x| x.as_str()));
+        // legacy exports write `is_up`; keep it so down hosts stay down
+        let is_up = ["is_up", "IsUp", "isUp"]
+            .iter()
+            .find_map(|k| item.get(*k).and_then(|x| x.as_bool()));
 
-        out.push(DiscoveryRecord::new(
-            ip,
-            port,
-            banner.as_deref(),
-            mac,
-            vendor,
-            timestamp,
-        ));
+        let mut rec = DiscoveryRecord::new(ip, port, banner, mac, vendor, timestamp);
+        rec.hostname = hostname.map(str::to_string);
+        rec.is_up = is_up;
+        out.push(rec);
     }
     Ok(out)
 }
 
+/// Read a JSON array of `DiscoveryRecord` as written by `serde_json` (the
+/// `json` output of the scanners), keeping every field and the provenance.
+pub fn read_records_json<P: AsRef<str>>(path: P) -> Result<Vec<DiscoveryRecord>, Box<dyn Error>> {
+    let s = std::fs::read_to_string(path.as_ref())?;
+    Ok(serde_json::from_str(&s)?)
+}
+
 /// Export a list of `DiscoveryRecord` as a JSON array compatible with the
 /// Target-compatible JSON exporter. Produces pretty-printed JSON arrays that
 /// are intended to be ingested by external consumers. The naming here is
@@ -78,52 +102,117 @@ pub fn to_target_json(
     records: &[DiscoveryRecord],
     default_method: &str,
 ) -> Result<String, Box<dyn Error>> {
-    use serde::Serialize;
+    to_target_json_with(records, default_method, &JsonExportOptions::default())
+}
 
-    #[derive(Serialize)]
-    struct GoDevice<'a> {
-        ip: &'a str,
-        #[serde(skip_serializing_if = "Option::is_none")]
-        mac: Option<&'a str>,
-        #[serde(skip_serializing_if = "Option::is_none")]
-        hostname: Option<&'a str>,
-        #[serde(skip_serializing_if = "Option::is_none")]
-        vendor: Option<&'a str>,
-        method: &'a str,
-        ports: Vec<u16>,
-        is_up: bool,
-        #[serde(skip_serializing_if = "Option::is_none")]
-        timestamp: Option<&'a str>,
+/// Options shared by the JSON exporters.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct JsonExportOptions {
+    /// Emit each record's `enrichment` provenance block (omitted when empty)
+    pub include_enrichment: bool,
+}
+
+impl JsonExportOptions {
+    pub fn with_enrichment(mut self, include: bool) -> Self {
+        self.include_enrichment = include;
+        self
     }
+}
 
-    let mut out = Vec::with_capacity(records.len());
-    for r in records {
-        let ports = r.port.map(|p| vec![p]).unwrap_or_default();
-        let hostname = r.banner.as_deref();
-        let dev = GoDevice {
+fn enrichment_block<'a>(
+    r: &'a DiscoveryRecord,
+    opts: &JsonExportOptions,
+) -> Option<&'a [FieldProvenance]> {
+    (opts.include_enrichment && !r.enrichment.is_empty()).then_some(r.enrichment.as_slice())
+}
+
+/// Service name for the record's port. Only emitted when the record carries
+/// a port; `service` already holds the best guess (banner match over the
+/// port table), so it is passed through as is.
+fn port_service(r: &DiscoveryRecord) -> Option<&str> {
+    r.port.and(r.service.as_deref())
+}
+
+/// One device of the target-compatible JSON export.
+#[derive(serde::Serialize)]
+struct GoDevice<'a> {
+    ip: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mac: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hostname: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    vendor: Option<&'a str>,
+    method: &'a str,
+    ports: Vec<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    service: Option<&'a str>,
+    is_up: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timestamp: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    enrichment: Option<&'a [FieldProvenance]>,
+}
+
+impl<'a> GoDevice<'a> {
+    fn new(r: &'a DiscoveryRecord, method: &'a str, opts: &JsonExportOptions) -> Self {
+        GoDevice {
             ip: &r.ip,
             mac: r.mac.as_deref(),
-            hostname,
+            hostname: r.hostname.as_deref(),
             vendor: r.vendor.as_deref(),
-            method: default_method,
-            ports,
-            is_up: true,
+            method,
+            ports: r.port.map(|p| vec![p]).unwrap_or_default(),
+            service: port_service(r),
+            is_up: r.is_up.unwrap_or(true),
             timestamp: r.timestamp.as_deref(),
-        };
-        out.push(dev);
+            enrichment: enrichment_block(r, opts),
+        }
     }
+}
 
+/// `to_target_json` with explicit export options.
+pub fn to_target_json_with(
+    records: &[DiscoveryRecord],
+    default_method: &str,
+    opts: &JsonExportOptions,
+) -> Result<String, Box<dyn Error>> {
+    let out: Vec<GoDevice> = records
+        .iter()
+        .map(|r| GoDevice::new(r, default_method, opts))
+        .collect();
     Ok(serde_json::to_string_pretty(&out)?)
 }
 
+/// Write the same pretty JSON as `to_target_json` straight to `writer`, one
+/// array element at a time, so the whole document is never held in memory.
+/// `writer` is not buffered here; wrap files in a `BufWriter`.
+pub fn write_target_json_stream<W: std::io::Write>(
+    writer: W,
+    records: &[DiscoveryRecord],
+    default_method: &str,
+) -> Result<(), Box<dyn Error>> {
+    use serde::ser::{SerializeSeq, Serializer};
+
+    let opts = JsonExportOptions::default();
+    let mut ser = serde_json::Serializer::pretty(writer);
+    let mut seq = (&mut ser).serialize_seq(Some(records.len()))?;
+    for r in records {
+        seq.serialize_element(&GoDevice::new(r, default_method, &opts))?;
+    }
+    seq.end()?;
+    Ok(())
+}
+
 /// Convenience: write target-compatible JSON to a file path.
 pub fn write_target_json_file<P: AsRef<str>>(
     path: P,
     records: &[DiscoveryRecord],
     default_method: &str,
 ) -> Result<(), Box<dyn Error>> {
-    let s = to_target_json(records, default_method)?;
-    std::fs::write(path.as_ref(), s)?;
+    let mut file = std::io::BufWriter::new(File::create(path.as_ref())?);
+    write_target_json_stream(&mut file, records, default_method)?;
+    std::io::Write::flush(&mut file)?;
     Ok(())
 }
 
@@ -134,6 +223,15 @@ pub fn write_target_json_file<P: AsRef<str>>(
 pub fn to_legacy_json(
     records: &[DiscoveryRecord],
     default_method: &str,
+) -> Result<String, Box<dyn Error>> {
+    to_legacy_json_with(records, default_method, &JsonExportOptions::default())
+}
+
+/// `to_legacy_json` with explicit export options.
+pub fn to_legacy_json_with(
+    records: &[DiscoveryRecord],
+    default_method: &str,
+    opts: &JsonExportOptions,
 ) -> Result<String, Box<dyn Error>> {
     use serde::Serialize;
 
@@ -154,32 +252,33 @@ pub fn to_legacy_json(
         // richer fields not present in minimal CSV
         ports: Vec<u16>,
         banners: Vec<&'a str>,
+        #[serde(rename = "Service", skip_serializing_if = "Option::is_none")]
+        service: Option<&'a str>,
         #[serde(rename = "is_up")]
         is_up: bool,
         #[serde(rename = "Method")]
         method: &'a str,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        enrichment: Option<&'a [FieldProvenance]>,
     }
 
     let mut out = Vec::with_capacity(records.len());
     for r in records {
         let ports = r.port.map(|p| vec![p]).unwrap_or_default();
-        let mut banners = Vec::new();
-        if let Some(b) = r.banner.as_deref() {
-            if !b.is_empty() {
-                banners.push(b);
-            }
-        }
+        let banners = dedup_banners(r.banner.as_deref());
 
         let dev = LegacyDevice {
             ip: &r.ip,
             mac: r.mac.as_deref(),
-            hostname: r.banner.as_deref(),
+            hostname: r.hostname.as_deref(),
             vendor: r.vendor.as_deref(),
             timestamp: r.timestamp.as_deref(),
             ports,
             banners,
-            is_up: true,
+            service: port_service(r),
+            is_up: r.is_up.unwrap_or(true),
             method: default_method,
+            enrichment: enrichment_block(r, opts),
         };
         out.push(dev);
     }
@@ -187,6 +286,22 @@ pub fn to_legacy_json(
     Ok(serde_json::to_string_pretty(&out)?)
 }
 
+/// The legacy `banners` array: non-empty banners with repeats dropped,
+/// first occurrence kept. Several ports behind one load balancer often
+/// answer with the same banner.
+pub fn dedup_banners<'a, I>(banners: I) -> Vec<&'a str>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let mut out: Vec<&str> = Vec::new();
+    for b in banners {
+        if !b.is_empty() && !out.contains(&b) {
+            out.push(b);
+        }
+    }
+    out
+}
+
 /// Convenience: write legacy-shaped JSON to a file path.
 pub fn write_legacy_json_file<P: AsRef<str>>(
     path: P,
@@ -198,77 +313,410 @@ pub fn write_legacy_json_file<P: AsRef<str>>(
     Ok(())
 }
 
+/// Export records in Elasticsearch bulk API format (NDJSON): for each record an
+/// `{"index":{"_index":...}}` action line followed by the record document.
+/// The output ends with a newline, as the bulk API requires.
+pub fn to_elasticsearch_bulk(records: &[DiscoveryRecord], index: &str) -> String {
+    let action = serde_json::json!({ "index": { "_index": index } }).to_string();
+    let mut out = String::new();
+    for r in records {
+        out.push_str(&action);
+        out.push('\n');
+        // DiscoveryRecord only holds strings/ints, so serialization cannot fail
+        out.push_str(&serde_json::to_string(r).unwrap_or_default());
+        out.push('\n');
+    }
+    out
+}
+
+/// Convenience: write Elasticsearch bulk NDJSON to a file path.
+pub fn write_elasticsearch_bulk_file(
+    path: &str,
+    records: &[DiscoveryRecord],
+    index: &str,
+) -> Result<(), Box<dyn Error>> {
+    std::fs::write(path, to_elasticsearch_bulk(records, index))?;
+    Ok(())
+}
+
+/// Export records as NDJSON: one compact `DiscoveryRecord` document per line.
+pub fn to_ndjson(records: &[DiscoveryRecord]) -> String {
+    let mut out = String::new();
+    for r in records {
+        out.push_str(&serde_json::to_string(r).unwrap_or_default());
+        out.push('\n');
+    }
+    out
+}
+
+/// Convenience: write NDJSON to a file path.
+pub fn write_ndjson_file(path: &str, records: &[DiscoveryRecord]) -> Result<(), Box<dyn Error>> {
+    std::fs::write(path, to_ndjson(records))?;
+    Ok(())
+}
+
+/// Columns written by `write_records_csv`, in order.
+pub const CSV_COLUMNS: &[&str] = &[
+    "ip",
+    "port",
+    "banner",
+    "mac",
+    "vendor",
+    "timestamp",
+    "device_type",
+    "service",
+    "product",
+    "os",
+    "port_scanned",
+    "is_up",
+    "ssh_hostkey",
+    "hostname",
+];
+
+/// Write records as CSV with a fixed header (`CSV_COLUMNS`); missing values
+/// are empty cells, so records with different fields set share one layout.
+/// Provenance is not representable in CSV and is left out. With `compress`
+/// the output is gzip-compressed, which needs the `gzip` feature (an error
+/// otherwise).
+pub fn write_records_csv<W: std::io::Write>(
+    writer: W,
+    records: &[DiscoveryRecord],
+    compress: bool,
+) -> Result<(), Box<dyn Error>> {
+    if !compress {
+        csv_rows(writer, records)?;
+        return Ok(());
+    }
+    #[cfg(feature = "gzip")]
+    {
+        let gz = flate2::write::GzEncoder::new(writer, flate2::Compression::default());
+        csv_rows(gz, records)?.finish()?;
+        Ok(())
+    }
+    #[cfg(not(feature = "gzip"))]
+    Err("gzip CSV output needs the io `gzip` feature".into())
+}
+
+/// Header of `stats_to_csv`, one column per scalar `ScanStats` field.
+pub const STATS_CSV_COLUMNS: [&str; 5] = [
+    "total_records",
+    "unique_hosts",
+    "hosts_with_mac",
+    "hosts_with_vendor",
+    "open_ports",
+];
+
+/// `stats` as a two-row CSV: `STATS_CSV_COLUMNS` and the values. The port
+/// frequencies are left to `stats_port_freq_to_csv`.
+pub fn stats_to_csv(stats: &ScanStats) -> Result<String, Box<dyn Error>> {
+    let mut wtr = csv::Writer::from_writer(Vec::new());
+    wtr.write_record(STATS_CSV_COLUMNS)?;
+    wtr.write_record(
+        [
+            stats.total_records,
+            stats.unique_hosts,
+            stats.hosts_with_mac,
+            stats.hosts_with_vendor,
+            stats.open_ports,
+        ]
+        .map(|n| n.to_string()),
+    )?;
+    Ok(String::from_utf8(wtr.into_inner()?)?)
+}
+
+/// `stats.port_frequency` as CSV (`port,count`), most frequent port first
+/// and ties in port order, whatever order the list is in.
+pub fn stats_port_freq_to_csv(stats: &ScanStats) -> Result<String, Box<dyn Error>> {
+    let mut freq = stats.port_frequency.clone();
+    freq.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    let mut wtr = csv::Writer::from_writer(Vec::new());
+    wtr.write_record(["port", "count"])?;
+    for (port, count) in freq {
+        wtr.write_record([port.to_string(), count.to_string()])?;
+    }
+    Ok(String::from_utf8(wtr.into_inner()?)?)
+}
+
+/// `write_records_csv` body; hands the writer back so it can be finished.
+fn csv_rows<W: std::io::Write>(
+    writer: W,
+    records: &[DiscoveryRecord],
+) -> Result<W, Box<dyn Error>> {
+    fn cell<T: ToString>(v: &Option<T>) -> String {
+        v.as_ref().map(|x| x.to_string()).unwrap_or_default()
+    }
+    let mut wtr = csv::Writer::from_writer(writer);
+    wtr.write_record(CSV_COLUMNS)?;
+    for r in records {
+        wtr.write_record([
+            r.ip.clone(),
+            cell(&r.port),
+            cell(&r.banner),
+            cell(&r.mac),
+            cell(&r.vendor),
+            cell(&r.timestamp),
+            cell(&r.device_type),
+            cell(&r.service),
+            cell(&r.product),
+            cell(&r.os),
+            cell(&r.port_scanned),
+            cell(&r.is_up),
+            cell(&r.ssh_hostkey),
+            cell(&r.hostname),
+        ])?;
+    }
+    Ok(wtr.into_inner().map_err(|e| e.into_error())?)
+}
+
+fn dot_escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Render records as a Graphviz DOT digraph: one node per host (labelled
+/// with IP, vendor and hostname when known) and one box node per open port,
+/// linked host -> port. Hosts appear in first-seen order.
+pub fn to_dot_graph(records: &[DiscoveryRecord]) -> String {
+    let mut hosts: Vec<&str> = Vec::new();
+    let mut labels: std::collections::HashMap<&str, Vec<&str>> = std::collections::HashMap::new();
+    let mut ports: Vec<(&str, u16, Option<&str>)> = Vec::new();
+    for r in records {
+        if !labels.contains_key(r.ip.as_str()) {
+            hosts.push(&r.ip);
+            labels.insert(&r.ip, vec![&r.ip]);
+        }
+        let label = labels.get_mut(r.ip.as_str()).expect("inserted above");
+        for extra in [r.vendor.as_deref(), r.hostname.as_deref()]
+            .into_iter()
+            .flatten()
+        {
+            if !label.contains(&extra) {
+                label.push(extra);
+            }
+        }
+        if let Some(p) = r.port {
+            if !ports.iter().any(|(ip, port, _)| *ip == r.ip && *port == p) {
+                ports.push((&r.ip, p, r.service.as_deref()));
+            }
+        }
+    }
+
+    let mut out = String::from("digraph discovery {\n    node [shape=ellipse];\n");
+    for ip in &hosts {
+        let label: Vec<String> = labels[ip].iter().map(|l| dot_escape(l)).collect();
+        out.push_str(&format!(
+            "    \"{}\" [label=\"{}\"];\n",
+            dot_escape(ip),
+            label.join("\\n")
+        ));
+    }
+    for (ip, port, service) in &ports {
+        let id = format!("{}:{}", dot_escape(ip), port);
+        let label = match service {
+            Some(s) => format!("{}/{}", port, dot_escape(s)),
+            None => port.to_string(),
+        };
+        out.push_str(&format!(
+            "    \"{}\" [shape=box, label=\"{}\"];\n    \"{}\" -> \"{}\";\n",
+            id,
+            label,
+            dot_escape(ip),
+            id
+        ));
+    }
+    out.push_str("}\n");
+    out
+}
+
+fn prometheus_label(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Render summary metrics in the Prometheus text exposition format:
+/// `discovered_hosts_total` (distinct IPs), `discovered_open_ports_total`
+/// per port and `discovered_hosts_by_vendor` per vendor (hosts without one
+/// count as `"Unknown"`). Hosts are counted once however many records they
+/// have; label values are sorted so output is stable between runs.
+pub fn to_prometheus(records: &[DiscoveryRecord]) -> String {
+    use std::collections::{BTreeMap, BTreeSet};
+
+    let mut hosts: BTreeSet<&str> = BTreeSet::new();
+    let mut ports: BTreeMap<u16, BTreeSet<&str>> = BTreeMap::new();
+    let mut vendors: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+    for r in records {
+        hosts.insert(&r.ip);
+        if let Some(p) = r.port {
+            ports.entry(p).or_default().insert(&r.ip);
+        }
+        if let Some(v) = r.vendor.as_deref() {
+            vendors.entry(v).or_default().insert(&r.ip);
+        }
+    }
+    // A host with a vendor on any record is not Unknown
+    let unknown = hosts
+        .iter()
+        .filter(|ip| !vendors.values().any(|v| v.contains(*ip)))
+        .count();
+
+    let mut out = String::new();
+    out.push_str("# HELP discovered_hosts_total Distinct hosts discovered.\n");
+    out.push_str("# TYPE discovered_hosts_total gauge\n");
+    out.push_str(&format!("discovered_hosts_total {}\n", hosts.len()));
+
+    out.push_str("# HELP discovered_open_ports_total Hosts with the port open.\n");
+    out.push_str("# TYPE discovered_open_ports_total gauge\n");
+    for (port, ips) in &ports {
+        out.push_str(&format!(
+            "discovered_open_ports_total{{port=\"{}\"}} {}\n",
+            port,
+            ips.len()
+        ));
+    }
+
+    out.push_str("# HELP discovered_hosts_by_vendor Distinct hosts per vendor.\n");
+    out.push_str("# TYPE discovered_hosts_by_vendor gauge\n");
+    let mut by_vendor: Vec<(&str, usize)> =
+        vendors.iter().map(|(v, ips)| (*v, ips.len())).collect();
+    if unknown > 0 {
+        by_vendor.push((formats::UNKNOWN_GROUP, unknown));
+        by_vendor.sort();
+    }
+    for (vendor, n) in by_vendor {
+        out.push_str(&format!(
+            "discovered_hosts_by_vendor{{vendor=\"{}\"}} {}\n",
+            prometheus_label(vendor),
+            n
+        ));
+    }
+    out
+}
+
+/// Convenience: write Prometheus metrics to a file path (e.g. for the node
+/// exporter's textfile collector).
+pub fn write_prometheus_file(
+    path: &str,
+    records: &[DiscoveryRecord],
+) -> Result<(), Box<dyn Error>> {
+    std::fs::write(path, to_prometheus(records))?;
+    Ok(())
+}
+
 /// Read a netscan-style CSV file and map to canonical DiscoveryRecord list.
 /// Expected CSV headers (common netscan): Timestamp,IP,MAC,Hostname,Vendor,OS
+/// Paths ending in `.gz` are decompressed on the fly (`gzip` feature).
+/// Files without a header row are detected (no IP header, but the second
+/// column of the first row is an address) and read in the column order above.
 pub fn read_netscan_csv<P: AsRef<str>>(path: P) -> Result<Vec<DiscoveryRecord>, Box<dyn Error>> {
-    let path = path.as_ref();
-    let mut rdr = csv::Reader::from_path(path)?;
+    // Builtin aliases; when no IP header is present the second column is used.
+    read_csv_with(path.as_ref(), &ColumnMapping::default(), Some(1), None)
+}
+
+/// `read_netscan_csv` without header detection: with `has_headers` false the
+/// first row is data and columns follow `NETSCAN_CSV_COLUMNS`.
+pub fn read_netscan_csv_with_headers<P: AsRef<str>>(
+    path: P,
+    has_headers: bool,
+) -> Result<Vec<DiscoveryRecord>, Box<dyn Error>> {
+    read_csv_with(
+        path.as_ref(),
+        &ColumnMapping::default(),
+        Some(1),
+        Some(has_headers),
+    )
+}
+
+/// Column order of a headerless netscan CSV.
+pub const NETSCAN_CSV_COLUMNS: [&str; 6] = ["Timestamp", "IP", "MAC", "Hostname", "Vendor", "OS"];
+
+/// Read a CSV with arbitrary headers, using `mapping` to decide which column
+/// feeds which field. Unlike `read_netscan_csv`, a missing IP column is an error.
+pub fn read_netscan_csv_mapped<P: AsRef<str>>(
+    path: P,
+    mapping: &ColumnMapping,
+) -> Result<Vec<DiscoveryRecord>, Box<dyn Error>> {
+    read_csv_with(path.as_ref(), mapping, None, Some(true))
+}
+
+/// `path` for reading, gunzipped when it ends in `.gz`.
+fn open_csv(path: &str) -> Result<Box<dyn Read>, Box<dyn Error>> {
+    let file = File::open(path)?;
+    if !path.ends_with(".gz") {
+        return Ok(Box::new(file));
+    }
+    #[cfg(feature = "gzip")]
+    return Ok(Box::new(flate2::read::GzDecoder::new(file)));
+    #[cfg(not(feature = "gzip"))]
+    Err(format!("{}: reading gzip CSV needs the io `gzip` feature", path).into())
+}
+
+/// `has_headers: None` guesses from the first row: it is data when no header
+/// matches `mapping.ip` and the `ip_fallback` cell parses as an address.
+fn read_csv_with(
+    path: &str,
+    mapping: &ColumnMapping,
+    ip_fallback: Option<usize>,
+    has_headers: Option<bool>,
+) -> Result<Vec<DiscoveryRecord>, Box<dyn Error>> {
+    let mut rdr = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .from_reader(open_csv(path)?);
+    let mut rows = rdr.records();
     let mut out = Vec::new();
 
+    let first = rows.next().transpose()?;
+    let headerless = match (has_headers, &first) {
+        (_, None) => false,
+        (Some(h), _) => !h,
+        (None, Some(row)) => {
+            find_column(row, &mapping.ip).is_none()
+                && ip_fallback
+                    .and_then(|i| row.get(i))
+                    .is_some_and(|c| c.trim().parse::<std::net::IpAddr>().is_ok())
+        }
+    };
     // Use header names to find columns so CSVs with different column order work.
-    // Expected headers include: Timestamp,IP,MAC,Hostname,Vendor,OS
-    let headers = rdr.headers()?.clone();
-    let find = |names: &[&str]| {
-        names
-            .iter()
-            .filter_map(|n| headers.iter().position(|h| h.eq_ignore_ascii_case(n)))
-            .next()
+    let (headers, first) = match first {
+        Some(row) if headerless => (csv::StringRecord::from(&NETSCAN_CSV_COLUMNS[..]), Some(row)),
+        row => (row.unwrap_or_default(), None),
     };
+    let ip_idx = find_column(&headers, &mapping.ip)
+        .or(ip_fallback)
+        .ok_or_else(|| format!("no IP column matching {:?}", mapping.ip))?;
+    let port_idx = find_column(&headers, &mapping.port);
+    let host_idx = find_column(&headers, &mapping.hostname);
+    let mac_idx = find_column(&headers, &mapping.mac);
+    let vendor_idx = find_column(&headers, &mapping.vendor);
+    let ts_idx = find_column(&headers, &mapping.timestamp);
+    let os_idx = find_column(&headers, &mapping.os);
 
-    let ip_idx_default = find(&["ip", "IP"]).or(Some(1)).unwrap_or(1);
-    let mac_idx_default = find(&["mac", "MAC"]);
-    let ts_idx_default = find(&["timestamp", "time", "Timestamp"]);
-    let host_idx_default = find(&["hostname", "host", "Host"]);
-    let vendor_idx_default = find(&["vendor", "Vendor"]);
-
-    for result in rdr.records() {
+    for result in first.map(Ok).into_iter().chain(rows) {
         let rec = result?;
+        // trimmed, non-empty cell at an optional column index
+        let cell = |idx: Option<usize>| {
+            idx.and_then(|i| rec.get(i))
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+        };
 
         let ip = rec
-            .get(ip_idx_default)
+            .get(ip_idx)
             .ok_or("missing IP column")?
             .trim()
             .to_string();
+        let port = cell(port_idx).and_then(|s| s.parse::<u16>().ok());
 
-        let hostname = host_idx_default.and_then(|i| rec.get(i)).and_then(|s| {
-            if s.trim().is_empty() {
-                None
-            } else {
-                Some(s.trim())
-            }
-        });
-
-        let mac = mac_idx_default.and_then(|i| rec.get(i)).and_then(|s| {
-            let t = s.trim();
-            if t.is_empty() {
-                None
-            } else {
-                Some(t)
-            }
-        });
-
-        let vendor = vendor_idx_default.and_then(|i| rec.get(i)).and_then(|s| {
-            let t = s.trim();
-            if t.is_empty() {
-                None
-            } else {
-                Some(t)
-            }
-        });
-
-        let timestamp = ts_idx_default.and_then(|i| rec.get(i)).and_then(|s| {
-            let t = s.trim();
-            if t.is_empty() {
-                None
-            } else {
-                Some(t)
-            }
-        });
-
-        // No port info in this CSV; leave None
-        out.push(DiscoveryRecord::new(
-            &ip, None, hostname, mac, vendor, timestamp,
-        ));
+        let mut record = DiscoveryRecord::new(
+            &ip,
+            port,
+            None,
+            cell(mac_idx),
+            cell(vendor_idx),
+            cell(ts_idx),
+        );
+        record.hostname = cell(host_idx).map(str::to_string);
+        record.os = cell(os_idx).map(str::to_string);
+        out.push(record);
     }
 
     Ok(out)
@@ -0,0 +1,96 @@
+//! Header -> field mapping for importing arbitrary CSV schemas.
+
+/// Which CSV headers feed which `DiscoveryRecord` field. Each field holds a
+/// list of accepted header names (matched case-insensitively, first present
+/// header wins). An empty list means the field is not imported.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnMapping {
+    pub ip: Vec<String>,
+    pub port: Vec<String>,
+    pub hostname: Vec<String>,
+    pub mac: Vec<String>,
+    pub vendor: Vec<String>,
+    pub timestamp: Vec<String>,
+    pub os: Vec<String>,
+}
+
+fn names(list: &[&str]) -> Vec<String> {
+    list.iter().map(|s| s.to_string()).collect()
+}
+
+impl Default for ColumnMapping {
+    /// The builtin netscan aliases used by `read_netscan_csv`.
+    fn default() -> Self {
+        Self {
+            ip: names(&["ip"]),
+            port: names(&["port"]),
+            hostname: names(&["hostname", "host"]),
+            mac: names(&["mac"]),
+            vendor: names(&["vendor"]),
+            timestamp: names(&["timestamp", "time"]),
+            os: names(&["os"]),
+        }
+    }
+}
+
+impl ColumnMapping {
+    /// A mapping with no columns configured; set fields with the `with_*` builders.
+    pub fn empty() -> Self {
+        Self {
+            ip: Vec::new(),
+            port: Vec::new(),
+            hostname: Vec::new(),
+            mac: Vec::new(),
+            vendor: Vec::new(),
+            timestamp: Vec::new(),
+            os: Vec::new(),
+        }
+    }
+
+    pub fn with_ip(mut self, header: &str) -> Self {
+        self.ip = vec![header.to_string()];
+        self
+    }
+
+    pub fn with_port(mut self, header: &str) -> Self {
+        self.port = vec![header.to_string()];
+        self
+    }
+
+    pub fn with_hostname(mut self, header: &str) -> Self {
+        self.hostname = vec![header.to_string()];
+        self
+    }
+
+    pub fn with_mac(mut self, header: &str) -> Self {
+        self.mac = vec![header.to_string()];
+        self
+    }
+
+    pub fn with_vendor(mut self, header: &str) -> Self {
+        self.vendor = vec![header.to_string()];
+        self
+    }
+
+    pub fn with_timestamp(mut self, header: &str) -> Self {
+        self.timestamp = vec![header.to_string()];
+        self
+    }
+
+    pub fn with_os(mut self, header: &str) -> Self {
+        self.os = vec![header.to_string()];
+        self
+    }
+}
+
+/// Index of the first header in `headers` matching any of `names`.
+pub(crate) fn find_column(headers: &csv::StringRecord, names: &[String]) -> Option<usize> {
+    names
+        .iter()
+        .filter_map(|n| {
+            headers
+                .iter()
+                .position(|h| h.trim().eq_ignore_ascii_case(n))
+        })
+        .next()
+}
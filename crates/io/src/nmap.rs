@@ -0,0 +1,226 @@
+//! Importer for nmap XML output (`nmap -oX`).
+//!
+//! Each up host becomes one record per open port; hosts without open ports
+//! keep a single record with `port: None`. The first hostname is stored in
+//! `hostname`.
+
+use std::error::Error;
+use std::io::BufRead;
+
+use formats::DiscoveryRecord;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+
+/// Read an nmap XML report and map each host/open port to a `DiscoveryRecord`.
+pub fn read_nmap_xml<P: AsRef<str>>(path: P) -> Result<Vec<DiscoveryRecord>, Box<dyn Error>> {
+    let reader = Reader::from_file(path.as_ref())?;
+    parse_nmap(reader)
+}
+
+#[derive(Default)]
+struct Host {
+    up: bool,
+    ip: Option<String>,
+    mac: Option<String>,
+    vendor: Option<String>,
+    hostname: Option<String>,
+    scanned: bool,
+    ports: Vec<Port>,
+}
+
+#[derive(Default)]
+struct Port {
+    port: u16,
+    open: bool,
+    service: Option<String>,
+    product: Option<String>,
+}
+
+fn attr(e: &BytesStart, key: &[u8]) -> Result<Option<String>, Box<dyn Error>> {
+    Ok(match e.try_get_attribute(key)? {
+        Some(a) => Some(a.unescape_value()?.into_owned()),
+        None => None,
+    })
+}
+
+fn parse_nmap<R: BufRead>(mut reader: Reader<R>) -> Result<Vec<DiscoveryRecord>, Box<dyn Error>> {
+    let mut out = Vec::new();
+    let mut buf = Vec::new();
+    let mut host: Option<Host> = None;
+    let mut port: Option<Port> = None;
+
+    loop {
+        let (e, empty) = match reader.read_event_into(&mut buf)? {
+            Event::Start(e) => (e, false),
+            Event::Empty(e) => (e, true),
+            Event::End(e) => {
+                match e.name().as_ref() {
+                    b"port" => {
+                        if let (Some(h), Some(p)) = (host.as_mut(), port.take()) {
+                            h.ports.push(p);
+                        }
+                    }
+                    b"host" => {
+                        if let Some(h) = host.take() {
+                            push_host(&mut out, h);
+                        }
+                    }
+                    _ => {}
+                }
+                buf.clear();
+                continue;
+            }
+            Event::Eof => break,
+            _ => {
+                buf.clear();
+                continue;
+            }
+        };
+
+        match e.name().as_ref() {
+            b"host" if !empty => host = Some(Host::default()),
+            b"status" => {
+                if let Some(h) = host.as_mut() {
+                    h.up = attr(&e, b"state")?.as_deref() == Some("up");
+                }
+            }
+            b"address" => {
+                if let Some(h) = host.as_mut() {
+                    let addr = attr(&e, b"addr")?;
+                    match attr(&e, b"addrtype")?.as_deref() {
+                        Some("mac") => {
+                            h.mac = addr;
+                            h.vendor = attr(&e, b"vendor")?;
+                        }
+                        // ipv4 / ipv6; keep the first one
+                        _ => {
+                            if h.ip.is_none() {
+                                h.ip = addr;
+                            }
+                        }
+                    }
+                }
+            }
+            b"hostname" => {
+                if let Some(h) = host.as_mut() {
+                    if h.hostname.is_none() {
+                        h.hostname = attr(&e, b"name")?;
+                    }
+                }
+            }
+            b"ports" => {
+                if let Some(h) = host.as_mut() {
+                    h.scanned = true;
+                }
+            }
+            b"port" if !empty => {
+                let portid = attr(&e, b"portid")?.ok_or("port element without portid")?;
+                port = Some(Port {
+                    port: portid.parse()?,
+                    ..Port::default()
+                });
+            }
+            b"state" => {
+                if let Some(p) = port.as_mut() {
+                    p.open = attr(&e, b"state")?.as_deref() == Some("open");
+                }
+            }
+            b"service" => {
+                if let Some(p) = port.as_mut() {
+                    p.service = attr(&e, b"name")?;
+                    p.product = match (attr(&e, b"product")?, attr(&e, b"version")?) {
+                        (Some(prod), Some(ver)) => Some(format!("{} {}", prod, ver)),
+                        (prod, _) => prod,
+                    };
+                }
+            }
+            _ => {}
+        }
+        buf.clear();
+    }
+    Ok(out)
+}
+
+fn push_host(out: &mut Vec<DiscoveryRecord>, h: Host) {
+    let ip = match (h.up, h.ip) {
+        (true, Some(ip)) => ip,
+        _ => return,
+    };
+    let mut base =
+        DiscoveryRecord::new(&ip, None, None, h.mac.as_deref(), h.vendor.as_deref(), None);
+    base.hostname = h.hostname;
+    base.is_up = Some(true);
+    if h.scanned {
+        base.port_scanned = Some(true);
+    }
+
+    let open: Vec<Port> = h.ports.into_iter().filter(|p| p.open).collect();
+    if open.is_empty() {
+        out.push(base);
+        return;
+    }
+    for p in open {
+        let mut rec = base.clone();
+        rec.port = Some(p.port);
+        rec.service = p.service;
+        rec.product = p.product;
+        out.push(rec);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REPORT: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<nmaprun scanner="nmap" args="nmap -sV -oX - 192.0.2.0/29">
+<host starttime="1700000000" endtime="1700000010"><status state="up" reason="arp-response"/>
+<address addr="192.0.2.1" addrtype="ipv4"/>
+<address addr="00:0C:29:AA:BB:CC" addrtype="mac" vendor="VMware"/>
+<hostnames><hostname name="gw.lan" type="PTR"/></hostnames>
+<ports><extraports state="closed" count="997"/>
+<port protocol="tcp" portid="22"><state state="open" reason="syn-ack"/><service name="ssh" product="OpenSSH" version="8.9p1" method="probed" conf="10"/></port>
+<port protocol="tcp" portid="80"><state state="open" reason="syn-ack"/><service name="http" product="nginx" method="probed" conf="10"/></port>
+<port protocol="tcp" portid="443"><state state="filtered" reason="no-response"/><service name="https" method="table" conf="3"/></port>
+</ports>
+</host>
+<host><status state="up" reason="echo-reply"/>
+<address addr="192.0.2.2" addrtype="ipv4"/>
+<hostnames/>
+<ports><extraports state="closed" count="1000"/></ports>
+</host>
+<host><status state="down" reason="no-response"/>
+<address addr="192.0.2.3" addrtype="ipv4"/>
+</host>
+</nmaprun>
+"#;
+
+    #[test]
+    fn parses_hosts_and_open_ports() {
+        let recs = parse_nmap(Reader::from_reader(REPORT.as_bytes())).expect("parse");
+        assert_eq!(recs.len(), 3);
+
+        let ssh = &recs[0];
+        assert_eq!(ssh.ip, "192.0.2.1");
+        assert_eq!(ssh.port, Some(22));
+        assert_eq!(ssh.mac.as_deref(), Some("00:0C:29:AA:BB:CC"));
+        assert_eq!(ssh.vendor.as_deref(), Some("VMware"));
+        assert_eq!(ssh.hostname.as_deref(), Some("gw.lan"));
+        assert_eq!(ssh.service.as_deref(), Some("ssh"));
+        assert_eq!(ssh.product.as_deref(), Some("OpenSSH 8.9p1"));
+
+        assert_eq!(recs[1].port, Some(80));
+        assert_eq!(recs[1].product.as_deref(), Some("nginx"));
+
+        // up host without open ports keeps a single portless record
+        assert_eq!(recs[2].ip, "192.0.2.2");
+        assert_eq!(recs[2].port, None);
+        assert_eq!(recs[2].port_scanned, Some(true));
+    }
+
+    #[test]
+    fn rejects_malformed_xml() {
+        let bad = "<nmaprun><host><port portid=\"x\"></port></host></nmaprun>";
+        assert!(parse_nmap(Reader::from_reader(bad.as_bytes())).is_err());
+    }
+}
@@ -9,103 +9,175 @@ use std::collections::HashMap;
 use std::error::Error;
 use std::fs;
 use std::path::Path;
+use std::sync::{Arc, RwLock};
 
 // Embedded comprehensive OUI CSV shipped with this crate for reproducible builds.
 static EMBEDDED_OUI_CSV: &str = include_str!("../data/oui.csv");
-static OUI_MAP: OnceCell<HashMap<String, String>> = OnceCell::new();
+static DEFAULT_DB: OnceCell<OuiDatabase> = OnceCell::new();
+
+/// OUI prefix -> vendor table that can be swapped out while in use. Clones
+/// share the same table, so a reload through one is seen by all of them.
+#[derive(Debug, Clone, Default)]
+pub struct OuiDatabase(Arc<RwLock<HashMap<String, String>>>);
+
+impl OuiDatabase {
+    /// Build a database from CSV text (see the free `load_from_str`).
+    pub fn load_from_str(s: &str) -> Self {
+        Self(Arc::new(RwLock::new(load_from_str(s))))
+    }
+
+    /// Build a database from a CSV file.
+    pub fn load_from_file(p: &Path) -> Result<Self, Box<dyn Error>> {
+        Ok(Self::load_from_str(&fs::read_to_string(p)?))
+    }
+
+    /// Vendor for `mac` (any of `aa:bb:cc:..`, `AA-BB-CC-..`, `aabbcc..`).
+    /// Returns None if not parseable or not found.
+    pub fn lookup(&self, mac: &str) -> Option<String> {
+        let raw: String = mac.chars().filter(|c| c.is_ascii_hexdigit()).collect();
+        if raw.len() < 6 {
+            return None;
+        }
+        let prefix = raw[..6].to_uppercase();
+        self.0.read().unwrap().get(&prefix).cloned()
+    }
+
+    /// Like `lookup`, filling in truncated prefixes (see `lookup_vendor_fuzzy`).
+    pub fn lookup_fuzzy(&self, mac: &str) -> Option<String> {
+        fuzzy_lookup_in(&self.0.read().unwrap(), mac)
+    }
+
+    /// Replace the table with the contents of `p`. The file is parsed before
+    /// the lock is taken, so lookups keep seeing the old table until the swap
+    /// and an unreadable file leaves it untouched.
+    pub fn reload_from_file(&self, p: &Path) -> Result<(), Box<dyn Error>> {
+        let map = load_from_str(&fs::read_to_string(p)?);
+        *self.0.write().unwrap() = map;
+        Ok(())
+    }
+}
 
 /// Load a map from a CSV-like string. Expected rows: prefix, vendor (prefix as hex, 6 chars / 3 bytes)
 pub fn load_from_str(s: &str) -> HashMap<String, String> {
+    load_from_bytes(s.as_bytes())
+}
+
+/// `load_from_str` over raw bytes; rows that fail to parse (e.g. invalid
+/// UTF-8) are skipped and the rest of the table is still read.
+fn load_from_bytes(bytes: &[u8]) -> HashMap<String, String> {
     let mut m = HashMap::new();
 
     // Use the csv crate to properly handle quoted fields and embedded commas.
+    // Rows vary in width (2-column prefix,vendor vs. IEEE MA-L dumps), so the
+    // reader must be flexible or mismatched rows are rejected.
     let mut rdr = csv::ReaderBuilder::new()
         .has_headers(false)
-        .from_reader(s.as_bytes());
+        .flexible(true)
+        .from_reader(bytes);
 
-    for result in rdr.records() {
-        if let Ok(rec) = result {
-            if rec.len() == 0 {
-                continue;
-            }
-            // skip comments/blank first field
-            let first = rec.get(0).unwrap_or("").trim();
-            if first.is_empty() || first.starts_with('#') {
+    for rec in rdr.records().flatten() {
+        if rec.is_empty() {
+            continue;
+        }
+        // skip comments/blank first field
+        let first = rec.get(0).unwrap_or("").trim();
+        if first.is_empty() || first.starts_with('#') {
+            continue;
+        }
+
+        // Determine which field is the assignment/prefix and which is the vendor/org
+        let (maybe_prefix, vendor_field) =
+            if first.to_uppercase().starts_with("MA") && rec.len() >= 3 {
+                (
+                    rec.get(1).unwrap_or("").trim(),
+                    rec.get(2).unwrap_or("").trim(),
+                )
+            } else if rec.len() >= 2 {
+                (
+                    rec.get(0).unwrap_or("").trim(),
+                    rec.get(1).unwrap_or("").trim(),
+                )
+            } else {
                 continue;
-            }
-
-            // Determine which field is the assignment/prefix and which is the vendor/org
-            let (maybe_prefix, vendor_field) =
-                if first.to_uppercase().starts_with("MA") && rec.len() >= 3 {
-                    (
-                        rec.get(1).unwrap_or("").trim(),
-                        rec.get(2).unwrap_or("").trim(),
-                    )
-                } else if rec.len() >= 2 {
-                    (
-                        rec.get(0).unwrap_or("").trim(),
-                        rec.get(1).unwrap_or("").trim(),
-                    )
-                } else {
-                    continue;
-                };
-
-            let key = maybe_prefix
-                .replace('-', "")
-                .replace(':', "")
-                .to_uppercase();
-            if key.len() >= 6 && key.chars().all(|c| c.is_ascii_hexdigit()) {
-                m.insert(
-                    key.chars().take(6).collect::<String>(),
-                    vendor_field.to_string(),
-                );
-            }
+            };
+
+        let key = maybe_prefix.replace(['-', ':'], "").to_uppercase();
+        if key.len() >= 6 && key.chars().all(|c| c.is_ascii_hexdigit()) {
+            m.insert(
+                key.chars().take(6).collect::<String>(),
+                vendor_field.to_string(),
+            );
         }
     }
 
     m
 }
 
-/// Initialize the default map (lazy).
-fn default_map() -> &'static HashMap<String, String> {
-    OUI_MAP.get_or_init(|| {
-        // Try env var override first
-        if let Ok(path) = std::env::var("NETWORK_SCANNER_OUI_PATH") {
-            if let Ok(s) = fs::read_to_string(path) {
-                return load_from_str(&s);
-            }
+/// The database behind `lookup_vendor`, loaded on first use from
+/// `NETWORK_SCANNER_OUI_PATH`, else the embedded CSV. Reload it to change
+/// what the global lookups return.
+pub fn default_database() -> &'static OuiDatabase {
+    DEFAULT_DB.get_or_init(|| OuiDatabase(Arc::new(RwLock::new(default_map()))))
+}
+
+/// Table for the default database: env override, repo copy, embedded CSV.
+fn default_map() -> HashMap<String, String> {
+    // Try env var override first
+    if let Ok(path) = std::env::var("NETWORK_SCANNER_OUI_PATH") {
+        if let Ok(s) = fs::read_to_string(path) {
+            return load_from_str(&s);
         }
-        // Try a workspace-relative path commonly used in this repo (optional)
-        let candidate = Path::new("../../java/netscan/rust_backend/netutils/oui.csv");
-        if candidate.exists() {
-            if let Ok(s) = fs::read_to_string(candidate) {
-                return load_from_str(&s);
-            }
+    }
+    // Try a workspace-relative path commonly used in this repo (optional)
+    let candidate = Path::new("../../java/netscan/rust_backend/netutils/oui.csv");
+    if candidate.exists() {
+        if let Ok(s) = fs::read_to_string(candidate) {
+            return load_from_str(&s);
         }
-        // Fallback to the embedded comprehensive CSV shipped with the crate
-        load_from_str(EMBEDDED_OUI_CSV)
-    })
+    }
+    // Fallback to the embedded comprehensive CSV shipped with the crate
+    load_from_str(EMBEDDED_OUI_CSV)
 }
 
-/// Initialize the OUI map from an explicit file path. Returns Err on IO errors.
-#[allow(dead_code)]
+/// Load the default database from an explicit file path, replacing whatever
+/// it held. Returns Err on IO errors.
 pub fn init_from_file<P: AsRef<Path>>(p: P) -> Result<(), Box<dyn Error>> {
-    let s = fs::read_to_string(p.as_ref())?;
-    let map = load_from_str(&s);
-    OUI_MAP
-        .set(map)
-        .map_err(|_| "OUI map already initialized")?;
-    Ok(())
+    default_database().reload_from_file(p.as_ref())
 }
 
-/// Lookup vendor given a MAC string. Returns None if not parseable or not found.
+/// Lookup vendor given a MAC string in the default database. Returns None if
+/// not parseable or not found.
 pub fn lookup_vendor(mac: &str) -> Option<String> {
-    let raw: String = mac.chars().filter(|c| c.is_ascii_hexdigit()).collect();
-    if raw.len() < 6 {
-        return None;
+    default_database().lookup(mac)
+}
+
+/// Lookup vendor for partial or malformed MACs. With 6+ hex digits this is
+/// the same as `lookup_vendor`. With 4-5 hex digits the missing prefix digits
+/// are filled in, zeros first and then in ascending order (16 candidates for
+/// 5 digits, 256 for 4), and the first known prefix wins. Shorter input
+/// returns None.
+pub fn lookup_vendor_fuzzy(mac: &str) -> Option<String> {
+    default_database().lookup_fuzzy(mac)
+}
+
+fn fuzzy_lookup_in(map: &HashMap<String, String>, mac: &str) -> Option<String> {
+    let raw: String = mac
+        .chars()
+        .filter(|c| c.is_ascii_hexdigit())
+        .collect::<String>()
+        .to_uppercase();
+    match raw.len() {
+        n if n >= 6 => map.get(&raw[..6]).cloned(),
+        n @ 4..=5 => {
+            let missing = 6 - n;
+            // candidates 0..16^missing, rendered as zero-padded hex suffixes
+            (0..16u32.pow(missing as u32)).find_map(|i| {
+                let key = format!("{}{:0width$X}", raw, i, width = missing);
+                map.get(&key).cloned()
+            })
+        }
+        _ => None,
     }
-    let prefix = raw[..6].to_uppercase();
-    default_map().get(&prefix).cloned()
 }
 
 #[cfg(test)]
@@ -121,25 +193,46 @@ mod tests {
     }
 
     #[test]
-    fn lookup_vendor_accepts_various_mac_formats() {
+    fn lookup_accepts_various_mac_formats() {
         let csv = "000C29,\"VMware, Inc.\"\n00163E,Cisco Systems";
-        let map = load_from_str(csv);
-        // install into OnceCell temporarly for this test
-        let _ = OUI_MAP.set(map);
+        let db = OuiDatabase::load_from_str(csv);
 
         assert_eq!(
-            lookup_vendor("00:0c:29:aa:bb:cc"),
+            db.lookup("00:0c:29:aa:bb:cc"),
             Some("VMware, Inc.".to_string())
         );
         assert_eq!(
-            lookup_vendor("00-16-3E-01-02-03"),
+            db.lookup("00-16-3E-01-02-03"),
             Some("Cisco Systems".to_string())
         );
+        assert_eq!(db.lookup("00163E010203"), Some("Cisco Systems".to_string()));
+        assert_eq!(db.lookup("badmac"), None);
+        assert_eq!(db.lookup_fuzzy("00:0C").as_deref(), Some("VMware, Inc."));
+    }
+
+    #[test]
+    fn fuzzy_lookup_fills_truncated_prefixes() {
+        let map = load_from_str("000C29,\"VMware, Inc.\"\n00163E,Cisco Systems");
         assert_eq!(
-            lookup_vendor("00163E010203"),
-            Some("Cisco Systems".to_string())
+            fuzzy_lookup_in(&map, "00:0C").as_deref(),
+            Some("VMware, Inc.")
         );
-        assert_eq!(lookup_vendor("badmac"), None);
+        assert_eq!(
+            fuzzy_lookup_in(&map, "00-16-3").as_deref(),
+            Some("Cisco Systems")
+        );
+        assert_eq!(
+            fuzzy_lookup_in(&map, "00:0c:29:aa").as_deref(),
+            Some("VMware, Inc.")
+        );
+        assert_eq!(fuzzy_lookup_in(&map, "00:1"), None);
+        assert_eq!(fuzzy_lookup_in(&map, "FF:FF"), None);
+    }
+
+    #[test]
+    fn fuzzy_lookup_prefers_zero_padding() {
+        let map = load_from_str("000C00,Zero Corp\n000C29,\"VMware, Inc.\"");
+        assert_eq!(fuzzy_lookup_in(&map, "000C").as_deref(), Some("Zero Corp"));
     }
 
     #[test]
@@ -176,6 +269,15 @@ mod tests {
         assert_eq!(m.get("00163E").map(|s| s.as_str()), Some("Cisco Systems"));
     }
 
+    #[test]
+    fn bad_rows_are_skipped_not_fatal() {
+        let m =
+            load_from_bytes(b"000C29,VMware\n00\xff11,Bad\xffVendor\nABC\n00163E,Cisco Systems\n");
+        assert_eq!(m.get("000C29").map(|s| s.as_str()), Some("VMware"));
+        assert_eq!(m.get("00163E").map(|s| s.as_str()), Some("Cisco Systems"));
+        assert_eq!(m.len(), 2);
+    }
+
     #[test]
     fn ignores_short_or_nonhex_prefixes() {
         // short assignment (too few hex digits) and non-hex characters
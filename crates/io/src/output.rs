@@ -0,0 +1,102 @@
+//! Write one scan in several output formats at once.
+
+use std::error::Error;
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use formats::DiscoveryRecord;
+
+/// Formats `write_discovery_outputs` can produce.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Fixed-column CSV (`write_records_csv`)
+    Csv,
+    /// Pretty JSON array of `DiscoveryRecord`
+    Json,
+    /// Target-compatible JSON (`to_target_json`)
+    TargetJson,
+    /// Legacy netscan JSON (`to_legacy_json`)
+    LegacyJson,
+    /// One JSON record per line (`to_ndjson`)
+    Ndjson,
+    /// Graphviz digraph of hosts and open ports (`to_dot_graph`)
+    Dot,
+    /// Prometheus text exposition (`to_prometheus`)
+    Prometheus,
+}
+
+impl OutputFormat {
+    /// Suffix appended to the base path, e.g. `scan` -> `scan.target.json`.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Csv => "csv",
+            OutputFormat::Json => "json",
+            OutputFormat::TargetJson => "target.json",
+            OutputFormat::LegacyJson => "legacy.json",
+            OutputFormat::Ndjson => "ndjson",
+            OutputFormat::Dot => "dot",
+            OutputFormat::Prometheus => "prom",
+        }
+    }
+
+    /// Write `records` in this format to `writer`. `method` fills the
+    /// `method` / `Method` field of the target and legacy JSON exports.
+    pub fn write<W: Write>(
+        &self,
+        mut writer: W,
+        records: &[DiscoveryRecord],
+        method: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        match self {
+            OutputFormat::Csv => crate::write_records_csv(writer, records, false)?,
+            OutputFormat::Json => serde_json::to_writer_pretty(writer, records)?,
+            OutputFormat::TargetJson => crate::write_target_json_stream(writer, records, method)?,
+            OutputFormat::LegacyJson => {
+                writer.write_all(crate::to_legacy_json(records, method)?.as_bytes())?
+            }
+            OutputFormat::Ndjson => writer.write_all(crate::to_ndjson(records).as_bytes())?,
+            OutputFormat::Dot => writer.write_all(crate::to_dot_graph(records).as_bytes())?,
+            OutputFormat::Prometheus => {
+                writer.write_all(crate::to_prometheus(records).as_bytes())?
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Scan method recorded in the JSON exports: `portscan` when any record
+/// carries a port, else `arp`.
+pub fn scan_method(records: &[DiscoveryRecord]) -> &'static str {
+    if records.iter().any(|r| r.port.is_some()) {
+        "portscan"
+    } else {
+        "arp"
+    }
+}
+
+/// Write `records` once per entry of `formats`, to `base_path` plus the
+/// format's extension (`scan` -> `scan.csv`, `scan.target.json`, ...).
+/// The method in the target/legacy exports comes from `scan_method`.
+/// Returns the paths written, in `formats` order; stops at the first error.
+pub fn write_discovery_outputs<P: AsRef<Path>>(
+    base_path: P,
+    records: &[DiscoveryRecord],
+    formats: &[OutputFormat],
+) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let method = scan_method(records);
+    let mut written = Vec::with_capacity(formats.len());
+    for format in formats {
+        let mut path = OsString::from(base_path.as_ref());
+        path.push(".");
+        path.push(format.extension());
+        let path = PathBuf::from(path);
+        let mut file =
+            BufWriter::new(File::create(&path).map_err(|e| format!("{}: {}", path.display(), e))?);
+        format.write(&mut file, records, method)?;
+        file.flush()?;
+        written.push(path);
+    }
+    Ok(written)
+}
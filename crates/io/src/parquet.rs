@@ -0,0 +1,155 @@
+//! Parquet export (`parquet` feature).
+//!
+//! One row per record, one Arrow column per `DiscoveryRecord` field, so the
+//! file loads straight into DuckDB, Spark or Pandas. `is_up` is written as a
+//! plain boolean (unknown counts as up, as in the JSON exports);
+//! `enrichment` provenance is not exported.
+
+use std::error::Error;
+use std::fs::File;
+use std::path::Path;
+use std::sync::Arc;
+
+use ::arrow::array::{ArrayRef, BooleanArray, StringArray, UInt16Array};
+use ::arrow::datatypes::{DataType, Field, Schema};
+use ::arrow::record_batch::RecordBatch;
+use ::parquet::arrow::ArrowWriter;
+use ::parquet::basic::Compression;
+use ::parquet::file::properties::WriterProperties;
+use formats::DiscoveryRecord;
+
+/// Rows per row group used by `write_parquet`.
+pub const PARQUET_ROW_GROUP_SIZE: usize = 1000;
+
+/// Write `records` to `path` as a Snappy-compressed Parquet file with
+/// `PARQUET_ROW_GROUP_SIZE` rows per row group.
+pub fn write_parquet(path: &Path, records: &[DiscoveryRecord]) -> Result<(), Box<dyn Error>> {
+    write_parquet_with_row_group_size(path, records, PARQUET_ROW_GROUP_SIZE)
+}
+
+/// `write_parquet` with a custom row group size (at least 1).
+pub fn write_parquet_with_row_group_size(
+    path: &Path,
+    records: &[DiscoveryRecord],
+    row_group_size: usize,
+) -> Result<(), Box<dyn Error>> {
+    let batch = record_batch(records)?;
+    let props = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .set_max_row_group_size(row_group_size.max(1))
+        .build();
+    let mut writer = ArrowWriter::try_new(File::create(path)?, batch.schema(), Some(props))?;
+    writer.write(&batch)?;
+    writer.close()?;
+    Ok(())
+}
+
+fn record_batch(records: &[DiscoveryRecord]) -> Result<RecordBatch, Box<dyn Error>> {
+    let strings = |f: fn(&DiscoveryRecord) -> Option<&str>| -> ArrayRef {
+        Arc::new(records.iter().map(f).collect::<StringArray>())
+    };
+    let text = |name: &str, nullable: bool| Field::new(name, DataType::Utf8, nullable);
+    let schema = Schema::new(vec![
+        text("ip", false),
+        Field::new("port", DataType::UInt16, true),
+        text("banner", true),
+        text("mac", true),
+        text("vendor", true),
+        text("timestamp", true),
+        text("device_type", true),
+        text("service", true),
+        text("product", true),
+        text("os", true),
+        Field::new("port_scanned", DataType::Boolean, true),
+        Field::new("is_up", DataType::Boolean, false),
+        text("ssh_hostkey", true),
+        text("hostname", true),
+    ]);
+    let columns: Vec<ArrayRef> = vec![
+        strings(|r| Some(&r.ip)),
+        Arc::new(records.iter().map(|r| r.port).collect::<UInt16Array>()),
+        strings(|r| r.banner.as_deref()),
+        strings(|r| r.mac.as_deref()),
+        strings(|r| r.vendor.as_deref()),
+        strings(|r| r.timestamp.as_deref()),
+        strings(|r| r.device_type.as_deref()),
+        strings(|r| r.service.as_deref()),
+        strings(|r| r.product.as_deref()),
+        strings(|r| r.os.as_deref()),
+        Arc::new(
+            records
+                .iter()
+                .map(|r| r.port_scanned)
+                .collect::<BooleanArray>(),
+        ),
+        Arc::new(
+            records
+                .iter()
+                .map(|r| Some(r.is_up.unwrap_or(true)))
+                .collect::<BooleanArray>(),
+        ),
+        strings(|r| r.ssh_hostkey.as_deref()),
+        strings(|r| r.hostname.as_deref()),
+    ];
+    Ok(RecordBatch::try_new(Arc::new(schema), columns)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::arrow::array::Array;
+    use ::parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use ::parquet::file::reader::{FileReader, SerializedFileReader};
+
+    fn sample(n: usize) -> Vec<DiscoveryRecord> {
+        (0..n)
+            .map(|i| {
+                let mut r = DiscoveryRecord::new(
+                    &format!("10.0.{}.{}", i / 250, i % 250 + 1),
+                    (i % 2 == 0).then_some(22),
+                    None,
+                    Some("aa:bb:cc:dd:ee:ff"),
+                    Some("ACME"),
+                    None,
+                );
+                r.is_up = (i == 1).then_some(false);
+                r
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parquet_round_trip_keeps_rows_and_ips() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("hosts.parquet");
+        let records = sample(2500);
+        write_parquet(&path, &records).expect("write parquet");
+
+        let meta = SerializedFileReader::new(File::open(&path).unwrap()).unwrap();
+        assert_eq!(meta.metadata().file_metadata().num_rows(), 2500);
+        assert_eq!(meta.metadata().num_row_groups(), 3);
+
+        let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(&path).unwrap())
+            .unwrap()
+            .build()
+            .unwrap();
+        let (mut ips, mut ports, mut up) = (Vec::new(), Vec::new(), Vec::new());
+        for batch in reader {
+            let batch = batch.unwrap();
+            let col = |name| batch.column(batch.schema().index_of(name).unwrap()).clone();
+            let ip = col("ip");
+            let ip = ip.as_any().downcast_ref::<StringArray>().unwrap();
+            ips.extend(ip.iter().map(|v| v.unwrap().to_string()));
+            let port = col("port");
+            let port = port.as_any().downcast_ref::<UInt16Array>().unwrap();
+            ports.extend(port.iter());
+            let is_up = col("is_up");
+            let is_up = is_up.as_any().downcast_ref::<BooleanArray>().unwrap();
+            up.extend(is_up.iter().map(Option::unwrap));
+        }
+        let expected: Vec<_> = records.iter().map(|r| r.ip.clone()).collect();
+        assert_eq!(ips, expected);
+        assert_eq!(&ports[..3], [Some(22), None, Some(22)]);
+        assert_eq!(&up[..3], [true, false, true]);
+    }
+}
@@ -0,0 +1,35 @@
+//! Port-level JSON reports (`port-report` feature).
+//!
+//! `DiscoveryRecord` keeps one open port per record and drops closed and
+//! filtered ports, RTTs and retry counts. These helpers write
+//! `netutils::portscan::HostPortReport`s as they are, one object per host
+//! with every `PortResult`.
+
+use std::error::Error;
+use std::fs::File;
+
+use netutils::portscan::HostPortReport;
+
+/// Pretty-printed JSON array of `reports`.
+pub fn to_port_report_json(reports: &[HostPortReport]) -> Result<String, Box<dyn Error>> {
+    Ok(serde_json::to_string_pretty(reports)?)
+}
+
+/// Write `reports` to `path` as a JSON array.
+pub fn write_port_report_json<P: AsRef<str>>(
+    path: P,
+    reports: &[HostPortReport],
+) -> Result<(), Box<dyn Error>> {
+    let mut file = std::io::BufWriter::new(File::create(path.as_ref())?);
+    serde_json::to_writer_pretty(&mut file, reports)?;
+    std::io::Write::flush(&mut file)?;
+    Ok(())
+}
+
+/// Read a report written by `write_port_report_json`.
+pub fn read_port_report_json<P: AsRef<str>>(
+    path: P,
+) -> Result<Vec<HostPortReport>, Box<dyn Error>> {
+    let s = std::fs::read_to_string(path.as_ref())?;
+    Ok(serde_json::from_str(&s)?)
+}
@@ -0,0 +1,99 @@
+use io::{read_netscan_csv, read_netscan_csv_mapped, read_netscan_csv_with_headers, ColumnMapping};
+
+fn write_tmp(name: &str, contents: &str) -> String {
+    let dir = std::env::temp_dir().join(format!("io-csv-mapping-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let p = dir.join(name);
+    std::fs::write(&p, contents).unwrap();
+    p.to_string_lossy().into_owned()
+}
+
+#[test]
+fn mapped_import_reads_third_party_headers() {
+    let path = write_tmp(
+        "vendor_tool.csv",
+        "Address,Physical Address,Manufacturer,Seen\n\
+         192.0.2.10,00:0c:29:aa:bb:cc,VMware,2025-11-02T12:00:00Z\n\
+         192.0.2.11,,,\n",
+    );
+    let mapping = ColumnMapping::empty()
+        .with_ip("Address")
+        .with_mac("physical address")
+        .with_vendor("Manufacturer")
+        .with_timestamp("Seen");
+    let recs = read_netscan_csv_mapped(&path, &mapping).expect("mapped read");
+    assert_eq!(recs.len(), 2);
+    assert_eq!(recs[0].ip, "192.0.2.10");
+    assert_eq!(recs[0].mac.as_deref(), Some("00:0c:29:aa:bb:cc"));
+    assert_eq!(recs[0].vendor.as_deref(), Some("VMware"));
+    assert_eq!(recs[0].timestamp.as_deref(), Some("2025-11-02T12:00:00Z"));
+    assert!(recs[1].mac.is_none() && recs[1].vendor.is_none());
+}
+
+#[test]
+fn mapped_import_errors_without_ip_column() {
+    let path = write_tmp("no_ip.csv", "Address,Manufacturer\n192.0.2.10,VMware\n");
+    let mapping = ColumnMapping::empty().with_ip("Host IP");
+    assert!(read_netscan_csv_mapped(&path, &mapping).is_err());
+}
+
+#[test]
+fn default_import_keeps_builtin_aliases() {
+    let path = write_tmp(
+        "netscan.csv",
+        "Timestamp,IP,MAC,Hostname,Vendor,OS\n\
+         2025-11-02T12:00:00Z,192.0.2.20,aa:bb:cc:dd:ee:ff,printer.lan,HP,Linux\n",
+    );
+    let recs = read_netscan_csv(&path).expect("default read");
+    assert_eq!(recs[0].ip, "192.0.2.20");
+    assert_eq!(recs[0].hostname.as_deref(), Some("printer.lan"));
+    assert_eq!(recs[0].vendor.as_deref(), Some("HP"));
+    assert_eq!(recs[0].os.as_deref(), Some("Linux"));
+    assert_eq!(
+        read_netscan_csv_mapped(&path, &ColumnMapping::default()).expect("mapped read"),
+        recs
+    );
+}
+
+#[test]
+fn default_import_reads_a_port_column_when_present() {
+    let path = write_tmp(
+        "with_port.csv",
+        "Timestamp,IP,Port,MAC,Hostname,Vendor,OS\n\
+         2025-11-02T12:00:00Z,192.0.2.40,22,,,,\n\
+         2025-11-02T12:00:00Z,192.0.2.41,,,,,\n",
+    );
+    let recs = read_netscan_csv(&path).expect("default read");
+    assert_eq!(recs[0].port, Some(22));
+    assert_eq!(recs[1].port, None);
+
+    // netscan files without the column keep port unset
+    let path = write_tmp(
+        "no_port.csv",
+        "Timestamp,IP,MAC,Hostname,Vendor,OS\n\
+         2025-11-02T12:00:00Z,192.0.2.42,,,,\n",
+    );
+    assert_eq!(read_netscan_csv(&path).expect("default read")[0].port, None);
+}
+
+#[test]
+fn headerless_import_keeps_the_first_host() {
+    let rows = "2025-11-02T12:00:00Z,192.0.2.30,aa:bb:cc:dd:ee:01,nas.lan,Synology,Linux\n\
+                2025-11-02T12:00:01Z,192.0.2.31,,,,\n";
+    let path = write_tmp("headerless.csv", rows);
+    let recs = read_netscan_csv(&path).expect("detected headerless read");
+    assert_eq!(recs.len(), 2);
+    assert_eq!(recs[0].ip, "192.0.2.30");
+    assert_eq!(recs[0].mac.as_deref(), Some("aa:bb:cc:dd:ee:01"));
+    assert_eq!(recs[0].hostname.as_deref(), Some("nas.lan"));
+    assert_eq!(recs[0].os.as_deref(), Some("Linux"));
+    assert_eq!(
+        read_netscan_csv_with_headers(&path, false).expect("explicit headerless read"),
+        recs
+    );
+
+    // Forcing a header row drops the first host, as before
+    let forced = read_netscan_csv_with_headers(&path, true).expect("header read");
+    assert_eq!(forced.len(), 1);
+    assert_eq!(forced[0].ip, "192.0.2.31");
+}
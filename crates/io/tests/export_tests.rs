@@ -32,3 +32,354 @@ fn exported_json_has_expected_shape() {
     let ports = obj.get("ports").unwrap().as_array().unwrap();
     assert_eq!(ports[0].as_u64().unwrap(), 22);
 }
+
+#[test]
+fn elasticsearch_bulk_alternates_action_and_document_lines() {
+    use io::{to_elasticsearch_bulk, write_elasticsearch_bulk_file};
+
+    let recs = vec![
+        DiscoveryRecord::new("192.0.2.1", Some(80), Some("http"), None, None, None),
+        DiscoveryRecord::new(
+            "192.0.2.2",
+            None,
+            None,
+            Some("aa:bb:cc:dd:ee:ff"),
+            Some("ACME"),
+            Some("2025-11-03T00:00:00Z"),
+        ),
+    ];
+    let bulk = to_elasticsearch_bulk(&recs, "scans");
+    assert!(bulk.ends_with('\n'));
+    let lines: Vec<&str> = bulk.lines().filter(|l| !l.is_empty()).collect();
+    assert_eq!(lines.len(), 2 * recs.len());
+
+    for (i, pair) in lines.chunks(2).enumerate() {
+        let action: serde_json::Value = serde_json::from_str(pair[0]).expect("action json");
+        assert_eq!(action["index"]["_index"], "scans");
+        let doc: DiscoveryRecord = serde_json::from_str(pair[1]).expect("document json");
+        assert_eq!(doc, recs[i]);
+    }
+
+    assert!(to_elasticsearch_bulk(&[], "scans").is_empty());
+
+    let path = std::env::temp_dir().join(format!("es-bulk-{}.ndjson", std::process::id()));
+    let path = path.to_str().unwrap();
+    write_elasticsearch_bulk_file(path, &recs, "scans").expect("write bulk");
+    assert_eq!(std::fs::read_to_string(path).unwrap(), bulk);
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn enrichment_block_is_opt_in() {
+    use io::{to_legacy_json_with, to_target_json_with, JsonExportOptions};
+
+    let mut r = DiscoveryRecord::new("192.0.2.3", None, None, None, Some("ACME"), None);
+    r.set_provenance("vendor", "oui", 90);
+    let bare = DiscoveryRecord::new("192.0.2.4", None, None, None, None, None);
+    let recs = vec![r, bare];
+
+    let plain = to_target_json(&recs, "arp").expect("to_target_json");
+    assert!(!plain.contains("enrichment"));
+
+    let opts = JsonExportOptions::default().with_enrichment(true);
+    for j in [
+        to_target_json_with(&recs, "arp", &opts).expect("target"),
+        to_legacy_json_with(&recs, "arp", &opts).expect("legacy"),
+    ] {
+        let v: serde_json::Value = serde_json::from_str(&j).expect("valid json");
+        let block = &v[0]["enrichment"][0];
+        assert_eq!(block["field"], "vendor");
+        assert_eq!(block["source"], "oui");
+        assert_eq!(block["confidence"], 90);
+        // records without provenance get no empty block
+        assert!(v[1].get("enrichment").is_none());
+    }
+}
+
+fn mixed_records() -> Vec<DiscoveryRecord> {
+    let mut ssh = DiscoveryRecord::new(
+        "192.0.2.10",
+        Some(22),
+        Some("SSH-2.0-OpenSSH_9.6"),
+        Some("aa:bb:cc:dd:ee:01"),
+        Some("ACME \"Labs\""),
+        None,
+    );
+    ssh.service = Some("ssh".into());
+    let http = DiscoveryRecord::new("192.0.2.10", Some(80), None, None, None, None);
+    let mut bare = DiscoveryRecord::new("192.0.2.11", None, None, None, None, None);
+    bare.hostname = Some("printer.lan".into());
+    bare.is_up = Some(false);
+    vec![ssh, http, bare]
+}
+
+#[test]
+fn ndjson_has_one_document_per_line() {
+    use io::{to_ndjson, write_ndjson_file};
+
+    let recs = mixed_records();
+    let nd = to_ndjson(&recs);
+    let docs: Vec<DiscoveryRecord> = nd
+        .lines()
+        .map(|l| serde_json::from_str(l).expect("document json"))
+        .collect();
+    assert_eq!(docs, recs);
+    assert!(to_ndjson(&[]).is_empty());
+
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("out.ndjson");
+    write_ndjson_file(path.to_str().unwrap(), &recs).expect("write");
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), nd);
+}
+
+#[test]
+fn records_json_round_trips_with_provenance() {
+    let mut recs = mixed_records();
+    recs[0].set_provenance("vendor", "oui", 90);
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("scan.json");
+    std::fs::write(&path, serde_json::to_string_pretty(&recs).unwrap()).unwrap();
+    assert_eq!(io::read_records_json(path.to_str().unwrap()).unwrap(), recs);
+
+    std::fs::write(&path, "{\"ip\": \"192.0.2.1\"}").unwrap();
+    assert!(io::read_records_json(path.to_str().unwrap()).is_err());
+}
+
+#[test]
+fn csv_export_uses_fixed_columns() {
+    use io::{read_netscan_csv, write_records_csv, CSV_COLUMNS};
+
+    let recs = mixed_records();
+    let mut buf = Vec::new();
+    write_records_csv(&mut buf, &recs, false).expect("write csv");
+    let text = String::from_utf8(buf).unwrap();
+    let mut lines = text.lines();
+    assert_eq!(lines.next().unwrap(), CSV_COLUMNS.join(","));
+    // records with different fields set still line up under one header
+    let mut rdr = csv::Reader::from_reader(text.as_bytes());
+    for row in rdr.records() {
+        assert_eq!(row.expect("row").len(), CSV_COLUMNS.len());
+    }
+
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("out.csv");
+    std::fs::write(&path, &text).unwrap();
+    let back = read_netscan_csv(path.to_str().unwrap()).expect("read back");
+    let ips: Vec<_> = back.iter().map(|r| (r.ip.as_str(), r.port)).collect();
+    assert_eq!(
+        ips,
+        [
+            ("192.0.2.10", Some(22)),
+            ("192.0.2.10", Some(80)),
+            ("192.0.2.11", None)
+        ]
+    );
+}
+
+#[cfg(feature = "gzip")]
+#[test]
+fn gzip_csv_round_trip() {
+    use io::{read_netscan_csv, write_records_csv};
+
+    let recs = mixed_records();
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("out.csv.gz");
+    let file = std::fs::File::create(&path).unwrap();
+    write_records_csv(file, &recs, true).expect("write gzip csv");
+    // gzip magic, not plain text
+    assert_eq!(&std::fs::read(&path).unwrap()[..2], [0x1f, 0x8b]);
+
+    let back = read_netscan_csv(path.to_str().unwrap()).expect("read gzip csv");
+    assert_eq!(back.len(), recs.len());
+    assert_eq!(back[2].ip, "192.0.2.11");
+}
+
+#[cfg(not(feature = "gzip"))]
+#[test]
+fn gzip_csv_needs_the_feature() {
+    let mut buf = Vec::new();
+    assert!(io::write_records_csv(&mut buf, &mixed_records(), true).is_err());
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("out.csv.gz");
+    std::fs::write(&path, "ip\n192.0.2.1\n").unwrap();
+    assert!(io::read_netscan_csv(path.to_str().unwrap()).is_err());
+}
+
+#[test]
+fn dot_graph_links_hosts_to_open_ports() {
+    let dot = io::to_dot_graph(&mixed_records());
+    assert!(dot.starts_with("digraph discovery {"));
+    assert!(dot.trim_end().ends_with('}'));
+    assert!(dot.contains(r#""192.0.2.10" [label="192.0.2.10\nACME \"Labs\""];"#));
+    assert!(dot.contains(r#""192.0.2.10:22" [shape=box, label="22/ssh"];"#));
+    assert!(dot.contains(r#""192.0.2.10" -> "192.0.2.10:80";"#));
+    assert!(dot.contains(r#""192.0.2.11" [label="192.0.2.11\nprinter.lan"];"#));
+    assert_eq!(dot.matches("->").count(), 2);
+}
+
+#[test]
+fn json_exports_carry_service_for_port_records() {
+    let mut recs = mixed_records();
+    // a service left on a portless record is not a port/service pair
+    recs[2].service = Some("ipp".into());
+
+    let target: serde_json::Value =
+        serde_json::from_str(&to_target_json(&recs, "portscan").unwrap()).unwrap();
+    assert_eq!(target[0]["service"], "ssh");
+    assert!(target[1].get("service").is_none());
+    assert!(target[2].get("service").is_none());
+
+    let legacy: serde_json::Value =
+        serde_json::from_str(&io::to_legacy_json(&recs, "portscan").unwrap()).unwrap();
+    assert_eq!(legacy[0]["Service"], "ssh");
+    assert!(legacy[2].get("Service").is_none());
+}
+
+#[test]
+fn prometheus_metrics_count_distinct_hosts() {
+    let mut recs = mixed_records();
+    recs.push(DiscoveryRecord::new(
+        "192.0.2.12",
+        Some(22),
+        None,
+        None,
+        Some("Cisco"),
+        None,
+    ));
+    let text = io::to_prometheus(&recs);
+    let samples: Vec<&str> = text.lines().filter(|l| !l.starts_with('#')).collect();
+    assert_eq!(
+        samples,
+        [
+            "discovered_hosts_total 3",
+            "discovered_open_ports_total{port=\"22\"} 2",
+            "discovered_open_ports_total{port=\"80\"} 1",
+            "discovered_hosts_by_vendor{vendor=\"ACME \\\"Labs\\\"\"} 1",
+            "discovered_hosts_by_vendor{vendor=\"Cisco\"} 1",
+            "discovered_hosts_by_vendor{vendor=\"Unknown\"} 1",
+        ]
+    );
+    assert!(text.contains("# TYPE discovered_hosts_total gauge\n"));
+    assert!(io::to_prometheus(&[]).contains("discovered_hosts_total 0\n"));
+}
+
+#[test]
+fn streamed_target_json_matches_in_memory_export() {
+    use io::{write_target_json_file, write_target_json_stream};
+
+    let recs = io::read_records_json(format!(
+        "{}/tests/fixtures/ten_records.json",
+        env!("CARGO_MANIFEST_DIR")
+    ))
+    .expect("fixture");
+    assert_eq!(recs.len(), 10);
+    let expected = to_target_json(&recs, "arp").unwrap();
+
+    let mut streamed = Vec::new();
+    write_target_json_stream(&mut streamed, &recs, "arp").unwrap();
+    assert_eq!(String::from_utf8(streamed).unwrap(), expected);
+
+    let mut empty = Vec::new();
+    write_target_json_stream(&mut empty, &[], "arp").unwrap();
+    assert_eq!(
+        String::from_utf8(empty).unwrap(),
+        to_target_json(&[], "arp").unwrap()
+    );
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("target.json");
+    write_target_json_file(path.to_str().unwrap(), &recs, "arp").unwrap();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), expected);
+}
+
+#[test]
+fn discovery_outputs_written_per_format_with_extensions() {
+    use io::{
+        to_legacy_json, to_ndjson, to_prometheus, write_discovery_outputs, write_records_csv,
+        OutputFormat,
+    };
+
+    let recs = io::read_records_json(format!(
+        "{}/tests/fixtures/ten_records.json",
+        env!("CARGO_MANIFEST_DIR")
+    ))
+    .expect("fixture");
+    let dir = tempfile::tempdir().unwrap();
+    let base = dir.path().join("scan");
+    let written = write_discovery_outputs(
+        &base,
+        &recs,
+        &[
+            OutputFormat::Csv,
+            OutputFormat::Json,
+            OutputFormat::TargetJson,
+            OutputFormat::LegacyJson,
+            OutputFormat::Ndjson,
+            OutputFormat::Prometheus,
+        ],
+    )
+    .expect("write outputs");
+    let names: Vec<_> = written
+        .iter()
+        .map(|p| p.file_name().unwrap().to_str().unwrap().to_string())
+        .collect();
+    assert_eq!(
+        names,
+        [
+            "scan.csv",
+            "scan.json",
+            "scan.target.json",
+            "scan.legacy.json",
+            "scan.ndjson",
+            "scan.prom"
+        ]
+    );
+
+    let read = |p: &std::path::PathBuf| std::fs::read_to_string(p).unwrap();
+    let mut csv = Vec::new();
+    write_records_csv(&mut csv, &recs, false).unwrap();
+    assert_eq!(read(&written[0]).into_bytes(), csv);
+    assert_eq!(
+        read(&written[1]),
+        serde_json::to_string_pretty(&recs).unwrap()
+    );
+    // the fixture has ports, so the exports say "portscan"
+    assert_eq!(
+        read(&written[2]),
+        to_target_json(&recs, "portscan").unwrap()
+    );
+    assert_eq!(
+        read(&written[3]),
+        to_legacy_json(&recs, "portscan").unwrap()
+    );
+    assert_eq!(read(&written[4]), to_ndjson(&recs));
+    assert_eq!(read(&written[5]), to_prometheus(&recs));
+
+    let missing_dir = dir.path().join("nope").join("scan");
+    let err = write_discovery_outputs(&missing_dir, &recs, &[OutputFormat::Csv]).unwrap_err();
+    assert!(err.to_string().contains("scan.csv"), "{}", err);
+}
+
+#[test]
+fn scan_stats_csv_has_one_header_and_one_value_row() {
+    let stats = formats::ScanStats::from_records(&mixed_records());
+    let csv = io::stats_to_csv(&stats).expect("stats csv");
+    let rows: Vec<&str> = csv.lines().collect();
+    assert_eq!(rows.len(), 2);
+    let header: Vec<&str> = rows[0].split(',').collect();
+    assert_eq!(header, io::STATS_CSV_COLUMNS);
+    assert!(header.contains(&"total_records") && header.contains(&"hosts_with_mac"));
+    assert_eq!(rows[1], "3,2,1,1,2");
+}
+
+#[test]
+fn port_frequency_csv_is_sorted_by_count() {
+    let stats = formats::ScanStats {
+        port_frequency: vec![(443, 2), (22, 5), (80, 2), (8080, 1)],
+        ..Default::default()
+    };
+    let csv = io::stats_port_freq_to_csv(&stats).expect("port csv");
+    assert_eq!(csv, "port,count\n22,5\n80,2\n443,2\n8080,1\n");
+    let empty = io::stats_port_freq_to_csv(&formats::ScanStats::default()).unwrap();
+    assert_eq!(empty, "port,count\n");
+}
@@ -3,7 +3,7 @@ use io::to_legacy_json;
 
 #[test]
 fn legacy_json_contains_csv_fields_and_more() {
-    let r = DiscoveryRecord::new(
+    let mut r = DiscoveryRecord::new(
         "198.51.100.99",
         Some(80),
         Some("http-banner"),
@@ -11,6 +11,7 @@ fn legacy_json_contains_csv_fields_and_more() {
         Some("VendorCo"),
         Some("2025-11-03T01:02:03Z"),
     );
+    r.hostname = Some("web-01.lan".into());
 
     let recs = vec![r];
     let j = to_legacy_json(&recs, "arp").expect("to_legacy_json");
@@ -31,7 +32,7 @@ fn legacy_json_contains_csv_fields_and_more() {
     );
     assert_eq!(
         obj.get("Hostname").and_then(|x| x.as_str()).unwrap(),
-        "http-banner"
+        "web-01.lan"
     );
     assert_eq!(
         obj.get("Vendor").and_then(|x| x.as_str()).unwrap(),
@@ -57,5 +58,90 @@ fn legacy_json_contains_csv_fields_and_more() {
 
     // Method and is_up fields
     assert_eq!(obj.get("Method").and_then(|m| m.as_str()).unwrap(), "arp");
-    assert_eq!(obj.get("is_up").and_then(|b| b.as_bool()).unwrap(), true);
+    assert!(obj.get("is_up").and_then(|b| b.as_bool()).unwrap());
+}
+
+#[test]
+fn is_up_survives_legacy_roundtrip() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("legacy.json");
+    std::fs::write(
+        &path,
+        r#"[{"IP":"198.51.100.1","is_up":false},{"IP":"198.51.100.2","is_up":true},{"IP":"198.51.100.3"}]"#,
+    )
+    .expect("write");
+
+    let recs = io::read_netscan_json(path.to_str().unwrap()).expect("read");
+    let states: Vec<_> = recs.iter().map(|r| r.is_up).collect();
+    assert_eq!(states, [Some(false), Some(true), None]);
+
+    for j in [
+        to_legacy_json(&recs, "arp").expect("legacy"),
+        io::to_target_json(&recs, "arp").expect("target"),
+    ] {
+        let v: serde_json::Value = serde_json::from_str(&j).expect("valid json");
+        let up: Vec<_> = v
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|d| d["is_up"].as_bool().unwrap())
+            .collect();
+        // unknown state exports as up (the host was discovered)
+        assert_eq!(up, [false, true, true]);
+    }
+}
+
+#[test]
+fn legacy_banners_are_deduplicated_in_order() {
+    use io::dedup_banners;
+
+    assert_eq!(
+        dedup_banners(["nginx", "", "SSH-2.0-OpenSSH_9.6", "nginx", "nginx"]),
+        ["nginx", "SSH-2.0-OpenSSH_9.6"]
+    );
+    assert!(dedup_banners(None).is_empty());
+
+    let mut r = DiscoveryRecord::new("198.51.100.7", Some(80), Some(""), None, None, None);
+    let v: serde_json::Value =
+        serde_json::from_str(&to_legacy_json(std::slice::from_ref(&r), "arp").unwrap()).unwrap();
+    assert_eq!(v[0]["banners"], serde_json::json!([]));
+    r.banner = Some("nginx".into());
+    let v: serde_json::Value = serde_json::from_str(&to_legacy_json(&[r], "arp").unwrap()).unwrap();
+    assert_eq!(v[0]["banners"], serde_json::json!(["nginx"]));
+}
+
+#[test]
+fn json_import_keeps_hostname_and_banner_apart() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("legacy.json");
+    std::fs::write(
+        &path,
+        r#"[{"IP":"198.51.100.1","Hostname":"gw.lan","banners":["SSH-2.0-OpenSSH_9.6"]},
+            {"IP":"198.51.100.2","hostname":"nas.lan"},
+            {"IP":"198.51.100.3","banners":["220 ftp ready"]}]"#,
+    )
+    .expect("write");
+    let recs = io::read_netscan_json(path.to_str().unwrap()).expect("read");
+    let fields: Vec<_> = recs
+        .iter()
+        .map(|r| (r.hostname.as_deref(), r.banner.as_deref()))
+        .collect();
+    assert_eq!(
+        fields,
+        [
+            (Some("gw.lan"), Some("SSH-2.0-OpenSSH_9.6")),
+            (Some("nas.lan"), None),
+            (None, Some("220 ftp ready")),
+        ]
+    );
+
+    // and both make it back out
+    let v: serde_json::Value =
+        serde_json::from_str(&to_legacy_json(&recs, "arp").unwrap()).unwrap();
+    assert_eq!(v[0]["Hostname"], "gw.lan");
+    assert_eq!(v[0]["banners"][0], "SSH-2.0-OpenSSH_9.6");
+    let t: serde_json::Value =
+        serde_json::from_str(&io::to_target_json(&recs, "arp").unwrap()).unwrap();
+    assert_eq!(t[1]["hostname"], "nas.lan");
+    assert!(t[2].get("hostname").is_none());
 }
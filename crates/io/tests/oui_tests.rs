@@ -26,3 +26,55 @@ fn oui_lookup_bad_mac_returns_none() {
     let vendor = io::lookup_vendor_from_oui("xyz");
     assert!(vendor.is_none());
 }
+
+#[test]
+fn oui_database_serves_concurrent_readers() {
+    let db = io::oui::OuiDatabase::load_from_str("000C29,\"VMware, Inc.\"\n00163E,Cisco Systems");
+    let readers: Vec<_> = (0..8)
+        .map(|_| {
+            let db = db.clone();
+            std::thread::spawn(move || {
+                (0..100).all(|_| {
+                    db.lookup("00:0c:29:01:02:03").as_deref() == Some("VMware, Inc.")
+                        && db.lookup("00:16:3e:01:02:03").as_deref() == Some("Cisco Systems")
+                })
+            })
+        })
+        .collect();
+    for r in readers {
+        assert!(r.join().unwrap());
+    }
+}
+
+#[test]
+fn oui_database_reload_replaces_entries_for_all_clones() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("oui.csv");
+    std::fs::write(&path, "000C29,\"VMware, Inc.\"\n").unwrap();
+    let db = io::oui::OuiDatabase::load_from_file(&path).unwrap();
+    let shared = db.clone();
+    assert_eq!(
+        shared.lookup("00:0c:29:aa:bb:cc").as_deref(),
+        Some("VMware, Inc.")
+    );
+
+    std::fs::write(&path, "000C29,Renamed Vendor\n00163E,Cisco Systems\n").unwrap();
+    db.reload_from_file(&path).unwrap();
+    assert_eq!(
+        shared.lookup("00:0c:29:aa:bb:cc").as_deref(),
+        Some("Renamed Vendor")
+    );
+    assert_eq!(
+        shared.lookup("00:16:3e:aa:bb:cc").as_deref(),
+        Some("Cisco Systems")
+    );
+
+    // a failed reload keeps the current table
+    assert!(db
+        .reload_from_file(&dir.path().join("missing.csv"))
+        .is_err());
+    assert_eq!(
+        shared.lookup("00:0c:29:aa:bb:cc").as_deref(),
+        Some("Renamed Vendor")
+    );
+}
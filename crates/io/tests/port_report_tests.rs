@@ -0,0 +1,85 @@
+#![cfg(feature = "port-report")]
+
+use io::{read_port_report_json, to_port_report_json, write_port_report_json};
+use netutils::portscan::{HostPortReport, PortResult, PortState};
+use std::net::Ipv4Addr;
+
+fn port(port: u16, state: PortState) -> PortResult {
+    let open = state == PortState::Open;
+    PortResult {
+        port,
+        proto: "tcp".into(),
+        open,
+        state,
+        banner: open.then(|| "SSH-2.0-OpenSSH_9.6".to_string()),
+        banner_hex: open.then(|| "5353482d322e30".to_string()),
+        rtt_ms: open.then_some(3),
+        service_name: open.then(|| "ssh".into()),
+        tls: None,
+        attempts: if state == PortState::Filtered { 3 } else { 1 },
+    }
+}
+
+fn reports() -> Vec<HostPortReport> {
+    vec![
+        HostPortReport {
+            ip: Ipv4Addr::new(192, 0, 2, 10),
+            results: vec![
+                port(22, PortState::Open),
+                port(23, PortState::Closed),
+                port(25, PortState::Filtered),
+            ],
+            scanned_at: 1_760_000_000,
+        },
+        HostPortReport::new(Ipv4Addr::new(192, 0, 2, 11), Vec::new()),
+    ]
+}
+
+/// (ip, [(port, state, attempts)], scanned_at) per host
+type Summary = Vec<(Ipv4Addr, Vec<(u16, PortState, u8)>, u64)>;
+
+fn summary(reports: &[HostPortReport]) -> Summary {
+    reports
+        .iter()
+        .map(|r| {
+            let ports = r
+                .results
+                .iter()
+                .map(|p| (p.port, p.state, p.attempts))
+                .collect();
+            (r.ip, ports, r.scanned_at)
+        })
+        .collect()
+}
+
+#[test]
+fn port_report_round_trips_through_json() {
+    let reports = reports();
+    let json = to_port_report_json(&reports).expect("serialize");
+    let v: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(v[0]["ip"], "192.0.2.10");
+    assert_eq!(v[0]["results"][1]["state"], "closed");
+    assert_eq!(v[0]["results"][0]["service_name"], "ssh");
+
+    let back: Vec<HostPortReport> = serde_json::from_str(&json).expect("deserialize");
+    assert_eq!(summary(&back), summary(&reports));
+    assert_eq!(back[0].results[0].banner, reports[0].results[0].banner);
+    assert_eq!(
+        back[0].results[0].banner_hex.as_deref(),
+        Some("5353482d322e30")
+    );
+    assert_eq!(back[0].results[0].rtt_ms, Some(3));
+    assert_eq!(back[0].results[0].proto, "tcp");
+    assert!(back[1].scanned_at > 0);
+}
+
+#[test]
+fn port_report_file_round_trip() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("ports.json");
+    let path = path.to_str().unwrap();
+    let reports = reports();
+    write_port_report_json(path, &reports).expect("write");
+    let back = read_port_report_json(path).expect("read");
+    assert_eq!(summary(&back), summary(&reports));
+}
@@ -1,4 +1,7 @@
+use crate::trace::{trace_debug, trace_span, trace_trace};
+use std::collections::HashMap;
 use std::net::Ipv4Addr;
+use std::path::Path;
 use std::process::Command;
 use std::time::Duration;
 use std::{fmt, io};
@@ -38,9 +41,18 @@ pub fn parse_proc_net_arp(content: &str) -> Vec<(Ipv4Addr, String, String)> {
     out
 }
 
+/// Default location of the kernel ARP table on Linux.
+pub const PROC_NET_ARP: &str = "/proc/net/arp";
+
 /// Read `/proc/net/arp` and parse.
 pub fn read_proc_net_arp() -> Result<Vec<(Ipv4Addr, String, String)>, ArpError> {
-    let s = std::fs::read_to_string("/proc/net/arp").map_err(ArpError::Io)?;
+    read_arp_table_from(Path::new(PROC_NET_ARP))
+}
+
+/// Read and parse an ARP table in `/proc/net/arp` format from `path`, e.g. a
+/// captured snapshot or a remapped proc inside a chroot.
+pub fn read_arp_table_from(path: &Path) -> Result<Vec<(Ipv4Addr, String, String)>, ArpError> {
+    let s = std::fs::read_to_string(path).map_err(ArpError::Io)?;
     Ok(parse_proc_net_arp(&s))
 }
 
@@ -73,51 +85,110 @@ pub fn parse_ip_neigh(output: &str) -> Vec<(Ipv4Addr, String, String)> {
 
 /// Try to lookup MAC for an IPv4 address using `ip neigh` then `/proc/net/arp`, then `arp -n`.
 pub fn lookup_mac(ip: Ipv4Addr) -> Option<[u8; 6]> {
+    let _span = trace_span!("lookup_mac", %ip);
+
     // Try ip neigh
-    if let Ok(output) = Command::new("ip").args(["neigh"]).output() {
-        if output.status.success() {
+    match Command::new("ip").args(["neigh"]).output() {
+        Ok(output) if output.status.success() => {
             let stdout = String::from_utf8_lossy(&output.stdout);
             for (addr, mac, _dev) in parse_ip_neigh(&stdout) {
                 if addr == ip {
                     if let Some(m) = parse_mac(&mac) {
+                        trace_debug!(source = "ip neigh", %mac, "mac found");
                         return Some(m);
                     }
                 }
             }
+            trace_trace!(source = "ip neigh", "no entry");
         }
+        Ok(_output) => trace_trace!(source = "ip neigh", status = %_output.status, "tool failed"),
+        Err(_e) => trace_trace!(source = "ip neigh", error = %_e, "tool unavailable"),
     }
 
     // Try /proc/net/arp
-    if let Ok(entries) = read_proc_net_arp() {
-        for (addr, mac, _dev) in entries {
-            if addr == ip {
-                if let Some(m) = parse_mac(&mac) {
-                    return Some(m);
+    match read_proc_net_arp() {
+        Ok(entries) => {
+            for (addr, mac, _dev) in entries {
+                if addr == ip {
+                    if let Some(m) = parse_mac(&mac) {
+                        trace_debug!(source = "/proc/net/arp", %mac, "mac found");
+                        return Some(m);
+                    }
                 }
             }
+            trace_trace!(source = "/proc/net/arp", "no entry");
         }
+        Err(_e) => trace_trace!(source = "/proc/net/arp", error = %_e, "read failed"),
     }
 
     // Fallback to `arp -n` if present
-    if let Ok(output) = Command::new("arp").arg("-n").output() {
-        if output.status.success() {
+    match Command::new("arp").arg("-n").output() {
+        Ok(output) if output.status.success() => {
             let stdout = String::from_utf8_lossy(&output.stdout);
             for line in stdout.lines().skip(1) {
                 let parts: Vec<&str> = line.split_whitespace().collect();
-                if parts.len() >= 3 {
-                    if parts[0] == ip.to_string() {
-                        if let Some(m) = parse_mac(parts[2]) {
-                            return Some(m);
-                        }
+                if parts.len() >= 3 && parts[0] == ip.to_string() {
+                    if let Some(m) = parse_mac(parts[2]) {
+                        trace_debug!(source = "arp -n", mac = parts[2], "mac found");
+                        return Some(m);
                     }
                 }
             }
+            trace_trace!(source = "arp -n", "no entry");
         }
+        Ok(_output) => trace_trace!(source = "arp -n", status = %_output.status, "tool failed"),
+        Err(_e) => trace_trace!(source = "arp -n", error = %_e, "tool unavailable"),
     }
 
+    trace_debug!("mac not found in any source");
     None
 }
 
+/// Resolve many addresses at once: `ip neigh` runs and `/proc/net/arp` is
+/// read exactly once each, instead of once per address as with `lookup_mac`.
+/// Returns only the addresses that have a MAC; duplicates in `ips` are
+/// harmless. No `arp -n` fallback and no active probing.
+pub fn lookup_mac_multi(ips: &[Ipv4Addr]) -> HashMap<Ipv4Addr, [u8; 6]> {
+    let _span = trace_span!("lookup_mac_multi", ips = ips.len());
+    let mut entries = Vec::new();
+    match Command::new("ip").args(["neigh"]).output() {
+        Ok(output) if output.status.success() => {
+            entries.extend(parse_ip_neigh(&String::from_utf8_lossy(&output.stdout)));
+        }
+        Ok(_output) => trace_trace!(source = "ip neigh", status = %_output.status, "tool failed"),
+        Err(_e) => trace_trace!(source = "ip neigh", error = %_e, "tool unavailable"),
+    }
+    match read_proc_net_arp() {
+        Ok(proc_entries) => entries.extend(proc_entries),
+        Err(_e) => trace_trace!(source = "/proc/net/arp", error = %_e, "read failed"),
+    }
+    let found = macs_for(ips, &mac_table(entries));
+    trace_debug!(found = found.len(), "bulk lookup done");
+    found
+}
+
+/// Index `(ip, mac, dev)` entries, as returned by `parse_ip_neigh` and
+/// `parse_proc_net_arp`, by address. The first usable MAC for an address
+/// wins; unparsable and all-zero (incomplete) MACs are skipped.
+pub fn mac_table<I>(entries: I) -> HashMap<Ipv4Addr, [u8; 6]>
+where
+    I: IntoIterator<Item = (Ipv4Addr, String, String)>,
+{
+    let mut table = HashMap::new();
+    for (ip, mac, _dev) in entries {
+        if let Some(mac) = parse_mac(&mac).filter(|m| *m != [0; 6]) {
+            table.entry(ip).or_insert(mac);
+        }
+    }
+    table
+}
+
+fn macs_for(ips: &[Ipv4Addr], table: &HashMap<Ipv4Addr, [u8; 6]>) -> HashMap<Ipv4Addr, [u8; 6]> {
+    ips.iter()
+        .filter_map(|ip| table.get(ip).map(|mac| (*ip, *mac)))
+        .collect()
+}
+
 /// Ensure an IPv4 address is in the ARP table; optionally perform an active probe using `arping` or `ping`.
 /// Returns the MAC if found.
 pub fn ensure_mac(
@@ -133,6 +204,7 @@ pub fn ensure_mac(
     if !perform_probe {
         return Ok(None);
     }
+    trace_debug!(%ip, "not cached; probing");
 
     // Try arping if available (Linux). Use -c1 -w timeout_seconds -I iface ip
     #[cfg(target_os = "linux")]
@@ -144,8 +216,8 @@ pub fn ensure_mac(
             cmd.arg("-I").arg(iface_name);
         }
         cmd.arg(ip.to_string());
-        if let Ok(output) = cmd.output() {
-            if output.status.success() {
+        match cmd.output() {
+            Ok(output) if output.status.success() => {
                 let stdout = String::from_utf8_lossy(&output.stdout);
                 for line in stdout.lines() {
                     if let Some(mac_str) = line
@@ -153,11 +225,16 @@ pub fn ensure_mac(
                         .find(|s| s.contains(':') && s.len() >= 16)
                     {
                         if let Some(mac) = parse_mac(mac_str) {
+                            trace_debug!(%ip, source = "arping", mac = mac_str, "mac found");
                             return Ok(Some(mac));
                         }
                     }
                 }
             }
+            Ok(_output) => {
+                trace_trace!(%ip, source = "arping", status = %_output.status, "no reply")
+            }
+            Err(_e) => trace_trace!(%ip, source = "arping", error = %_e, "tool unavailable"),
         }
         // Fallback: run ping once to trigger ARP resolution, then lookup again
         let mut ping_cmd = Command::new("ping");
@@ -168,7 +245,8 @@ pub fn ensure_mac(
             ping_cmd.arg("-I").arg(iface_name);
         }
         ping_cmd.arg(ip.to_string());
-        let _ = ping_cmd.output();
+        let _ping = ping_cmd.output();
+        trace_trace!(%ip, source = "ping", ok = _ping.is_ok(), "ping sent to prime arp cache");
 
         // Try lookup again
         if let Some(mac) = lookup_mac(ip) {
@@ -182,22 +260,53 @@ pub fn ensure_mac(
 
 /// Parse a MAC like "00:11:22:33:44:55" into [u8;6]
 pub fn parse_mac(s: &str) -> Option<[u8; 6]> {
-    let cleaned = s.trim();
-    let parts: Vec<&str> = cleaned.split(|c| c == ':' || c == '-').collect();
-    if parts.len() != 6 {
+    parse_hex_octets(s)
+}
+
+/// Parse a 64-bit EUI (`xx:xx:xx:xx:xx:xx:xx:xx` or dash separated).
+pub fn parse_eui64(s: &str) -> Option<[u8; 8]> {
+    parse_hex_octets(s)
+}
+
+/// `N` hex octets separated by `:` or `-`.
+fn parse_hex_octets<const N: usize>(s: &str) -> Option<[u8; N]> {
+    let parts: Vec<&str> = s.trim().split([':', '-']).collect();
+    if parts.len() != N {
         return None;
     }
-    let mut mac = [0u8; 6];
+    let mut out = [0u8; N];
     for (i, p) in parts.iter().enumerate() {
-        if let Ok(b) = u8::from_str_radix(p, 16) {
-            mac[i] = b;
-        } else {
+        if p.is_empty() || p.len() > 2 {
             return None;
         }
+        out[i] = u8::from_str_radix(p, 16).ok()?;
+    }
+    Some(out)
+}
+
+/// Recover the 48-bit MAC from an EUI-64 built by inserting `FF:FE` after
+/// the OUI (bytes 3-4). Returns None when those bytes are not `FF:FE`.
+pub fn eui64_to_eui48(eui64: [u8; 8]) -> Option<[u8; 6]> {
+    if eui64[3] != 0xff || eui64[4] != 0xfe {
+        return None;
     }
+    Some([eui64[0], eui64[1], eui64[2], eui64[5], eui64[6], eui64[7]])
+}
+
+/// MAC behind an IPv6 SLAAC interface identifier (modified EUI-64): like
+/// `eui64_to_eui48`, but also flips the universal/local bit back.
+pub fn slaac_iid_to_mac(iid: [u8; 8]) -> Option<[u8; 6]> {
+    let mut mac = eui64_to_eui48(iid)?;
+    mac[0] ^= 0x02;
     Some(mac)
 }
 
+// Minimal stub for arp module to allow incremental porting.
+
+pub fn placeholder() {
+    // to be implemented: ARP active probing, cache parsing, helpers
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -213,6 +322,66 @@ mod tests {
         assert_eq!(entries[0].2, "eth0");
     }
 
+    #[test]
+    fn reads_arp_table_snapshot() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/proc_net_arp.txt");
+        let entries = read_arp_table_from(&path).expect("snapshot");
+        assert_eq!(
+            entries,
+            [
+                (
+                    Ipv4Addr::new(192, 168, 1, 1),
+                    "00:11:22:33:44:55".into(),
+                    "eth0".into()
+                ),
+                // incomplete entries keep the all-zero MAC the kernel reports
+                (
+                    Ipv4Addr::new(192, 168, 1, 23),
+                    "00:00:00:00:00:00".into(),
+                    "eth0".into()
+                ),
+                (
+                    Ipv4Addr::new(10, 8, 0, 2),
+                    "b8:27:eb:12:34:56".into(),
+                    "wlan0".into()
+                ),
+            ]
+        );
+        assert!(matches!(
+            read_arp_table_from(Path::new("/nonexistent/arp")),
+            Err(ArpError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn bulk_lookup_returns_only_known_addresses() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/proc_net_arp.txt");
+        let neigh = parse_ip_neigh(
+            "192.168.1.1 dev eth0 lladdr 00:aa:bb:cc:dd:ee REACHABLE\n\
+             192.168.1.7 dev eth0 lladdr 02:00:00:00:00:07 STALE\n\
+             192.168.1.9 dev eth0  FAILED\n",
+        );
+        let proc_arp = read_arp_table_from(&path).expect("snapshot");
+        let table = mac_table(neigh.into_iter().chain(proc_arp));
+
+        let ips = [
+            Ipv4Addr::new(192, 168, 1, 1),
+            Ipv4Addr::new(192, 168, 1, 7),
+            Ipv4Addr::new(192, 168, 1, 9),
+            Ipv4Addr::new(192, 168, 1, 23),
+            Ipv4Addr::new(10, 8, 0, 2),
+            Ipv4Addr::new(10, 8, 0, 2),
+        ];
+        let found = macs_for(&ips, &table);
+        assert_eq!(found.len(), 3);
+        // `ip neigh` is consulted first, as in `lookup_mac`
+        assert_eq!(found[&ips[0]], [0x00, 0xaa, 0xbb, 0xcc, 0xdd, 0xee]);
+        assert_eq!(found[&ips[1]], [0x02, 0, 0, 0, 0, 0x07]);
+        assert_eq!(found[&ips[4]], [0xb8, 0x27, 0xeb, 0x12, 0x34, 0x56]);
+        // FAILED and incomplete entries count as not found
+        assert!(!found.contains_key(&ips[2]) && !found.contains_key(&ips[3]));
+    }
+
     #[test]
     fn parse_ip_neigh_basic() {
         let sample = "192.168.1.1 dev eth0 lladdr 00:aa:bb:cc:dd:ee REACHABLE\n";
@@ -234,6 +403,35 @@ mod tests {
             [0, 17, 34, 51, 68, 85]
         );
         assert!(parse_mac("not-a-mac").is_none());
+        assert!(parse_mac("00:11:22:33:44:555").is_none());
+    }
+
+    #[test]
+    fn parse_eui64_formats() {
+        let eui = [0x00, 0x11, 0x22, 0xff, 0xfe, 0x33, 0x44, 0x55];
+        assert_eq!(parse_eui64("00:11:22:ff:fe:33:44:55"), Some(eui));
+        assert_eq!(parse_eui64("00-11-22-FF-FE-33-44-55"), Some(eui));
+        assert!(parse_eui64("00:11:22:33:44:55").is_none());
+        assert!(parse_eui64("00:11:22:ff:fe:33:44:zz").is_none());
+    }
+
+    #[test]
+    fn eui64_conversion_recovers_mac() {
+        let eui = parse_eui64("00:11:22:ff:fe:33:44:55").unwrap();
+        assert_eq!(
+            eui64_to_eui48(eui),
+            Some([0x00, 0x11, 0x22, 0x33, 0x44, 0x55])
+        );
+        // not derived from a MAC
+        assert!(eui64_to_eui48([1, 2, 3, 4, 5, 6, 7, 8]).is_none());
+
+        // fe80::211:22ff:fe33:4455 is the SLAAC address of 00:11:22:33:44:55
+        let addr: std::net::Ipv6Addr = "fe80::211:22ff:fe33:4455".parse().unwrap();
+        let iid: [u8; 8] = addr.octets()[8..].try_into().unwrap();
+        assert_eq!(
+            slaac_iid_to_mac(iid),
+            Some([0x00, 0x11, 0x22, 0x33, 0x44, 0x55])
+        );
     }
 
     #[test]
@@ -244,8 +442,3 @@ mod tests {
         assert!(m.is_none() || m.is_some());
     }
 }
-// Minimal stub for arp module to allow incremental porting.
-
-pub fn placeholder() {
-    // to be implemented: ARP active probing, cache parsing, helpers
-}
@@ -0,0 +1,81 @@
+//! Minimal BER encoding, enough to build SNMP requests.
+//!
+//! Shared by the SNMP payload in `portscan`'s UDP probes and the SNMP
+//! lookup in `enrich`; decoding stays with the code that reads replies.
+
+pub const TAG_INTEGER: u8 = 0x02;
+pub const TAG_OCTET_STRING: u8 = 0x04;
+pub const TAG_NULL: u8 = 0x05;
+pub const TAG_OID: u8 = 0x06;
+pub const TAG_SEQUENCE: u8 = 0x30;
+
+/// Type-length-value, using the long length form past 127 bytes.
+pub fn tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut out = vec![tag];
+    let len = content.len();
+    if len < 0x80 {
+        out.push(len as u8);
+    } else {
+        let bytes: Vec<u8> = len
+            .to_be_bytes()
+            .into_iter()
+            .skip_while(|&b| b == 0)
+            .collect();
+        out.push(0x80 | bytes.len() as u8);
+        out.extend_from_slice(&bytes);
+    }
+    out.extend_from_slice(content);
+    out
+}
+
+/// INTEGER in the fewest bytes that keep the sign bit intact.
+pub fn integer(v: i64) -> Vec<u8> {
+    let bytes = v.to_be_bytes();
+    let mut start = 0;
+    while start < bytes.len() - 1 {
+        let (b, next) = (bytes[start], bytes[start + 1]);
+        if (b == 0x00 && next & 0x80 == 0) || (b == 0xff && next & 0x80 != 0) {
+            start += 1;
+        } else {
+            break;
+        }
+    }
+    tlv(TAG_INTEGER, &bytes[start..])
+}
+
+/// OBJECT IDENTIFIER; `oid` has at least two components.
+pub fn oid(oid: &[u32]) -> Vec<u8> {
+    let mut content = vec![(oid[0] * 40 + oid[1]) as u8];
+    for &arc in &oid[2..] {
+        let mut groups = vec![(arc & 0x7f) as u8];
+        let mut rest = arc >> 7;
+        while rest > 0 {
+            groups.push(0x80 | (rest & 0x7f) as u8);
+            rest >>= 7;
+        }
+        content.extend(groups.iter().rev());
+    }
+    tlv(TAG_OID, &content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_primitives() {
+        assert_eq!(integer(0), [0x02, 0x01, 0x00]);
+        assert_eq!(integer(0x80), [0x02, 0x02, 0x00, 0x80]);
+        assert_eq!(integer(-1), [0x02, 0x01, 0xff]);
+        assert_eq!(integer(-129), [0x02, 0x02, 0xff, 0x7f]);
+        assert_eq!(
+            oid(&[1, 3, 6, 1, 4, 1, 311]),
+            [0x06, 0x07, 0x2b, 6, 1, 4, 1, 0x82, 0x37]
+        );
+        assert_eq!(&tlv(TAG_OCTET_STRING, &[0; 200])[..3], [0x04, 0x81, 200]);
+        assert_eq!(
+            &tlv(TAG_OCTET_STRING, &[0; 300])[..4],
+            [0x04, 0x82, 0x01, 0x2c]
+        );
+    }
+}
@@ -0,0 +1,43 @@
+//! Cooperative cancellation for long-running scans.
+//!
+//! A `CancelToken` is a shared flag: the caller keeps one clone (e.g. in a
+//! Ctrl-C handler) and hands another to a `*_with_cancel` scan. Scans check
+//! it before starting each lookup or probe, so work already in flight still
+//! finishes (or times out) and its results are kept.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Shared cancellation flag; clones observe the same state.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask every scan holding a clone of this token to stop. Idempotent.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clones_share_the_flag() {
+        let token = CancelToken::new();
+        let held_by_scan = token.clone();
+        assert!(!held_by_scan.is_cancelled());
+        token.cancel();
+        token.cancel();
+        assert!(held_by_scan.is_cancelled());
+    }
+}
@@ -1,12 +1,125 @@
 use crate::arp;
+use crate::cancel::CancelToken;
+use crate::portscan::PartialScan;
+use crate::trace::{trace_debug, trace_span};
 use ipnetwork::Ipv4Network;
+use rand::seq::SliceRandom;
 use std::net::Ipv4Addr;
-use std::sync::mpsc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
 use std::thread;
 use std::time::Duration;
 
+mod checkpoint;
+pub use checkpoint::{ScanCheckpoint, CHECKPOINT_FLUSH_EVERY};
+
+/// A single ARP scan observation: host address and MAC when resolved.
+pub type ArpScanEntry = (Ipv4Addr, Option<[u8; 6]>);
+
+/// Largest CIDR the `scan_cidr*` functions accept unless
+/// `CidrScanOptions::max_hosts` says otherwise: a /16. A mistyped prefix (/8
+/// for /24) is an error rather than hours of ARP lookups.
+pub const DEFAULT_MAX_HOSTS: usize = 65536;
+
+/// How a CIDR is expanded into hosts: visiting order, size limit and
+/// whether reserved ranges are skipped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CidrScanOptions {
+    /// order in which hosts are visited
+    pub order: HostOrder,
+    /// ranges with more hosts than this are rejected
+    pub max_hosts: usize,
+    /// leave out `RESERVED_RANGES` addresses the CIDR overlaps (on by default)
+    pub skip_reserved: bool,
+}
+
+impl Default for CidrScanOptions {
+    fn default() -> Self {
+        Self {
+            order: HostOrder::Sequential,
+            max_hosts: DEFAULT_MAX_HOSTS,
+            skip_reserved: true,
+        }
+    }
+}
+
+impl CidrScanOptions {
+    pub fn with_order(mut self, order: HostOrder) -> Self {
+        self.order = order;
+        self
+    }
+
+    pub fn with_max_hosts(mut self, max_hosts: usize) -> Self {
+        self.max_hosts = max_hosts;
+        self
+    }
+
+    pub fn with_skip_reserved(mut self, skip: bool) -> Self {
+        self.skip_reserved = skip;
+        self
+    }
+}
+
+/// Number of hosts a scan of `cidr` would visit, or an error naming that
+/// count and `max_hosts` when it is over the limit. Lets callers check a
+/// range before scanning it.
+pub fn check_host_limit(cidr: &str, max_hosts: usize) -> Result<usize, String> {
+    let net: Ipv4Network = cidr.parse().map_err(|e| format!("invalid cidr: {}", e))?;
+    let hosts = host_count(net);
+    if hosts > max_hosts as u64 {
+        return Err(format!(
+            "cidr {} has {} hosts, over the limit of {} (see scan_cidr_unbounded)",
+            cidr, hosts, max_hosts
+        ));
+    }
+    Ok(hosts as usize)
+}
+
+/// Ranges that cannot hold a host answering ARP or TCP: "this network"
+/// (0.0.0.0/8), link-local (169.254.0.0/16) and multicast (224.0.0.0/4).
+pub const RESERVED_RANGES: [(Ipv4Addr, u8); 3] = [
+    (Ipv4Addr::new(0, 0, 0, 0), 8),
+    (Ipv4Addr::new(169, 254, 0, 0), 16),
+    (Ipv4Addr::new(224, 0, 0, 0), 4),
+];
+
+/// Reserved ranges overlapping `net` without containing it: a CIDR inside
+/// one (say 169.254.0.0/16 itself) is scanned as asked.
+fn reserved_overlaps(net: Ipv4Network) -> Vec<Ipv4Network> {
+    RESERVED_RANGES
+        .iter()
+        .filter_map(|&(ip, prefix)| Ipv4Network::new(ip, prefix).ok())
+        .filter(|r| {
+            let contains_net = net.prefix() >= r.prefix() && r.contains(net.ip());
+            !contains_net && (r.contains(net.ip()) || net.contains(r.ip()))
+        })
+        .collect()
+}
+
+/// Length of `all_hosts(net)`, without building it.
+fn host_count(net: Ipv4Network) -> u64 {
+    match net.prefix() {
+        32 => 1,
+        p => (1u64 << (32 - p)).saturating_sub(2),
+    }
+}
+
 /// Expand an IPv4 network into usable host addresses (skip network and broadcast when applicable).
-fn hosts_from_network(net: Ipv4Network) -> Vec<Ipv4Addr> {
+/// With `skip_reserved`, addresses in `RESERVED_RANGES` are left out too.
+fn hosts_from_network(net: Ipv4Network, skip_reserved: bool) -> Vec<Ipv4Addr> {
+    let mut hosts = all_hosts(net);
+    let reserved = if skip_reserved {
+        reserved_overlaps(net)
+    } else {
+        Vec::new()
+    };
+    if !reserved.is_empty() {
+        hosts.retain(|ip| !reserved.iter().any(|r| r.contains(*ip)));
+    }
+    hosts
+}
+
+fn all_hosts(net: Ipv4Network) -> Vec<Ipv4Addr> {
     let prefix = net.prefix();
     let octets = net.ip().octets();
     let base = u32::from_be_bytes(octets);
@@ -29,44 +142,321 @@ fn hosts_from_network(net: Ipv4Network) -> Vec<Ipv4Addr> {
     hosts
 }
 
+/// True if `ip` falls inside `net` (network and broadcast addresses
+/// included; host bits in `net`'s address are ignored).
+pub fn network_contains(net: &Ipv4Network, ip: Ipv4Addr) -> bool {
+    net.contains(ip)
+}
+
+/// Parse `cidr` and `ip` and check containment, e.g. to tag records found
+/// outside the scanned range (a gateway on another subnet) as out of scope.
+pub fn parse_and_contains(cidr: &str, ip: &str) -> Result<bool, String> {
+    let net: Ipv4Network = cidr.parse().map_err(|e| format!("invalid cidr: {}", e))?;
+    let ip: Ipv4Addr = ip.parse().map_err(|e| format!("invalid ip: {}", e))?;
+    Ok(network_contains(&net, ip))
+}
+
+/// Order in which a CIDR scan visits its hosts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HostOrder {
+    /// Ascending addresses (.1, .2, .3, ...)
+    #[default]
+    Sequential,
+    /// Bit-reversed index order (.1, .129, .65, .193, ...): consecutive
+    /// probes land far apart, deterministically
+    Striped,
+    /// Random permutation, different on every scan
+    Shuffled,
+}
+
+/// Reorder `hosts` in place according to `order`.
+pub fn order_hosts(hosts: &mut Vec<Ipv4Addr>, order: HostOrder) {
+    match order {
+        HostOrder::Sequential => {}
+        HostOrder::Striped => {
+            let n = hosts.len();
+            let bits = usize::BITS - n.saturating_sub(1).leading_zeros();
+            let striped = (0..1usize << bits)
+                .map(|i| {
+                    i.reverse_bits()
+                        .checked_shr(usize::BITS - bits)
+                        .unwrap_or(0)
+                })
+                .filter(|&j| j < n)
+                .map(|j| hosts[j])
+                .collect();
+            *hosts = striped;
+        }
+        HostOrder::Shuffled => hosts.shuffle(&mut rand::thread_rng()),
+    }
+}
+
 /// Scan a CIDR and attempt to resolve MAC addresses using ARP.
 /// - `cidr` like "192.168.1.0/24"
 /// - `workers` number of concurrent worker threads (>=1)
 /// - `perform_probe` if true will actively probe (opt-in)
 /// - `timeout` per-lookup timeout
-/// Returns vector of (ip, Option<mac>) in no particular order.
+///
+/// Hosts are visited in ascending order; see `scan_cidr_ordered` to spread
+/// probes across the range instead. Ranges with more than
+/// `DEFAULT_MAX_HOSTS` hosts are rejected; use `scan_cidr_unbounded` for
+/// those, or `scan_cidr_with_progress` with a different
+/// `CidrScanOptions::max_hosts`.
+///
+/// Returns vector of (ip, Option<mac>) in no particular order; see
+/// `scan_cidr_sorted` for ascending IP order.
 pub fn scan_cidr(
     cidr: &str,
     workers: usize,
     perform_probe: bool,
     timeout: Duration,
-) -> Result<Vec<(Ipv4Addr, Option<[u8; 6]>)>, String> {
+) -> Result<Vec<ArpScanEntry>, String> {
+    scan_cidr_ordered(cidr, workers, perform_probe, timeout, HostOrder::Sequential)
+}
+
+/// `scan_cidr` with the results in ascending numeric IP order, so
+/// successive scans of a range line up regardless of which worker finished
+/// first.
+pub fn scan_cidr_sorted(
+    cidr: &str,
+    workers: usize,
+    perform_probe: bool,
+    timeout: Duration,
+) -> Result<Vec<ArpScanEntry>, String> {
+    let mut results = scan_cidr(cidr, workers, perform_probe, timeout)?;
+    sort_by_ip(&mut results);
+    Ok(results)
+}
+
+/// Sort scan entries by IP as a number (10.0.0.2 before 10.0.0.10).
+pub fn sort_by_ip(entries: &mut [ArpScanEntry]) {
+    entries.sort_by_key(|(ip, _)| u32::from(*ip));
+}
+
+/// `scan_cidr` without the host limit.
+pub fn scan_cidr_unbounded(
+    cidr: &str,
+    workers: usize,
+    perform_probe: bool,
+    timeout: Duration,
+) -> Result<Vec<ArpScanEntry>, String> {
+    let hosts = ordered_hosts(cidr, CidrScanOptions::default().with_max_hosts(usize::MAX))?;
+    Ok(scan_hosts(
+        hosts,
+        workers,
+        perform_probe,
+        timeout,
+        None,
+        |_, _, _| true,
+    ))
+}
+
+/// Like `scan_cidr`, visiting hosts in the given `order`. Each worker takes a
+/// contiguous slice of the reordered list, so with `Striped` or `Shuffled`
+/// no worker sweeps a single block of neighbouring addresses.
+pub fn scan_cidr_ordered(
+    cidr: &str,
+    workers: usize,
+    perform_probe: bool,
+    timeout: Duration,
+    order: HostOrder,
+) -> Result<Vec<ArpScanEntry>, String> {
+    let options = CidrScanOptions::default().with_order(order);
+    scan_cidr_with_progress(cidr, workers, perform_probe, timeout, options, |_, _, _| {})
+}
+
+/// Like `scan_cidr_ordered` with the order and host limit taken from
+/// `options`, calling `on_host(entry, done, total)` on the calling thread as
+/// each host's lookup completes.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(name = "scan_cidr", level = "debug", skip(timeout, on_host))
+)]
+pub fn scan_cidr_with_progress<F>(
+    cidr: &str,
+    workers: usize,
+    perform_probe: bool,
+    timeout: Duration,
+    options: CidrScanOptions,
+    mut on_host: F,
+) -> Result<Vec<ArpScanEntry>, String>
+where
+    F: FnMut(&ArpScanEntry, usize, usize),
+{
+    let hosts = ordered_hosts(cidr, options)?;
+    Ok(scan_hosts(
+        hosts,
+        workers,
+        perform_probe,
+        timeout,
+        None,
+        |entry, done, total| {
+            on_host(entry, done, total);
+            true
+        },
+    ))
+}
+
+/// Like `scan_cidr_with_progress`, stopping once `cancel` fires: each
+/// worker finishes its current lookup and takes no further host. Returns
+/// the hosts looked up so far, with `cancelled` set when the token fired
+/// before every host was visited.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(name = "scan_cidr", level = "debug", skip(timeout, cancel, on_host))
+)]
+pub fn scan_cidr_with_cancel<F>(
+    cidr: &str,
+    workers: usize,
+    perform_probe: bool,
+    timeout: Duration,
+    options: CidrScanOptions,
+    cancel: &CancelToken,
+    mut on_host: F,
+) -> Result<PartialScan<ArpScanEntry>, String>
+where
+    F: FnMut(&ArpScanEntry, usize, usize),
+{
+    let hosts = ordered_hosts(cidr, options)?;
+    let total = hosts.len();
+    let results = scan_hosts(
+        hosts,
+        workers,
+        perform_probe,
+        timeout,
+        Some(cancel),
+        |entry, done, total| {
+            on_host(entry, done, total);
+            true
+        },
+    );
+    Ok(PartialScan {
+        cancelled: cancel.is_cancelled() && results.len() < total,
+        results,
+        timed_out: false,
+    })
+}
+
+/// Like `scan_cidr_with_progress`, skipping hosts already completed in
+/// `checkpoint` and recording each host there as it finishes, so a killed
+/// scan can be resumed with `ScanCheckpoint::resume_from`. Only hosts
+/// scanned by this call are returned; the checkpoint keeps addresses, not
+/// results. A failed checkpoint write stops the scan with an error.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(name = "scan_cidr", level = "debug", skip(timeout, checkpoint))
+)]
+pub fn scan_cidr_with_checkpoint(
+    cidr: &str,
+    workers: usize,
+    perform_probe: bool,
+    timeout: Duration,
+    options: CidrScanOptions,
+    checkpoint: &mut ScanCheckpoint,
+) -> Result<Vec<ArpScanEntry>, String> {
+    let mut hosts = ordered_hosts(cidr, options)?;
+    hosts.retain(|ip| !checkpoint.is_done(*ip));
+    let mut write_error = None;
+    let results = scan_hosts(
+        hosts,
+        workers,
+        perform_probe,
+        timeout,
+        None,
+        |(ip, _), _, _| match checkpoint.record(*ip) {
+            Ok(()) => true,
+            Err(e) => {
+                write_error = Some(e);
+                false
+            }
+        },
+    );
+    match write_error.map_or_else(|| checkpoint.flush(), Err) {
+        Ok(()) => Ok(results),
+        Err(e) => Err(format!("checkpoint write failed: {}", e)),
+    }
+}
+
+/// Usable host addresses of `cidr` in `options.order`, for callers that
+/// probe the range some other way than ARP. Subject to `options.max_hosts`.
+pub fn cidr_hosts(cidr: &str, options: CidrScanOptions) -> Result<Vec<Ipv4Addr>, String> {
+    ordered_hosts(cidr, options)
+}
+
+fn ordered_hosts(cidr: &str, options: CidrScanOptions) -> Result<Vec<Ipv4Addr>, String> {
+    check_host_limit(cidr, options.max_hosts)?;
     let net: Ipv4Network = cidr.parse().map_err(|e| format!("invalid cidr: {}", e))?;
-    let hosts = hosts_from_network(net);
+    let mut hosts = hosts_from_network(net, options.skip_reserved);
+    order_hosts(&mut hosts, options.order);
+    Ok(hosts)
+}
+
+/// Resolve `hosts` on `workers` threads, calling `on_host(entry, done, total)`
+/// on the calling thread per result. When `on_host` returns false or
+/// `cancel` fires, the workers stop after their current lookup and the
+/// results so far are returned. Without `perform_probe` nothing is sent on
+/// the wire, so the ARP cache is read once with `arp::lookup_mac_multi` and
+/// no workers are spawned.
+fn scan_hosts<F>(
+    hosts: Vec<Ipv4Addr>,
+    workers: usize,
+    perform_probe: bool,
+    timeout: Duration,
+    cancel: Option<&CancelToken>,
+    mut on_host: F,
+) -> Vec<ArpScanEntry>
+where
+    F: FnMut(&ArpScanEntry, usize, usize) -> bool,
+{
     if hosts.is_empty() {
-        return Ok(Vec::new());
+        return Vec::new();
+    }
+    if !perform_probe {
+        return scan_hosts_cached(hosts, cancel, on_host);
     }
     let workers = std::cmp::max(1, workers);
     let (res_tx, res_rx) = mpsc::channel();
+    let stop = Arc::new(AtomicBool::new(false));
 
     // Partition hosts into chunks for each worker to avoid channel contention.
-    let chunk_size = (hosts.len() + workers - 1) / workers;
+    let chunk_size = hosts.len().div_ceil(workers);
     let mut handles = Vec::new();
+    trace_debug!(
+        hosts = hosts.len(),
+        workers,
+        chunk_size,
+        "dispatching workers"
+    );
     for chunk in hosts.chunks(chunk_size) {
         let chunk_vec = chunk.to_vec();
         let res_tx = res_tx.clone();
-        let timeout = timeout.clone();
+        let stop = stop.clone();
+        let cancel = cancel.cloned();
         let chunk_perform = perform_probe;
+        #[cfg(feature = "tracing")]
+        let parent = tracing::Span::current();
         let handle = thread::spawn(move || {
+            #[cfg(feature = "tracing")]
+            let _parent = parent.entered();
+            let _worker_span =
+                trace_span!("worker", first = %chunk_vec[0], hosts = chunk_vec.len());
             for ip in chunk_vec {
+                if stop.load(Ordering::Relaxed) || cancel.as_ref().is_some_and(|c| c.is_cancelled())
+                {
+                    break;
+                }
+                let _host_span = trace_span!("host", %ip);
                 match arp::ensure_mac(ip, None, timeout, chunk_perform) {
                     Ok(Some(mac)) => {
+                        trace_debug!("resolved");
                         let _ = res_tx.send((ip, Some(mac)));
                     }
                     Ok(None) => {
+                        trace_debug!("unresolved");
                         let _ = res_tx.send((ip, None));
                     }
-                    Err(_) => {
+                    Err(_e) => {
+                        trace_debug!(error = %_e, "lookup failed");
                         let _ = res_tx.send((ip, None));
                     }
                 }
@@ -79,8 +469,12 @@ pub fn scan_cidr(
 
     let mut results = Vec::new();
     for _ in 0..hosts.len() {
-        if let Ok(r) = res_rx.recv() {
-            results.push(r);
+        let Ok(r) = res_rx.recv() else { break };
+        let keep_going = on_host(&r, results.len() + 1, hosts.len());
+        results.push(r);
+        if !keep_going {
+            stop.store(true, Ordering::Relaxed);
+            break;
         }
     }
 
@@ -88,7 +482,38 @@ pub fn scan_cidr(
         let _ = h.join();
     }
 
-    Ok(results)
+    results
+}
+
+/// `scan_hosts` without probing: one bulk ARP cache lookup, results in
+/// `hosts` order. Emits the same `worker` and `host` spans as the probing
+/// path, with a single worker on the calling thread.
+fn scan_hosts_cached<F>(
+    hosts: Vec<Ipv4Addr>,
+    cancel: Option<&CancelToken>,
+    mut on_host: F,
+) -> Vec<ArpScanEntry>
+where
+    F: FnMut(&ArpScanEntry, usize, usize) -> bool,
+{
+    let _worker_span = trace_span!("worker", first = %hosts[0], hosts = hosts.len());
+    let macs = arp::lookup_mac_multi(&hosts);
+    trace_debug!(hosts = hosts.len(), resolved = macs.len(), "cache lookup");
+    let total = hosts.len();
+    let mut results = Vec::new();
+    for ip in hosts {
+        if cancel.is_some_and(|c| c.is_cancelled()) {
+            break;
+        }
+        let _host_span = trace_span!("host", %ip);
+        let entry = (ip, macs.get(&ip).copied());
+        let keep_going = on_host(&entry, results.len() + 1, total);
+        results.push(entry);
+        if !keep_going {
+            break;
+        }
+    }
+    results
 }
 
 #[cfg(test)]
@@ -99,13 +524,276 @@ mod tests {
     #[test]
     fn hosts_from_small_cidr() {
         let net: Ipv4Network = "192.168.0.0/30".parse().unwrap();
-        let hosts = hosts_from_network(net);
+        let hosts = hosts_from_network(net, true);
         // /30 has 2 usable hosts
         assert_eq!(hosts.len(), 2);
         assert_eq!(hosts[0].to_string(), "192.168.0.1");
         assert_eq!(hosts[1].to_string(), "192.168.0.2");
     }
 
+    #[test]
+    fn reserved_ranges_are_skipped_when_overlapping() {
+        // 169.254.0.0 - 169.255.255.255: link-local, then 169.255/16
+        let net: Ipv4Network = "169.254.0.0/15".parse().unwrap();
+        let hosts = hosts_from_network(net, true);
+        assert_eq!(hosts.len(), 65536 - 1);
+        assert!(hosts.iter().all(|ip| !ip.is_link_local()));
+        assert_eq!(hosts[0], Ipv4Addr::new(169, 255, 0, 0));
+
+        let net: Ipv4Network = "222.0.0.0/7".parse().unwrap();
+        assert_eq!(reserved_overlaps(net).len(), 0);
+        let net: Ipv4Network = "192.0.0.0/2".parse().unwrap();
+        assert_eq!(reserved_overlaps(net), ["224.0.0.0/4".parse().unwrap()]);
+        let net: Ipv4Network = "0.0.0.0/0".parse().unwrap();
+        assert_eq!(reserved_overlaps(net).len(), 3);
+
+        // Ranges inside a reserved block are scanned as asked
+        let net: Ipv4Network = "169.254.10.0/30".parse().unwrap();
+        assert_eq!(hosts_from_network(net, true).len(), 2);
+        let options = CidrScanOptions::default().with_skip_reserved(false);
+        assert_eq!(
+            cidr_hosts("169.254.0.0/15", options.with_max_hosts(usize::MAX))
+                .unwrap()
+                .len(),
+            131070
+        );
+        assert!(reserved_overlaps("0.0.0.5/32".parse().unwrap()).is_empty());
+    }
+
+    #[test]
+    fn host_limit_rejects_oversized_ranges() {
+        assert_eq!(
+            check_host_limit("192.168.1.0/24", DEFAULT_MAX_HOSTS),
+            Ok(254)
+        );
+        assert_eq!(
+            check_host_limit("10.0.0.0/16", DEFAULT_MAX_HOSTS),
+            Ok(65534)
+        );
+        assert_eq!(check_host_limit("10.0.0.1/32", 1), Ok(1));
+        for cidr in ["192.168.0.0/30", "192.168.0.0/31", "10.1.0.0/16"] {
+            let net: Ipv4Network = cidr.parse().unwrap();
+            assert_eq!(host_count(net), hosts_from_network(net, true).len() as u64);
+        }
+
+        let err = check_host_limit("10.0.0.0/8", DEFAULT_MAX_HOSTS).unwrap_err();
+        assert!(err.contains("16777214") && err.contains("65536"), "{}", err);
+        assert_eq!(
+            check_host_limit("10.0.0.0/24", 100).unwrap_err(),
+            "cidr 10.0.0.0/24 has 254 hosts, over the limit of 100 (see scan_cidr_unbounded)"
+        );
+        // rejected before any lookup runs
+        let err = scan_cidr("10.0.0.0/8", 4, false, Duration::from_millis(1)).unwrap_err();
+        assert!(err.contains("over the limit"), "{}", err);
+        // the limit travels with the call, not the process
+        let small = CidrScanOptions::default().with_max_hosts(4);
+        assert!(cidr_hosts("192.168.254.0/29", small).is_err());
+        assert_eq!(cidr_hosts("192.168.254.0/30", small).unwrap().len(), 2);
+        assert_eq!(
+            cidr_hosts("192.168.254.0/29", CidrScanOptions::default())
+                .unwrap()
+                .len(),
+            6
+        );
+        assert!(check_host_limit("10.0.0.0/33", 1)
+            .unwrap_err()
+            .starts_with("invalid cidr"));
+    }
+
+    #[test]
+    fn containment_checks() {
+        let net: Ipv4Network = "192.168.1.77/24".parse().unwrap();
+        assert!(network_contains(&net, Ipv4Addr::new(192, 168, 1, 0)));
+        assert!(network_contains(&net, Ipv4Addr::new(192, 168, 1, 255)));
+        assert!(!network_contains(&net, Ipv4Addr::new(192, 168, 2, 1)));
+
+        assert_eq!(parse_and_contains("10.0.0.0/30", "10.0.0.3"), Ok(true));
+        assert_eq!(parse_and_contains("10.0.0.0/30", "10.0.0.4"), Ok(false));
+        assert_eq!(parse_and_contains("10.0.0.1/32", "10.0.0.1"), Ok(true));
+        assert_eq!(parse_and_contains("0.0.0.0/0", "203.0.113.9"), Ok(true));
+        assert!(parse_and_contains("10.0.0.0/33", "10.0.0.1").is_err());
+        assert!(parse_and_contains("10.0.0.0/24", "10.0.0").is_err());
+    }
+
+    #[test]
+    fn striped_order_spreads_hosts() {
+        let net: Ipv4Network = "10.0.0.0/24".parse().unwrap();
+        let sequential = hosts_from_network(net, true);
+        let mut striped = sequential.clone();
+        order_hosts(&mut striped, HostOrder::Striped);
+        let last_octets: Vec<u8> = striped.iter().take(4).map(|ip| ip.octets()[3]).collect();
+        assert_eq!(last_octets, [1, 129, 65, 193]);
+
+        // still a permutation of the same hosts
+        let mut sorted = striped.clone();
+        sorted.sort();
+        assert_eq!(sorted, sequential);
+
+        let mut shuffled = sequential.clone();
+        order_hosts(&mut shuffled, HostOrder::Shuffled);
+        shuffled.sort();
+        assert_eq!(shuffled, sequential);
+
+        let mut single = vec![Ipv4Addr::new(10, 0, 0, 1)];
+        order_hosts(&mut single, HostOrder::Striped);
+        assert_eq!(single, [Ipv4Addr::new(10, 0, 0, 1)]);
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn scan_cidr_emits_per_host_spans() {
+        use std::sync::{Arc, Mutex, PoisonError};
+        use tracing::span::{Attributes, Id, Record};
+        use tracing::{Event, Metadata, Subscriber};
+
+        // Minimal subscriber that records the names of spans created.
+        struct SpanNames(Arc<Mutex<Vec<&'static str>>>);
+        impl Subscriber for SpanNames {
+            fn enabled(&self, _: &Metadata<'_>) -> bool {
+                true
+            }
+            fn new_span(&self, attrs: &Attributes<'_>) -> Id {
+                let mut names = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+                names.push(attrs.metadata().name());
+                Id::from_u64(names.len() as u64)
+            }
+            fn record(&self, _: &Id, _: &Record<'_>) {}
+            fn record_follows_from(&self, _: &Id, _: &Id) {}
+            fn event(&self, _: &Event<'_>) {}
+            fn enter(&self, _: &Id) {}
+            fn exit(&self, _: &Id) {}
+        }
+
+        let names = Arc::new(Mutex::new(Vec::new()));
+        let dispatch = tracing::Dispatch::new(SpanNames(names.clone()));
+        // worker threads don't inherit a scoped dispatcher, so install it globally
+        let _ = tracing::dispatcher::set_global_default(dispatch);
+        // the passive path reads the cache in bulk rather than per host
+        scan_cidr("192.168.254.0/30", 2, false, Duration::from_secs(1)).unwrap();
+
+        // copy out so a failed assertion cannot poison the subscriber's lock
+        let names = names.lock().unwrap_or_else(PoisonError::into_inner).clone();
+        for expected in ["scan_cidr", "worker", "host", "lookup_mac_multi"] {
+            assert!(names.contains(&expected), "missing span {}", expected);
+        }
+        // other tests may scan concurrently once the global subscriber is set
+        assert!(names.iter().filter(|n| **n == "host").count() >= 2);
+    }
+
+    #[test]
+    fn progress_callback_counts_every_host() {
+        let mut seen = Vec::new();
+        let res = scan_cidr_with_progress(
+            "192.168.254.0/29",
+            3,
+            false,
+            Duration::from_secs(1),
+            CidrScanOptions::default(),
+            |entry, done, total| seen.push((entry.0, done, total)),
+        )
+        .unwrap();
+        assert_eq!(seen.len(), res.len());
+        let counts: Vec<(usize, usize)> = seen.iter().map(|(_, d, t)| (*d, *t)).collect();
+        assert_eq!(counts, (1..=6).map(|d| (d, 6)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn checkpoint_skips_completed_hosts() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("scan.checkpoint");
+        std::fs::write(&path, "192.168.254.1\n192.168.254.2\n").unwrap();
+
+        let mut checkpoint = ScanCheckpoint::resume_from(&path).unwrap();
+        let res = scan_cidr_with_checkpoint(
+            "192.168.254.0/29",
+            3,
+            false,
+            Duration::from_secs(1),
+            CidrScanOptions::default(),
+            &mut checkpoint,
+        )
+        .unwrap();
+        let mut scanned: Vec<u8> = res.iter().map(|(ip, _)| ip.octets()[3]).collect();
+        scanned.sort();
+        assert_eq!(scanned, [3, 4, 5, 6]);
+        assert_eq!(ScanCheckpoint::resume_from(&path).unwrap().completed(), 6);
+    }
+
+    #[test]
+    fn checkpoint_write_failure_stops_scan() {
+        // Every write to /dev/full fails with ENOSPC
+        let Ok(checkpoint) = ScanCheckpoint::create("/dev/full") else {
+            return;
+        };
+        let err = scan_cidr_with_checkpoint(
+            "192.168.254.0/29",
+            1,
+            false,
+            Duration::from_secs(1),
+            CidrScanOptions::default(),
+            &mut checkpoint.with_flush_every(1),
+        )
+        .unwrap_err();
+        assert!(err.starts_with("checkpoint write failed"), "{}", err);
+    }
+
+    #[test]
+    fn cancel_mid_scan_returns_hosts_done_so_far() {
+        let cancel = CancelToken::new();
+        let res = scan_cidr_with_cancel(
+            "192.168.254.0/28",
+            1,
+            false,
+            Duration::from_secs(1),
+            CidrScanOptions::default(),
+            &cancel,
+            |_, done, _| {
+                if done == 2 {
+                    cancel.cancel();
+                }
+            },
+        )
+        .unwrap();
+        assert!(res.cancelled);
+        assert!(
+            res.results.len() >= 2 && res.results.len() < 14,
+            "{:?}",
+            res.results
+        );
+
+        // A token that never fires scans everything and reports no cancellation
+        let res = scan_cidr_with_cancel(
+            "192.168.254.0/30",
+            2,
+            false,
+            Duration::from_secs(1),
+            CidrScanOptions::default(),
+            &CancelToken::new(),
+            |_, _, _| {},
+        )
+        .unwrap();
+        assert_eq!((res.results.len(), res.cancelled), (2, false));
+    }
+
+    #[test]
+    fn sorted_scan_is_ascending_whatever_the_worker_count() {
+        let expected: Vec<Ipv4Addr> = (1..=14).map(|i| Ipv4Addr::new(192, 168, 254, i)).collect();
+        for workers in [1, 3, 14] {
+            let res = scan_cidr_sorted("192.168.254.0/28", workers, false, Duration::from_secs(1))
+                .unwrap();
+            let ips: Vec<Ipv4Addr> = res.iter().map(|(ip, _)| *ip).collect();
+            assert_eq!(ips, expected);
+        }
+        let mut entries = vec![
+            (Ipv4Addr::new(10, 0, 0, 10), None),
+            (Ipv4Addr::new(9, 255, 255, 255), None),
+            (Ipv4Addr::new(10, 0, 0, 2), None),
+        ];
+        sort_by_ip(&mut entries);
+        assert_eq!(entries[0].0, Ipv4Addr::new(9, 255, 255, 255));
+        assert_eq!(entries[2].0, Ipv4Addr::new(10, 0, 0, 10));
+    }
+
     #[test]
     fn scan_cidr_no_probe_returns_all_hosts() {
         let res = scan_cidr("192.168.254.0/30", 2, false, Duration::from_secs(1)).unwrap();
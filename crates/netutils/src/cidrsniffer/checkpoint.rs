@@ -0,0 +1,128 @@
+//! Resumable CIDR scans: a line-delimited file of completed host IPs.
+
+use std::collections::HashSet;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::net::Ipv4Addr;
+use std::path::Path;
+
+/// Completed hosts written between flushes unless `with_flush_every` says otherwise.
+pub const CHECKPOINT_FLUSH_EVERY: usize = 64;
+
+/// Hosts finished by `scan_cidr_with_checkpoint`, mirrored to a file with
+/// one IP per line. Lines are appended as hosts complete and flushed every
+/// `flush_every` hosts, so a killed scan loses at most that many.
+pub struct ScanCheckpoint {
+    done: HashSet<Ipv4Addr>,
+    file: BufWriter<File>,
+    flush_every: usize,
+    unflushed: usize,
+}
+
+impl ScanCheckpoint {
+    /// Start an empty checkpoint at `path`, truncating any existing file.
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(Self::with_file(File::create(path)?, HashSet::new()))
+    }
+
+    /// Load the hosts completed by an earlier run from `path` and keep
+    /// appending to it. A missing file starts an empty checkpoint; lines that
+    /// are not an IPv4 address (e.g. one cut short by a crash) are ignored.
+    pub fn resume_from<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e),
+        };
+        let done = text.lines().filter_map(|l| l.trim().parse().ok()).collect();
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        if !text.is_empty() && !text.ends_with('\n') {
+            // Terminate a partial last line so the next IP starts on its own
+            file.write_all(b"\n")?;
+        }
+        Ok(Self::with_file(file, done))
+    }
+
+    fn with_file(file: File, done: HashSet<Ipv4Addr>) -> Self {
+        Self {
+            done,
+            file: BufWriter::new(file),
+            flush_every: CHECKPOINT_FLUSH_EVERY,
+            unflushed: 0,
+        }
+    }
+
+    /// Flush to disk after every `n` completed hosts (at least 1).
+    pub fn with_flush_every(mut self, n: usize) -> Self {
+        self.flush_every = n.max(1);
+        self
+    }
+
+    /// Whether `ip` was completed by this or an earlier run.
+    pub fn is_done(&self, ip: Ipv4Addr) -> bool {
+        self.done.contains(&ip)
+    }
+
+    /// Number of completed hosts.
+    pub fn completed(&self) -> usize {
+        self.done.len()
+    }
+
+    /// Mark `ip` completed, flushing when `flush_every` hosts are pending.
+    pub fn record(&mut self, ip: Ipv4Addr) -> io::Result<()> {
+        if !self.done.insert(ip) {
+            return Ok(());
+        }
+        writeln!(self.file, "{}", ip)?;
+        self.unflushed += 1;
+        if self.unflushed >= self.flush_every {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Write pending hosts to disk.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.unflushed = 0;
+        self.file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resume_reads_back_recorded_hosts() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("scan.checkpoint");
+        let a = Ipv4Addr::new(10, 0, 0, 1);
+        let b = Ipv4Addr::new(10, 0, 0, 2);
+
+        let mut cp = ScanCheckpoint::create(&path).unwrap().with_flush_every(1);
+        cp.record(a).unwrap();
+        cp.record(a).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "10.0.0.1\n");
+        drop(cp);
+
+        // a crash mid-write leaves a partial line behind
+        let mut f = OpenOptions::new().append(true).open(&path).unwrap();
+        f.write_all(b"10.0.").unwrap();
+        drop(f);
+
+        let mut cp = ScanCheckpoint::resume_from(&path).unwrap();
+        assert!(cp.is_done(a) && !cp.is_done(b));
+        cp.record(b).unwrap();
+        // not flushed yet: below the default batch size
+        assert_eq!(ScanCheckpoint::resume_from(&path).unwrap().completed(), 1);
+        cp.flush().unwrap();
+        assert_eq!(ScanCheckpoint::resume_from(&path).unwrap().completed(), 2);
+
+        let missing = dir.path().join("fresh.checkpoint");
+        assert_eq!(
+            ScanCheckpoint::resume_from(&missing).unwrap().completed(),
+            0
+        );
+    }
+}
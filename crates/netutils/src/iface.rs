@@ -44,44 +44,64 @@ impl std::error::Error for IfaceError {}
 pub fn get_default_cidr() -> Result<Ipv4Network, IfaceError> {
     let iface = get_default_interface()?;
     let ipv4 = iface.ipv4.ok_or(IfaceError::NoUpInterface)?;
-    // Try to get netmask from pnet_datalink
-    let interfaces = pnet_datalink::interfaces();
-    for i in interfaces {
-        if i.name == iface.name {
-            for ip in i.ips {
-                if let IpNetwork::V4(net) = ip {
-                    if net.ip() == ipv4 {
-                        return Ok(net);
-                    }
-                }
-            }
-        }
+    if let Some(net) = interface_network(&iface.name, ipv4) {
+        return Ok(net);
     }
     // Fallback: /24
-    Ok(Ipv4Network::new(ipv4, 24).map_err(|_| IfaceError::NoUpInterface)?)
+    Ipv4Network::new(ipv4, 24).map_err(|_| IfaceError::NoUpInterface)
+}
+
+/// The CIDR to scan for the primary interface, e.g. `"192.168.1.0/24"`,
+/// ready for `LiveArpDiscover::new`. Uses the interface's real prefix and
+/// network address; unlike `get_default_cidr` it never guesses /24 and fails
+/// when the prefix is unknown.
+pub fn default_scan_target() -> Result<String, IfaceError> {
+    let iface = get_default_interface()?;
+    let ipv4 = iface.ipv4.ok_or(IfaceError::NoUpInterface)?;
+    let net = interface_network(&iface.name, ipv4)
+        .ok_or_else(|| IfaceError::Other(format!("no IPv4 prefix for {}", iface.name)))?;
+    Ok(scan_target(net))
+}
+
+/// `net` with its host bits cleared, as a CIDR string.
+fn scan_target(net: Ipv4Network) -> String {
+    format!("{}/{}", net.network(), net.prefix())
+}
+
+/// The address/prefix pnet reports for `ipv4` on interface `name`.
+fn interface_network(name: &str, ipv4: Ipv4Addr) -> Option<Ipv4Network> {
+    pnet_datalink::interfaces()
+        .into_iter()
+        .filter(|i| i.name == name)
+        .flat_map(|i| i.ips)
+        .find_map(|ip| match ip {
+            IpNetwork::V4(net) if net.ip() == ipv4 => Some(net),
+            _ => None,
+        })
 }
 
 use std::fs;
 use std::io::{BufRead, BufReader};
 use std::process::Command;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
 
 /// Returns the default gateway IPv4 address by parsing /proc/net/route (Linux only).
 pub fn get_default_gateway_ipv4() -> Option<Ipv4Addr> {
     let file = fs::File::open("/proc/net/route").ok()?;
     let reader = BufReader::new(file);
-    for line in reader.lines().skip(1) {
-        if let Ok(line) = line {
-            let fields: Vec<&str> = line.split_whitespace().collect();
-            if fields.len() >= 3 && fields[1] == "00000000" {
-                if let Ok(gw_hex) = u32::from_str_radix(fields[2], 16) {
-                    let gw_bytes = gw_hex.to_le_bytes();
-                    return Some(Ipv4Addr::new(
-                        gw_bytes[0],
-                        gw_bytes[1],
-                        gw_bytes[2],
-                        gw_bytes[3],
-                    ));
-                }
+    for line in reader.lines().skip(1).map_while(Result::ok) {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() >= 3 && fields[1] == "00000000" {
+            if let Ok(gw_hex) = u32::from_str_radix(fields[2], 16) {
+                let gw_bytes = gw_hex.to_le_bytes();
+                return Some(Ipv4Addr::new(
+                    gw_bytes[0],
+                    gw_bytes[1],
+                    gw_bytes[2],
+                    gw_bytes[3],
+                ));
             }
         }
     }
@@ -154,6 +174,15 @@ pub fn get_interface_by_name(name: &str) -> Result<NetworkInterface, IfaceError>
         .ok_or(IfaceError::NotFound)
 }
 
+/// IPv4 address of the interface called `name`, for binding probes to that
+/// interface (`ProbeStrategy::with_local_addr`). `InvalidInterface` when the
+/// interface has no IPv4 address.
+pub fn get_ipv4_by_interface_name(name: &str) -> Result<Ipv4Addr, IfaceError> {
+    get_interface_by_name(name)?
+        .ipv4
+        .ok_or_else(|| IfaceError::InvalidInterface(format!("{} has no IPv4 address", name)))
+}
+
 /// Finds an interface by index.
 pub fn get_interface_by_index(index: u32) -> Result<NetworkInterface, IfaceError> {
     let interfaces = list_interfaces()?;
@@ -265,6 +294,57 @@ pub fn is_interface_unmanaged(interface: &str) -> Result<bool, IfaceError> {
     }
 }
 
+/// Re-read the current up/down state of interface `name` (unlike
+/// `NetworkInterface::up`, which is a snapshot taken when listed).
+pub fn is_interface_up(name: &str) -> Result<bool, IfaceError> {
+    pnet_datalink::interfaces()
+        .into_iter()
+        .find(|iface| iface.name == name)
+        .map(|iface| iface.is_up())
+        .ok_or(IfaceError::NotFound)
+}
+
+/// Handle for a `monitor_interface` thread; dropping it stops the thread.
+pub struct MonitorHandle {
+    stop: Option<mpsc::Sender<()>>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl Drop for MonitorHandle {
+    fn drop(&mut self) {
+        // Dropping the sender wakes the thread out of its interval wait.
+        self.stop.take();
+        if let Some(t) = self.thread.take() {
+            let _ = t.join();
+        }
+    }
+}
+
+/// Poll `is_interface_up(name)` every `interval` on a background thread and
+/// call `callback` with the new state whenever it changes. A missing
+/// interface counts as down. The initial state is not reported.
+pub fn monitor_interface(
+    name: String,
+    interval: Duration,
+    callback: impl Fn(bool) + Send + 'static,
+) -> MonitorHandle {
+    let (stop, stop_rx) = mpsc::channel::<()>();
+    let thread = thread::spawn(move || {
+        let mut last = is_interface_up(&name).unwrap_or(false);
+        while let Err(mpsc::RecvTimeoutError::Timeout) = stop_rx.recv_timeout(interval) {
+            let now = is_interface_up(&name).unwrap_or(false);
+            if now != last {
+                last = now;
+                callback(now);
+            }
+        }
+    });
+    MonitorHandle {
+        stop: Some(stop),
+        thread: Some(thread),
+    }
+}
+
 pub fn resolve_iface_name(interface: &Option<String>) -> String {
     match interface.as_deref() {
         Some(name) => name.to_string(),
@@ -306,6 +386,18 @@ mod tests {
         );
     }
 
+    #[test]
+    fn scan_target_clears_host_bits_and_keeps_prefix() {
+        let net = |s: &str| s.parse::<Ipv4Network>().unwrap();
+        assert_eq!(scan_target(net("192.168.1.23/24")), "192.168.1.0/24");
+        assert_eq!(scan_target(net("10.20.30.40/20")), "10.20.16.0/20");
+        assert_eq!(scan_target(net("172.16.5.9/32")), "172.16.5.9/32");
+
+        let target = default_scan_target().expect("default interface has a prefix");
+        let ipv4 = get_default_interface().unwrap().ipv4.unwrap();
+        assert!(target.parse::<Ipv4Network>().unwrap().contains(ipv4));
+    }
+
     #[test]
     fn test_get_interface_by_name_roundtrip() {
         let interfaces = list_interfaces().expect("Should list interfaces");
@@ -371,9 +463,48 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_is_interface_up_loopback() {
+        if let Ok(lo) = get_interface_by_name("lo") {
+            assert_eq!(is_interface_up("lo").expect("lo exists"), lo.up);
+        }
+        assert!(matches!(
+            is_interface_up("definitely_not_a_real_interface_name_12345"),
+            Err(IfaceError::NotFound)
+        ));
+    }
+
+    #[test]
+    fn test_monitor_loopback_stays_quiet() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        if !is_interface_up("lo").unwrap_or(false) {
+            return;
+        }
+        let fired = Arc::new(AtomicUsize::new(0));
+        let counter = fired.clone();
+        let handle = monitor_interface("lo".into(), Duration::from_millis(20), move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+        thread::sleep(Duration::from_millis(200));
+        let started = std::time::Instant::now();
+        drop(handle);
+        assert!(started.elapsed() < Duration::from_millis(200));
+        assert_eq!(fired.load(Ordering::SeqCst), 0);
+    }
+
     #[test]
     fn test_get_interface_by_name_not_found() {
         let result = get_interface_by_name("definitely_not_a_real_interface_name_12345");
         assert!(matches!(result, Err(IfaceError::NotFound)));
+        let result = get_ipv4_by_interface_name("definitely_not_a_real_interface_name_12345");
+        assert!(matches!(result, Err(IfaceError::NotFound)));
+    }
+
+    #[test]
+    fn interface_name_resolves_to_its_ipv4() {
+        let iface = get_default_interface().expect("default interface");
+        assert_eq!(get_ipv4_by_interface_name(&iface.name).ok(), iface.ipv4);
     }
 }
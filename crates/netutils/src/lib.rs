@@ -1,9 +1,13 @@
 pub mod arp;
+pub mod ber;
+pub mod cancel;
 pub mod cidrsniffer;
 pub mod iface;
 pub mod netcheck;
 pub mod portscan;
 pub mod rawsocket;
+mod trace;
 
 // Re-export common types for consumers
+pub use cancel::CancelToken;
 pub use iface::NetworkInterface;
@@ -1,11 +1,11 @@
+//! Lightweight, non-privileged network checks.
+//!
+//! This module intentionally avoids raw sockets and privileged operations.
+//! It provides simple heuristics to check gateway presence and outbound TCP reachability.
+
 use std::io;
 use std::net::{IpAddr, SocketAddr, TcpStream, UdpSocket};
-use std::time::Duration;
-
-/// Lightweight, non-privileged network checks.
-///
-/// This module intentionally avoids raw sockets and privileged operations.
-/// It provides simple heuristics to check gateway presence and outbound TCP reachability.
+use std::time::{Duration, Instant};
 
 /// Try to open a UDP socket bound to an ephemeral local port and read the local socket address.
 /// This helps discover the local outbound IP used by the OS (not guaranteed behind complex NATs).
@@ -21,14 +21,74 @@ pub fn local_outbound_ip() -> io::Result<IpAddr> {
 /// Check outbound TCP connectivity to a stable endpoint and port with a short timeout.
 /// Returns Ok(()) on success, or the underlying IO error on failure.
 pub fn check_outbound_tcp(addr: &str, port: u16, timeout: Duration) -> io::Result<()> {
+    TcpStream::connect_timeout(&socket_addr(addr, port)?, timeout).map(|_| ())
+}
+
+fn socket_addr(addr: &str, port: u16) -> io::Result<SocketAddr> {
     let socket = format!("{}:{}", addr, port);
-    let addr = socket.parse::<SocketAddr>().map_err(|e| {
+    socket.parse::<SocketAddr>().map_err(|e| {
         io::Error::new(
             io::ErrorKind::InvalidInput,
             format!("invalid socket addr: {}", e),
         )
-    })?;
-    TcpStream::connect_timeout(&addr, timeout).map(|_| ())
+    })
+}
+
+/// Time one TCP handshake to `addr:port`: the connect is the round trip, so
+/// this estimates latency without ICMP. A refused connect is an error here
+/// even though it took a round trip too.
+pub fn measure_tcp_rtt(addr: &str, port: u16, timeout: Duration) -> io::Result<Duration> {
+    let addr = socket_addr(addr, port)?;
+    let start = Instant::now();
+    TcpStream::connect_timeout(&addr, timeout)?;
+    Ok(start.elapsed())
+}
+
+/// Summary of several `measure_tcp_rtt` samples, in whole milliseconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RttStats {
+    pub min_ms: u64,
+    pub max_ms: u64,
+    pub avg_ms: u64,
+    /// Mean absolute difference between consecutive samples (0 for one)
+    pub jitter_ms: u64,
+}
+
+/// `count` back-to-back `measure_tcp_rtt` samples. Fails on the first
+/// connect that fails, or with `InvalidInput` when `count` is 0.
+pub fn measure_tcp_rtt_series(
+    addr: &str,
+    port: u16,
+    count: u8,
+    timeout: Duration,
+) -> io::Result<RttStats> {
+    if count == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "rtt series needs at least one sample",
+        ));
+    }
+    let samples = (0..count)
+        .map(|_| measure_tcp_rtt(addr, port, timeout))
+        .collect::<io::Result<Vec<_>>>()?;
+    Ok(rtt_stats(&samples))
+}
+
+fn rtt_stats(samples: &[Duration]) -> RttStats {
+    let ms = |d: Duration| d.as_millis() as u64;
+    let total: Duration = samples.iter().sum();
+    let jitter: Duration = samples
+        .windows(2)
+        .map(|w| w[0].abs_diff(w[1]))
+        .sum::<Duration>()
+        .checked_div(samples.len().saturating_sub(1) as u32)
+        .unwrap_or_default();
+    RttStats {
+        min_ms: samples.iter().copied().min().map_or(0, ms),
+        max_ms: samples.iter().copied().max().map_or(0, ms),
+        avg_ms: ms(total / samples.len().max(1) as u32),
+        jitter_ms: ms(jitter),
+    }
 }
 
 /// Quick gateway check: attempt to connect TCP to the gateway on port 80/443 with a short timeout.
@@ -59,4 +119,36 @@ mod tests {
         let res = check_outbound_tcp("192.0.2.1", 9, Duration::from_millis(200));
         assert!(res.is_err());
     }
+
+    #[test]
+    fn loopback_rtt_is_small() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("bind");
+        let port = listener.local_addr().unwrap().port();
+        let timeout = Duration::from_secs(1);
+
+        let rtt = measure_tcp_rtt("127.0.0.1", port, timeout).expect("connect");
+        assert!(rtt < Duration::from_millis(50), "{:?}", rtt);
+
+        let stats = measure_tcp_rtt_series("127.0.0.1", port, 5, timeout).expect("series");
+        assert!(stats.min_ms <= stats.avg_ms && stats.avg_ms <= stats.max_ms);
+        for v in [stats.min_ms, stats.max_ms, stats.avg_ms, stats.jitter_ms] {
+            assert!(v < 50, "{:?}", stats);
+        }
+        assert!(measure_tcp_rtt_series("127.0.0.1", port, 0, timeout).is_err());
+    }
+
+    #[test]
+    fn rtt_stats_jitter_is_mean_consecutive_difference() {
+        let ms = Duration::from_millis;
+        let stats = rtt_stats(&[ms(10), ms(14), ms(12)]);
+        assert_eq!(
+            stats,
+            RttStats {
+                min_ms: 10,
+                max_ms: 14,
+                avg_ms: 12,
+                jitter_ms: 3
+            }
+        );
+    }
 }
@@ -1,82 +1,444 @@
+use std::borrow::Cow;
 use std::net::{Ipv4Addr, SocketAddrV4};
 use std::time::Duration;
 
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use tokio::net::{TcpStream, UdpSocket};
+use tokio::net::TcpStream;
 use tokio::sync::Semaphore;
-use std::sync::Arc;
+
+use crate::cancel::CancelToken;
+use crate::trace::{spawn_in_set, spawn_in_span, trace_debug, trace_trace};
+
+mod auto;
+mod fingerprint;
+mod host;
+mod icmp;
+mod liveness;
+mod order;
+mod probes;
+mod rate;
+mod services;
+mod source;
+#[cfg(feature = "syn-scan")]
+mod syn;
+mod tls;
+mod udp_probes;
+pub use auto::{scan_auto, AutoScan, AutoScanOptions, ScanMethod};
+pub use fingerprint::{fingerprint_service, ServiceInfo};
+pub use host::{scan_host, scan_host_async, HostScanResult};
+pub use icmp::{parse_port_unreachable, IcmpUnreachableListener};
+pub use liveness::{
+    tcp_alive_async, tcp_alive_many, tcp_ping_sweep, LIVENESS_PORTS, TCP_PING_PORTS,
+};
+pub use order::ScanOrder;
+pub use probes::{
+    udp_payload_for, ProbeStrategy, ServiceProbe, DEFAULT_BANNER_TIMEOUT, HTTP_PROBE_PORTS,
+};
+use rate::ConnectGate;
+pub use services::{port_to_service, service_name};
+pub use source::check_local_addr;
+use source::{bind_udp, connect_from};
+#[cfg(feature = "syn-scan")]
+pub use syn::{
+    build_rst_frame, build_syn_frame, build_syn_segment, parse_syn_reply, parse_syn_reply_any,
+    syn_scan, syn_scan_port, SynScanError,
+};
+#[cfg(feature = "tls")]
+pub use tls::parse_certificate;
+pub use tls::{probe_tls_async, TlsInfo, TLS_PROBE_PORTS};
+pub use udp_probes::{udp_probe_payload, UDP_PROBE_PORTS};
 
 /// Result of a TCP probe: optional banner string (trimmed) when available.
 pub type TcpProbeResult = (Ipv4Addr, Option<String>);
 
+/// Result of a UDP probe: any reply bytes received.
+pub type UdpProbeResult = (Ipv4Addr, Option<Vec<u8>>);
+
+/// UDP probe outcome with the reply both raw and as a display banner.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UdpProbeReply {
+    pub ip: Ipv4Addr,
+    /// Reply bytes exactly as received
+    pub raw: Option<Vec<u8>>,
+    /// `udp_banner` of the reply, ready for `DiscoveryRecord.banner`
+    pub banner: Option<String>,
+}
+
+impl From<UdpProbeResult> for UdpProbeReply {
+    fn from((ip, raw): UdpProbeResult) -> Self {
+        let banner = raw.as_deref().and_then(udp_banner);
+        Self { ip, raw, banner }
+    }
+}
+
+/// What a probe learned about a port.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum PortState {
+    /// TCP connect succeeded, or a UDP reply arrived
+    Open,
+    /// Actively refused (TCP RST, ICMP port unreachable for UDP)
+    Closed,
+    /// TCP connect timed out
+    Filtered,
+    /// UDP without any reply: an open service may simply ignore the probe
+    OpenFiltered,
+}
+
+impl PortState {
+    /// nmap-style label ("open", "closed", "filtered", "open|filtered").
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PortState::Open => "open",
+            PortState::Closed => "closed",
+            PortState::Filtered => "filtered",
+            PortState::OpenFiltered => "open|filtered",
+        }
+    }
+}
+
 /// Structured port scan result for a single port.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PortResult {
     pub port: u16,
-    pub proto: &'static str,
+    /// `"tcp"` or `"udp"`
+    pub proto: Cow<'static, str>,
+    /// `state == PortState::Open`
     pub open: bool,
+    pub state: PortState,
     pub banner: Option<String>,
+    /// First `BANNER_HEX_BYTES` of the raw reply as lowercase hex, set
+    /// whenever anything was read; keeps binary greetings (MySQL, RDP,
+    /// MQTT) that `banner`'s printable-ascii rendering drops
+    pub banner_hex: Option<String>,
     pub rtt_ms: Option<u128>,
+    /// Conventional service name for the port (set only for open ports)
+    pub service_name: Option<Cow<'static, str>>,
+    /// Server certificate, when the port was TLS-probed (`ProbeStrategy::tls_ports`)
+    pub tls: Option<TlsInfo>,
+    /// Connection attempts made; above 1 when timed-out connects were
+    /// retried (`ProbeStrategy::with_retries`), which marks a flaky port
+    pub attempts: u8,
+}
+
+/// One finished port probe, passed to a scan's progress hook.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScanProgress {
+    /// Probes finished so far in this scan, including this one
+    pub done: usize,
+    /// Probes in the whole scan (all ports of all hosts)
+    pub total: usize,
+    pub ip: Ipv4Addr,
+    pub port: u16,
+    pub open: bool,
+}
+
+/// Progress hook for the TCP port scans, called once per finished probe from
+/// the scan's tasks (so possibly from several threads at once). It runs after
+/// the probe's concurrency permit is released; a panic inside it is caught
+/// and ignored, but a hook that blocks still holds up its own task.
+pub type ProgressFn = Arc<dyn Fn(ScanProgress) + Send + Sync>;
+
+/// Shared per-scan state behind a `ProgressFn`.
+struct ProgressTracker {
+    hook: ProgressFn,
+    done: AtomicUsize,
+    total: usize,
+}
+
+impl ProgressTracker {
+    fn new(hook: Option<ProgressFn>, total: usize) -> Option<Arc<Self>> {
+        hook.map(|hook| {
+            Arc::new(Self {
+                hook,
+                done: AtomicUsize::new(0),
+                total,
+            })
+        })
+    }
+
+    fn report(&self, ip: Ipv4Addr, result: &PortResult) {
+        let progress = ScanProgress {
+            done: self.done.fetch_add(1, Ordering::Relaxed) + 1,
+            total: self.total,
+            ip,
+            port: result.port,
+            open: result.open,
+        };
+        let hook = &self.hook;
+        let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| hook(progress)));
+    }
+}
+
+/// Probe one port and report it to `progress`, if any. None when the scan
+/// was cancelled before the port was tried.
+async fn probe_tcp_port_reporting(
+    ip: Ipv4Addr,
+    port: u16,
+    timeout: Duration,
+    strategy: Arc<ProbeStrategy>,
+    gate: Arc<ConnectGate>,
+    progress: Option<Arc<ProgressTracker>>,
+) -> Option<PortResult> {
+    let result = probe_tcp_port(ip, port, timeout, strategy, gate).await?;
+    if let Some(progress) = progress {
+        progress.report(ip, &result);
+    }
+    Some(result)
+}
+
+/// Output of a `_with_deadline` or `_with_cancel` call: whatever finished
+/// before the overall deadline or cancellation, and which one cut it short.
+#[derive(Debug, Clone)]
+pub struct PartialScan<T> {
+    pub results: Vec<T>,
+    pub timed_out: bool,
+    /// The scan's `CancelToken` fired before every probe had started
+    pub cancelled: bool,
+}
+
+/// Why a blocking wrapper could not run its scan.
+#[derive(Debug)]
+pub enum PortScanError {
+    /// The shared tokio runtime could not be created
+    Runtime(std::io::Error),
+    /// The helper thread driving the scan panicked
+    Panicked,
+    /// Probes could not be bound to the requested source address
+    Bind(Ipv4Addr, std::io::Error),
+}
+
+impl std::fmt::Display for PortScanError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PortScanError::Runtime(e) => write!(f, "failed to create tokio runtime: {}", e),
+            PortScanError::Panicked => write!(f, "port scan thread panicked"),
+            PortScanError::Bind(addr, e) => {
+                write!(f, "cannot bind to source address {}: {}", addr, e)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PortScanError {}
+
+/// Process-wide runtime for the blocking wrappers, built on first use.
+fn shared_runtime() -> Result<&'static tokio::runtime::Runtime, PortScanError> {
+    static RUNTIME: std::sync::OnceLock<tokio::runtime::Runtime> = std::sync::OnceLock::new();
+    if let Some(rt) = RUNTIME.get() {
+        return Ok(rt);
+    }
+    let rt = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .map_err(PortScanError::Runtime)?;
+    // A racing caller may have won; its runtime is used and ours dropped
+    let _ = RUNTIME.set(rt);
+    Ok(RUNTIME.get().expect("runtime set above"))
+}
+
+/// Run `fut` to completion from synchronous code. Outside tokio this uses
+/// the shared runtime. Inside a multi-thread runtime the current worker is
+/// handed over with `block_in_place`; inside a current-thread runtime (which
+/// cannot block in place) the future runs on the shared runtime from a
+/// helper thread. Either way callers embedded in their own tokio app do not
+/// hit "cannot start a runtime from within a runtime". Public so other
+/// crates' blocking wrappers can share the runtime.
+pub fn block_on<F>(fut: F) -> Result<F::Output, PortScanError>
+where
+    F: std::future::Future + Send,
+    F::Output: Send,
+{
+    use tokio::runtime::{Handle, RuntimeFlavor};
+    match Handle::try_current() {
+        Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread => {
+            Ok(tokio::task::block_in_place(|| handle.block_on(fut)))
+        }
+        Ok(_) => std::thread::scope(|s| {
+            s.spawn(|| shared_runtime().map(|rt| rt.block_on(fut)))
+                .join()
+                .map_err(|_| PortScanError::Panicked)?
+        }),
+        Err(_) => Ok(shared_runtime()?.block_on(fut)),
+    }
+}
+
+/// Drive `fut` (which pushes into `out` as work completes) via `block_on`,
+/// giving up after `deadline` and keeping what was collected so far.
+fn block_on_with_deadline<T, F>(deadline: Duration, f: F) -> Result<PartialScan<T>, PortScanError>
+where
+    T: Send,
+    F: for<'a> FnOnce(
+            &'a mut Vec<T>,
+        )
+            -> std::pin::Pin<Box<dyn std::future::Future<Output = ()> + Send + 'a>>
+        + Send,
+{
+    block_on(async {
+        let mut results = Vec::new();
+        let timed_out = tokio::time::timeout(deadline, f(&mut results))
+            .await
+            .is_err();
+        PartialScan {
+            results,
+            timed_out,
+            cancelled: false,
+        }
+    })
 }
 
 /// Async TCP scanner over a list of IPv4 addresses on a single port.
 /// - `timeout` is per-connection timeout
 /// - `concurrency` limits number of simultaneous connection attempts
+/// - `rate_limit` caps connect attempts per second, independent of
+///   `concurrency` (`None` for no cap)
+/// - `banner_timeout` is how long to wait for a banner after connecting
+///   (`Some(DEFAULT_BANNER_TIMEOUT)` normally); `None` skips the read and
+///   closes the connection right away
+/// - `local_addr` binds every connect to that source address (multi-homed
+///   hosts); `None` leaves the choice to the routing table. A source
+///   address that cannot be bound yields no results (see `check_local_addr`)
 pub async fn scan_tcp_async(
     ips: Vec<Ipv4Addr>,
     port: u16,
     timeout: Duration,
     concurrency: usize,
+    rate_limit: Option<u32>,
+    banner_timeout: Option<Duration>,
+    local_addr: Option<Ipv4Addr>,
 ) -> Vec<TcpProbeResult> {
-    let sem = Arc::new(Semaphore::new(concurrency.max(1)));
+    let mut out = Vec::new();
+    scan_tcp_collect(
+        ips,
+        port,
+        timeout,
+        ConnectGate::new(concurrency, rate_limit, None),
+        banner_timeout,
+        local_addr,
+        &mut out,
+    )
+    .await;
+    out
+}
+
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        name = "scan_tcp",
+        level = "debug",
+        skip(ips, timeout, gate, banner_timeout, out),
+        fields(hosts = ips.len())
+    )
+)]
+async fn scan_tcp_collect(
+    ips: Vec<Ipv4Addr>,
+    port: u16,
+    timeout: Duration,
+    gate: Arc<ConnectGate>,
+    banner_timeout: Option<Duration>,
+    local_addr: Option<Ipv4Addr>,
+    out: &mut Vec<TcpProbeResult>,
+) {
+    if !source_bindable(local_addr) {
+        return;
+    }
     let mut handles = Vec::with_capacity(ips.len());
 
     for ip in ips {
-    let sem_cloned = sem.clone();
-    let permit = sem_cloned.acquire_owned().await.unwrap();
+        let Some(permit) = gate.acquire().await else {
+            break;
+        };
         let addr = SocketAddrV4::new(ip, port);
-        let timeout = timeout.clone();
-        let h = tokio::spawn(async move {
+        let h = spawn_in_span(async move {
             // Drop permit when finished
             let _p = permit;
-            let res = tokio::time::timeout(timeout, TcpStream::connect(addr)).await;
+            let res = tokio::time::timeout(timeout, connect_from(addr, local_addr)).await;
             match res {
                 Ok(Ok(mut stream)) => {
-                    // Try to read a small banner with a short timeout
-                    let mut buf = vec![0u8; 512];
-                    let read_res = tokio::time::timeout(Duration::from_millis(300), stream.read(&mut buf)).await;
-                    let banner = match read_res {
-                        Ok(Ok(n)) if n > 0 => Some(String::from_utf8_lossy(&buf[..n]).trim().to_string()),
-                        _ => None,
+                    trace_debug!(%ip, port, "tcp connect open");
+                    let banner = match banner_timeout {
+                        Some(wait) => send_probe_and_read(stream, None, BANNER_READ_BYTES, wait)
+                            .await
+                            .map(|b| String::from_utf8_lossy(&b).trim().to_string()),
+                        None => {
+                            // Attempt to close gracefully
+                            let _ = stream.shutdown().await;
+                            None
+                        }
                     };
-                    // Attempt to close gracefully
-                    let _ = stream.shutdown().await;
                     (ip, banner)
                 }
-                _ => (ip, None),
+                Ok(Err(_e)) => {
+                    trace_trace!(%ip, port, error = %_e, "tcp connect failed");
+                    (ip, None)
+                }
+                Err(_) => {
+                    trace_trace!(%ip, port, "tcp connect timed out");
+                    (ip, None)
+                }
             }
         });
         handles.push(h);
     }
 
-    let mut out = Vec::new();
     for h in handles {
         if let Ok(item) = h.await {
             out.push(item);
         }
     }
-    out
 }
 
-/// Blocking wrapper for `scan_tcp_async` using a runtime created locally.
+/// Blocking wrapper for `scan_tcp_async`; safe to call from inside a tokio
+/// runtime (see `PortScanError` for what can fail).
 pub fn scan_tcp(
     ips: Vec<Ipv4Addr>,
     port: u16,
     timeout: Duration,
     concurrency: usize,
-) -> Vec<TcpProbeResult> {
-    let rt = tokio::runtime::Runtime::new().expect("failed to create tokio runtime");
-    rt.block_on(scan_tcp_async(ips, port, timeout, concurrency))
+    rate_limit: Option<u32>,
+    banner_timeout: Option<Duration>,
+) -> Result<Vec<TcpProbeResult>, PortScanError> {
+    let scan = scan_tcp_with_deadline(
+        ips,
+        port,
+        timeout,
+        concurrency,
+        Duration::MAX,
+        rate_limit,
+        banner_timeout,
+    )?;
+    Ok(scan.results)
+}
+
+/// Like `scan_tcp`, but gives up after `deadline` overall and returns the
+/// results gathered so far with `timed_out` set.
+pub fn scan_tcp_with_deadline(
+    ips: Vec<Ipv4Addr>,
+    port: u16,
+    timeout: Duration,
+    concurrency: usize,
+    deadline: Duration,
+    rate_limit: Option<u32>,
+    banner_timeout: Option<Duration>,
+) -> Result<PartialScan<TcpProbeResult>, PortScanError> {
+    block_on_with_deadline(deadline, |out| {
+        Box::pin(scan_tcp_collect(
+            ips,
+            port,
+            timeout,
+            ConnectGate::new(concurrency, rate_limit, None),
+            banner_timeout,
+            None,
+            out,
+        ))
+    })
 }
 
 /// Normalize a banner string: trim, keep printable ascii, collapse whitespace, limit length.
@@ -94,52 +456,350 @@ pub fn normalize_banner(s: &str) -> String {
     }
 }
 
+/// Printable ascii rendering of a UDP reply via `normalize_banner`; binary
+/// framing (DNS headers, SNMP BER lengths) drops out and the text fields
+/// remain. `None` when nothing printable is left.
+pub fn udp_banner(reply: &[u8]) -> Option<String> {
+    Some(normalize_banner(&String::from_utf8_lossy(reply))).filter(|b| !b.is_empty())
+}
+
 /// Scan multiple ports on a single host (TCP). Returns a Vec<PortResult>.
+/// Ports that stay silent get the default `ProbeStrategy` nudge (HTTP `HEAD`
+/// on web ports, `\r\n` elsewhere). `progress` is called as each port
+/// finishes (see `ProgressFn`). Each port gets a single connect attempt,
+/// unpaced, from the address the routing table picks; pass a
+/// `ProbeStrategy` to `scan_host_ports_with_strategy_async` for retries
+/// (`with_retries`), a connect rate cap (`with_rate_limit`) or a source
+/// address (`with_local_addr`). Ports are probed in the order given;
+/// `ProbeStrategy::with_order` with `ScanOrder::Random` shuffles them and
+/// returns results sorted by port.
 pub async fn scan_host_ports_async(
     ip: Ipv4Addr,
     ports: Vec<u16>,
     timeout: Duration,
     concurrency: usize,
+    progress: Option<ProgressFn>,
+) -> Vec<PortResult> {
+    scan_host_ports_with_strategy_async(
+        ip,
+        ports,
+        timeout,
+        concurrency,
+        ProbeStrategy::default(),
+        progress,
+    )
+    .await
+}
+
+/// `scan_host_ports_async` with an explicit probe table and scan options.
+pub async fn scan_host_ports_with_strategy_async(
+    ip: Ipv4Addr,
+    ports: Vec<u16>,
+    timeout: Duration,
+    concurrency: usize,
+    strategy: ProbeStrategy,
+    progress: Option<ProgressFn>,
 ) -> Vec<PortResult> {
+    let mut out = Vec::new();
+    scan_host_ports_collect(
+        ip,
+        ports,
+        timeout,
+        ConnectGate::new(concurrency, strategy.rate_limit, None),
+        Arc::new(strategy),
+        progress,
+        &mut out,
+    )
+    .await;
+    out
+}
+
+/// `scan_host_ports_with_strategy_async` that stops starting probes once
+/// `cancel` fires. Probes already connecting finish (bounded by `timeout`)
+/// and are kept; ports never tried are left out. `cancelled` is set when the
+/// token fired before every port had been tried.
+pub async fn scan_host_ports_with_cancel_async(
+    ip: Ipv4Addr,
+    ports: Vec<u16>,
+    timeout: Duration,
+    concurrency: usize,
+    strategy: ProbeStrategy,
+    progress: Option<ProgressFn>,
+    cancel: CancelToken,
+) -> PartialScan<PortResult> {
+    let total = ports.iter().filter(|p| !strategy.excludes(**p)).count();
+    let mut results = Vec::new();
+    scan_host_ports_collect(
+        ip,
+        ports,
+        timeout,
+        ConnectGate::new(concurrency, strategy.rate_limit, Some(cancel.clone())),
+        Arc::new(strategy),
+        progress,
+        &mut results,
+    )
+    .await;
+    PartialScan {
+        cancelled: cancel.is_cancelled() && results.len() < total,
+        results,
+        timed_out: false,
+    }
+}
+
+/// Blocking wrapper for `scan_host_ports_with_cancel_async`.
+pub fn scan_host_ports_with_cancel(
+    ip: Ipv4Addr,
+    ports: Vec<u16>,
+    timeout: Duration,
+    concurrency: usize,
+    strategy: ProbeStrategy,
+    progress: Option<ProgressFn>,
+    cancel: CancelToken,
+) -> Result<PartialScan<PortResult>, PortScanError> {
+    block_on(scan_host_ports_with_cancel_async(
+        ip,
+        ports,
+        timeout,
+        concurrency,
+        strategy,
+        progress,
+        cancel,
+    ))
+}
+
+/// Bytes kept from a banner read by the port scanners.
+const BANNER_READ_BYTES: usize = 512;
+
+/// Raw reply bytes kept in `PortResult.banner_hex`.
+pub const BANNER_HEX_BYTES: usize = 64;
+
+/// Lowercase hex of the first `BANNER_HEX_BYTES` of `raw`.
+fn banner_hex(raw: &[u8]) -> String {
+    raw.iter()
+        .take(BANNER_HEX_BYTES)
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// Send `probe` first when given (for request-driven protocols such as
+/// HTTP), then wait up to `timeout` for the peer's reply and return it raw,
+/// at most `max_bytes` of it. Data already queued behind the first chunk is
+/// taken too, so a banner split over several segments comes back whole.
+/// None when the write fails or the peer closes or stays silent. The
+/// connection is shut down before returning.
+pub async fn send_probe_and_read(
+    mut stream: TcpStream,
+    probe: Option<&[u8]>,
+    max_bytes: usize,
+    timeout: Duration,
+) -> Option<Vec<u8>> {
+    let reply = exchange(&mut stream, probe, max_bytes, timeout).await;
+    let _ = stream.shutdown().await;
+    reply
+}
+
+/// `send_probe_and_read` on a borrowed stream, which stays open.
+async fn exchange(
+    stream: &mut TcpStream,
+    probe: Option<&[u8]>,
+    max_bytes: usize,
+    timeout: Duration,
+) -> Option<Vec<u8>> {
+    if let Some(probe) = probe {
+        stream.write_all(probe).await.ok()?;
+    }
+    let mut buf = vec![0u8; max_bytes];
+    let mut n = match tokio::time::timeout(timeout, stream.read(&mut buf)).await {
+        Ok(Ok(n)) if n > 0 => n,
+        _ => return None,
+    };
+    // Drain what has already arrived without waiting for more
+    while n < max_bytes {
+        match stream.try_read(&mut buf[n..]) {
+            Ok(more) if more > 0 => n += more,
+            _ => break,
+        }
+    }
+    buf.truncate(n);
+    Some(buf)
+}
+
+/// First non-empty line of a probe reply, normalized.
+fn first_line(reply: &str) -> Option<String> {
+    reply.lines().map(normalize_banner).find(|l| !l.is_empty())
+}
+
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        name = "scan_host_ports",
+        level = "debug",
+        skip(ports, timeout, gate, strategy, progress, out),
+        fields(ports = ports.len())
+    )
+)]
+async fn scan_host_ports_collect(
+    ip: Ipv4Addr,
+    ports: Vec<u16>,
+    timeout: Duration,
+    gate: Arc<ConnectGate>,
+    strategy: Arc<ProbeStrategy>,
+    progress: Option<ProgressFn>,
+    out: &mut Vec<PortResult>,
+) {
+    if !source_bindable(strategy.local_addr) {
+        return;
+    }
+    let mut ports = strategy.without_excluded(ports);
+    if strategy.order.is_random() {
+        ports.sort_unstable();
+    }
+    let progress = ProgressTracker::new(progress, ports.len());
+    let mut dispatch: Vec<usize> = (0..ports.len()).collect();
+    strategy.order.arrange(&mut dispatch);
+    let mut handles: Vec<_> = ports.iter().map(|_| None).collect();
+    for i in dispatch {
+        let fut = probe_tcp_port_reporting(
+            ip,
+            ports[i],
+            timeout,
+            strategy.clone(),
+            gate.clone(),
+            progress.clone(),
+        );
+        handles[i] = Some(spawn_in_span(fut));
+    }
+    // Collected in port-list order whatever order they were started in
+    for h in handles.into_iter().flatten() {
+        if let Ok(Some(item)) = h.await {
+            out.push(item);
+        }
+    }
+}
+
+/// Connect to one TCP port (holding a `gate` permit while connected) and
+/// read its banner, nudging silent ports per `strategy`. None when the gate
+/// was cancelled before the first attempt; cancelled retries keep the
+/// timeout already seen.
+async fn probe_tcp_port(
+    ip: Ipv4Addr,
+    port: u16,
+    timeout: Duration,
+    strategy: Arc<ProbeStrategy>,
+    gate: Arc<ConnectGate>,
+) -> Option<PortResult> {
     use tokio::time::Instant;
-    let sem = Arc::new(Semaphore::new(concurrency.max(1)));
-    let mut handles = Vec::with_capacity(ports.len());
-    for port in ports {
-        let sem_cloned = sem.clone();
-        let timeout = timeout.clone();
-        let handle = tokio::spawn(async move {
-            let permit = sem_cloned.acquire_owned().await.unwrap();
-            let addr = SocketAddrV4::new(ip, port);
-            let start = Instant::now();
-            let res = tokio::time::timeout(timeout, TcpStream::connect(addr)).await;
-            let rtt = start.elapsed().as_millis();
-            match res {
-                Ok(Ok(mut stream)) => {
-                    let mut buf = vec![0u8; 512];
-                    let read_res = tokio::time::timeout(Duration::from_millis(300), stream.read(&mut buf)).await;
-                    let banner = match read_res {
-                        Ok(Ok(n)) if n > 0 => Some(normalize_banner(&String::from_utf8_lossy(&buf[..n]))),
-                        _ => None,
-                    };
-                    let _ = stream.shutdown().await;
-                    drop(permit);
-                    PortResult { port, proto: "tcp", open: true, banner, rtt_ms: Some(rtt) }
+    let addr = SocketAddrV4::new(ip, port);
+    let mut attempts = 0u8;
+    let (permit, res, rtt) = loop {
+        attempts += 1;
+        let Some(permit) = gate.acquire().await else {
+            return (attempts > 1).then(|| unanswered(port, PortState::Filtered, attempts - 1));
+        };
+        let start = Instant::now();
+        let res = tokio::time::timeout(timeout, connect_from(addr, strategy.local_addr)).await;
+        let rtt = start.elapsed().as_millis();
+        if res.is_ok() || attempts > strategy.retries {
+            break (permit, res, rtt);
+        }
+        // Give the budget back while waiting, so other ports are not held up
+        drop(permit);
+        trace_trace!(%ip, port, attempts, "tcp connect timed out; retrying");
+        tokio::time::sleep(retry_delay()).await;
+    };
+    match res {
+        Ok(Ok(mut stream)) => {
+            let tls_port = strategy.tls_ports.contains(&port);
+            let wait = strategy.banner_timeout;
+            let mut raw = None;
+            if strategy.grab_banners {
+                raw = exchange(&mut stream, None, BANNER_READ_BYTES, wait).await;
+            }
+            let mut banner = raw
+                .as_deref()
+                .map(|b| normalize_banner(&String::from_utf8_lossy(b)));
+            // A plaintext nudge only provokes a TLS alert; the certificate says more
+            if banner.is_none() && !tls_port && strategy.grab_banners {
+                if let Some(payload) = strategy.payload_for(port) {
+                    raw = exchange(&mut stream, Some(payload), BANNER_READ_BYTES, wait).await;
+                    banner = raw
+                        .as_deref()
+                        .and_then(|r| first_line(&String::from_utf8_lossy(r)));
+                    trace_trace!(%ip, port, found = banner.is_some(), "banner probe");
+                }
+            }
+            let _ = stream.shutdown().await;
+            let tls = if tls_port {
+                tls::probe_tls_from_async(ip, port, timeout, strategy.local_addr).await
+            } else {
+                None
+            };
+            drop(permit);
+            trace_debug!(%ip, port, rtt_ms = rtt as u64, tls = tls.is_some(), "tcp connect open");
+            Some(PortResult {
+                port,
+                proto: "tcp".into(),
+                open: true,
+                state: PortState::Open,
+                banner,
+                banner_hex: raw.as_deref().map(banner_hex),
+                rtt_ms: Some(rtt),
+                service_name: port_to_service(port, "tcp").map(Into::into),
+                tls,
+                attempts,
+            })
+        }
+        other => {
+            drop(permit);
+            let state = match &other {
+                Ok(Err(_e)) => {
+                    trace_trace!(%ip, port, error = %_e, "tcp connect failed");
+                    PortState::Closed
                 }
                 _ => {
-                    drop(permit);
-                    PortResult { port, proto: "tcp", open: false, banner: None, rtt_ms: None }
+                    trace_trace!(%ip, port, "tcp connect timed out");
+                    PortState::Filtered
                 }
-            }
-        });
-        handles.push(handle);
+            };
+            Some(unanswered(port, state, attempts))
+        }
     }
-    let mut out = Vec::new();
-    for h in handles {
-        if let Ok(item) = h.await {
-            out.push(item);
+}
+
+/// Result for a TCP port whose connect did not succeed.
+fn unanswered(port: u16, state: PortState, attempts: u8) -> PortResult {
+    PortResult {
+        port,
+        proto: "tcp".into(),
+        open: false,
+        state,
+        banner: None,
+        banner_hex: None,
+        rtt_ms: None,
+        service_name: None,
+        tls: None,
+        attempts,
+    }
+}
+
+/// False (and traced) when `local_addr` is set but cannot be bound, in
+/// which case the scans return nothing rather than report every port
+/// closed.
+fn source_bindable(local_addr: Option<Ipv4Addr>) -> bool {
+    match local_addr.map(check_local_addr) {
+        Some(Err(_e)) => {
+            trace_debug!(error = %_e, "source address unusable; nothing probed");
+            false
         }
+        _ => true,
     }
-    out
+}
+
+/// Pause before a connect retry: 50-150 ms, jittered so retries from many
+/// ports do not land at once.
+fn retry_delay() -> Duration {
+    use rand::Rng;
+    Duration::from_millis(rand::thread_rng().gen_range(50..=150))
 }
 
 /// Blocking wrapper for scan_host_ports_async.
@@ -148,23 +808,317 @@ pub fn scan_host_ports(
     ports: Vec<u16>,
     timeout: Duration,
     concurrency: usize,
-) -> Vec<PortResult> {
-    let rt = tokio::runtime::Runtime::new().expect("failed to create tokio runtime");
-    rt.block_on(scan_host_ports_async(ip, ports, timeout, concurrency))
+    progress: Option<ProgressFn>,
+) -> Result<Vec<PortResult>, PortScanError> {
+    scan_host_ports_with_strategy(
+        ip,
+        ports,
+        timeout,
+        concurrency,
+        ProbeStrategy::default(),
+        progress,
+    )
 }
 
-/// UDP probe: send an empty datagram and wait for a response for `timeout`.
+/// Blocking wrapper for `scan_host_ports_with_strategy_async`; set the
+/// source address, rate cap and retries on `strategy`.
+pub fn scan_host_ports_with_strategy(
+    ip: Ipv4Addr,
+    ports: Vec<u16>,
+    timeout: Duration,
+    concurrency: usize,
+    strategy: ProbeStrategy,
+    progress: Option<ProgressFn>,
+) -> Result<Vec<PortResult>, PortScanError> {
+    Ok(scan_host_ports_with_deadline(
+        ip,
+        ports,
+        timeout,
+        concurrency,
+        strategy,
+        Duration::MAX,
+        progress,
+    )?
+    .results)
+}
+
+/// Like `scan_host_ports_with_strategy`, but gives up after `deadline`
+/// overall and returns the port results gathered so far with `timed_out` set.
+pub fn scan_host_ports_with_deadline(
+    ip: Ipv4Addr,
+    ports: Vec<u16>,
+    timeout: Duration,
+    concurrency: usize,
+    strategy: ProbeStrategy,
+    deadline: Duration,
+    progress: Option<ProgressFn>,
+) -> Result<PartialScan<PortResult>, PortScanError> {
+    block_on_with_deadline(deadline, |out| {
+        Box::pin(scan_host_ports_collect(
+            ip,
+            ports,
+            timeout,
+            ConnectGate::new(concurrency, strategy.rate_limit, None),
+            Arc::new(strategy),
+            progress,
+            out,
+        ))
+    })
+}
+
+/// Port results for one host of a `scan_many_async` call.
+pub type HostPortResults = (Ipv4Addr, Vec<PortResult>);
+
+/// Every port result for one host, closed and filtered ports included, for
+/// raw port-level output (`io::write_port_report_json`).
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HostPortReport {
+    pub ip: Ipv4Addr,
+    pub results: Vec<PortResult>,
+    /// Seconds since the Unix epoch when the report was made
+    pub scanned_at: u64,
+}
+
+impl HostPortReport {
+    /// Report for `ip`, stamped with the current time.
+    pub fn new(ip: Ipv4Addr, results: Vec<PortResult>) -> Self {
+        let scanned_at = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        Self {
+            ip,
+            results,
+            scanned_at,
+        }
+    }
+}
+
+impl From<HostPortResults> for HostPortReport {
+    fn from((ip, results): HostPortResults) -> Self {
+        Self::new(ip, results)
+    }
+}
+
+/// Scan several hosts' TCP ports under one concurrency budget: at most
+/// `total_concurrency` connections are in flight across all hosts. Probes
+/// are issued round-robin over the hosts (each host's first port, then each
+/// host's second port, ...) so no single host takes the whole budget.
+/// Returns one entry per target, in target order, with ports in the order
+/// given (sorted by port under `ScanOrder::Random`, which also starts the
+/// probes of all hosts in one shuffled sequence).
+/// `progress` is called as each probe finishes, counting over all hosts.
+pub async fn scan_many_async(
+    targets: Vec<(Ipv4Addr, Vec<u16>)>,
+    timeout: Duration,
+    total_concurrency: usize,
+    progress: Option<ProgressFn>,
+) -> Vec<HostPortResults> {
+    scan_many_with_strategy_async(
+        targets,
+        timeout,
+        total_concurrency,
+        ProbeStrategy::default(),
+        progress,
+    )
+    .await
+}
+
+/// `scan_many_async` with an explicit probe table and scan options; a
+/// `ProbeStrategy::rate_limit` applies across all hosts.
+pub async fn scan_many_with_strategy_async(
+    targets: Vec<(Ipv4Addr, Vec<u16>)>,
+    timeout: Duration,
+    total_concurrency: usize,
+    strategy: ProbeStrategy,
+    progress: Option<ProgressFn>,
+) -> Vec<HostPortResults> {
+    scan_many_collect(
+        targets,
+        timeout,
+        ConnectGate::new(total_concurrency, strategy.rate_limit, None),
+        strategy,
+        progress,
+        |_| {},
+    )
+    .await
+}
+
+fn without_excluded(
+    targets: Vec<(Ipv4Addr, Vec<u16>)>,
+    strategy: &ProbeStrategy,
+) -> Vec<(Ipv4Addr, Vec<u16>)> {
+    targets
+        .into_iter()
+        .map(|(ip, ports)| (ip, strategy.without_excluded(ports)))
+        .collect()
+}
+
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        name = "scan_many",
+        level = "debug",
+        skip_all,
+        fields(hosts = targets.len())
+    )
+)]
+async fn scan_many_collect<F>(
+    targets: Vec<(Ipv4Addr, Vec<u16>)>,
+    timeout: Duration,
+    gate: Arc<ConnectGate>,
+    strategy: ProbeStrategy,
+    progress: Option<ProgressFn>,
+    mut on_host_done: F,
+) -> Vec<HostPortResults>
+where
+    F: FnMut(&HostPortResults),
+{
+    let mut targets = without_excluded(targets, &strategy);
+    if strategy.order.is_random() {
+        for (_, ports) in &mut targets {
+            ports.sort_unstable();
+        }
+    }
+    let strategy = Arc::new(strategy);
+    let total = targets.iter().map(|(_, p)| p.len()).sum();
+    let progress = ProgressTracker::new(progress, total);
+    let rounds = targets.iter().map(|(_, p)| p.len()).max().unwrap_or(0);
+    let mut dispatch: Vec<(usize, usize)> = (0..rounds)
+        .flat_map(|i| (0..targets.len()).map(move |host| (host, i)))
+        .filter(|&(host, i)| i < targets[host].1.len())
+        .collect();
+    strategy.order.arrange(&mut dispatch);
+    // The semaphore is fair, so spawn order is roughly connect order
+    let mut tasks = tokio::task::JoinSet::new();
+    for (host, i) in dispatch {
+        let fut = probe_tcp_port_reporting(
+            targets[host].0,
+            targets[host].1[i],
+            timeout,
+            strategy.clone(),
+            gate.clone(),
+            progress.clone(),
+        );
+        spawn_in_set(&mut tasks, async move { (host, i, fut.await) });
+    }
+
+    let mut slots: Vec<Vec<Option<PortResult>>> =
+        targets.iter().map(|(_, p)| vec![None; p.len()]).collect();
+    let mut pending: Vec<usize> = targets.iter().map(|(_, p)| p.len()).collect();
+    let mut out: Vec<HostPortResults> = targets.iter().map(|(ip, _)| (*ip, Vec::new())).collect();
+    let mut finish = |host: usize, slots: &mut Vec<Option<PortResult>>| {
+        out[host].1 = slots.drain(..).flatten().collect();
+        on_host_done(&out[host]);
+    };
+    // Hosts with nothing to scan are done straight away
+    for host in (0..targets.len()).filter(|&h| pending[h] == 0) {
+        finish(host, &mut slots[host]);
+    }
+    while let Some(joined) = tasks.join_next().await {
+        // A panicked or cancelled probe leaves its slot empty but still
+        // counts as finished
+        let Ok((host, i, result)) = joined else {
+            continue;
+        };
+        slots[host][i] = result;
+        pending[host] -= 1;
+        if pending[host] == 0 {
+            finish(host, &mut slots[host]);
+        }
+    }
+    out
+}
+
+/// Blocking wrapper for `scan_many_with_strategy_async`. `on_host_done` is
+/// called with each host's results as its last port finishes, in completion
+/// order; `progress` once per port probe.
+pub fn scan_many_with_strategy<F>(
+    targets: Vec<(Ipv4Addr, Vec<u16>)>,
+    timeout: Duration,
+    total_concurrency: usize,
+    strategy: ProbeStrategy,
+    progress: Option<ProgressFn>,
+    on_host_done: F,
+) -> Result<Vec<HostPortResults>, PortScanError>
+where
+    F: FnMut(&HostPortResults) + Send,
+{
+    block_on(scan_many_collect(
+        targets,
+        timeout,
+        ConnectGate::new(total_concurrency, strategy.rate_limit, None),
+        strategy,
+        progress,
+        on_host_done,
+    ))
+}
+
+/// `scan_many_with_strategy` that stops starting probes once `cancel` fires.
+/// Probes already connecting finish (bounded by `timeout`); ports never
+/// tried are missing from their host's results, so a host cut off before
+/// any probe comes back with an empty list. `cancelled` is set when the
+/// token fired before every probe had started.
+pub fn scan_many_with_cancel<F>(
+    targets: Vec<(Ipv4Addr, Vec<u16>)>,
+    timeout: Duration,
+    total_concurrency: usize,
+    strategy: ProbeStrategy,
+    cancel: CancelToken,
+    on_host_done: F,
+) -> Result<PartialScan<HostPortResults>, PortScanError>
+where
+    F: FnMut(&HostPortResults) + Send,
+{
+    let total: usize = targets
+        .iter()
+        .flat_map(|(_, ports)| ports)
+        .filter(|port| !strategy.excludes(**port))
+        .count();
+    let gate = ConnectGate::new(total_concurrency, strategy.rate_limit, Some(cancel.clone()));
+    let results = block_on(scan_many_collect(
+        targets,
+        timeout,
+        gate,
+        strategy,
+        None,
+        on_host_done,
+    ))?;
+    let finished: usize = results.iter().map(|(_, r)| r.len()).sum();
+    Ok(PartialScan {
+        results,
+        timed_out: false,
+        cancelled: cancel.is_cancelled() && finished < total,
+    })
+}
+
+/// UDP probe: send the port's `udp_probe_payload` (an empty datagram for
+/// ports without one) and wait for a response for `timeout`. The socket is
+/// bound to `local_addr` when given (no reply if that fails).
 /// Returns (ip, Option<Vec<u8>>) where Vec<u8> is any response bytes received.
 pub async fn probe_udp_async(
     ip: Ipv4Addr,
     port: u16,
     timeout: Duration,
+    local_addr: Option<Ipv4Addr>,
+) -> (Ipv4Addr, Option<Vec<u8>>) {
+    probe_udp_with_payload_async(ip, port, udp_payload_for(port), timeout, local_addr).await
+}
+
+/// `probe_udp_async` sending `payload` instead of the builtin one; pass
+/// `&[]` to force an empty datagram.
+pub async fn probe_udp_with_payload_async(
+    ip: Ipv4Addr,
+    port: u16,
+    payload: &[u8],
+    timeout: Duration,
+    local_addr: Option<Ipv4Addr>,
 ) -> (Ipv4Addr, Option<Vec<u8>>) {
-    // Bind to ephemeral address on local system
-    match UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await {
+    // Ephemeral port on the source address (any address by default)
+    match bind_udp(local_addr).await {
         Ok(socket) => {
             let target = SocketAddrV4::new(ip, port);
-            let _ = socket.send_to(&[], target).await;
+            let _ = socket.send_to(payload, target).await;
             let mut buf = vec![0u8; 1500];
             let res = tokio::time::timeout(timeout, socket.recv_from(&mut buf)).await;
             match res {
@@ -172,26 +1126,319 @@ pub async fn probe_udp_async(
                 _ => (ip, None),
             }
         }
-        Err(_) => (ip, None),
+        Err(_e) => {
+            trace_debug!(%ip, port, error = %_e, "udp bind failed");
+            (ip, None)
+        }
     }
 }
 
+/// `probe_udp_async` that also returns the reply as a normalized banner.
+pub async fn probe_udp_banner_async(ip: Ipv4Addr, port: u16, timeout: Duration) -> UdpProbeReply {
+    probe_udp_async(ip, port, timeout, None).await.into()
+}
+
+/// Blocking wrapper for `probe_udp_banner_async`.
+pub fn probe_udp_banner(
+    ip: Ipv4Addr,
+    port: u16,
+    timeout: Duration,
+) -> Result<UdpProbeReply, PortScanError> {
+    probe_udp(ip, port, timeout).map(UdpProbeReply::from)
+}
+
 /// Blocking wrapper for UDP probe.
-pub fn probe_udp(ip: Ipv4Addr, port: u16, timeout: Duration) -> (Ipv4Addr, Option<Vec<u8>>) {
-    let rt = tokio::runtime::Runtime::new().expect("failed to create tokio runtime");
-    rt.block_on(probe_udp_async(ip, port, timeout))
+pub fn probe_udp(
+    ip: Ipv4Addr,
+    port: u16,
+    timeout: Duration,
+) -> Result<UdpProbeResult, PortScanError> {
+    Ok(probe_udp_with_deadline(ip, port, timeout, Duration::MAX)?
+        .results
+        .pop()
+        .unwrap_or((ip, None)))
+}
+
+/// Like `probe_udp`, but gives up after `deadline`; `results` holds the single
+/// probe outcome, or is empty when the deadline was hit.
+pub fn probe_udp_with_deadline(
+    ip: Ipv4Addr,
+    port: u16,
+    timeout: Duration,
+    deadline: Duration,
+) -> Result<PartialScan<UdpProbeResult>, PortScanError> {
+    block_on_with_deadline(deadline, |out| {
+        Box::pin(async move { out.push(probe_udp_async(ip, port, timeout, None).await) })
+    })
+}
+
+/// Scan UDP ports on a single host. Each port gets its `udp_probe_payload`
+/// (DNS, NTP, NetBIOS, SNMP, SSDP, mDNS) or an empty datagram. A reply marks the port `Open` with
+/// the normalized reply as banner; ICMP port unreachable marks it `Closed`;
+/// silence within `timeout` leaves it `OpenFiltered` (with `open == false`).
+pub async fn scan_host_udp_ports_async(
+    ip: Ipv4Addr,
+    ports: Vec<u16>,
+    timeout: Duration,
+    concurrency: usize,
+) -> Vec<PortResult> {
+    let mut out = Vec::new();
+    scan_host_udp_ports_collect(
+        ip,
+        ports,
+        timeout,
+        concurrency,
+        default_udp_payloads(),
+        &mut out,
+    )
+    .await;
+    out
+}
+
+/// Like `scan_host_udp_ports_async`, with per-port payloads supplied by
+/// `payload_for` (e.g. a custom DNS query for 53, or a protocol the builtin
+/// `udp_probe_payload` table doesn't know).
+pub async fn scan_host_ports_udp_async(
+    ip: Ipv4Addr,
+    ports: Vec<u16>,
+    payload_for: impl Fn(u16) -> Vec<u8> + Send + Sync + 'static,
+    timeout: Duration,
+    concurrency: usize,
+) -> Vec<PortResult> {
+    let mut out = Vec::new();
+    scan_host_udp_ports_collect(
+        ip,
+        ports,
+        timeout,
+        concurrency,
+        Arc::new(payload_for),
+        &mut out,
+    )
+    .await;
+    out
+}
+
+type UdpPayloadFn = Arc<dyn Fn(u16) -> Vec<u8> + Send + Sync>;
+
+fn default_udp_payloads() -> UdpPayloadFn {
+    Arc::new(|port| udp_payload_for(port).to_vec())
+}
+
+async fn udp_port_state(
+    ip: Ipv4Addr,
+    port: u16,
+    payload: &[u8],
+    timeout: Duration,
+) -> (PortState, Option<Vec<u8>>, Option<u128>) {
+    use tokio::time::Instant;
+    let Ok(socket) = bind_udp(None).await else {
+        return (PortState::OpenFiltered, None, None);
+    };
+    // A connected socket surfaces ICMP port unreachable as ConnectionRefused.
+    if socket.connect(SocketAddrV4::new(ip, port)).await.is_err() {
+        return (PortState::OpenFiltered, None, None);
+    }
+    let start = Instant::now();
+    if socket.send(payload).await.is_err() {
+        return (PortState::OpenFiltered, None, None);
+    }
+    let mut buf = vec![0u8; 1500];
+    match tokio::time::timeout(timeout, socket.recv(&mut buf)).await {
+        Ok(Ok(n)) => {
+            buf.truncate(n);
+            (
+                PortState::Open,
+                Some(buf),
+                Some(start.elapsed().as_millis()),
+            )
+        }
+        Ok(Err(e)) if e.kind() == std::io::ErrorKind::ConnectionRefused => {
+            (PortState::Closed, None, None)
+        }
+        _ => (PortState::OpenFiltered, None, None),
+    }
+}
+
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        name = "scan_host_udp_ports",
+        level = "debug",
+        skip(ports, timeout, payload_for, out),
+        fields(ports = ports.len())
+    )
+)]
+async fn scan_host_udp_ports_collect(
+    ip: Ipv4Addr,
+    ports: Vec<u16>,
+    timeout: Duration,
+    concurrency: usize,
+    payload_for: UdpPayloadFn,
+    out: &mut Vec<PortResult>,
+) {
+    // Privileged runs also watch for ICMP port unreachables the connected
+    // sockets miss; unprivileged ones rely on ConnectionRefused alone.
+    let icmp = match IcmpUnreachableListener::open() {
+        Ok(listener) => Some(listener),
+        Err(_e) => {
+            trace_debug!(error = %_e, "icmp listener unavailable; udp closed ports from socket errors only");
+            None
+        }
+    };
+    let sem = Arc::new(Semaphore::new(concurrency.max(1)));
+    let mut handles = Vec::with_capacity(ports.len());
+    for port in ports {
+        let sem_cloned = sem.clone();
+        let payload_for = payload_for.clone();
+        handles.push(spawn_in_span(async move {
+            let _permit = sem_cloned.acquire_owned().await.unwrap();
+            let payload = payload_for(port);
+            let (state, reply, rtt_ms) = udp_port_state(ip, port, &payload, timeout).await;
+            trace_debug!(%ip, port, state = state.as_str(), "udp probe");
+            let open = state == PortState::Open;
+            PortResult {
+                port,
+                proto: "udp".into(),
+                open,
+                state,
+                banner: reply.as_deref().and_then(udp_banner),
+                banner_hex: reply.as_deref().filter(|r| !r.is_empty()).map(banner_hex),
+                rtt_ms,
+                service_name: if open {
+                    port_to_service(port, "udp").map(Into::into)
+                } else {
+                    None
+                },
+                tls: None,
+                attempts: 1,
+            }
+        }));
+    }
+    let start = out.len();
+    for h in handles {
+        if let Ok(item) = h.await {
+            out.push(item);
+        }
+    }
+    if let Some(listener) = icmp {
+        let unreachable = tokio::task::spawn_blocking(move || listener.finish())
+            .await
+            .unwrap_or_default();
+        for r in &mut out[start..] {
+            if r.state == PortState::OpenFiltered && unreachable.contains(&(ip, r.port)) {
+                r.state = PortState::Closed;
+            }
+        }
+    }
+}
+
+/// Blocking wrapper for `scan_host_udp_ports_async`.
+pub fn scan_host_udp_ports(
+    ip: Ipv4Addr,
+    ports: Vec<u16>,
+    timeout: Duration,
+    concurrency: usize,
+) -> Result<Vec<PortResult>, PortScanError> {
+    Ok(block_on_with_deadline(Duration::MAX, |out| {
+        Box::pin(scan_host_udp_ports_collect(
+            ip,
+            ports,
+            timeout,
+            concurrency,
+            default_udp_payloads(),
+            out,
+        ))
+    })?
+    .results)
+}
+
+/// Largest reply read by `probe_service`.
+const MAX_PROBE_REPLY: usize = 8 * 1024;
+
+/// Connect to `ip:port`, run the probe chosen for the port (HTTP GET, TLS
+/// ClientHello, DNS `version.bind`, ...) and return a normalized banner.
+/// Unknown ports fall back to a passive read. `timeout` bounds the connect
+/// and, separately, waiting for the reply.
+pub async fn probe_service_async(ip: Ipv4Addr, port: u16, timeout: Duration) -> Option<String> {
+    probe_with_async(ip, port, ServiceProbe::for_port(port), timeout).await
+}
+
+/// Like `probe_service_async` with an explicit probe, e.g. HTTP on an odd port.
+pub async fn probe_with_async(
+    ip: Ipv4Addr,
+    port: u16,
+    probe: ServiceProbe,
+    timeout: Duration,
+) -> Option<String> {
+    let addr = SocketAddrV4::new(ip, port);
+    let mut stream = tokio::time::timeout(timeout, TcpStream::connect(addr))
+        .await
+        .ok()?
+        .ok()?;
+    // passive reads keep the short wait used by the port scanners
+    let read_timeout = match probe.payload(ip) {
+        Some(payload) => {
+            stream.write_all(&payload).await.ok()?;
+            timeout
+        }
+        None => Duration::from_millis(300),
+    };
+
+    let mut reply = Vec::new();
+    let mut buf = [0u8; 1024];
+    let banner = tokio::time::timeout(read_timeout, async {
+        loop {
+            match stream.read(&mut buf).await {
+                Ok(n) if n > 0 => {
+                    reply.extend_from_slice(&buf[..n]);
+                    if let Some(b) = probe.interpret(&reply) {
+                        return Some(b);
+                    }
+                    if reply.len() >= MAX_PROBE_REPLY {
+                        return None;
+                    }
+                }
+                _ => return None,
+            }
+        }
+    })
+    .await
+    .ok()
+    .flatten();
+    let _ = stream.shutdown().await;
+    trace_debug!(%ip, port, ?probe, found = banner.is_some(), "service probe");
+    banner
+        .map(|b| normalize_banner(&b))
+        .filter(|b| !b.is_empty())
+}
+
+/// Blocking wrapper for `probe_service_async`.
+pub fn probe_service(
+    ip: Ipv4Addr,
+    port: u16,
+    timeout: Duration,
+) -> Result<Option<String>, PortScanError> {
+    block_on(probe_service_async(ip, port, timeout))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::net::{Ipv4Addr, TcpListener};
-    use std::time::Duration;
     use std::thread;
+    use std::time::Duration;
 
     #[test]
     fn scan_tcp_empty_ips_returns_empty() {
-        let res = scan_tcp(vec![], 80, Duration::from_secs(1), 10);
+        let res = scan_tcp(
+            vec![],
+            80,
+            Duration::from_secs(1),
+            10,
+            None,
+            Some(DEFAULT_BANNER_TIMEOUT),
+        )
+        .expect("runtime");
         assert!(res.is_empty());
     }
 
@@ -210,8 +1457,977 @@ mod tests {
         });
 
         let ips = vec![addr.ip().to_string().parse().unwrap()];
-        let res = scan_tcp(ips, addr.port(), Duration::from_secs(2), 2);
+        let res = scan_tcp(
+            ips,
+            addr.port(),
+            Duration::from_secs(2),
+            2,
+            None,
+            Some(DEFAULT_BANNER_TIMEOUT),
+        )
+        .expect("runtime");
         assert_eq!(res.len(), 1);
         assert_eq!(res[0].1.as_deref(), Some("HELLO"));
     }
+
+    /// Listener that answers every connection with `LATE` after 500 ms.
+    fn late_banner_listener() -> std::net::SocketAddr {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).expect("bind");
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            for mut s in listener.incoming().flatten() {
+                thread::spawn(move || {
+                    use std::io::Write;
+                    thread::sleep(Duration::from_millis(500));
+                    let _ = s.write_all(b"LATE\n");
+                    thread::sleep(Duration::from_millis(300));
+                });
+            }
+        });
+        addr
+    }
+
+    #[test]
+    fn banner_window_decides_whether_a_late_banner_is_seen() {
+        let addr = late_banner_listener();
+        let scan = |wait| {
+            scan_tcp(
+                vec![Ipv4Addr::LOCALHOST],
+                addr.port(),
+                Duration::from_secs(2),
+                1,
+                None,
+                wait,
+            )
+            .expect("runtime")
+        };
+        assert_eq!(
+            scan(Some(Duration::from_secs(1)))[0].1.as_deref(),
+            Some("LATE")
+        );
+        assert_eq!(scan(Some(DEFAULT_BANNER_TIMEOUT))[0].1, None);
+        assert_eq!(scan(None)[0].1, None);
+
+        let host_scan = |strategy| {
+            scan_host_ports_with_strategy(
+                Ipv4Addr::LOCALHOST,
+                vec![addr.port()],
+                Duration::from_secs(2),
+                1,
+                strategy,
+                None,
+            )
+            .expect("runtime")
+        };
+        let slow = ProbeStrategy::passive().with_banner_timeout(Duration::from_secs(1));
+        assert_eq!(host_scan(slow)[0].banner.as_deref(), Some("LATE"));
+        assert_eq!(host_scan(ProbeStrategy::passive())[0].banner, None);
+
+        // No banner grab: open is reported without waiting for anything
+        let start = std::time::Instant::now();
+        let bare = host_scan(ProbeStrategy::default().with_banner_grab(false));
+        assert!(bare[0].open && bare[0].banner.is_none());
+        assert!(start.elapsed() < Duration::from_millis(300));
+    }
+
+    #[test]
+    fn excluded_ports_are_never_probed() {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).expect("bind");
+        let port = listener.local_addr().unwrap().port();
+        let strategy = ProbeStrategy::passive().with_excluded_ports(vec![port]);
+        let res = scan_host_ports_with_strategy(
+            Ipv4Addr::LOCALHOST,
+            vec![port, port],
+            Duration::from_secs(1),
+            2,
+            strategy.clone(),
+            None,
+        )
+        .expect("runtime");
+        assert!(res.is_empty());
+
+        let many = scan_many_with_strategy(
+            vec![(Ipv4Addr::LOCALHOST, vec![port])],
+            Duration::from_secs(1),
+            2,
+            strategy,
+            None,
+            |_| {},
+        )
+        .expect("runtime");
+        assert_eq!(many.len(), 1);
+        assert!(many[0].1.is_empty());
+    }
+
+    #[test]
+    fn random_order_still_reports_ports_sorted() {
+        let listeners: Vec<TcpListener> = (0..6)
+            .map(|_| TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).expect("bind"))
+            .collect();
+        let mut ports: Vec<u16> = listeners
+            .iter()
+            .map(|l| l.local_addr().unwrap().port())
+            .collect();
+        ports.reverse();
+        let strategy = ProbeStrategy::passive()
+            .with_banner_grab(false)
+            .with_order(ScanOrder::Random { seed: Some(1) });
+        let mut sorted = ports.clone();
+        sorted.sort_unstable();
+
+        let res = scan_host_ports_with_strategy(
+            Ipv4Addr::LOCALHOST,
+            ports.clone(),
+            Duration::from_secs(1),
+            2,
+            strategy.clone(),
+            None,
+        )
+        .expect("runtime");
+        assert_eq!(res.iter().map(|r| r.port).collect::<Vec<_>>(), sorted);
+
+        let many = scan_many_with_strategy(
+            vec![
+                (Ipv4Addr::LOCALHOST, ports.clone()),
+                (Ipv4Addr::new(127, 0, 0, 1), ports),
+            ],
+            Duration::from_secs(1),
+            3,
+            strategy,
+            None,
+            |_| {},
+        )
+        .expect("runtime");
+        for (_, results) in many {
+            assert_eq!(results.iter().map(|r| r.port).collect::<Vec<_>>(), sorted);
+        }
+    }
+
+    #[test]
+    fn scan_host_ports_with_deadline_completes_before_deadline() {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).expect("bind");
+        let port = listener.local_addr().unwrap().port();
+        let res = scan_host_ports_with_deadline(
+            Ipv4Addr::LOCALHOST,
+            vec![port],
+            Duration::from_secs(2),
+            1,
+            ProbeStrategy::default(),
+            Duration::from_secs(5),
+            None,
+        )
+        .expect("runtime");
+        assert!(!res.timed_out);
+        assert_eq!(res.results.len(), 1);
+        assert!(res.results[0].open);
+    }
+
+    fn listening_port() -> u16 {
+        let listener = std::net::TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).expect("bind");
+        let port = listener.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            let _held: Vec<_> = listener.incoming().take(1).collect();
+        });
+        port
+    }
+
+    #[tokio::test]
+    async fn blocking_scan_inside_current_thread_runtime() {
+        let port = listening_port();
+        let res = scan_host_ports(
+            Ipv4Addr::LOCALHOST,
+            vec![port],
+            Duration::from_secs(2),
+            1,
+            None,
+        )
+        .expect("no nested runtime panic");
+        assert!(res[0].open);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn blocking_scan_inside_multi_thread_runtime() {
+        let port = listening_port();
+        let res = scan_host_ports(
+            Ipv4Addr::LOCALHOST,
+            vec![port],
+            Duration::from_secs(2),
+            1,
+            None,
+        )
+        .expect("no nested runtime panic");
+        assert!(res[0].open);
+    }
+
+    #[test]
+    fn deadline_keeps_partial_results_from_wedged_work() {
+        let start = std::time::Instant::now();
+        let res = block_on_with_deadline(Duration::from_millis(100), |out| {
+            Box::pin(async move {
+                out.push(1u16);
+                // a task that never completes
+                std::future::pending::<()>().await;
+                out.push(2);
+            })
+        })
+        .expect("runtime");
+        assert!(res.timed_out);
+        assert_eq!(res.results, vec![1]);
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn probe_udp_with_deadline_times_out() {
+        let sock = std::net::UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).expect("bind");
+        let port = sock.local_addr().unwrap().port();
+        // the socket never replies, and the probe timeout outlasts the deadline
+        let res = probe_udp_with_deadline(
+            Ipv4Addr::LOCALHOST,
+            port,
+            Duration::from_secs(30),
+            Duration::from_millis(100),
+        )
+        .expect("runtime");
+        assert!(res.timed_out);
+        assert!(res.results.is_empty());
+    }
+
+    #[test]
+    fn probe_udp_banner_keeps_raw_reply_and_normalizes_text() {
+        let sock = std::net::UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).expect("bind");
+        let port = sock.local_addr().unwrap().port();
+        let reply = b"\x30\x1a\x02\x01\x00 Linux  router\r\n 5.10\x00".to_vec();
+        let sent = reply.clone();
+        thread::spawn(move || {
+            let mut buf = [0u8; 64];
+            if let Ok((_, from)) = sock.recv_from(&mut buf) {
+                let _ = sock.send_to(&sent, from);
+            }
+        });
+        let res =
+            probe_udp_banner(Ipv4Addr::LOCALHOST, port, Duration::from_secs(2)).expect("runtime");
+        assert_eq!(res.raw.as_deref(), Some(&reply[..]));
+        assert_eq!(res.banner.as_deref(), Some("0 Linux router 5.10"));
+        assert_eq!(udp_banner(&[0x00, 0x01, 0xff]), None);
+    }
+
+    /// UDP DNS stub that only answers well-formed A queries for example.com.
+    fn dns_stub() -> u16 {
+        let sock = std::net::UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).expect("bind");
+        let port = sock.local_addr().unwrap().port();
+        thread::spawn(move || {
+            let mut buf = [0u8; 512];
+            while let Ok((n, src)) = sock.recv_from(&mut buf) {
+                let q = &buf[..n];
+                let question = b"\x07example\x03com\x00\x00\x01\x00\x01";
+                if n < 12 || q[4..6] != [0, 1] || &q[12..] != question {
+                    continue;
+                }
+                let mut reply = q.to_vec();
+                reply[2] |= 0x80; // QR
+                reply[7] = 1; // ancount
+                reply.extend_from_slice(&[0xc0, 0x0c, 0, 1, 0, 1, 0, 0, 0, 60, 0, 4]);
+                reply.extend_from_slice(&[93, 184, 215, 14]);
+                let _ = sock.send_to(&reply, src);
+            }
+        });
+        port
+    }
+
+    #[tokio::test]
+    async fn builtin_dns_payload_elicits_a_reply() {
+        let port = dns_stub();
+        let wait = Duration::from_millis(500);
+        let dns = udp_probe_payload(53).expect("dns payload");
+        let (_, reply) =
+            probe_udp_with_payload_async(Ipv4Addr::LOCALHOST, port, dns, wait, None).await;
+        let reply = reply.expect("stub answered");
+        assert_eq!(&reply[..2], &dns[..2]); // same query id
+        assert_eq!(reply[7], 1);
+        // an empty datagram gets nothing back
+        let (_, none) =
+            probe_udp_with_payload_async(Ipv4Addr::LOCALHOST, port, &[], wait, None).await;
+        assert_eq!(none, None);
+    }
+
+    /// Second loopback address; Linux routes all of 127/8 to `lo`.
+    const LOOPBACK_2: Ipv4Addr = Ipv4Addr::new(127, 0, 0, 2);
+
+    #[tokio::test]
+    async fn probes_connect_from_the_local_addr() {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).expect("bind");
+        let port = listener.local_addr().unwrap().port();
+        let (tx, rx) = std::sync::mpsc::channel();
+        thread::spawn(move || {
+            for stream in listener.incoming().take(2) {
+                let _ = tx.send(stream.expect("accept").peer_addr().unwrap().ip());
+            }
+        });
+        let wait = Duration::from_millis(500);
+        let swept = scan_tcp_async(
+            vec![Ipv4Addr::LOCALHOST],
+            port,
+            wait,
+            1,
+            None,
+            None,
+            Some(LOOPBACK_2),
+        )
+        .await;
+        assert_eq!(swept.len(), 1);
+        let res = scan_host_ports_with_strategy_async(
+            Ipv4Addr::LOCALHOST,
+            vec![port],
+            wait,
+            1,
+            ProbeStrategy::default().with_local_addr(Some(LOOPBACK_2)),
+            None,
+        )
+        .await;
+        assert!(res[0].open);
+        let peers: Vec<_> = rx.iter().take(2).collect();
+        assert_eq!(peers, [std::net::IpAddr::V4(LOOPBACK_2); 2]);
+
+        let udp = std::net::UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).expect("bind");
+        let udp_port = udp.local_addr().unwrap().port();
+        thread::spawn(move || {
+            let mut buf = [0u8; 64];
+            if let Ok((_, src)) = udp.recv_from(&mut buf) {
+                let _ = udp.send_to(src.ip().to_string().as_bytes(), src);
+            }
+        });
+        let (_, reply) =
+            probe_udp_async(Ipv4Addr::LOCALHOST, udp_port, wait, Some(LOOPBACK_2)).await;
+        assert_eq!(reply.as_deref(), Some(&b"127.0.0.2"[..]));
+    }
+
+    #[tokio::test]
+    async fn unbindable_local_addr_probes_nothing() {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).expect("bind");
+        let port = listener.local_addr().unwrap().port();
+        let foreign = Some(Ipv4Addr::new(192, 0, 2, 1));
+        let wait = Duration::from_millis(300);
+        let res = scan_host_ports_with_strategy_async(
+            Ipv4Addr::LOCALHOST,
+            vec![port],
+            wait,
+            1,
+            ProbeStrategy::default().with_local_addr(foreign),
+            None,
+        )
+        .await;
+        assert!(res.is_empty());
+        // the blocking scans take the source address from the strategy too
+        let res = scan_host_ports_with_strategy(
+            Ipv4Addr::LOCALHOST,
+            vec![port],
+            wait,
+            1,
+            ProbeStrategy::default().with_local_addr(foreign),
+            None,
+        )
+        .expect("runtime");
+        assert!(res.is_empty());
+        let swept = scan_tcp_async(
+            vec![Ipv4Addr::LOCALHOST],
+            port,
+            wait,
+            1,
+            None,
+            None,
+            foreign,
+        );
+        assert!(swept.await.is_empty());
+        assert_eq!(
+            probe_udp_async(Ipv4Addr::LOCALHOST, port, wait, foreign).await,
+            (Ipv4Addr::LOCALHOST, None)
+        );
+    }
+
+    #[test]
+    fn probe_sends_request_and_reads_reply() {
+        use std::io::{Read, Write};
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).expect("bind");
+        let port = listener.local_addr().unwrap().port();
+        thread::spawn(move || {
+            if let Ok((mut s, _)) = listener.accept() {
+                let mut req = [0u8; 256];
+                let n = s.read(&mut req).unwrap_or(0);
+                if req[..n].starts_with(b"GET / HTTP/1.0") {
+                    let _ = s.write_all(b"HTTP/1.1 200 OK\r\nServer: test-httpd/1.0\r\n\r\nhi");
+                }
+                thread::sleep(Duration::from_millis(200));
+            }
+        });
+
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let banner = rt.block_on(probe_with_async(
+            Ipv4Addr::LOCALHOST,
+            port,
+            ServiceProbe::HttpGet,
+            Duration::from_secs(2),
+        ));
+        assert_eq!(
+            banner.as_deref(),
+            Some("HTTP/1.1 200 OK Server: test-httpd/1.0")
+        );
+    }
+
+    #[test]
+    fn probe_service_falls_back_to_passive_read() {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).expect("bind");
+        let port = listener.local_addr().unwrap().port();
+        thread::spawn(move || {
+            if let Ok((mut s, _)) = listener.accept() {
+                use std::io::Write;
+                let _ = s.write_all(b"SSH-2.0-OpenSSH_9.6\r\n");
+                thread::sleep(Duration::from_millis(200));
+            }
+        });
+        assert_eq!(
+            probe_service(Ipv4Addr::LOCALHOST, port, Duration::from_secs(2))
+                .expect("runtime")
+                .as_deref(),
+            Some("SSH-2.0-OpenSSH_9.6")
+        );
+    }
+
+    /// Fake HTTP server: silent until it receives a request, then answers.
+    fn fake_http_server() -> u16 {
+        use std::io::{Read, Write};
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).expect("bind");
+        let port = listener.local_addr().unwrap().port();
+        thread::spawn(move || {
+            for mut s in listener.incoming().flatten() {
+                let mut req = [0u8; 256];
+                let n = s.read(&mut req).unwrap_or(0);
+                if req[..n].starts_with(b"HEAD / HTTP/1.0") {
+                    let _ = s.write_all(b"HTTP/1.0 200 OK\r\nServer: fake/0.1\r\n\r\n");
+                }
+            }
+        });
+        port
+    }
+
+    #[test]
+    fn silent_http_port_yields_status_line() {
+        let port = fake_http_server();
+        let rt = tokio::runtime::Runtime::new().unwrap();
+
+        // a passive read gets nothing from a request-first server
+        let passive = rt.block_on(scan_host_ports_with_strategy_async(
+            Ipv4Addr::LOCALHOST,
+            vec![port],
+            Duration::from_secs(2),
+            1,
+            ProbeStrategy::passive(),
+            None,
+        ));
+        assert_eq!(passive[0].banner, None);
+
+        // the fake listens on an ephemeral port, so map it like an HTTP port
+        let probed = rt.block_on(scan_host_ports_with_strategy_async(
+            Ipv4Addr::LOCALHOST,
+            vec![port],
+            Duration::from_secs(2),
+            1,
+            ProbeStrategy::passive().with_probe(port, "HEAD / HTTP/1.0\r\n\r\n"),
+            None,
+        ));
+        assert_eq!(probed[0].banner.as_deref(), Some("HTTP/1.0 200 OK"));
+    }
+
+    #[tokio::test]
+    async fn send_probe_and_read_waits_for_a_banner() {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).expect("bind");
+        let port = listener.local_addr().unwrap().port();
+        thread::spawn(move || {
+            if let Ok((mut s, _)) = listener.accept() {
+                use std::io::Write;
+                thread::sleep(Duration::from_millis(100));
+                let _ = s.write_all(b"SSH-2.0-OpenSSH_9.6\r\n");
+                thread::sleep(Duration::from_millis(200));
+            }
+        });
+        let stream = TcpStream::connect((Ipv4Addr::LOCALHOST, port))
+            .await
+            .unwrap();
+        let reply = send_probe_and_read(stream, None, 7, Duration::from_secs(2)).await;
+        assert_eq!(reply.as_deref(), Some(&b"SSH-2.0"[..]));
+    }
+
+    #[tokio::test]
+    async fn send_probe_and_read_sends_the_probe_first() {
+        let port = fake_http_server();
+        let connect = || TcpStream::connect((Ipv4Addr::LOCALHOST, port));
+        let silent = send_probe_and_read(
+            connect().await.unwrap(),
+            None,
+            512,
+            Duration::from_millis(200),
+        );
+        assert_eq!(silent.await, None);
+
+        let probe = b"HEAD / HTTP/1.0\r\n\r\n";
+        let reply = send_probe_and_read(
+            connect().await.unwrap(),
+            Some(probe),
+            512,
+            Duration::from_secs(2),
+        )
+        .await
+        .expect("reply");
+        assert!(reply.starts_with(b"HTTP/1.0 200 OK\r\n"));
+    }
+
+    #[test]
+    fn binary_greeting_keeps_raw_hex_next_to_display_banner() {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).expect("bind");
+        let port = listener.local_addr().unwrap().port();
+        // MySQL-style handshake: length header, protocol 10, version, binary tail
+        let mut greeting = vec![0x4a, 0x00, 0x00, 0x00, 0x0a, b'8', b'.', b'0', 0x00];
+        greeting.extend([0xff; 80]);
+        let sent = greeting.clone();
+        thread::spawn(move || {
+            if let Ok((mut s, _)) = listener.accept() {
+                use std::io::Write;
+                let _ = s.write_all(&sent);
+                thread::sleep(Duration::from_millis(200));
+            }
+        });
+        let res = scan_host_ports(
+            Ipv4Addr::LOCALHOST,
+            vec![port],
+            Duration::from_secs(2),
+            1,
+            None,
+        )
+        .expect("runtime");
+        assert_eq!(res[0].banner.as_deref(), Some("J8.0"));
+        let hex = res[0].banner_hex.as_deref().expect("raw bytes kept");
+        assert_eq!(hex.len(), 2 * BANNER_HEX_BYTES);
+        assert!(hex.starts_with("4a0000000a382e3000ffff"));
+        assert_eq!(banner_hex(&greeting[..3]), "4a0000");
+    }
+
+    #[test]
+    fn talk_first_services_are_not_probed() {
+        use std::io::{Read, Write};
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).expect("bind");
+        let port = listener.local_addr().unwrap().port();
+        let (tx, rx) = std::sync::mpsc::channel();
+        thread::spawn(move || {
+            if let Ok((mut s, _)) = listener.accept() {
+                let _ = s.write_all(b"220 mail.example ESMTP\r\n");
+                let mut buf = [0u8; 64];
+                let n = s.read(&mut buf).unwrap_or(0);
+                let _ = tx.send(n);
+            }
+        });
+        let res = scan_host_ports(
+            Ipv4Addr::LOCALHOST,
+            vec![port],
+            Duration::from_secs(2),
+            1,
+            None,
+        )
+        .expect("runtime");
+        assert_eq!(res[0].banner.as_deref(), Some("220 mail.example ESMTP"));
+        // the client closed without sending anything
+        assert_eq!(rx.recv_timeout(Duration::from_secs(2)), Ok(0));
+    }
+
+    #[test]
+    fn udp_scan_reports_open_closed_and_open_filtered() {
+        let echo = std::net::UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).expect("bind");
+        let echo_port = echo.local_addr().unwrap().port();
+        thread::spawn(move || {
+            let mut buf = [0u8; 1500];
+            while let Ok((n, src)) = echo.recv_from(&mut buf) {
+                let mut reply = b"echo:".to_vec();
+                reply.extend_from_slice(&buf[..n]);
+                let _ = echo.send_to(&reply, src);
+            }
+        });
+        let silent = std::net::UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).expect("bind");
+        let silent_port = silent.local_addr().unwrap().port();
+        let closed_port = {
+            let s = std::net::UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).expect("bind");
+            s.local_addr().unwrap().port()
+        };
+
+        let mut res = scan_host_udp_ports(
+            Ipv4Addr::LOCALHOST,
+            vec![echo_port, silent_port, closed_port],
+            Duration::from_millis(300),
+            4,
+        )
+        .expect("runtime");
+        res.sort_by_key(|r| {
+            [echo_port, silent_port, closed_port]
+                .iter()
+                .position(|p| *p == r.port)
+        });
+        assert!(res.iter().all(|r| r.proto == "udp"));
+
+        assert_eq!(res[0].state, PortState::Open);
+        assert!(res[0].open);
+        assert_eq!(res[0].banner.as_deref(), Some("echo:"));
+
+        assert_eq!(res[1].state, PortState::OpenFiltered);
+        assert!(!res[1].open);
+        assert_eq!(res[1].banner, None);
+
+        assert_eq!(res[2].state, PortState::Closed);
+        drop(silent);
+    }
+
+    #[test]
+    fn udp_scan_uses_payload_closure() {
+        let sock = std::net::UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).expect("bind");
+        let port = sock.local_addr().unwrap().port();
+        thread::spawn(move || {
+            let mut buf = [0u8; 64];
+            // only answer the expected query
+            while let Ok((n, src)) = sock.recv_from(&mut buf) {
+                if &buf[..n] == b"STATUS?" {
+                    let _ = sock.send_to(b"STATUS ok", src);
+                }
+            }
+        });
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let res = rt.block_on(scan_host_ports_udp_async(
+            Ipv4Addr::LOCALHOST,
+            vec![port],
+            move |p| {
+                if p == port {
+                    b"STATUS?".to_vec()
+                } else {
+                    Vec::new()
+                }
+            },
+            Duration::from_secs(1),
+            2,
+        ));
+        assert_eq!(res[0].state, PortState::Open);
+        assert_eq!(res[0].banner.as_deref(), Some("STATUS ok"));
+
+        // the builtin table sends an empty datagram here, which is ignored
+        let res = rt.block_on(scan_host_udp_ports_async(
+            Ipv4Addr::LOCALHOST,
+            vec![port],
+            Duration::from_millis(200),
+            1,
+        ));
+        assert_eq!(res[0].state, PortState::OpenFiltered);
+    }
+
+    #[test]
+    fn tcp_scan_distinguishes_closed_ports() {
+        let closed_port = {
+            let l = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).expect("bind");
+            l.local_addr().unwrap().port()
+        };
+        let res = scan_host_ports(
+            Ipv4Addr::LOCALHOST,
+            vec![closed_port],
+            Duration::from_secs(1),
+            1,
+            None,
+        )
+        .expect("runtime");
+        assert_eq!(res[0].state, PortState::Closed);
+        assert_eq!(PortState::OpenFiltered.as_str(), "open|filtered");
+    }
+
+    #[test]
+    fn progress_hook_runs_once_per_port_and_survives_panics() {
+        let l = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).expect("bind");
+        let open = l.local_addr().unwrap().port();
+        let closed = {
+            let l = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).expect("bind");
+            l.local_addr().unwrap().port()
+        };
+        let ports = vec![open, closed, closed];
+        let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let hook: ProgressFn = {
+            let seen = seen.clone();
+            Arc::new(move |p: ScanProgress| {
+                seen.lock().unwrap().push(p);
+                if p.done == 1 {
+                    panic!("a broken progress hook must not lose results");
+                }
+            })
+        };
+        let res = scan_host_ports(
+            Ipv4Addr::LOCALHOST,
+            ports.clone(),
+            Duration::from_secs(1),
+            2,
+            Some(hook),
+        )
+        .expect("runtime");
+        assert_eq!(res.len(), ports.len());
+
+        let mut seen = seen.lock().unwrap().clone();
+        assert_eq!(seen.len(), ports.len());
+        seen.sort_by_key(|p| p.done);
+        assert_eq!(seen.iter().map(|p| p.done).collect::<Vec<_>>(), [1, 2, 3]);
+        assert!(seen
+            .iter()
+            .all(|p| p.total == 3 && p.ip == Ipv4Addr::LOCALHOST));
+        assert_eq!(seen.iter().filter(|p| p.open).count(), 1);
+    }
+
+    #[tokio::test]
+    async fn rate_limit_paces_connects_beyond_concurrency() {
+        let closed = {
+            let l = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).expect("bind");
+            l.local_addr().unwrap().port()
+        };
+        let start = std::time::Instant::now();
+        let res = scan_host_ports_with_strategy_async(
+            Ipv4Addr::LOCALHOST,
+            vec![closed; 20],
+            Duration::from_secs(1),
+            64,
+            ProbeStrategy::default().with_rate_limit(Some(10)),
+            None,
+        )
+        .await;
+        // 20 attempts at 10/s: the first starts at once, the last 1.9 s in
+        assert_eq!(res.len(), 20);
+        assert!(start.elapsed() >= Duration::from_millis(1800));
+    }
+
+    #[tokio::test]
+    async fn cancel_mid_scan_keeps_finished_probes() {
+        let closed = {
+            let l = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).expect("bind");
+            l.local_addr().unwrap().port()
+        };
+        let cancel = CancelToken::new();
+        let trigger = cancel.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            trigger.cancel();
+        });
+        // 40 ports at 20/s would take 2 s; cancelled after ~6 attempts
+        let res = scan_host_ports_with_cancel_async(
+            Ipv4Addr::LOCALHOST,
+            vec![closed; 40],
+            Duration::from_secs(1),
+            4,
+            ProbeStrategy::default().with_rate_limit(Some(20)),
+            None,
+            cancel,
+        )
+        .await;
+        assert!(res.cancelled);
+        assert!(!res.results.is_empty() && res.results.len() < 40);
+        assert!(res.results.iter().all(|p| p.state == PortState::Closed));
+    }
+
+    #[test]
+    fn scan_many_with_cancel_drops_untried_ports() {
+        let closed = {
+            let l = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).expect("bind");
+            l.local_addr().unwrap().port()
+        };
+        let cancel = CancelToken::new();
+        let trigger = cancel.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(150));
+            trigger.cancel();
+        });
+        // 20 probes at 50/s would take 400 ms
+        let mut hosts_done = 0;
+        let res = scan_many_with_cancel(
+            vec![
+                (Ipv4Addr::LOCALHOST, vec![closed; 10]),
+                (Ipv4Addr::new(127, 0, 0, 2), vec![closed; 10]),
+            ],
+            Duration::from_secs(1),
+            2,
+            ProbeStrategy::passive().with_rate_limit(Some(50)),
+            cancel,
+            |_| hosts_done += 1,
+        )
+        .expect("runtime");
+        assert!(res.cancelled);
+        // Cut-off hosts still count as done, with whatever was probed
+        assert_eq!((hosts_done, res.results.len()), (2, 2));
+        let probed: usize = res.results.iter().map(|(_, r)| r.len()).sum();
+        assert!(probed > 0 && probed < 20, "{}", probed);
+    }
+
+    #[tokio::test]
+    async fn scan_many_finds_listeners_on_two_hosts_in_one_call() {
+        let second_host = Ipv4Addr::new(127, 0, 0, 2);
+        let listen = |ip: Ipv4Addr| {
+            let l = TcpListener::bind((ip, 0)).expect("bind");
+            let port = l.local_addr().unwrap().port();
+            (l, port)
+        };
+        let (_a, port_a) = listen(Ipv4Addr::LOCALHOST);
+        let (_b, port_b) = listen(second_host);
+        let closed = listen(Ipv4Addr::LOCALHOST).1;
+
+        let res = scan_many_async(
+            vec![
+                (Ipv4Addr::LOCALHOST, vec![closed, port_a]),
+                (second_host, vec![port_b]),
+                (Ipv4Addr::new(127, 0, 0, 3), vec![]),
+            ],
+            Duration::from_secs(2),
+            2,
+            None,
+        )
+        .await;
+        assert_eq!(res.len(), 3);
+        assert_eq!(res[0].0, Ipv4Addr::LOCALHOST);
+        let ports: Vec<_> = res[0].1.iter().map(|p| (p.port, p.open)).collect();
+        assert_eq!(ports, [(closed, false), (port_a, true)]);
+        assert_eq!(res[1].0, second_host);
+        assert!(res[1].1[0].open && res[1].1[0].port == port_b);
+        assert!(res[2].1.is_empty());
+    }
+
+    #[test]
+    fn scan_many_reports_each_host_once() {
+        let l = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).expect("bind");
+        let port = l.local_addr().unwrap().port();
+        let mut done = Vec::new();
+        let res = scan_many_with_strategy(
+            vec![
+                (Ipv4Addr::LOCALHOST, vec![port, port]),
+                (Ipv4Addr::new(127, 0, 0, 2), vec![port]),
+            ],
+            Duration::from_secs(1),
+            1,
+            ProbeStrategy::default(),
+            None,
+            |(ip, results)| done.push((*ip, results.len())),
+        )
+        .expect("runtime");
+        done.sort();
+        assert_eq!(
+            done,
+            [(Ipv4Addr::LOCALHOST, 2), (Ipv4Addr::new(127, 0, 0, 2), 1)]
+        );
+        assert_eq!(res[0].1.len(), 2);
+    }
+
+    /// A listener whose accept queue is already full, so new SYNs are
+    /// dropped and connects time out. Draining the queue after `after`
+    /// lets the next connect through, like a port behind a lossy link.
+    fn flaky_listener(after: Duration) -> u16 {
+        use socket2::{Domain, Socket, Type};
+        let sock = Socket::new(Domain::IPV4, Type::STREAM, None).unwrap();
+        sock.bind(&SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0).into())
+            .unwrap();
+        // backlog 0 still queues one connection; the filler takes it
+        sock.listen(0).unwrap();
+        let listener: TcpListener = sock.into();
+        let port = listener.local_addr().unwrap().port();
+        let filler = std::net::TcpStream::connect((Ipv4Addr::LOCALHOST, port)).unwrap();
+        std::thread::spawn(move || {
+            std::thread::sleep(after);
+            let _held: Vec<_> = listener.incoming().take(2).collect();
+            drop(filler);
+        });
+        port
+    }
+
+    #[tokio::test]
+    async fn timed_out_connect_is_retried() {
+        let strategy = ProbeStrategy::passive().with_retries(2);
+        let port = flaky_listener(Duration::from_millis(100));
+        let res = scan_host_ports_with_strategy_async(
+            Ipv4Addr::LOCALHOST,
+            vec![port],
+            Duration::from_millis(200),
+            1,
+            strategy.clone(),
+            None,
+        )
+        .await;
+        assert_eq!(res[0].state, PortState::Open);
+        assert_eq!(res[0].attempts, 2);
+
+        // refused connects are definitive
+        let closed = {
+            let l = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+            l.local_addr().unwrap().port()
+        };
+        let res = scan_host_ports_with_strategy_async(
+            Ipv4Addr::LOCALHOST,
+            vec![closed],
+            Duration::from_millis(200),
+            1,
+            strategy,
+            None,
+        )
+        .await;
+        assert_eq!((res[0].state, res[0].attempts), (PortState::Closed, 1));
+    }
+
+    #[tokio::test]
+    async fn without_retries_flaky_port_looks_filtered() {
+        let port = flaky_listener(Duration::from_millis(100));
+        let res = scan_host_ports_with_strategy_async(
+            Ipv4Addr::LOCALHOST,
+            vec![port],
+            Duration::from_millis(200),
+            1,
+            ProbeStrategy::passive(),
+            None,
+        )
+        .await;
+        assert_eq!((res[0].state, res[0].attempts), (PortState::Filtered, 1));
+    }
+
+    #[test]
+    fn port_to_service_known_and_unknown() {
+        assert_eq!(port_to_service(22, "tcp"), Some("ssh"));
+        assert_eq!(port_to_service(443, "tcp"), Some("https"));
+        assert_eq!(port_to_service(53, "udp"), Some("domain"));
+        assert_eq!(port_to_service(80, "TCP"), Some("http"));
+        assert_eq!(port_to_service(65000, "tcp"), None);
+        assert_eq!(port_to_service(22, "sctp"), None);
+    }
+
+    #[test]
+    fn service_name_spot_checks() {
+        for (port, proto, name) in [
+            (21, "tcp", "ftp"),
+            (25, "tcp", "smtp"),
+            (3389, "tcp", "ms-wbt-server"),
+            (161, "udp", "snmp"),
+            (1900, "udp", "upnp"),
+        ] {
+            assert_eq!(service_name(port, proto), Some(name), "{port}/{proto}");
+        }
+        assert_eq!(service_name(1, "udp"), None);
+    }
+
+    #[test]
+    fn scan_host_ports_sets_service_name_for_open_ports() {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).expect("bind");
+        let port = listener.local_addr().unwrap().port();
+        thread::spawn(move || {
+            let _ = listener.accept();
+        });
+        let res = scan_host_ports(
+            Ipv4Addr::LOCALHOST,
+            vec![port],
+            Duration::from_secs(2),
+            1,
+            None,
+        )
+        .expect("runtime");
+        assert_eq!(res.len(), 1);
+        assert!(res[0].open);
+        assert_eq!(res[0].service_name.as_deref(), port_to_service(port, "tcp"));
+    }
 }
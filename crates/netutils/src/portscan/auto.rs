@@ -0,0 +1,153 @@
+//! One scan call that picks the backend: half-open SYN when raw sockets are
+//! available (`syn-scan` feature, root or `CAP_NET_RAW`), TCP connect
+//! otherwise.
+//!
+//! Privilege is detected by trying: `syn_scan` opens its raw socket before
+//! sending anything, so an unprivileged process falls back without having
+//! put a packet on the wire.
+
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use super::{scan_host_ports_with_strategy, PortResult, PortScanError, ProbeStrategy};
+
+/// Backend that produced a `scan_auto` result.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScanMethod {
+    /// Full TCP handshake (`scan_host_ports_with_strategy`); banners are read
+    Connect,
+    /// Half-open SYN scan (`syn_scan`); no banners
+    Syn,
+}
+
+/// Settings for `scan_auto` (shared by both backends where they apply) and
+/// `scan_host`.
+#[derive(Debug, Clone)]
+pub struct AutoScanOptions {
+    /// per-port timeout (connect), or wait after the last SYN
+    pub timeout: Duration,
+    /// simultaneous connects; unused by the SYN backend
+    pub concurrency: usize,
+    /// interface for SYN frames; `None` uses `iface::get_default_interface`
+    pub iface: Option<String>,
+    /// probe table for the connect backend; its `rate_limit` (connect
+    /// attempts or SYNs per second) paces both backends
+    pub strategy: ProbeStrategy,
+}
+
+impl Default for AutoScanOptions {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(1),
+            concurrency: 64,
+            iface: None,
+            strategy: ProbeStrategy::default(),
+        }
+    }
+}
+
+impl AutoScanOptions {
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency;
+        self
+    }
+
+    /// Sets `strategy.rate_limit`; call after `with_strategy`, which
+    /// replaces it.
+    pub fn with_rate_limit(mut self, rate: Option<u32>) -> Self {
+        self.strategy.rate_limit = rate;
+        self
+    }
+
+    pub fn with_iface<S: Into<String>>(mut self, iface: S) -> Self {
+        self.iface = Some(iface.into());
+        self
+    }
+
+    pub fn with_strategy(mut self, strategy: ProbeStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+}
+
+/// `scan_auto` output: the port results and the backend that produced them.
+#[derive(Debug, Clone)]
+pub struct AutoScan {
+    pub method: ScanMethod,
+    pub results: Vec<PortResult>,
+}
+
+/// Scan `ports` on `ip` with the best backend available: SYN when built
+/// with `syn-scan` and a raw socket can be opened, else TCP connect. SYN
+/// results follow `ports` order and carry no banners. Loopback targets, and
+/// any SYN scan that cannot start (no privilege, no interface, no next-hop
+/// MAC), use connect. Errors only when the connect backend has no runtime.
+pub fn scan_auto(
+    ip: Ipv4Addr,
+    ports: Vec<u16>,
+    options: &AutoScanOptions,
+) -> Result<AutoScan, PortScanError> {
+    #[cfg(feature = "syn-scan")]
+    if !ip.is_loopback() {
+        if let Some(results) = try_syn(ip, &ports, options) {
+            return Ok(AutoScan {
+                method: ScanMethod::Syn,
+                results,
+            });
+        }
+    }
+    let results = scan_host_ports_with_strategy(
+        ip,
+        ports,
+        options.timeout,
+        options.concurrency,
+        options.strategy.clone(),
+        None,
+    )?;
+    Ok(AutoScan {
+        method: ScanMethod::Connect,
+        results,
+    })
+}
+
+/// SYN results, or None when the SYN backend cannot run here.
+#[cfg(feature = "syn-scan")]
+fn try_syn(ip: Ipv4Addr, ports: &[u16], options: &AutoScanOptions) -> Option<Vec<PortResult>> {
+    let iface = match &options.iface {
+        Some(name) => name.clone(),
+        None => crate::iface::get_default_interface().ok()?.name,
+    };
+    let ports = options.strategy.without_excluded(ports.to_vec());
+    let rate = options.strategy.rate_limit.unwrap_or(0);
+    match super::syn_scan(ip, ports, &iface, options.timeout, rate) {
+        Ok(results) => Some(results),
+        Err(_e) => {
+            crate::trace::trace_debug!(%ip, error = %_e, "syn scan unavailable; using connect");
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    #[test]
+    fn loopback_uses_connect_and_finds_open_port() {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).expect("bind");
+        let port = listener.local_addr().unwrap().port();
+        let options = AutoScanOptions::default()
+            .with_timeout(Duration::from_millis(500))
+            .with_strategy(ProbeStrategy::passive().with_banner_grab(false));
+        let scan = scan_auto(Ipv4Addr::LOCALHOST, vec![port], &options).expect("runtime");
+        assert_eq!(scan.method, ScanMethod::Connect);
+        assert_eq!(scan.results.len(), 1);
+        assert!(scan.results[0].open);
+    }
+}
@@ -0,0 +1,242 @@
+//! Service/product/version identification from grabbed banners.
+//!
+//! Rules are tried in order and the first match wins, so product-specific
+//! patterns sit above the generic ones. Named capture groups `product` and
+//! `version` fill the matching `ServiceInfo` fields when present. Banners may
+//! be raw or already passed through `normalize_banner` (newlines collapsed),
+//! so the rules avoid relying on line breaks.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use super::port_to_service;
+
+/// What a banner says about the service behind a port.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServiceInfo {
+    /// Service (protocol) name, e.g. "ssh", "http", "ftp"
+    pub name: String,
+    /// Implementation, e.g. "OpenSSH", "nginx"
+    pub product: Option<String>,
+    pub version: Option<String>,
+}
+
+impl ServiceInfo {
+    /// A service name with no product or version, e.g. from the port table.
+    pub fn from_name(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            product: None,
+            version: None,
+        }
+    }
+
+    /// "product version" (or just the product), if a product is known.
+    pub fn product_label(&self) -> Option<String> {
+        match (&self.product, &self.version) {
+            (Some(p), Some(v)) => Some(format!("{} {}", p, v)),
+            (Some(p), None) => Some(p.clone()),
+            _ => None,
+        }
+    }
+}
+
+/// Banner rule; `service: None` takes the service name from the port, and
+/// `product` labels matches without a `product` capture group.
+struct ServiceRule {
+    service: Option<&'static str>,
+    re: Regex,
+    product: Option<&'static str>,
+}
+
+fn rule(service: Option<&'static str>, pattern: &str) -> ServiceRule {
+    ServiceRule {
+        service,
+        re: Regex::new(pattern).expect("valid service rule"),
+        product: None,
+    }
+}
+
+fn labeled(service: &'static str, pattern: &str, product: &'static str) -> ServiceRule {
+    ServiceRule {
+        product: Some(product),
+        ..rule(Some(service), pattern)
+    }
+}
+
+static SERVICE_RULES: Lazy<Vec<ServiceRule>> = Lazy::new(|| {
+    vec![
+        // "SSH-2.0-OpenSSH_8.9p1 Ubuntu-3", "SSH-2.0-dropbear_2022.83"
+        rule(
+            Some("ssh"),
+            r"^SSH-\d+\.\d+-(?P<product>[A-Za-z][\w.-]*?)(?:[_-](?P<version>\d[\w.]*))?(?:\s|$)",
+        ),
+        rule(Some("ssh"), r"^SSH-\d+\.\d+-(?P<product>[^\s_]+)?"),
+        // FTP greetings (checked before SMTP since both start with 220)
+        rule(
+            Some("ftp"),
+            r"(?i)^220[ -].*?\((?P<product>vsFTPd) (?P<version>[\d.]+)\)",
+        ),
+        rule(
+            Some("ftp"),
+            r"(?i)^220[ -].*?(?P<product>ProFTPD|Pure-FTPd|FileZilla Server)(?: (?P<version>\d[\w.]*))?",
+        ),
+        labeled(
+            "ftp",
+            r"(?i)^220[ -].*Microsoft FTP Service",
+            "Microsoft ftpd",
+        ),
+        rule(Some("ftp"), r"(?i)^220[ -].*\bFTP\b"),
+        // SMTP greetings
+        rule(
+            Some("smtp"),
+            r"(?i)^220[ -]\S+ ESMTP (?P<product>Postfix|Exim|Sendmail|OpenSMTPD)(?: (?P<version>\d[\w./-]*))?",
+        ),
+        labeled(
+            "smtp",
+            r"(?i)^220[ -].*Microsoft ESMTP",
+            "Microsoft Exchange smtpd",
+        ),
+        rule(Some("smtp"), r"(?i)^220[ -].*\bE?SMTP\b"),
+        // POP3 / IMAP
+        rule(Some("pop3"), r"^\+OK (?P<product>Dovecot)"),
+        rule(Some("imap"), r"^\* OK .*?(?P<product>Dovecot)"),
+        rule(Some("imap"), r"^\* OK .*IMAP4"),
+        // HTTP probe banners are "<status line> Server: <header>"
+        rule(
+            Some("http"),
+            r"(?i)\bserver:\s*(?P<product>[^/\s]+)(?:/(?P<version>\d[\w.-]*))?",
+        ),
+        rule(
+            Some("http"),
+            r"(?i)\b(?P<product>apache|nginx|lighttpd|microsoft-iis|openresty|caddy)(?:/(?P<version>\d[\w.-]*))?",
+        ),
+        rule(Some("http"), r"(?i)^HTTP/\d(?:\.\d)? \d{3}"),
+        // Redis replies to PING and to unsolicited/inline commands
+        labeled("redis", r"redis_version:(?P<version>[\d.]+)", "Redis"),
+        labeled(
+            "redis",
+            r"^(?:\+PONG|-(?:NOAUTH|DENIED Redis|ERR unknown command))",
+            "Redis",
+        ),
+        // MySQL/MariaDB handshake: version string followed by the auth plugin
+        rule(
+            Some("mysql"),
+            r"(?s)(?P<version>\d+\.\d+\.\d+)-(?P<product>MariaDB).*(?:mysql_native_password|caching_sha2_password)",
+        ),
+        labeled(
+            "mysql",
+            r"(?s)(?P<version>\d+\.\d+\.\d+)[\w.-]*?.*(?:mysql_native_password|caching_sha2_password)",
+            "MySQL",
+        ),
+        // Bare "product/version" tokens; the port says what protocol it is
+        rule(
+            None,
+            r"^(?P<product>[A-Za-z][\w.-]*)/(?P<version>\d[\w.-]*)",
+        ),
+    ]
+});
+
+/// Identify the service, product and version named in `banner`, e.g.
+/// `SSH-2.0-OpenSSH_8.9p1` -> ssh / OpenSSH / 8.9p1, or an HTTP
+/// `Server: Apache/2.4.51` -> http / Apache / 2.4.51 (https on 443 and
+/// 8443). `port` names the protocol for banners that carry only a product
+/// token. Returns None without a banner or when no rule matches.
+pub fn fingerprint_service(port: u16, banner: Option<&str>) -> Option<ServiceInfo> {
+    let banner = banner.map(str::trim).filter(|b| !b.is_empty())?;
+    SERVICE_RULES.iter().find_map(|rule| {
+        let caps = rule.re.captures(banner)?;
+        let service = match rule.service {
+            // HTTP on a TLS port is still reported as https
+            Some("http") if matches!(port, 443 | 8443) => "https",
+            Some(service) => service,
+            None => port_to_service(port, "tcp")?,
+        };
+        let field = |n: &str| caps.name(n).map(|m| m.as_str().to_string());
+        Some(ServiceInfo {
+            name: service.to_string(),
+            product: field("product").or(rule.product.map(str::to_string)),
+            version: field("version"),
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(name: &str, product: Option<&str>, version: Option<&str>) -> ServiceInfo {
+        ServiceInfo {
+            name: name.to_string(),
+            product: product.map(str::to_string),
+            version: version.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn openssh_banner() {
+        assert_eq!(
+            fingerprint_service(22, Some("SSH-2.0-OpenSSH_8.9p1 Ubuntu-3ubuntu0.1")),
+            Some(info("ssh", Some("OpenSSH"), Some("8.9p1")))
+        );
+        assert_eq!(
+            fingerprint_service(2222, Some("SSH-2.0-dropbear_2022.83")),
+            Some(info("ssh", Some("dropbear"), Some("2022.83")))
+        );
+    }
+
+    #[test]
+    fn apache_server_header() {
+        assert_eq!(
+            fingerprint_service(80, Some("HTTP/1.1 200 OK Server: Apache/2.4.51 (Debian)")),
+            Some(info("http", Some("Apache"), Some("2.4.51")))
+        );
+        assert_eq!(
+            fingerprint_service(8080, Some("Apache/2.4.51")),
+            Some(info("http", Some("Apache"), Some("2.4.51")))
+        );
+    }
+
+    #[test]
+    fn nginx_server_header() {
+        assert_eq!(
+            fingerprint_service(
+                443,
+                Some("HTTP/1.1 301 Moved Permanently Server: nginx/1.24.0")
+            ),
+            Some(info("https", Some("nginx"), Some("1.24.0")))
+        );
+        // No Server header: still HTTP, product unknown
+        assert_eq!(
+            fingerprint_service(80, Some("HTTP/1.0 404 Not Found")),
+            Some(info("http", None, None))
+        );
+    }
+
+    #[test]
+    fn ftp_and_smtp_greetings() {
+        assert_eq!(
+            fingerprint_service(21, Some("220 (vsFTPd 3.0.5)")),
+            Some(info("ftp", Some("vsFTPd"), Some("3.0.5")))
+        );
+        assert_eq!(
+            fingerprint_service(25, Some("220 mail.example ESMTP Exim 4.96")),
+            Some(info("smtp", Some("Exim"), Some("4.96")))
+        );
+    }
+
+    #[test]
+    fn bare_product_token_named_by_port() {
+        assert_eq!(
+            fingerprint_service(3306, Some("MySQLd/8.0.36")),
+            Some(info("mysql", Some("MySQLd"), Some("8.0.36")))
+        );
+        assert_eq!(fingerprint_service(40123, Some("MySQLd/8.0.36")), None);
+    }
+
+    #[test]
+    fn unrecognized_or_missing_banner() {
+        assert_eq!(fingerprint_service(9999, Some("hello there")), None);
+        assert_eq!(fingerprint_service(22, None), None);
+    }
+}
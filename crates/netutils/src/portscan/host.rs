@@ -0,0 +1,168 @@
+//! One-struct summary of a host's port scan: state counts, RTT figures and
+//! wall-clock time, with the raw results kept alongside.
+
+use std::net::Ipv4Addr;
+use std::time::{Duration, Instant};
+
+use super::{
+    block_on, scan_host_ports_with_strategy_async, AutoScanOptions, PortResult, PortScanError,
+    PortState,
+};
+
+/// Aggregates over one host's `PortResult`s.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HostScanResult {
+    pub ip: Ipv4Addr,
+    /// ports with a result (exclusions and cancelled probes not counted)
+    pub ports_probed: usize,
+    pub open: usize,
+    pub closed: usize,
+    /// filtered and open|filtered ports
+    pub filtered: usize,
+    /// fastest connect among open ports
+    pub fastest_rtt_ms: Option<u128>,
+    /// median connect time among open ports (mean of the middle two for an
+    /// even count)
+    pub median_rtt_ms: Option<u128>,
+    /// wall-clock time of the whole scan
+    pub duration_ms: u128,
+    pub results: Vec<PortResult>,
+}
+
+impl HostScanResult {
+    /// Summarize `results` for `ip`; `elapsed` is the scan's wall-clock time.
+    /// RTT figures only count open ports, since a refused or timed-out
+    /// connect says nothing about service latency.
+    pub fn new(ip: Ipv4Addr, results: Vec<PortResult>, elapsed: Duration) -> Self {
+        let count = |state: PortState| results.iter().filter(|r| r.state == state).count();
+        let mut rtts: Vec<u128> = results
+            .iter()
+            .filter(|r| r.open)
+            .filter_map(|r| r.rtt_ms)
+            .collect();
+        rtts.sort_unstable();
+        let median_rtt_ms = match rtts.len() {
+            0 => None,
+            n if n % 2 == 1 => Some(rtts[n / 2]),
+            n => Some((rtts[n / 2 - 1] + rtts[n / 2]) / 2),
+        };
+        Self {
+            ip,
+            ports_probed: results.len(),
+            open: count(PortState::Open),
+            closed: count(PortState::Closed),
+            filtered: count(PortState::Filtered) + count(PortState::OpenFiltered),
+            fastest_rtt_ms: rtts.first().copied(),
+            median_rtt_ms,
+            duration_ms: elapsed.as_millis(),
+            results,
+        }
+    }
+
+    /// Any open port at all.
+    pub fn is_interesting(&self) -> bool {
+        self.open > 0
+    }
+}
+
+/// TCP-scan `ports` on `ip` (connect scan, `options.strategy` probes,
+/// `options.iface` unused) and summarize it.
+pub async fn scan_host_async(
+    ip: Ipv4Addr,
+    ports: Vec<u16>,
+    options: &AutoScanOptions,
+) -> HostScanResult {
+    let start = Instant::now();
+    let results = scan_host_ports_with_strategy_async(
+        ip,
+        ports,
+        options.timeout,
+        options.concurrency,
+        options.strategy.clone(),
+        None,
+    )
+    .await;
+    HostScanResult::new(ip, results, start.elapsed())
+}
+
+/// Blocking wrapper for `scan_host_async`.
+pub fn scan_host(
+    ip: Ipv4Addr,
+    ports: Vec<u16>,
+    options: &AutoScanOptions,
+) -> Result<HostScanResult, PortScanError> {
+    block_on(scan_host_async(ip, ports, options))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::portscan::ProbeStrategy;
+    use std::net::TcpListener;
+
+    fn result(port: u16, state: PortState, rtt_ms: Option<u128>) -> PortResult {
+        PortResult {
+            port,
+            proto: "tcp".into(),
+            open: state == PortState::Open,
+            state,
+            banner: None,
+            banner_hex: None,
+            rtt_ms,
+            service_name: None,
+            tls: None,
+            attempts: 1,
+        }
+    }
+
+    #[test]
+    fn counts_states_and_takes_rtt_from_open_ports_only() {
+        let results = vec![
+            result(22, PortState::Open, Some(9)),
+            result(80, PortState::Open, Some(3)),
+            result(443, PortState::Open, Some(5)),
+            result(8080, PortState::Open, Some(40)),
+            // a fast refusal must not drag the figures down
+            result(23, PortState::Closed, Some(1)),
+            result(25, PortState::Filtered, None),
+            result(53, PortState::OpenFiltered, None),
+        ];
+        let ip = Ipv4Addr::new(192, 0, 2, 1);
+        let summary = HostScanResult::new(ip, results, Duration::from_millis(1500));
+        assert_eq!(summary.ports_probed, 7);
+        assert_eq!((summary.open, summary.closed, summary.filtered), (4, 1, 2));
+        assert_eq!(summary.fastest_rtt_ms, Some(3));
+        assert_eq!(summary.median_rtt_ms, Some(7));
+        assert_eq!(summary.duration_ms, 1500);
+        assert!(summary.is_interesting());
+
+        let quiet = HostScanResult::new(
+            ip,
+            vec![result(23, PortState::Closed, Some(1))],
+            Duration::ZERO,
+        );
+        assert_eq!((quiet.fastest_rtt_ms, quiet.median_rtt_ms), (None, None));
+        assert!(!quiet.is_interesting());
+    }
+
+    #[test]
+    fn scan_host_summarizes_a_real_scan() {
+        let open = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).expect("bind");
+        let open_port = open.local_addr().unwrap().port();
+        let closed_port = {
+            let l = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).expect("bind");
+            l.local_addr().unwrap().port()
+        };
+        let options = AutoScanOptions::default()
+            .with_strategy(ProbeStrategy::passive().with_banner_grab(false));
+        let summary = scan_host(Ipv4Addr::LOCALHOST, vec![open_port, closed_port], &options)
+            .expect("runtime");
+        assert_eq!(
+            (summary.ports_probed, summary.open, summary.closed),
+            (2, 1, 1)
+        );
+        assert!(summary.median_rtt_ms.is_some());
+        assert_eq!(summary.results.len(), 2);
+    }
+}
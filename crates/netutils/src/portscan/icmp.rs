@@ -0,0 +1,185 @@
+//! ICMP port-unreachable listener for UDP scans.
+//!
+//! Opening a raw ICMP socket needs root or `CAP_NET_RAW`; without it
+//! `IcmpUnreachableListener::open` fails and UDP scans fall back to what a
+//! connected socket reports on its own.
+
+use std::collections::HashSet;
+use std::io::Read;
+use std::net::Ipv4Addr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use socket2::{Domain, Protocol, Socket, Type};
+
+const ICMP_DEST_UNREACHABLE: u8 = 3;
+const ICMP_PORT_UNREACHABLE: u8 = 3;
+const IPPROTO_UDP: u8 = 17;
+
+/// How long a blocked read waits before re-checking the stop flag.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+type Seen = Arc<Mutex<HashSet<(Ipv4Addr, u16)>>>;
+
+/// Records ICMP port-unreachable messages for UDP datagrams on a background
+/// thread. Dropping the listener stops the thread.
+pub struct IcmpUnreachableListener {
+    seen: Seen,
+    stop: Arc<AtomicBool>,
+    worker: Option<thread::JoinHandle<()>>,
+}
+
+impl IcmpUnreachableListener {
+    /// Open a raw ICMP socket and start listening. Fails with
+    /// `PermissionDenied` when the process lacks raw-socket privileges.
+    pub fn open() -> std::io::Result<Self> {
+        let socket = Socket::new(Domain::IPV4, Type::RAW, Some(Protocol::ICMPV4))?;
+        socket.set_read_timeout(Some(POLL_INTERVAL))?;
+        let seen: Seen = Arc::default();
+        let stop = Arc::new(AtomicBool::new(false));
+        let worker = {
+            let seen = seen.clone();
+            let stop = stop.clone();
+            thread::spawn(move || listen(socket, seen, stop))
+        };
+        Ok(Self {
+            seen,
+            stop,
+            worker: Some(worker),
+        })
+    }
+
+    /// Whether a port-unreachable has been seen for UDP to `ip:port`.
+    pub fn is_unreachable(&self, ip: Ipv4Addr, port: u16) -> bool {
+        self.seen.lock().unwrap().contains(&(ip, port))
+    }
+
+    /// Stop listening, draining anything already queued on the socket.
+    pub fn finish(mut self) -> HashSet<(Ipv4Addr, u16)> {
+        self.shutdown();
+        std::mem::take(&mut *self.seen.lock().unwrap())
+    }
+
+    fn shutdown(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl Drop for IcmpUnreachableListener {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+fn listen(socket: Socket, seen: Seen, stop: Arc<AtomicBool>) {
+    let mut buf = [0u8; 1500];
+    loop {
+        let stopping = stop.load(Ordering::Relaxed);
+        match (&socket).read(&mut buf) {
+            Ok(n) => {
+                if let Some(target) = parse_port_unreachable(&buf[..n]) {
+                    seen.lock().unwrap().insert(target);
+                }
+            }
+            // Timed out with nothing queued: done if asked to stop
+            Err(_) if stopping => return,
+            Err(_) => {}
+        }
+    }
+}
+
+/// Parse a raw IPv4 packet carrying an ICMP port-unreachable for a UDP
+/// datagram and return the datagram's destination address and port.
+pub fn parse_port_unreachable(packet: &[u8]) -> Option<(Ipv4Addr, u16)> {
+    let icmp = skip_ipv4_header(packet, 1)?;
+    if icmp.len() < 8 || icmp[0] != ICMP_DEST_UNREACHABLE || icmp[1] != ICMP_PORT_UNREACHABLE {
+        return None;
+    }
+    // The quoted datagram: its IP header plus at least the UDP ports
+    let quoted = &icmp[8..];
+    let udp = skip_ipv4_header(quoted, IPPROTO_UDP)?;
+    if udp.len() < 4 {
+        return None;
+    }
+    let dst = Ipv4Addr::new(quoted[16], quoted[17], quoted[18], quoted[19]);
+    Some((dst, u16::from_be_bytes([udp[2], udp[3]])))
+}
+
+/// Return the payload of an IPv4 packet whose protocol is `proto`.
+fn skip_ipv4_header(packet: &[u8], proto: u8) -> Option<&[u8]> {
+    let first = *packet.first()?;
+    let ihl = usize::from(first & 0x0f) * 4;
+    if first >> 4 != 4 || ihl < 20 || packet.len() < ihl || packet[9] != proto {
+        return None;
+    }
+    Some(&packet[ihl..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ipv4_header(proto: u8, src: [u8; 4], dst: [u8; 4]) -> Vec<u8> {
+        let mut h = vec![0x45, 0, 0, 0, 0, 0, 0, 0, 64, proto, 0, 0];
+        h.extend_from_slice(&src);
+        h.extend_from_slice(&dst);
+        h
+    }
+
+    fn unreachable(code: u8, quoted_proto: u8) -> Vec<u8> {
+        let mut pkt = ipv4_header(1, [192, 0, 2, 7], [192, 0, 2, 1]);
+        pkt.extend_from_slice(&[ICMP_DEST_UNREACHABLE, code, 0, 0, 0, 0, 0, 0]);
+        pkt.extend(ipv4_header(quoted_proto, [192, 0, 2, 1], [192, 0, 2, 7]));
+        // UDP: 40000 -> 161
+        pkt.extend_from_slice(&[0x9c, 0x40, 0x00, 0xa1, 0, 8, 0, 0]);
+        pkt
+    }
+
+    #[test]
+    fn parses_quoted_udp_destination() {
+        assert_eq!(
+            parse_port_unreachable(&unreachable(ICMP_PORT_UNREACHABLE, IPPROTO_UDP)),
+            Some((Ipv4Addr::new(192, 0, 2, 7), 161))
+        );
+    }
+
+    #[test]
+    fn ignores_other_icmp_and_truncated_packets() {
+        // host unreachable, or a quoted TCP segment
+        assert_eq!(parse_port_unreachable(&unreachable(1, IPPROTO_UDP)), None);
+        assert_eq!(
+            parse_port_unreachable(&unreachable(ICMP_PORT_UNREACHABLE, 6)),
+            None
+        );
+        let pkt = unreachable(ICMP_PORT_UNREACHABLE, IPPROTO_UDP);
+        assert_eq!(parse_port_unreachable(&pkt[..pkt.len() - 6]), None);
+        assert_eq!(parse_port_unreachable(&[]), None);
+    }
+
+    #[test]
+    fn listener_sees_unreachable_on_loopback() {
+        // Needs raw-socket privileges; nothing to check without them
+        let Ok(listener) = IcmpUnreachableListener::open() else {
+            return;
+        };
+        let closed = {
+            let s = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+            s.local_addr().unwrap().port()
+        };
+        // Unconnected, so the socket itself never learns about the ICMP error
+        let sender = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        sender.send_to(b"x", ("127.0.0.1", closed)).unwrap();
+        for _ in 0..40 {
+            if listener.is_unreachable(Ipv4Addr::LOCALHOST, closed) {
+                break;
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+        assert!(listener.finish().contains(&(Ipv4Addr::LOCALHOST, closed)));
+    }
+}
@@ -0,0 +1,169 @@
+//! Quick TCP liveness check, run before a full port sweep, and the TCP-ping
+//! sweep built on it for ranges ARP cannot reach.
+//!
+//! A host is alive when any of a few common ports answers at all: an
+//! accepted connect or an RST both prove something is there, while a stale
+//! neighbour entry just times out. Hosts that drop every packet look dead
+//! too, which is the price of not needing raw sockets for ICMP.
+
+use std::net::{Ipv4Addr, SocketAddrV4};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use tokio::sync::Semaphore;
+
+use super::{block_on, connect_from, PortScanError};
+
+/// Ports tried by the liveness check: common services on both servers and
+/// desktops, so at least one usually accepts or refuses.
+pub const LIVENESS_PORTS: &[u16] = &[80, 443, 22, 445, 139, 3389];
+
+/// Default ports for `tcp_ping_sweep`.
+pub const TCP_PING_PORTS: &[u16] = &[80, 443, 22, 445];
+
+/// True when `ip` accepts or refuses a connect on any of `ports` within
+/// `timeout`. All ports are tried at once; the first answer wins.
+pub async fn tcp_alive_async(ip: Ipv4Addr, ports: &[u16], timeout: Duration) -> bool {
+    first_answer_async(ip, ports, timeout, None).await.is_some()
+}
+
+/// Time until the first of `ports` answers, or None if none does.
+async fn first_answer_async(
+    ip: Ipv4Addr,
+    ports: &[u16],
+    timeout: Duration,
+    local_addr: Option<Ipv4Addr>,
+) -> Option<Duration> {
+    let start = Instant::now();
+    let mut probes = tokio::task::JoinSet::new();
+    for &port in ports {
+        probes.spawn(answers(SocketAddrV4::new(ip, port), timeout, local_addr));
+    }
+    while let Some(answered) = probes.join_next().await {
+        if answered.unwrap_or(false) {
+            // Dropping the set aborts the connects still pending
+            return Some(start.elapsed());
+        }
+    }
+    None
+}
+
+async fn answers(addr: SocketAddrV4, timeout: Duration, local_addr: Option<Ipv4Addr>) -> bool {
+    match tokio::time::timeout(timeout, connect_from(addr, local_addr)).await {
+        Ok(Ok(_)) => true,
+        Ok(Err(e)) => e.kind() == std::io::ErrorKind::ConnectionRefused,
+        Err(_) => false,
+    }
+}
+
+/// `tcp_alive_async` for many hosts, at most `concurrency` checked at once,
+/// connecting from `local_addr` when given. Returns one flag per entry of
+/// `ips`, in order.
+pub fn tcp_alive_many(
+    ips: &[Ipv4Addr],
+    ports: &[u16],
+    timeout: Duration,
+    concurrency: usize,
+    local_addr: Option<Ipv4Addr>,
+) -> Result<Vec<bool>, PortScanError> {
+    let answers = first_answers(ips, ports, timeout, concurrency, local_addr)?;
+    Ok(answers.iter().map(Option::is_some).collect())
+}
+
+/// Host discovery without ARP, for routed subnets: each of `ips` is up when
+/// any of `probe_ports` (e.g. `TCP_PING_PORTS`) accepts or refuses a connect
+/// within `timeout`. Returns `(ip, up, rtt_ms)` per input address, in order;
+/// `rtt_ms` is the time to the first answer and is None for hosts that are
+/// down. At most `concurrency` hosts are probed at once.
+pub fn tcp_ping_sweep(
+    ips: Vec<Ipv4Addr>,
+    probe_ports: &[u16],
+    timeout: Duration,
+    concurrency: usize,
+) -> Result<Vec<(Ipv4Addr, bool, Option<u128>)>, PortScanError> {
+    let answers = first_answers(&ips, probe_ports, timeout, concurrency, None)?;
+    Ok(ips
+        .into_iter()
+        .zip(answers)
+        .map(|(ip, rtt)| (ip, rtt.is_some(), rtt.map(|d| d.as_millis())))
+        .collect())
+}
+
+fn first_answers(
+    ips: &[Ipv4Addr],
+    ports: &[u16],
+    timeout: Duration,
+    concurrency: usize,
+    local_addr: Option<Ipv4Addr>,
+) -> Result<Vec<Option<Duration>>, PortScanError> {
+    block_on(async {
+        let sem = Arc::new(Semaphore::new(concurrency.max(1)));
+        let ports: Arc<[u16]> = ports.into();
+        let mut handles = Vec::with_capacity(ips.len());
+        for &ip in ips {
+            let sem = sem.clone();
+            let ports = ports.clone();
+            handles.push(tokio::spawn(async move {
+                let _permit = sem.acquire_owned().await.expect("semaphore never closed");
+                first_answer_async(ip, &ports, timeout, local_addr).await
+            }));
+        }
+        let mut answers = Vec::with_capacity(handles.len());
+        for h in handles {
+            answers.push(h.await.unwrap_or(None));
+        }
+        answers
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    #[test]
+    fn listening_or_refusing_host_is_alive() {
+        let open = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).expect("bind");
+        let open_port = open.local_addr().unwrap().port();
+        let closed_port = {
+            let l = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).expect("bind");
+            l.local_addr().unwrap().port()
+        };
+        let timeout = Duration::from_millis(500);
+        let alive = tcp_alive_many(
+            &[Ipv4Addr::LOCALHOST, Ipv4Addr::LOCALHOST],
+            &[open_port],
+            timeout,
+            2,
+            None,
+        )
+        .expect("runtime");
+        assert_eq!(alive, [true, true]);
+        // An RST is an answer too
+        let refused = tcp_alive_many(&[Ipv4Addr::LOCALHOST], &[closed_port], timeout, 1, None);
+        assert_eq!(refused.expect("runtime"), [true]);
+    }
+
+    #[test]
+    fn ping_sweep_reports_rtt_for_answering_hosts() {
+        let open = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).expect("bind");
+        let port = open.local_addr().unwrap().port();
+        let swept = tcp_ping_sweep(
+            vec![Ipv4Addr::LOCALHOST, Ipv4Addr::new(127, 0, 0, 2)],
+            &[port],
+            Duration::from_millis(500),
+            4,
+        )
+        .expect("runtime");
+        assert_eq!(swept.len(), 2);
+        assert_eq!((swept[0].0, swept[0].1), (Ipv4Addr::LOCALHOST, true));
+        assert!(swept[0].2.is_some_and(|ms| ms < 500));
+        let none = tcp_ping_sweep(vec![Ipv4Addr::LOCALHOST], &[], Duration::from_millis(50), 1);
+        assert_eq!(none.expect("runtime"), [(Ipv4Addr::LOCALHOST, false, None)]);
+    }
+
+    #[tokio::test]
+    async fn no_answer_is_not_alive() {
+        assert!(!tcp_alive_async(Ipv4Addr::LOCALHOST, &[], Duration::from_millis(100)).await);
+    }
+}
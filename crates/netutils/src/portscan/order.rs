@@ -0,0 +1,65 @@
+//! Probe dispatch order for the TCP port scans.
+//!
+//! Walking 1..1024 in order is the easiest scan for an IDS to spot and puts
+//! back-to-back load on neighbouring services. `ScanOrder::Random` shuffles
+//! the order in which probes are started; results are still reported in
+//! port order, so only the traffic pattern changes.
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+
+/// Order in which a scan starts its probes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ScanOrder {
+    /// Ports in the order given, hosts round-robin.
+    #[default]
+    Sequential,
+    /// Ports shuffled and, for multi-host scans, hosts interleaved at
+    /// random. A fixed `seed` reproduces the same order on every run.
+    Random { seed: Option<u64> },
+}
+
+impl ScanOrder {
+    pub fn is_random(&self) -> bool {
+        matches!(self, ScanOrder::Random { .. })
+    }
+
+    /// Reorder `items` into dispatch order; a no-op for `Sequential`.
+    pub(super) fn arrange<T>(&self, items: &mut [T]) {
+        match *self {
+            ScanOrder::Sequential => {}
+            ScanOrder::Random { seed: Some(seed) } => {
+                items.shuffle(&mut StdRng::seed_from_u64(seed))
+            }
+            ScanOrder::Random { seed: None } => items.shuffle(&mut rand::thread_rng()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seeded_shuffle_is_reproducible() {
+        let ports: Vec<u16> = (1..=1024).collect();
+        let shuffled = |order: ScanOrder| {
+            let mut p = ports.clone();
+            order.arrange(&mut p);
+            p
+        };
+        let seeded = ScanOrder::Random { seed: Some(42) };
+        assert_eq!(shuffled(seeded), shuffled(seeded));
+        assert_ne!(shuffled(seeded), ports);
+        assert_ne!(
+            shuffled(seeded),
+            shuffled(ScanOrder::Random { seed: Some(7) })
+        );
+        assert_eq!(shuffled(ScanOrder::Sequential), ports);
+
+        let mut back = shuffled(ScanOrder::Random { seed: None });
+        back.sort_unstable();
+        assert_eq!(back, ports);
+    }
+}
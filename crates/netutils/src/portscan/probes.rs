@@ -0,0 +1,539 @@
+//! Protocol-specific probes for services that stay silent until spoken to.
+//!
+//! Each probe knows what to send and how to turn the reply into a short
+//! banner. Payload building and reply parsing are pure so they can be tested
+//! without a network.
+
+use std::collections::HashMap;
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use super::{udp_probe_payload, ScanOrder};
+
+/// What to send after connecting to a port.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServiceProbe {
+    /// Send nothing; read whatever the server volunteers
+    Passive,
+    /// `GET / HTTP/1.0`; banner is the status line plus `Server` header
+    HttpGet,
+    /// TLS 1.2 ClientHello; banner describes the ServerHello or alert
+    TlsClientHello,
+    /// DNS-over-TCP `version.bind` CHAOS TXT query
+    DnsVersionBind,
+    /// Redis inline `PING`
+    RedisPing,
+}
+
+impl ServiceProbe {
+    /// Pick the probe for a well-known port; unknown ports stay passive.
+    pub fn for_port(port: u16) -> Self {
+        match port {
+            80 | 8000 | 8008 | 8080 | 8081 | 8888 => ServiceProbe::HttpGet,
+            443 | 465 | 636 | 853 | 993 | 995 | 8443 => ServiceProbe::TlsClientHello,
+            53 => ServiceProbe::DnsVersionBind,
+            6379 => ServiceProbe::RedisPing,
+            _ => ServiceProbe::Passive,
+        }
+    }
+
+    /// Bytes to send once connected (`None` for passive reads).
+    pub fn payload(&self, host: Ipv4Addr) -> Option<Vec<u8>> {
+        match self {
+            ServiceProbe::Passive => None,
+            ServiceProbe::HttpGet => Some(
+                format!(
+                    "GET / HTTP/1.0\r\nHost: {}\r\nUser-Agent: netutils-probe\r\nAccept: */*\r\n\r\n",
+                    host
+                )
+                .into_bytes(),
+            ),
+            ServiceProbe::TlsClientHello => Some(tls_client_hello()),
+            ServiceProbe::DnsVersionBind => Some(dns_version_bind_query()),
+            ServiceProbe::RedisPing => Some(b"PING\r\n".to_vec()),
+        }
+    }
+
+    /// Turn a (possibly partial) reply into a banner. Returns None until the
+    /// reply holds enough to describe the service.
+    pub fn interpret(&self, reply: &[u8]) -> Option<String> {
+        if reply.is_empty() {
+            return None;
+        }
+        match self {
+            ServiceProbe::HttpGet => http_banner(reply),
+            ServiceProbe::TlsClientHello => tls_banner(reply),
+            ServiceProbe::DnsVersionBind => dns_banner(reply),
+            ServiceProbe::Passive | ServiceProbe::RedisPing => {
+                Some(String::from_utf8_lossy(reply).into_owned())
+            }
+        }
+    }
+}
+
+/// Nudges sent by the port scanners when a port stays silent after connect.
+///
+/// `probes` maps ports to payloads; `fallback` is sent to silent ports not
+/// in the table. Talk-first services (SSH, SMTP, FTP, ...) have already
+/// answered by then, so they never see a probe. Open ports listed in
+/// `tls_ports` get a TLS handshake instead (needs the `tls` feature).
+/// TCP connects that time out are retried up to `retries` times. Each read
+/// waits up to `banner_timeout`; with `grab_banners` off nothing is read or
+/// sent and the connection is closed as soon as it is established. Ports in
+/// `exclude_ports` are never connected to, even when asked for explicitly.
+/// `order` decides the order probes are started in (see `ScanOrder`).
+/// `local_addr` binds every connect to that source address, for hosts
+/// with more than one network (VPN + LAN); `None` follows the routing table.
+/// `rate_limit` caps connect attempts (retries included) per second across
+/// the whole scan, on top of its concurrency cap; `None` leaves them unpaced.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProbeStrategy {
+    pub probes: HashMap<u16, Vec<u8>>,
+    pub fallback: Option<Vec<u8>>,
+    pub tls_ports: Vec<u16>,
+    pub retries: u8,
+    pub banner_timeout: Duration,
+    pub grab_banners: bool,
+    pub exclude_ports: Vec<u16>,
+    pub order: ScanOrder,
+    pub local_addr: Option<Ipv4Addr>,
+    pub rate_limit: Option<u32>,
+}
+
+/// How long the scanners wait for a banner unless told otherwise.
+pub const DEFAULT_BANNER_TIMEOUT: Duration = Duration::from_millis(300);
+
+/// Ports that get `HEAD / HTTP/1.0` in the default strategy.
+pub const HTTP_PROBE_PORTS: &[u16] = &[80, 8000, 8008, 8080, 8081, 8888];
+
+impl Default for ProbeStrategy {
+    /// `HEAD / HTTP/1.0` for the common HTTP ports, `\r\n` for anything else.
+    fn default() -> Self {
+        let head = b"HEAD / HTTP/1.0\r\n\r\n".to_vec();
+        Self {
+            probes: HTTP_PROBE_PORTS
+                .iter()
+                .map(|p| (*p, head.clone()))
+                .collect(),
+            fallback: Some(b"\r\n".to_vec()),
+            tls_ports: Vec::new(),
+            retries: 0,
+            banner_timeout: DEFAULT_BANNER_TIMEOUT,
+            grab_banners: true,
+            exclude_ports: Vec::new(),
+            order: ScanOrder::Sequential,
+            local_addr: None,
+            rate_limit: None,
+        }
+    }
+}
+
+impl ProbeStrategy {
+    /// Never send anything; banners come from passive reads only.
+    pub fn passive() -> Self {
+        Self {
+            probes: HashMap::new(),
+            fallback: None,
+            tls_ports: Vec::new(),
+            retries: 0,
+            banner_timeout: DEFAULT_BANNER_TIMEOUT,
+            grab_banners: true,
+            exclude_ports: Vec::new(),
+            order: ScanOrder::Sequential,
+            local_addr: None,
+            rate_limit: None,
+        }
+    }
+
+    /// Add or replace the payload for `port`.
+    pub fn with_probe(mut self, port: u16, payload: impl Into<Vec<u8>>) -> Self {
+        self.probes.insert(port, payload.into());
+        self
+    }
+
+    /// Payload for ports without a table entry (`None` disables the nudge).
+    pub fn with_fallback(mut self, payload: Option<Vec<u8>>) -> Self {
+        self.fallback = payload;
+        self
+    }
+
+    /// Read the certificate of open well-known TLS ports (`TLS_PROBE_PORTS`)
+    /// into `PortResult::tls`, or stop doing so. Without the `tls` feature
+    /// this has no effect.
+    pub fn with_tls_probe(mut self, enabled: bool) -> Self {
+        self.tls_ports = if enabled {
+            super::TLS_PROBE_PORTS.to_vec()
+        } else {
+            Vec::new()
+        };
+        self
+    }
+
+    /// Also TLS-probe `port`, e.g. HTTPS on an odd port.
+    pub fn with_tls_port(mut self, port: u16) -> Self {
+        if !self.tls_ports.contains(&port) {
+            self.tls_ports.push(port);
+        }
+        self
+    }
+
+    /// Retry a timed-out TCP connect up to `retries` more times, after a short
+    /// jittered pause. Refused connects are definitive and never retried.
+    pub fn with_retries(mut self, retries: u8) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Wait up to `timeout` for each banner read (default 300 ms): longer
+    /// for slow embedded devices, shorter for fast sweeps.
+    pub fn with_banner_timeout(mut self, timeout: Duration) -> Self {
+        self.banner_timeout = timeout;
+        self
+    }
+
+    /// Read banners (the default) or just check that the port accepts.
+    pub fn with_banner_grab(mut self, enabled: bool) -> Self {
+        self.grab_banners = enabled;
+        self
+    }
+
+    /// Never probe `ports` (printers that misbehave on 9100, appliance
+    /// management ports, ...); they are left out of the results.
+    pub fn with_excluded_ports(mut self, ports: Vec<u16>) -> Self {
+        self.exclude_ports = ports;
+        self
+    }
+
+    /// Start probes in `order`: `ScanOrder::Random` shuffles ports (and
+    /// interleaves hosts in `scan_many*`) and reports results sorted by port.
+    pub fn with_order(mut self, order: ScanOrder) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// Connect from `addr` instead of the address the routing table picks.
+    pub fn with_local_addr(mut self, addr: Option<Ipv4Addr>) -> Self {
+        self.local_addr = addr;
+        self
+    }
+
+    /// Space connect attempts to at most `rate` per second (`None`: unpaced).
+    pub fn with_rate_limit(mut self, rate: Option<u32>) -> Self {
+        self.rate_limit = rate;
+        self
+    }
+
+    /// Whether `port` is in `exclude_ports`.
+    pub(super) fn excludes(&self, port: u16) -> bool {
+        self.exclude_ports.contains(&port)
+    }
+
+    /// `ports` minus `exclude_ports`, in order.
+    pub(super) fn without_excluded(&self, ports: Vec<u16>) -> Vec<u16> {
+        if self.exclude_ports.is_empty() {
+            return ports;
+        }
+        ports.into_iter().filter(|p| !self.excludes(*p)).collect()
+    }
+
+    /// What to send to a silent `port`, if anything.
+    pub fn payload_for(&self, port: u16) -> Option<&[u8]> {
+        self.probes
+            .get(&port)
+            .or(self.fallback.as_ref())
+            .map(Vec::as_slice)
+    }
+}
+
+/// Payload the UDP port scanner sends to `port`: its `udp_probe_payload`,
+/// or an empty datagram.
+pub fn udp_payload_for(port: u16) -> &'static [u8] {
+    udp_probe_payload(port).unwrap_or_default()
+}
+
+fn http_banner(reply: &[u8]) -> Option<String> {
+    let text = String::from_utf8_lossy(reply);
+    let head_end = text.find("\r\n\r\n").or_else(|| text.find("\n\n"))?;
+    let mut lines = text[..head_end].lines();
+    let status = lines.next()?.trim();
+    if !status.starts_with("HTTP/") {
+        return Some(status.to_string());
+    }
+    let server = lines.find_map(|l| {
+        let (name, value) = l.split_once(':')?;
+        name.trim()
+            .eq_ignore_ascii_case("server")
+            .then(|| value.trim().to_string())
+    });
+    Some(match server {
+        Some(s) => format!("{} Server: {}", status, s),
+        None => status.to_string(),
+    })
+}
+
+/// A TLS 1.2 ClientHello offering common ECDHE/RSA suites, without SNI.
+fn tls_client_hello() -> Vec<u8> {
+    const CIPHERS: &[u16] = &[
+        0xc02f, 0xc02b, 0xc030, 0xc02c, 0xcca8, 0xcca9, 0xc013, 0xc009, 0xc014, 0xc00a, 0x009c,
+        0x009d, 0x002f, 0x0035,
+    ];
+    let mut ext = Vec::new();
+    // supported_groups: x25519, secp256r1, secp384r1
+    ext.extend_from_slice(&[
+        0x00, 0x0a, 0x00, 0x08, 0x00, 0x06, 0x00, 0x1d, 0x00, 0x17, 0x00, 0x18,
+    ]);
+    // ec_point_formats: uncompressed
+    ext.extend_from_slice(&[0x00, 0x0b, 0x00, 0x02, 0x01, 0x00]);
+    // signature_algorithms
+    let sig_algs: &[u16] = &[0x0403, 0x0804, 0x0401, 0x0503, 0x0805, 0x0501, 0x0201];
+    ext.extend_from_slice(&[0x00, 0x0d]);
+    ext.extend_from_slice(&((sig_algs.len() * 2 + 2) as u16).to_be_bytes());
+    ext.extend_from_slice(&((sig_algs.len() * 2) as u16).to_be_bytes());
+    sig_algs
+        .iter()
+        .for_each(|a| ext.extend_from_slice(&a.to_be_bytes()));
+
+    let mut body = vec![0x03, 0x03];
+    body.extend((0u8..32).map(|i| i.wrapping_mul(37).wrapping_add(11)));
+    body.push(0); // no session id
+    body.extend_from_slice(&((CIPHERS.len() * 2) as u16).to_be_bytes());
+    CIPHERS
+        .iter()
+        .for_each(|c| body.extend_from_slice(&c.to_be_bytes()));
+    body.extend_from_slice(&[0x01, 0x00]); // null compression only
+    body.extend_from_slice(&(ext.len() as u16).to_be_bytes());
+    body.extend_from_slice(&ext);
+
+    let mut handshake = vec![0x01];
+    handshake.extend_from_slice(&(body.len() as u32).to_be_bytes()[1..]);
+    handshake.extend_from_slice(&body);
+
+    let mut record = vec![0x16, 0x03, 0x01];
+    record.extend_from_slice(&(handshake.len() as u16).to_be_bytes());
+    record.extend_from_slice(&handshake);
+    record
+}
+
+fn tls_version_name(v: u16) -> String {
+    match v {
+        0x0300 => "SSL 3.0".to_string(),
+        0x0301 => "TLS 1.0".to_string(),
+        0x0302 => "TLS 1.1".to_string(),
+        0x0303 => "TLS 1.2".to_string(),
+        0x0304 => "TLS 1.3".to_string(),
+        other => format!("TLS 0x{:04x}", other),
+    }
+}
+
+fn tls_banner(reply: &[u8]) -> Option<String> {
+    if reply.len() < 5 {
+        return None;
+    }
+    match reply[0] {
+        // alert: level, description
+        0x15 if reply.len() >= 7 => Some(format!("TLS alert {}", reply[6])),
+        0x16 => {
+            // record header (5) + handshake type (1) + length (3) + version (2)
+            // + random (32) + session id length (1)
+            let hs = &reply[5..];
+            if hs.len() < 39 || hs[0] != 0x02 {
+                return (hs.len() >= 39).then(|| "TLS".to_string());
+            }
+            let version = u16::from_be_bytes([hs[4], hs[5]]);
+            let sid_len = hs[38] as usize;
+            let cipher_at = 39 + sid_len;
+            let cipher = hs.get(cipher_at..cipher_at + 2)?;
+            Some(format!(
+                "{} ServerHello cipher 0x{:02x}{:02x}",
+                tls_version_name(version),
+                cipher[0],
+                cipher[1]
+            ))
+        }
+        _ => Some(String::from_utf8_lossy(reply).into_owned()),
+    }
+}
+
+const DNS_QUERY_ID: u16 = 0x6e73;
+
+/// `version.bind TXT CH`, framed for DNS over TCP (2-byte length prefix).
+fn dns_version_bind_query() -> Vec<u8> {
+    let mut msg = Vec::new();
+    msg.extend_from_slice(&DNS_QUERY_ID.to_be_bytes());
+    msg.extend_from_slice(&[0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+    msg.extend_from_slice(b"\x07version\x04bind\x00");
+    msg.extend_from_slice(&[0x00, 0x10, 0x00, 0x03]); // TXT, CH
+    let mut framed = (msg.len() as u16).to_be_bytes().to_vec();
+    framed.extend_from_slice(&msg);
+    framed
+}
+
+/// Skip a (possibly compressed) domain name starting at `at`.
+fn skip_dns_name(msg: &[u8], mut at: usize) -> Option<usize> {
+    loop {
+        let len = *msg.get(at)? as usize;
+        if len == 0 {
+            return Some(at + 1);
+        }
+        if len & 0xc0 == 0xc0 {
+            return Some(at + 2);
+        }
+        at += 1 + len;
+    }
+}
+
+fn dns_banner(reply: &[u8]) -> Option<String> {
+    if reply.len() < 2 {
+        return None;
+    }
+    let len = u16::from_be_bytes([reply[0], reply[1]]) as usize;
+    let msg = reply.get(2..2 + len)?;
+    if msg.len() < 12 || u16::from_be_bytes([msg[0], msg[1]]) != DNS_QUERY_ID {
+        return Some("DNS".to_string());
+    }
+    let rcode = msg[3] & 0x0f;
+    let qdcount = u16::from_be_bytes([msg[4], msg[5]]);
+    let ancount = u16::from_be_bytes([msg[6], msg[7]]);
+
+    let mut at = 12;
+    for _ in 0..qdcount {
+        at = skip_dns_name(msg, at)? + 4;
+    }
+    for _ in 0..ancount {
+        at = skip_dns_name(msg, at)?;
+        let rtype = u16::from_be_bytes([*msg.get(at)?, *msg.get(at + 1)?]);
+        let rdlen = u16::from_be_bytes([*msg.get(at + 8)?, *msg.get(at + 9)?]) as usize;
+        let rdata = msg.get(at + 10..at + 10 + rdlen)?;
+        at += 10 + rdlen;
+        if rtype == 16 {
+            let txt_len = *rdata.first()? as usize;
+            let txt = rdata.get(1..1 + txt_len)?;
+            return Some(format!("DNS version: {}", String::from_utf8_lossy(txt)));
+        }
+    }
+    Some(format!("DNS (version hidden, rcode {})", rcode))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ports_map_to_probes() {
+        assert_eq!(ServiceProbe::for_port(80), ServiceProbe::HttpGet);
+        assert_eq!(ServiceProbe::for_port(8080), ServiceProbe::HttpGet);
+        assert_eq!(ServiceProbe::for_port(443), ServiceProbe::TlsClientHello);
+        assert_eq!(ServiceProbe::for_port(53), ServiceProbe::DnsVersionBind);
+        assert_eq!(ServiceProbe::for_port(22), ServiceProbe::Passive);
+        assert!(ServiceProbe::Passive.payload(Ipv4Addr::LOCALHOST).is_none());
+    }
+
+    #[test]
+    fn probe_table_lookup_and_extension() {
+        let s = ProbeStrategy::default();
+        assert_eq!(s.payload_for(8080), Some(&b"HEAD / HTTP/1.0\r\n\r\n"[..]));
+        assert_eq!(s.payload_for(5000), Some(&b"\r\n"[..]));
+        let s = s.with_probe(5000, "STATUS\n").with_fallback(None);
+        assert_eq!(s.payload_for(5000), Some(&b"STATUS\n"[..]));
+        assert_eq!(s.payload_for(5001), None);
+        assert_eq!(ProbeStrategy::passive().payload_for(80), None);
+    }
+
+    #[test]
+    fn udp_payloads_are_well_formed() {
+        assert_eq!(udp_payload_for(123).len(), 48);
+        assert_eq!(udp_payload_for(123)[0] & 0x07, 3); // client mode
+        let snmp = udp_payload_for(161);
+        assert_eq!(snmp[1] as usize, snmp.len() - 2);
+        let dns = udp_payload_for(53);
+        assert_eq!(u16::from_be_bytes([dns[4], dns[5]]), 1); // one question
+        assert!(udp_payload_for(9999).is_empty());
+    }
+
+    #[test]
+    fn http_reply_yields_status_and_server() {
+        let p = ServiceProbe::HttpGet;
+        let req = String::from_utf8(p.payload(Ipv4Addr::new(192, 0, 2, 1)).unwrap()).unwrap();
+        assert!(req.starts_with("GET / HTTP/1.0\r\nHost: 192.0.2.1\r\n"));
+        assert!(req.ends_with("\r\n\r\n"));
+
+        let reply =
+            b"HTTP/1.1 301 Moved Permanently\r\nserver: nginx/1.24.0\r\nLocation: /\r\n\r\n";
+        assert_eq!(
+            p.interpret(reply).as_deref(),
+            Some("HTTP/1.1 301 Moved Permanently Server: nginx/1.24.0")
+        );
+        // headers not complete yet
+        assert!(p.interpret(b"HTTP/1.1 200 OK\r\nServer: x").is_none());
+    }
+
+    #[test]
+    fn client_hello_is_well_formed() {
+        let hello = tls_client_hello();
+        assert_eq!(&hello[..3], &[0x16, 0x03, 0x01]);
+        let record_len = u16::from_be_bytes([hello[3], hello[4]]) as usize;
+        assert_eq!(record_len, hello.len() - 5);
+        assert_eq!(hello[5], 0x01);
+        let hs_len = u32::from_be_bytes([0, hello[6], hello[7], hello[8]]) as usize;
+        assert_eq!(hs_len, hello.len() - 9);
+        assert_eq!(&hello[9..11], &[0x03, 0x03]);
+    }
+
+    #[test]
+    fn tls_server_hello_and_alert() {
+        let mut hello = vec![
+            0x16, 0x03, 0x03, 0x00, 0x4a, 0x02, 0x00, 0x00, 0x46, 0x03, 0x03,
+        ];
+        hello.extend([0xab; 32]);
+        hello.push(32);
+        hello.extend([0xcd; 32]);
+        hello.extend([0xc0, 0x2f, 0x00]);
+        let p = ServiceProbe::TlsClientHello;
+        assert_eq!(
+            p.interpret(&hello).as_deref(),
+            Some("TLS 1.2 ServerHello cipher 0xc02f")
+        );
+        // truncated before the cipher suite: wait for more data
+        assert!(p.interpret(&hello[..60]).is_none());
+
+        let alert = [0x15, 0x03, 0x03, 0x00, 0x02, 0x02, 0x28];
+        assert_eq!(p.interpret(&alert).as_deref(), Some("TLS alert 40"));
+    }
+
+    #[test]
+    fn dns_version_bind_roundtrip() {
+        let query = dns_version_bind_query();
+        assert_eq!(
+            u16::from_be_bytes([query[0], query[1]]) as usize,
+            query.len() - 2
+        );
+
+        // response: echo the question, one TXT answer via name pointer 0xc00c
+        let mut msg = query[2..].to_vec();
+        msg[2] = 0x84; // QR + AA
+        msg[7] = 1; // ancount
+        msg.extend_from_slice(&[0xc0, 0x0c, 0x00, 0x10, 0x00, 0x03, 0, 0, 0, 0]);
+        let txt = b"9.18.24-1-Debian";
+        msg.extend_from_slice(&((txt.len() + 1) as u16).to_be_bytes());
+        msg.push(txt.len() as u8);
+        msg.extend_from_slice(txt);
+        let mut reply = (msg.len() as u16).to_be_bytes().to_vec();
+        reply.extend_from_slice(&msg);
+
+        let p = ServiceProbe::DnsVersionBind;
+        assert_eq!(
+            p.interpret(&reply).as_deref(),
+            Some("DNS version: 9.18.24-1-Debian")
+        );
+
+        // refused, no answers
+        let mut refused = query[2..].to_vec();
+        refused[3] = 0x05;
+        let mut reply = (refused.len() as u16).to_be_bytes().to_vec();
+        reply.extend_from_slice(&refused);
+        assert_eq!(
+            p.interpret(&reply).as_deref(),
+            Some("DNS (version hidden, rcode 5)")
+        );
+    }
+}
@@ -0,0 +1,124 @@
+//! Connect budget for the TCP scans: a concurrency cap plus an optional
+//! attempts-per-second limit and cancellation token.
+//!
+//! The semaphore bounds how many connections are open at once; the rate
+//! limiter bounds how often a new one may start, which is what switches and
+//! IDS boxes actually react to. The limiter is a single-token bucket kept as
+//! the time the next attempt is allowed (GCRA, as in `governor`), so attempts
+//! are spaced evenly rather than released in bursts.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::{Mutex, OwnedSemaphorePermit, Semaphore};
+use tokio::time::Instant;
+
+use crate::cancel::CancelToken;
+
+/// Spaces connect attempts at least `interval` apart.
+struct RateLimiter {
+    interval: Duration,
+    next: Mutex<Option<Instant>>,
+}
+
+impl RateLimiter {
+    /// Reserve the next free slot and sleep until it comes up.
+    async fn wait(&self) {
+        let at = {
+            let mut next = self.next.lock().await;
+            let now = Instant::now();
+            let at = next.map_or(now, |n| n.max(now));
+            *next = Some(at + self.interval);
+            at
+        };
+        tokio::time::sleep_until(at).await;
+    }
+}
+
+/// Shared by every probe of one scan call.
+pub(super) struct ConnectGate {
+    sem: Arc<Semaphore>,
+    rate: Option<RateLimiter>,
+    cancel: Option<CancelToken>,
+}
+
+impl ConnectGate {
+    /// `concurrency` simultaneous connections (at least one) and, with
+    /// `rate_limit`, at most that many connect attempts per second.
+    /// `Some(0)` is treated like `None`. Once `cancel` fires no further
+    /// attempts are let through.
+    pub(super) fn new(
+        concurrency: usize,
+        rate_limit: Option<u32>,
+        cancel: Option<CancelToken>,
+    ) -> Arc<Self> {
+        Arc::new(Self {
+            sem: Arc::new(Semaphore::new(concurrency.max(1))),
+            rate: rate_limit.filter(|&r| r > 0).map(|r| RateLimiter {
+                interval: Duration::from_secs(1) / r,
+                next: Mutex::new(None),
+            }),
+            cancel,
+        })
+    }
+
+    /// Wait for a concurrency permit, then for the rate limiter; the
+    /// connect attempt may start once this returns `Some`. Hold the permit
+    /// for as long as the connection is open. `None` means the scan was
+    /// cancelled and the attempt must not start.
+    pub(super) async fn acquire(&self) -> Option<OwnedSemaphorePermit> {
+        let permit = self
+            .sem
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("semaphore never closed");
+        if let Some(rate) = &self.rate {
+            if self.is_cancelled() {
+                return None;
+            }
+            rate.wait().await;
+        }
+        (!self.is_cancelled()).then_some(permit)
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancel.as_ref().is_some_and(|c| c.is_cancelled())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn rate_limit_spaces_attempts_evenly() {
+        let gate = ConnectGate::new(8, Some(20), None);
+        let start = Instant::now();
+        for _ in 0..5 {
+            drop(gate.acquire().await);
+        }
+        // First attempt is immediate, the other four 50 ms apart
+        assert!(start.elapsed() >= Duration::from_millis(200));
+    }
+
+    #[tokio::test]
+    async fn zero_rate_means_unlimited() {
+        let gate = ConnectGate::new(1, Some(0), None);
+        assert!(gate.rate.is_none());
+        let start = Instant::now();
+        for _ in 0..100 {
+            drop(gate.acquire().await);
+        }
+        assert!(start.elapsed() < Duration::from_millis(100));
+    }
+
+    #[tokio::test]
+    async fn cancelled_gate_lets_nothing_through() {
+        let cancel = CancelToken::new();
+        let gate = ConnectGate::new(4, Some(1000), Some(cancel.clone()));
+        assert!(gate.acquire().await.is_some());
+        cancel.cancel();
+        assert!(gate.acquire().await.is_none());
+    }
+}
@@ -0,0 +1,300 @@
+//! Static IANA / nmap-services style port-to-service-name tables.
+
+use phf::phf_map;
+
+/// Well-known TCP services (top ports by observed frequency).
+static TCP_SERVICES: phf::Map<u16, &'static str> = phf_map! {
+    1u16 => "tcpmux",
+    5u16 => "rje",
+    7u16 => "echo",
+    9u16 => "discard",
+    11u16 => "systat",
+    13u16 => "daytime",
+    17u16 => "qotd",
+    19u16 => "chargen",
+    20u16 => "ftp-data",
+    21u16 => "ftp",
+    22u16 => "ssh",
+    23u16 => "telnet",
+    25u16 => "smtp",
+    26u16 => "rsftp",
+    37u16 => "time",
+    42u16 => "nameserver",
+    43u16 => "whois",
+    49u16 => "tacacs",
+    53u16 => "domain",
+    70u16 => "gopher",
+    79u16 => "finger",
+    80u16 => "http",
+    81u16 => "hosts2-ns",
+    82u16 => "xfer",
+    83u16 => "mit-ml-dev",
+    84u16 => "ctf",
+    85u16 => "mit-ml-dev",
+    88u16 => "kerberos-sec",
+    89u16 => "su-mit-tg",
+    90u16 => "dnsix",
+    99u16 => "metagram",
+    100u16 => "newacct",
+    106u16 => "pop3pw",
+    109u16 => "pop2",
+    110u16 => "pop3",
+    111u16 => "rpcbind",
+    113u16 => "ident",
+    119u16 => "nntp",
+    125u16 => "locus-map",
+    135u16 => "msrpc",
+    139u16 => "netbios-ssn",
+    143u16 => "imap",
+    144u16 => "news",
+    146u16 => "iso-tp0",
+    161u16 => "snmp",
+    163u16 => "cmip-man",
+    179u16 => "bgp",
+    199u16 => "smux",
+    211u16 => "914c-g",
+    212u16 => "anet",
+    222u16 => "rsh-spx",
+    256u16 => "fw1-secureremote",
+    259u16 => "esro-gen",
+    264u16 => "bgmp",
+    280u16 => "http-mgmt",
+    311u16 => "asip-webadmin",
+    366u16 => "odmr",
+    389u16 => "ldap",
+    406u16 => "imsp",
+    407u16 => "timbuktu",
+    416u16 => "silverplatter",
+    417u16 => "onmux",
+    425u16 => "icad-el",
+    427u16 => "svrloc",
+    443u16 => "https",
+    444u16 => "snpp",
+    445u16 => "microsoft-ds",
+    458u16 => "appleqtc",
+    464u16 => "kpasswd5",
+    465u16 => "smtps",
+    481u16 => "dvs",
+    497u16 => "retrospect",
+    500u16 => "isakmp",
+    512u16 => "exec",
+    513u16 => "login",
+    514u16 => "shell",
+    515u16 => "printer",
+    524u16 => "ncp",
+    541u16 => "uucp-rlogin",
+    543u16 => "klogin",
+    544u16 => "kshell",
+    545u16 => "ekshell",
+    548u16 => "afp",
+    554u16 => "rtsp",
+    555u16 => "dsf",
+    563u16 => "snews",
+    587u16 => "submission",
+    593u16 => "http-rpc-epmap",
+    616u16 => "sco-sysmgr",
+    617u16 => "sco-dtmgr",
+    625u16 => "apple-xsrvr-admin",
+    631u16 => "ipp",
+    636u16 => "ldapssl",
+    646u16 => "ldp",
+    648u16 => "rrp",
+    666u16 => "doom",
+    667u16 => "disclose",
+    668u16 => "mecomm",
+    683u16 => "corba-iiop",
+    687u16 => "asipregistry",
+    691u16 => "resvc",
+    700u16 => "epp",
+    705u16 => "agentx",
+    711u16 => "cisco-tdp",
+    714u16 => "iris-xpcs",
+    749u16 => "kerberos-adm",
+    765u16 => "webster",
+    777u16 => "multiling-http",
+    783u16 => "spamassassin",
+    787u16 => "qsc",
+    800u16 => "mdbs_daemon",
+    801u16 => "device",
+    808u16 => "ccproxy-http",
+    873u16 => "rsync",
+    888u16 => "accessbuilder",
+    898u16 => "sun-manageconsole",
+    900u16 => "omginitialrefs",
+    901u16 => "samba-swat",
+    902u16 => "iss-realsecure",
+    903u16 => "iss-console-mgr",
+    911u16 => "xact-backup",
+    912u16 => "apex-mesh",
+    989u16 => "ftps-data",
+    990u16 => "ftps",
+    992u16 => "telnets",
+    993u16 => "imaps",
+    995u16 => "pop3s",
+    999u16 => "garcon",
+    1000u16 => "cadlock",
+    1025u16 => "NFS-or-IIS",
+    1080u16 => "socks",
+    1194u16 => "openvpn",
+    1214u16 => "fasttrack",
+    1241u16 => "nessus",
+    1311u16 => "rxmon",
+    1337u16 => "waste",
+    1433u16 => "ms-sql-s",
+    1434u16 => "ms-sql-m",
+    1521u16 => "oracle",
+    1604u16 => "citrix-ica",
+    1701u16 => "l2tp",
+    1720u16 => "h323q931",
+    1723u16 => "pptp",
+    1755u16 => "wms",
+    1812u16 => "radius",
+    1883u16 => "mqtt",
+    1900u16 => "upnp",
+    2000u16 => "cisco-sccp",
+    2049u16 => "nfs",
+    2082u16 => "infowave",
+    2083u16 => "radsec",
+    2086u16 => "gnunet",
+    2087u16 => "eli",
+    2121u16 => "ccproxy-ftp",
+    2181u16 => "eforward",
+    2222u16 => "EtherNetIP-1",
+    2375u16 => "docker",
+    2376u16 => "docker-s",
+    2483u16 => "ttc",
+    2484u16 => "ttc-ssl",
+    3000u16 => "ppp",
+    3128u16 => "squid-http",
+    3260u16 => "iscsi",
+    3268u16 => "globalcatLDAP",
+    3269u16 => "globalcatLDAPssl",
+    3306u16 => "mysql",
+    3389u16 => "ms-wbt-server",
+    3478u16 => "stun",
+    3690u16 => "svn",
+    3986u16 => "mapper-ws_ethd",
+    4369u16 => "epmd",
+    4443u16 => "pharos",
+    4500u16 => "sae-urn",
+    4899u16 => "radmin",
+    5000u16 => "upnp",
+    5001u16 => "commplex-link",
+    5060u16 => "sip",
+    5061u16 => "sip-tls",
+    5222u16 => "xmpp-client",
+    5269u16 => "xmpp-server",
+    5353u16 => "mdns",
+    5357u16 => "wsdapi",
+    5432u16 => "postgresql",
+    5555u16 => "freeciv",
+    5601u16 => "esmagent",
+    5672u16 => "amqp",
+    5800u16 => "vnc-http",
+    5900u16 => "vnc",
+    5984u16 => "couchdb",
+    5985u16 => "wsman",
+    5986u16 => "wsmans",
+    6000u16 => "X11",
+    6379u16 => "redis",
+    6443u16 => "sun-sr-https",
+    6667u16 => "irc",
+    6881u16 => "bittorrent-tracker",
+    7000u16 => "afs3-fileserver",
+    7001u16 => "afs3-callback",
+    7070u16 => "realserver",
+    7199u16 => "cassandra",
+    8000u16 => "http-alt",
+    8008u16 => "http",
+    8009u16 => "ajp13",
+    8080u16 => "http-proxy",
+    8081u16 => "blackice-icecap",
+    8086u16 => "d-s-n",
+    8088u16 => "radan-http",
+    8443u16 => "https-alt",
+    8888u16 => "sun-answerbook",
+    9000u16 => "cslistener",
+    9001u16 => "tor-orport",
+    9042u16 => "cassandra-native",
+    9090u16 => "zeus-admin",
+    9100u16 => "jetdirect",
+    9200u16 => "elasticsearch",
+    9300u16 => "vrace",
+    9418u16 => "git",
+    9999u16 => "abyss",
+    10000u16 => "snet-sensor-mgmt",
+    11211u16 => "memcache",
+    15672u16 => "rabbitmq-mgmt",
+    25565u16 => "minecraft",
+    27017u16 => "mongod",
+    50000u16 => "ibm-db2",
+};
+
+/// Well-known UDP services.
+static UDP_SERVICES: phf::Map<u16, &'static str> = phf_map! {
+    7u16 => "echo",
+    9u16 => "discard",
+    13u16 => "daytime",
+    17u16 => "qotd",
+    19u16 => "chargen",
+    53u16 => "domain",
+    67u16 => "dhcps",
+    68u16 => "dhcpc",
+    69u16 => "tftp",
+    88u16 => "kerberos-sec",
+    111u16 => "rpcbind",
+    123u16 => "ntp",
+    135u16 => "msrpc",
+    137u16 => "netbios-ns",
+    138u16 => "netbios-dgm",
+    139u16 => "netbios-ssn",
+    161u16 => "snmp",
+    162u16 => "snmptrap",
+    177u16 => "xdmcp",
+    389u16 => "ldap",
+    427u16 => "svrloc",
+    443u16 => "https",
+    445u16 => "microsoft-ds",
+    464u16 => "kpasswd5",
+    500u16 => "isakmp",
+    514u16 => "syslog",
+    520u16 => "route",
+    623u16 => "asf-rmcp",
+    631u16 => "ipp",
+    1194u16 => "openvpn",
+    1434u16 => "ms-sql-m",
+    1645u16 => "radius",
+    1701u16 => "l2tp",
+    1812u16 => "radius",
+    1813u16 => "radacct",
+    1900u16 => "upnp",
+    2049u16 => "nfs",
+    3478u16 => "stun",
+    3702u16 => "ws-discovery",
+    4500u16 => "nat-t-ike",
+    5060u16 => "sip",
+    5353u16 => "zeroconf",
+    5355u16 => "llmnr",
+    11211u16 => "memcache",
+    51820u16 => "wireguard",
+};
+
+/// Look up the conventional service name for a port/protocol pair.
+/// `proto` is matched case-insensitively ("tcp" or "udp"); unknown ports or
+/// protocols return None.
+pub fn port_to_service(port: u16, proto: &str) -> Option<&'static str> {
+    let table = if proto.eq_ignore_ascii_case("tcp") {
+        &TCP_SERVICES
+    } else if proto.eq_ignore_ascii_case("udp") {
+        &UDP_SERVICES
+    } else {
+        return None;
+    };
+    table.get(&port).copied()
+}
+
+/// Same lookup as `port_to_service`, under the name output code formats as
+/// "22/ssh".
+pub fn service_name(port: u16, proto: &str) -> Option<&'static str> {
+    port_to_service(port, proto)
+}
@@ -0,0 +1,82 @@
+//! Source address selection for probes on multi-homed hosts.
+//!
+//! With a VPN and a LAN up at once, plain connects follow the default route
+//! and may leave through the wrong interface. Binding the socket to a local
+//! address first (ephemeral port) pins the probe to that address's network.
+
+use std::io;
+use std::net::{Ipv4Addr, SocketAddrV4};
+
+use tokio::net::{TcpSocket, TcpStream, UdpSocket};
+
+use super::PortScanError;
+
+/// Check that `local_addr` is an address of this host that probes can be
+/// bound to. The scanners give up on a source address they cannot bind, so
+/// call this first to find out why.
+pub fn check_local_addr(local_addr: Ipv4Addr) -> Result<(), PortScanError> {
+    std::net::UdpSocket::bind((local_addr, 0))
+        .map(drop)
+        .map_err(|e| PortScanError::Bind(local_addr, e))
+}
+
+/// `TcpStream::connect(addr)`, from `local_addr` when given.
+pub(super) async fn connect_from(
+    addr: SocketAddrV4,
+    local_addr: Option<Ipv4Addr>,
+) -> io::Result<TcpStream> {
+    let Some(local) = local_addr else {
+        return TcpStream::connect(addr).await;
+    };
+    let socket = TcpSocket::new_v4()?;
+    socket
+        .bind(SocketAddrV4::new(local, 0).into())
+        .map_err(|e| bind_error(local, e))?;
+    socket.connect(addr.into()).await
+}
+
+/// UDP socket on an ephemeral port of `local_addr` (any address when None).
+pub(super) async fn bind_udp(local_addr: Option<Ipv4Addr>) -> io::Result<UdpSocket> {
+    match local_addr {
+        Some(local) => UdpSocket::bind((local, 0))
+            .await
+            .map_err(|e| bind_error(local, e)),
+        None => UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await,
+    }
+}
+
+fn bind_error(addr: Ipv4Addr, e: io::Error) -> io::Error {
+    io::Error::new(
+        e.kind(),
+        format!("cannot bind to source address {}: {}", addr, e),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unbindable_source_names_the_address() {
+        assert!(check_local_addr(Ipv4Addr::LOCALHOST).is_ok());
+        // TEST-NET-1 is never assigned to a local interface
+        let err = check_local_addr(Ipv4Addr::new(192, 0, 2, 1)).unwrap_err();
+        assert!(matches!(err, PortScanError::Bind(ip, _) if ip == Ipv4Addr::new(192, 0, 2, 1)));
+        assert!(err
+            .to_string()
+            .starts_with("cannot bind to source address 192.0.2.1: "));
+    }
+
+    #[tokio::test]
+    async fn bind_errors_carry_the_address() {
+        let err = bind_udp(Some(Ipv4Addr::new(192, 0, 2, 1)))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("192.0.2.1"));
+        let target = SocketAddrV4::new(Ipv4Addr::LOCALHOST, 9);
+        let err = connect_from(target, Some(Ipv4Addr::new(192, 0, 2, 1)))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("source address 192.0.2.1"));
+    }
+}
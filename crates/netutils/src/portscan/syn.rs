@@ -0,0 +1,486 @@
+//! TCP half-open (SYN) scanning over a raw Ethernet channel (`syn-scan` feature).
+//!
+//! A SYN is sent without ever completing the handshake: a SYN-ACK means the
+//! port is open, a RST that it is closed. The kernel never saw the SYN go
+//! out, so it answers the SYN-ACK with its own RST and no connection is left
+//! behind. Sending needs root or `CAP_NET_RAW` (see `RawSocket::open`).
+
+use std::collections::HashMap;
+use std::fmt;
+use std::net::Ipv4Addr;
+use std::time::{Duration, Instant};
+
+use pnet_packet::ethernet::{EtherTypes, EthernetPacket, MutableEthernetPacket};
+use pnet_packet::ip::IpNextHeaderProtocols;
+use pnet_packet::ipv4::{self, Ipv4Packet, MutableIpv4Packet};
+use pnet_packet::tcp::{self, MutableTcpPacket, TcpFlags, TcpPacket};
+use pnet_packet::Packet;
+
+use super::{port_to_service, PortResult, PortState};
+use crate::rawsocket::{RawSocket, RawSocketError, ETHERNET_HEADER_LEN};
+use crate::{arp, iface};
+
+const IPV4_HEADER_LEN: usize = 20;
+/// TCP header without options
+const TCP_HEADER_LEN: usize = 20;
+const SYN_WINDOW: u16 = 64240;
+const SYN_TTL: u8 = 64;
+/// How long `syn_scan` blocks on a read before checking whether to send more
+const SYN_POLL: Duration = Duration::from_millis(10);
+/// Source ports `syn_scan` picks from: below Linux's default ephemeral range
+/// (32768-60999), so the kernel's RST to a SYN-ACK never hits a real connection
+const SYN_SRC_PORTS: std::ops::RangeInclusive<u16> = 10000..=32767;
+
+/// Why `syn_scan` could not run. `PermissionDenied` means the process lacks
+/// root / `CAP_NET_RAW`; callers typically fall back to a connect scan.
+#[derive(Debug)]
+pub enum SynScanError {
+    PermissionDenied,
+    /// The interface does not exist or has no MAC / IPv4 address
+    Interface(String),
+    /// No MAC for the target or the default gateway in the neighbour table
+    NoRoute(Ipv4Addr),
+    Socket(RawSocketError),
+}
+
+impl fmt::Display for SynScanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SynScanError::PermissionDenied => {
+                write!(f, "raw sockets need root or CAP_NET_RAW")
+            }
+            SynScanError::Interface(s) => write!(f, "interface {}", s),
+            SynScanError::NoRoute(ip) => write!(f, "no next-hop MAC known for {}", ip),
+            SynScanError::Socket(e) => write!(f, "raw socket: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for SynScanError {}
+
+impl From<RawSocketError> for SynScanError {
+    fn from(e: RawSocketError) -> Self {
+        match e {
+            RawSocketError::Io(e) if e.kind() == std::io::ErrorKind::PermissionDenied => {
+                SynScanError::PermissionDenied
+            }
+            RawSocketError::InterfaceNotFound => SynScanError::Interface("not found".into()),
+            e => SynScanError::Socket(e),
+        }
+    }
+}
+
+/// Build a 20-byte TCP header from `src:src_port` to `dst:dst_port` with the
+/// checksum over the IPv4 pseudo-header filled in.
+fn build_tcp_segment(
+    src: Ipv4Addr,
+    dst: Ipv4Addr,
+    src_port: u16,
+    dst_port: u16,
+    seq: u32,
+    ack: u32,
+    flags: u16,
+) -> Vec<u8> {
+    let mut buf = vec![0u8; TCP_HEADER_LEN];
+    let mut tcp = MutableTcpPacket::new(&mut buf).expect("buffer fits a TCP header");
+    tcp.set_source(src_port);
+    tcp.set_destination(dst_port);
+    tcp.set_sequence(seq);
+    tcp.set_acknowledgement(ack);
+    tcp.set_data_offset((TCP_HEADER_LEN / 4) as u8);
+    tcp.set_flags(flags);
+    tcp.set_window(SYN_WINDOW);
+    let checksum = tcp::ipv4_checksum(&tcp.to_immutable(), &src, &dst);
+    tcp.set_checksum(checksum);
+    buf
+}
+
+/// The TCP SYN segment `syn_scan_port` sends.
+pub fn build_syn_segment(
+    src: Ipv4Addr,
+    dst: Ipv4Addr,
+    src_port: u16,
+    dst_port: u16,
+    seq: u32,
+) -> Vec<u8> {
+    build_tcp_segment(src, dst, src_port, dst_port, seq, 0, TcpFlags::SYN)
+}
+
+/// Wrap a TCP segment in IPv4 and Ethernet headers.
+fn build_frame(
+    src_mac: [u8; 6],
+    dst_mac: [u8; 6],
+    src: Ipv4Addr,
+    dst: Ipv4Addr,
+    segment: &[u8],
+) -> Vec<u8> {
+    let ip_len = IPV4_HEADER_LEN + segment.len();
+    let mut buf = vec![0u8; ETHERNET_HEADER_LEN + ip_len];
+    {
+        let mut eth = MutableEthernetPacket::new(&mut buf).expect("buffer fits Ethernet");
+        eth.set_destination(dst_mac.into());
+        eth.set_source(src_mac.into());
+        eth.set_ethertype(EtherTypes::Ipv4);
+    }
+    let mut ip = MutableIpv4Packet::new(&mut buf[ETHERNET_HEADER_LEN..]).expect("buffer fits IPv4");
+    ip.set_version(4);
+    ip.set_header_length((IPV4_HEADER_LEN / 4) as u8);
+    ip.set_total_length(ip_len as u16);
+    ip.set_identification(rand::random());
+    ip.set_flags(ipv4::Ipv4Flags::DontFragment);
+    ip.set_ttl(SYN_TTL);
+    ip.set_next_level_protocol(IpNextHeaderProtocols::Tcp);
+    ip.set_source(src);
+    ip.set_destination(dst);
+    ip.set_payload(segment);
+    let checksum = ipv4::checksum(&ip.to_immutable());
+    ip.set_checksum(checksum);
+    buf
+}
+
+/// Ethernet frame carrying a SYN from `src_mac`/`src:src_port` to
+/// `dst_mac`/`dst:dst_port`.
+pub fn build_syn_frame(
+    src_mac: [u8; 6],
+    dst_mac: [u8; 6],
+    src: Ipv4Addr,
+    dst: Ipv4Addr,
+    src_port: u16,
+    dst_port: u16,
+    seq: u32,
+) -> Vec<u8> {
+    let segment = build_syn_segment(src, dst, src_port, dst_port, seq);
+    build_frame(src_mac, dst_mac, src, dst, &segment)
+}
+
+/// Ethernet frame carrying a RST that tears down the half-open connection
+/// to `dst:dst_port` after its SYN-ACK; `seq` is the SYN's sequence number.
+pub fn build_rst_frame(
+    src_mac: [u8; 6],
+    dst_mac: [u8; 6],
+    src: Ipv4Addr,
+    dst: Ipv4Addr,
+    src_port: u16,
+    dst_port: u16,
+    seq: u32,
+) -> Vec<u8> {
+    let segment = build_tcp_segment(
+        src,
+        dst,
+        src_port,
+        dst_port,
+        seq.wrapping_add(1),
+        0,
+        TcpFlags::RST,
+    );
+    build_frame(src_mac, dst_mac, src, dst, &segment)
+}
+
+/// Classify `frame` as the answer to a SYN sent to `ip:port` from `src_port`
+/// with sequence number `seq`: `Some(true)` for SYN-ACK, `Some(false)` for
+/// RST, `None` for any other traffic (VLAN tags are not expected here).
+pub fn parse_syn_reply(
+    frame: &[u8],
+    ip: Ipv4Addr,
+    port: u16,
+    src_port: u16,
+    seq: u32,
+) -> Option<bool> {
+    parse_syn_reply_any(frame, ip, src_port, seq)
+        .filter(|(from, _)| *from == port)
+        .map(|(_, open)| open)
+}
+
+/// `parse_syn_reply` for SYNs sent to many ports of `ip`: the replying
+/// port and whether it is open.
+pub fn parse_syn_reply_any(
+    frame: &[u8],
+    ip: Ipv4Addr,
+    src_port: u16,
+    seq: u32,
+) -> Option<(u16, bool)> {
+    let eth = EthernetPacket::new(frame)?;
+    if eth.get_ethertype() != EtherTypes::Ipv4 {
+        return None;
+    }
+    let ipv4 = Ipv4Packet::new(eth.payload())?;
+    if ipv4.get_next_level_protocol() != IpNextHeaderProtocols::Tcp || ipv4.get_source() != ip {
+        return None;
+    }
+    let tcp = TcpPacket::new(ipv4.payload())?;
+    if tcp.get_destination() != src_port || tcp.get_acknowledgement() != seq.wrapping_add(1) {
+        return None;
+    }
+    let flags = tcp.get_flags();
+    let open = if flags & TcpFlags::RST != 0 {
+        false
+    } else if flags & (TcpFlags::SYN | TcpFlags::ACK) == TcpFlags::SYN | TcpFlags::ACK {
+        true
+    } else {
+        return None;
+    };
+    Some((tcp.get_source(), open))
+}
+
+/// Source MAC and IPv4 of `iface_name`, and the MAC of the next hop to `ip`:
+/// `ip` itself when it is in the neighbour table, else the default gateway.
+fn link_endpoints(
+    iface_name: &str,
+    ip: Ipv4Addr,
+) -> Result<([u8; 6], Ipv4Addr, [u8; 6]), SynScanError> {
+    let local = iface::get_interface_by_name(iface_name)
+        .map_err(|e| SynScanError::Interface(format!("{}: {}", iface_name, e)))?;
+    let (Some(src_mac), Some(src)) = (local.mac, local.ipv4) else {
+        return Err(SynScanError::Interface(format!(
+            "{}: no MAC or IPv4 address",
+            iface_name
+        )));
+    };
+    let dst_mac = arp::lookup_mac(ip)
+        .or_else(|| iface::get_default_gateway_ipv4().and_then(arp::lookup_mac))
+        .ok_or(SynScanError::NoRoute(ip))?;
+    Ok((src_mac, src, dst_mac))
+}
+
+/// Send a SYN to `ip:port` from `src_port` through interface `iface` and
+/// wait up to `timeout` for the answer: `Open` on SYN-ACK (the port is then
+/// reset), `Closed` on RST and `Filtered` on silence. Socket, neighbour and
+/// interface failures come back as errors.
+///
+/// The next hop is `ip` itself when it is in the neighbour table, else the
+/// default gateway. `src_port` should be outside the local ephemeral range
+/// so the kernel's RST to the SYN-ACK does not hit a real connection. The
+/// neighbour lookup and raw socket reads block, so the probe runs on tokio's
+/// blocking pool with the same poll loop as `syn_scan`.
+pub async fn syn_scan_port(
+    ip: Ipv4Addr,
+    port: u16,
+    src_port: u16,
+    iface: &str,
+    timeout: Duration,
+) -> Result<PortState, SynScanError> {
+    let iface = iface.to_string();
+    let probe = tokio::task::spawn_blocking(move || {
+        syn_scan_from(ip, vec![port], &iface, timeout, 0, src_port).map(|r| r[0].state)
+    });
+    match probe.await {
+        Ok(state) => state,
+        Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+        Err(e) => Err(SynScanError::Socket(RawSocketError::RecvError(
+            e.to_string(),
+        ))),
+    }
+}
+
+/// Half-open scan of `ports` on `ip` through interface `iface`, sending at
+/// most `rate` SYNs per second (0 for no pacing). Replies are read while
+/// sending; SYN-ACK marks a port open (and is answered with a RST so no
+/// half-open connection lingers on the target), RST closed, and silence
+/// for `timeout` after the last SYN filtered. Results follow `ports` order.
+///
+/// The raw socket is opened before anything is sent, so missing privileges
+/// come back as `SynScanError::PermissionDenied` with no traffic generated.
+pub fn syn_scan(
+    ip: Ipv4Addr,
+    ports: Vec<u16>,
+    iface: &str,
+    timeout: Duration,
+    rate: u32,
+) -> Result<Vec<PortResult>, SynScanError> {
+    let src_port = rand::Rng::gen_range(&mut rand::thread_rng(), SYN_SRC_PORTS);
+    syn_scan_from(ip, ports, iface, timeout, rate, src_port)
+}
+
+/// `syn_scan` from a chosen source port.
+fn syn_scan_from(
+    ip: Ipv4Addr,
+    ports: Vec<u16>,
+    iface: &str,
+    timeout: Duration,
+    rate: u32,
+    src_port: u16,
+) -> Result<Vec<PortResult>, SynScanError> {
+    let mut socket = RawSocket::open_with_read_timeout(iface, SYN_POLL)?;
+    let (src_mac, src, dst_mac) = link_endpoints(iface, ip)?;
+    let seq: u32 = rand::random();
+    let interval = match rate {
+        0 => Duration::ZERO,
+        r => Duration::from_secs(1) / r,
+    };
+
+    let mut sent_at: HashMap<u16, Instant> = HashMap::new();
+    let mut answers: HashMap<u16, (bool, u128)> = HashMap::new();
+    let mut next = 0;
+    let mut next_send = Instant::now();
+    let mut last_send = Instant::now();
+    loop {
+        while next < ports.len() && Instant::now() >= next_send {
+            let port = ports[next];
+            let frame = build_syn_frame(src_mac, dst_mac, src, ip, src_port, port, seq);
+            socket.send(&frame)?;
+            last_send = Instant::now();
+            sent_at.entry(port).or_insert(last_send);
+            next += 1;
+            next_send += interval;
+        }
+        if let Some(frame) = socket.recv()? {
+            if let Some((port, open)) = parse_syn_reply_any(&frame.data, ip, src_port, seq) {
+                if let (Some(sent), false) = (sent_at.get(&port), answers.contains_key(&port)) {
+                    answers.insert(port, (open, sent.elapsed().as_millis()));
+                    if open {
+                        let rst = build_rst_frame(src_mac, dst_mac, src, ip, src_port, port, seq);
+                        let _ = socket.send(&rst);
+                    }
+                }
+            }
+        }
+        let all_sent = next == ports.len();
+        if all_sent && (answers.len() == sent_at.len() || last_send.elapsed() >= timeout) {
+            break;
+        }
+    }
+
+    Ok(ports
+        .into_iter()
+        .map(|port| syn_port_result(port, answers.get(&port).copied()))
+        .collect())
+}
+
+/// `PortResult` for a port that answered `(open, rtt_ms)`, or stayed silent.
+fn syn_port_result(port: u16, answer: Option<(bool, u128)>) -> PortResult {
+    let (state, rtt_ms) = match answer {
+        Some((true, rtt)) => (PortState::Open, Some(rtt)),
+        Some((false, _)) => (PortState::Closed, None),
+        None => (PortState::Filtered, None),
+    };
+    let open = state == PortState::Open;
+    PortResult {
+        port,
+        proto: "tcp".into(),
+        open,
+        state,
+        banner: None,
+        banner_hex: None,
+        rtt_ms,
+        service_name: if open {
+            port_to_service(port, "tcp").map(Into::into)
+        } else {
+            None
+        },
+        tls: None,
+        attempts: 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SRC: Ipv4Addr = Ipv4Addr::new(192, 168, 1, 10);
+    const DST: Ipv4Addr = Ipv4Addr::new(192, 168, 1, 20);
+    const SRC_MAC: [u8; 6] = [0x02, 0, 0, 0, 0, 0x0a];
+    const DST_MAC: [u8; 6] = [0x02, 0, 0, 0, 0, 0x14];
+
+    #[test]
+    fn syn_segment_matches_known_good_checksum() {
+        // 192.168.1.10:40000 -> 192.168.1.20:80, seq 0x01020304, SYN,
+        // window 64240; checksum 0x90ec computed independently
+        let expected: [u8; 20] = [
+            0x9c, 0x40, 0x00, 0x50, 0x01, 0x02, 0x03, 0x04, 0x00, 0x00, 0x00, 0x00, 0x50, 0x02,
+            0xfa, 0xf0, 0x90, 0xec, 0x00, 0x00,
+        ];
+        assert_eq!(
+            build_syn_segment(SRC, DST, 40000, 80, 0x0102_0304),
+            expected
+        );
+    }
+
+    #[test]
+    fn syn_frame_has_valid_ip_header() {
+        let frame = build_syn_frame(SRC_MAC, DST_MAC, SRC, DST, 40000, 80, 7);
+        let ip = Ipv4Packet::new(&frame[ETHERNET_HEADER_LEN..]).unwrap();
+        assert_eq!(ip.get_checksum(), ipv4::checksum(&ip));
+        assert_eq!(
+            ip.get_total_length() as usize,
+            frame.len() - ETHERNET_HEADER_LEN
+        );
+        assert_eq!(ip.payload(), build_syn_segment(SRC, DST, 40000, 80, 7));
+    }
+
+    #[test]
+    fn classifies_syn_ack_and_rst_replies() {
+        let seq = 1000;
+        let reply = |flags: u16, ack: u32, from_port: u16| {
+            let segment = build_tcp_segment(DST, SRC, from_port, 40000, 5000, ack, flags);
+            build_frame(DST_MAC, SRC_MAC, DST, SRC, &segment)
+        };
+        let parse = |frame: &[u8]| parse_syn_reply(frame, DST, 80, 40000, seq);
+
+        assert_eq!(
+            parse(&reply(TcpFlags::SYN | TcpFlags::ACK, seq + 1, 80)),
+            Some(true)
+        );
+        assert_eq!(
+            parse(&reply(TcpFlags::RST | TcpFlags::ACK, seq + 1, 80)),
+            Some(false)
+        );
+        // wrong port, stale ack, or a plain ACK are someone else's traffic
+        assert_eq!(
+            parse(&reply(TcpFlags::SYN | TcpFlags::ACK, seq + 1, 81)),
+            None
+        );
+        assert_eq!(parse(&reply(TcpFlags::SYN | TcpFlags::ACK, seq, 80)), None);
+        assert_eq!(parse(&reply(TcpFlags::ACK, seq + 1, 80)), None);
+        // our own outgoing SYN is not a reply
+        let own = build_syn_frame(SRC_MAC, DST_MAC, SRC, DST, 40000, 80, seq);
+        assert_eq!(parse(&own), None);
+
+        let from_443 = reply(TcpFlags::SYN | TcpFlags::ACK, seq + 1, 443);
+        assert_eq!(
+            parse_syn_reply_any(&from_443, DST, 40000, seq),
+            Some((443, true))
+        );
+    }
+
+    #[test]
+    fn rst_follows_the_syn_sequence() {
+        let frame = build_rst_frame(SRC_MAC, DST_MAC, SRC, DST, 40000, 80, 1000);
+        let ip = Ipv4Packet::new(&frame[ETHERNET_HEADER_LEN..]).unwrap();
+        let tcp = TcpPacket::new(ip.payload()).unwrap();
+        assert_eq!(tcp.get_flags(), TcpFlags::RST);
+        assert_eq!(tcp.get_sequence(), 1001);
+        assert_eq!(
+            tcp.get_checksum(),
+            tcp::ipv4_checksum(&tcp, &SRC, &DST),
+            "checksum over the pseudo-header"
+        );
+    }
+
+    #[test]
+    fn port_results_and_privilege_errors() {
+        let open = syn_port_result(22, Some((true, 3)));
+        assert_eq!((open.state, open.rtt_ms), (PortState::Open, Some(3)));
+        assert_eq!(open.service_name.as_deref(), port_to_service(22, "tcp"));
+        assert_eq!(
+            syn_port_result(23, Some((false, 3))).state,
+            PortState::Closed
+        );
+        assert_eq!(syn_port_result(24, None).state, PortState::Filtered);
+
+        let denied = std::io::Error::from(std::io::ErrorKind::PermissionDenied);
+        assert!(matches!(
+            SynScanError::from(RawSocketError::Io(denied)),
+            SynScanError::PermissionDenied
+        ));
+        assert!(matches!(
+            syn_scan(DST, vec![80], "no_such_iface_12345", Duration::ZERO, 0),
+            Err(SynScanError::Interface(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn single_port_probe_returns_socket_errors() {
+        let res = syn_scan_port(DST, 80, 20000, "no_such_iface_12345", Duration::ZERO).await;
+        assert!(matches!(res, Err(SynScanError::Interface(_))));
+    }
+}
@@ -0,0 +1,307 @@
+//! TLS certificate probing: complete a handshake and read the server's leaf
+//! certificate (CN/SAN hostnames, issuer, expiry). Certificates are never
+//! verified; an expired or self-signed one is exactly what we want to see.
+
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+/// Ports probed for a certificate by `ProbeStrategy::with_tls_probe(true)`.
+pub const TLS_PROBE_PORTS: &[u16] = &[443, 465, 636, 853, 990, 993, 995, 5986, 8443, 9443];
+
+/// What a server certificate says about the host.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TlsInfo {
+    pub subject_cn: Option<String>,
+    /// DNS names and IP addresses from subjectAltName, in certificate order
+    pub san: Vec<String>,
+    pub issuer: String,
+    /// Expiry as RFC 3339 UTC, e.g. `2036-10-13T19:23:54Z`
+    pub not_after: String,
+    /// Subject and issuer are the same name
+    pub self_signed: bool,
+}
+
+impl TlsInfo {
+    /// Best hostname for the host: the first non-wildcard SAN that isn't an
+    /// IP address, falling back to the subject CN.
+    pub fn hostname(&self) -> Option<&str> {
+        self.san
+            .iter()
+            .map(String::as_str)
+            .chain(self.subject_cn.as_deref())
+            .find(|n| !n.starts_with("*.") && n.parse::<std::net::IpAddr>().is_err())
+    }
+}
+
+/// Parse a DER-encoded X.509 certificate.
+#[cfg(feature = "tls")]
+pub fn parse_certificate(der: &[u8]) -> Option<TlsInfo> {
+    use x509_parser::extensions::GeneralName;
+    use x509_parser::prelude::{FromDer, X509Certificate};
+
+    let (_, cert) = X509Certificate::from_der(der).ok()?;
+    let subject_cn = cert
+        .subject()
+        .iter_common_name()
+        .next()
+        .and_then(|cn| cn.as_str().ok())
+        .map(str::to_string);
+    let san = match cert.subject_alternative_name() {
+        Ok(Some(ext)) => ext
+            .value
+            .general_names
+            .iter()
+            .filter_map(|name| match name {
+                GeneralName::DNSName(dns) => Some(dns.to_string()),
+                GeneralName::IPAddress(ip) => ip_from_bytes(ip),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+    let expiry = cert.validity().not_after.to_datetime();
+    Some(TlsInfo {
+        subject_cn,
+        san,
+        issuer: cert.issuer().to_string(),
+        not_after: format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+            expiry.year(),
+            u8::from(expiry.month()),
+            expiry.day(),
+            expiry.hour(),
+            expiry.minute(),
+            expiry.second()
+        ),
+        self_signed: cert.subject().as_raw() == cert.issuer().as_raw(),
+    })
+}
+
+#[cfg(feature = "tls")]
+fn ip_from_bytes(b: &[u8]) -> Option<String> {
+    match b.len() {
+        4 => Some(Ipv4Addr::new(b[0], b[1], b[2], b[3]).to_string()),
+        16 => Some(std::net::Ipv6Addr::from(<[u8; 16]>::try_from(b).ok()?).to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(feature = "tls")]
+mod verifier {
+    use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+    use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+    use rustls::{DigitallySignedStruct, SignatureScheme};
+    use std::sync::Arc;
+
+    /// Accepts any certificate: the probe only reads it.
+    #[derive(Debug)]
+    struct NoVerify(Arc<rustls::crypto::CryptoProvider>);
+
+    impl ServerCertVerifier for NoVerify {
+        fn verify_server_cert(
+            &self,
+            _end_entity: &CertificateDer<'_>,
+            _intermediates: &[CertificateDer<'_>],
+            _server_name: &ServerName<'_>,
+            _ocsp_response: &[u8],
+            _now: UnixTime,
+        ) -> Result<ServerCertVerified, rustls::Error> {
+            Ok(ServerCertVerified::assertion())
+        }
+
+        fn verify_tls12_signature(
+            &self,
+            message: &[u8],
+            cert: &CertificateDer<'_>,
+            dss: &DigitallySignedStruct,
+        ) -> Result<HandshakeSignatureValid, rustls::Error> {
+            rustls::crypto::verify_tls12_signature(
+                message,
+                cert,
+                dss,
+                &self.0.signature_verification_algorithms,
+            )
+        }
+
+        fn verify_tls13_signature(
+            &self,
+            message: &[u8],
+            cert: &CertificateDer<'_>,
+            dss: &DigitallySignedStruct,
+        ) -> Result<HandshakeSignatureValid, rustls::Error> {
+            rustls::crypto::verify_tls13_signature(
+                message,
+                cert,
+                dss,
+                &self.0.signature_verification_algorithms,
+            )
+        }
+
+        fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+            self.0.signature_verification_algorithms.supported_schemes()
+        }
+    }
+
+    pub(super) fn connector() -> tokio_rustls::TlsConnector {
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let config = rustls::ClientConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()
+            .expect("ring supports the default protocol versions")
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(NoVerify(provider)))
+            .with_no_client_auth();
+        tokio_rustls::TlsConnector::from(Arc::new(config))
+    }
+}
+
+/// Handshake with `ip:port` and return the leaf certificate's details.
+/// Plain-TCP ports, handshake failures and anything slower than `timeout`
+/// (connect and handshake together) yield None, as does every port without
+/// the `tls` feature.
+pub async fn probe_tls_async(ip: Ipv4Addr, port: u16, timeout: Duration) -> Option<TlsInfo> {
+    probe_tls_from_async(ip, port, timeout, None).await
+}
+
+/// `probe_tls_async` connecting from `local_addr` when given.
+#[cfg(feature = "tls")]
+pub(super) async fn probe_tls_from_async(
+    ip: Ipv4Addr,
+    port: u16,
+    timeout: Duration,
+    local_addr: Option<Ipv4Addr>,
+) -> Option<TlsInfo> {
+    use rustls::pki_types::ServerName;
+
+    let der = tokio::time::timeout(timeout, async {
+        let addr = std::net::SocketAddrV4::new(ip, port);
+        let tcp = super::connect_from(addr, local_addr).await.ok()?;
+        let name = ServerName::IpAddress(std::net::IpAddr::V4(ip).into());
+        let stream = verifier::connector().connect(name, tcp).await.ok()?;
+        let (_, conn) = stream.get_ref();
+        conn.peer_certificates()?.first().map(|c| c.to_vec())
+    })
+    .await
+    .ok()??;
+    parse_certificate(&der)
+}
+
+/// Without the `tls` feature there is no TLS stack; always None.
+#[cfg(not(feature = "tls"))]
+pub(super) async fn probe_tls_from_async(
+    _ip: Ipv4Addr,
+    _port: u16,
+    _timeout: Duration,
+    _local_addr: Option<Ipv4Addr>,
+) -> Option<TlsInfo> {
+    None
+}
+
+#[cfg(all(test, feature = "tls"))]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use tokio::net::TcpListener;
+
+    const CERT: &[u8] = include_bytes!("../../tests/fixtures/tls_selfsigned_cert.der");
+    const KEY: &[u8] = include_bytes!("../../tests/fixtures/tls_selfsigned_key.der");
+
+    #[test]
+    fn parses_self_signed_fixture() {
+        let info = parse_certificate(CERT).expect("parse");
+        assert_eq!(info.subject_cn.as_deref(), Some("printer.lan"));
+        assert_eq!(info.san, ["printer.lan", "printer", "192.0.2.10"]);
+        assert_eq!(info.issuer, "CN=printer.lan, O=Example Print Co");
+        assert_eq!(info.not_after, "2036-10-13T19:23:54Z");
+        assert!(info.self_signed);
+        assert_eq!(info.hostname(), Some("printer.lan"));
+        assert!(parse_certificate(&CERT[..100]).is_none());
+    }
+
+    #[test]
+    fn hostname_skips_wildcards_and_addresses() {
+        let info = TlsInfo {
+            subject_cn: Some("nas.lan".into()),
+            san: vec!["*.example.com".into(), "10.0.0.2".into()],
+            issuer: "CN=ca".into(),
+            not_after: String::new(),
+            self_signed: false,
+        };
+        assert_eq!(info.hostname(), Some("nas.lan"));
+    }
+
+    async fn tls_server() -> u16 {
+        use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let config = rustls::ServerConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_no_client_auth()
+            .with_single_cert(
+                vec![CertificateDer::from(CERT.to_vec())],
+                PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(KEY.to_vec())),
+            )
+            .unwrap();
+        let acceptor = tokio_rustls::TlsAcceptor::from(Arc::new(config));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((tcp, _)) = listener.accept().await {
+                let acceptor = acceptor.clone();
+                tokio::spawn(async move {
+                    let _ = acceptor.accept(tcp).await;
+                });
+            }
+        });
+        port
+    }
+
+    #[tokio::test]
+    async fn probe_reads_certificate_from_local_server() {
+        let port = tls_server().await;
+        let info = probe_tls_async(Ipv4Addr::LOCALHOST, port, Duration::from_secs(2))
+            .await
+            .expect("tls info");
+        assert_eq!(info.subject_cn.as_deref(), Some("printer.lan"));
+        assert!(info.self_signed);
+    }
+
+    #[tokio::test]
+    async fn strategy_scan_attaches_certificate() {
+        use crate::portscan::{scan_host_ports_with_strategy_async, ProbeStrategy};
+
+        let port = tls_server().await;
+        let strategy = ProbeStrategy::default().with_tls_port(port);
+        let res = scan_host_ports_with_strategy_async(
+            Ipv4Addr::LOCALHOST,
+            vec![port],
+            Duration::from_secs(2),
+            4,
+            strategy,
+            None,
+        )
+        .await;
+        let tls = res[0].tls.as_ref().expect("certificate");
+        assert_eq!(tls.hostname(), Some("printer.lan"));
+        // TLS servers stay silent and the plaintext nudge is skipped
+        assert!(res[0].banner.is_none());
+    }
+
+    #[tokio::test]
+    async fn plain_tcp_port_returns_none_without_stalling() {
+        // Accepts and then says nothing, like a non-TLS service waiting for input
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let mut held = Vec::new();
+            while let Ok((tcp, _)) = listener.accept().await {
+                held.push(tcp);
+            }
+        });
+        let start = std::time::Instant::now();
+        let info = probe_tls_async(Ipv4Addr::LOCALHOST, port, Duration::from_millis(300)).await;
+        assert!(info.is_none());
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+}
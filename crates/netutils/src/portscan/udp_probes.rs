@@ -0,0 +1,197 @@
+//! Minimal query datagrams for UDP services that ignore empty packets.
+//!
+//! Each payload is assembled by a small builder from its protocol fields
+//! rather than kept as an opaque byte dump, so the bytes can be checked
+//! against the spec (and the tests below) field by field.
+
+use std::collections::HashMap;
+
+use once_cell::sync::Lazy;
+
+use crate::ber;
+
+/// Ports with a builtin UDP payload: DNS, NTP, NetBIOS-NS, SNMP, SSDP, mDNS.
+pub const UDP_PROBE_PORTS: &[u16] = &[53, 123, 137, 161, 1900, 5353];
+
+const DNS_QUERY_ID: u16 = 0x4e53;
+const NBSTAT_QUERY_ID: u16 = 0x4e42;
+const DNS_TYPE_A: u16 = 1;
+const DNS_TYPE_PTR: u16 = 12;
+const DNS_CLASS_IN: u16 = 1;
+/// IN with the mDNS "unicast response" bit, so the reply comes back to us
+const MDNS_CLASS_IN_QU: u16 = 0x8001;
+/// sysDescr.0
+const SNMP_SYS_DESCR: &[u32] = &[1, 3, 6, 1, 2, 1, 1, 1, 0];
+
+static PAYLOADS: Lazy<HashMap<u16, Vec<u8>>> = Lazy::new(|| {
+    UDP_PROBE_PORTS
+        .iter()
+        .filter_map(|&port| build_payload(port).map(|p| (port, p)))
+        .collect()
+});
+
+fn build_payload(port: u16) -> Option<Vec<u8>> {
+    Some(match port {
+        53 => dns_query(DNS_QUERY_ID, true, "example.com", DNS_TYPE_A, DNS_CLASS_IN),
+        123 => ntp_client_request(),
+        137 => nbstat_query(NBSTAT_QUERY_ID),
+        161 => snmp_get("public", SNMP_SYS_DESCR),
+        1900 => ssdp_msearch("ssdp:all"),
+        5353 => dns_query(
+            0,
+            false,
+            "_services._dns-sd._udp.local",
+            DNS_TYPE_PTR,
+            MDNS_CLASS_IN_QU,
+        ),
+        _ => return None,
+    })
+}
+
+/// Query datagram for a well-known UDP `port` (see `UDP_PROBE_PORTS`), or
+/// None when the port has no builtin payload.
+pub fn udp_probe_payload(port: u16) -> Option<&'static [u8]> {
+    PAYLOADS.get(&port).map(Vec::as_slice)
+}
+
+/// DNS labels for `name`, root label included.
+fn dns_name(name: &str) -> Vec<u8> {
+    let mut out = Vec::with_capacity(name.len() + 2);
+    for label in name.split('.').filter(|l| !l.is_empty()) {
+        out.push(label.len() as u8);
+        out.extend_from_slice(label.as_bytes());
+    }
+    out.push(0);
+    out
+}
+
+/// One-question DNS query; `recursion` sets the RD flag.
+fn dns_query(id: u16, recursion: bool, name: &str, qtype: u16, qclass: u16) -> Vec<u8> {
+    let flags: u16 = if recursion { 0x0100 } else { 0 };
+    let mut msg = Vec::new();
+    msg.extend_from_slice(&id.to_be_bytes());
+    msg.extend_from_slice(&flags.to_be_bytes());
+    // qdcount 1; no answer, authority or additional records
+    msg.extend_from_slice(&[0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+    msg.extend_from_slice(&dns_name(name));
+    msg.extend_from_slice(&qtype.to_be_bytes());
+    msg.extend_from_slice(&qclass.to_be_bytes());
+    msg
+}
+
+/// NTPv3 client request (LI 0, VN 3, mode 3) with zero timestamps.
+fn ntp_client_request() -> Vec<u8> {
+    let mut msg = vec![0u8; 48];
+    msg[0] = (3 << 3) | 3;
+    msg
+}
+
+/// NetBIOS name service node status (NBSTAT) request for the wildcard name
+/// `*`, which every Windows/Samba host answers with its name table.
+fn nbstat_query(id: u16) -> Vec<u8> {
+    let mut msg = Vec::new();
+    msg.extend_from_slice(&id.to_be_bytes());
+    msg.extend_from_slice(&[0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+    // First-level encoding: 16 name bytes, each split into two nibbles + 'A'
+    let mut name = [0u8; 16];
+    name[0] = b'*';
+    msg.push(32);
+    for b in name {
+        msg.push(b'A' + (b >> 4));
+        msg.push(b'A' + (b & 0x0f));
+    }
+    msg.push(0);
+    msg.extend_from_slice(&[0x00, 0x21, 0x00, 0x01]); // NBSTAT, IN
+    msg
+}
+
+/// SNMPv2c GetRequest for one `oid` (request id 1).
+fn snmp_get(community: &str, oid: &[u32]) -> Vec<u8> {
+    let varbind = ber::tlv(
+        ber::TAG_SEQUENCE,
+        &[ber::oid(oid), ber::tlv(ber::TAG_NULL, &[])].concat(),
+    );
+    let pdu = [
+        ber::integer(1),
+        ber::integer(0),
+        ber::integer(0),
+        ber::tlv(ber::TAG_SEQUENCE, &varbind),
+    ]
+    .concat();
+    let msg = [
+        ber::integer(1), // version: v2c
+        ber::tlv(ber::TAG_OCTET_STRING, community.as_bytes()),
+        ber::tlv(0xa0, &pdu),
+    ]
+    .concat();
+    ber::tlv(ber::TAG_SEQUENCE, &msg)
+}
+
+/// SSDP `M-SEARCH` for search target `st`, answered within a second.
+fn ssdp_msearch(st: &str) -> Vec<u8> {
+    format!(
+        "M-SEARCH * HTTP/1.1\r\nHOST: 239.255.255.250:1900\r\n\
+         MAN: \"ssdp:discover\"\r\nMX: 1\r\nST: {st}\r\n\r\n"
+    )
+    .into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_listed_port_has_a_payload() {
+        for &port in UDP_PROBE_PORTS {
+            assert!(
+                udp_probe_payload(port).is_some_and(|p| !p.is_empty()),
+                "{port}"
+            );
+        }
+        assert_eq!(udp_probe_payload(9999), None);
+    }
+
+    #[test]
+    fn dns_queries_encode_the_question() {
+        let a = udp_probe_payload(53).unwrap();
+        assert_eq!(&a[..6], [0x4e, 0x53, 0x01, 0x00, 0x00, 0x01]);
+        assert_eq!(&a[12..25], b"\x07example\x03com\x00");
+        assert_eq!(&a[25..], [0x00, 0x01, 0x00, 0x01]); // A, IN
+
+        let mdns = udp_probe_payload(5353).unwrap();
+        assert_eq!(&mdns[..4], [0, 0, 0, 0]);
+        assert!(mdns.ends_with(&[0x05, b'l', b'o', b'c', b'a', b'l', 0, 0x00, 0x0c, 0x80, 0x01]));
+    }
+
+    #[test]
+    fn nbstat_query_uses_the_wildcard_name() {
+        let q = udp_probe_payload(137).unwrap();
+        assert_eq!(q.len(), 50);
+        assert_eq!(q[12], 32);
+        // '*' (0x2a) encodes as "CK", NUL padding as "AA"
+        assert_eq!(&q[13..17], b"CKAA");
+        assert!(q[17..45].iter().all(|&b| b == b'A'));
+        assert_eq!(&q[46..], [0x00, 0x21, 0x00, 0x01]);
+    }
+
+    #[test]
+    fn snmp_get_matches_a_captured_request() {
+        // sysDescr.0 GetRequest, community public, request id 1
+        let expected: &[u8] = &[
+            0x30, 0x26, 0x02, 0x01, 0x01, 0x04, 0x06, b'p', b'u', b'b', b'l', b'i', b'c', 0xa0,
+            0x19, 0x02, 0x01, 0x01, 0x02, 0x01, 0x00, 0x02, 0x01, 0x00, 0x30, 0x0e, 0x30, 0x0c,
+            0x06, 0x08, 0x2b, 0x06, 0x01, 0x02, 0x01, 0x01, 0x01, 0x00, 0x05, 0x00,
+        ];
+        assert_eq!(udp_probe_payload(161).unwrap(), expected);
+    }
+
+    #[test]
+    fn ntp_and_ssdp_requests() {
+        let ntp = udp_probe_payload(123).unwrap();
+        assert_eq!((ntp.len(), ntp[0]), (48, 0x1b));
+        let ssdp = std::str::from_utf8(udp_probe_payload(1900).unwrap()).unwrap();
+        assert!(ssdp.starts_with("M-SEARCH * HTTP/1.1\r\n"));
+        assert!(ssdp.contains("MAN: \"ssdp:discover\"\r\n"));
+        assert!(ssdp.ends_with("ST: ssdp:all\r\n\r\n"));
+    }
+}
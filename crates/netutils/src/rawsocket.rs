@@ -3,6 +3,8 @@ use std::fmt;
 use std::sync::mpsc;
 use std::thread;
 use std::time::Duration;
+#[cfg(feature = "pcap")]
+use std::time::{SystemTime, UNIX_EPOCH};
 
 #[derive(Debug)]
 pub enum RawSocketError {
@@ -27,34 +29,177 @@ impl fmt::Display for RawSocketError {
 
 impl std::error::Error for RawSocketError {}
 
+/// Writes captured Ethernet frames to a classic (libpcap) capture file that
+/// Wireshark/tcpdump can open. Available with the `pcap` feature.
+#[cfg(feature = "pcap")]
+pub struct PcapWriter<W: std::io::Write> {
+    inner: pcap_file::pcap::PcapWriter<W>,
+}
+
+#[cfg(feature = "pcap")]
+impl PcapWriter<std::io::BufWriter<std::fs::File>> {
+    /// Create (or truncate) a capture file at `path` and write the pcap header.
+    pub fn create<P: AsRef<std::path::Path>>(path: P) -> Result<Self, RawSocketError> {
+        let file = std::fs::File::create(path).map_err(RawSocketError::Io)?;
+        Self::new(std::io::BufWriter::new(file))
+    }
+}
+
+#[cfg(feature = "pcap")]
+impl<W: std::io::Write> PcapWriter<W> {
+    /// Wrap any writer; the pcap global header (Ethernet link type) is written immediately.
+    pub fn new(writer: W) -> Result<Self, RawSocketError> {
+        let inner = pcap_file::pcap::PcapWriter::new(writer).map_err(pcap_err)?;
+        Ok(Self { inner })
+    }
+
+    /// Append a frame stamped with the current wall-clock time.
+    pub fn write_frame(&mut self, frame: &[u8]) -> Result<(), RawSocketError> {
+        let ts = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        self.write_frame_at(frame, ts)
+    }
+
+    /// Append a frame with an explicit timestamp (offset from the Unix epoch).
+    pub fn write_frame_at(&mut self, frame: &[u8], ts: Duration) -> Result<(), RawSocketError> {
+        let packet = pcap_file::pcap::PcapPacket::new(ts, frame.len() as u32, frame);
+        self.inner.write_packet(&packet).map_err(pcap_err)?;
+        Ok(())
+    }
+
+    /// Consume the writer and return the underlying sink.
+    pub fn into_inner(self) -> W {
+        self.inner.into_writer()
+    }
+}
+
+#[cfg(feature = "pcap")]
+fn pcap_err(e: pcap_file::PcapError) -> RawSocketError {
+    match e {
+        pcap_file::PcapError::IoError(e) => RawSocketError::Io(e),
+        other => RawSocketError::Io(std::io::Error::other(other.to_string())),
+    }
+}
+
+/// EtherType of an 802.1Q VLAN tag.
+const ETHERTYPE_VLAN: [u8; 2] = [0x81, 0x00];
+/// Length of the 802.1Q header (TPID + TCI) inserted after the source MAC.
+const VLAN_TAG_LEN: usize = 4;
+
+/// A received Ethernet frame plus its 802.1Q VLAN ID, if tagged.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawFrame {
+    pub data: Vec<u8>,
+    pub vlan: Option<u16>,
+}
+
+impl RawFrame {
+    /// Wrap raw frame bytes, detecting the VLAN tag.
+    pub fn new(data: Vec<u8>) -> Self {
+        let vlan = parse_vlan_tag(&data);
+        Self { data, vlan }
+    }
+
+    /// Frame bytes with any 802.1Q header removed.
+    pub fn untagged(&self) -> Vec<u8> {
+        strip_vlan_tag(&self.data)
+    }
+}
+
+/// Return the VLAN ID (lower 12 bits of the TCI) when the EtherType at
+/// offset 12 is 802.1Q (`0x8100`).
+pub fn parse_vlan_tag(frame: &[u8]) -> Option<u16> {
+    if frame.len() < 12 + VLAN_TAG_LEN || frame[12..14] != ETHERTYPE_VLAN {
+        return None;
+    }
+    Some(u16::from_be_bytes([frame[14], frame[15]]) & 0x0fff)
+}
+
+/// Remove the 4-byte 802.1Q header, if present; untagged frames are returned unchanged.
+pub fn strip_vlan_tag(frame: &[u8]) -> Vec<u8> {
+    if parse_vlan_tag(frame).is_none() {
+        return frame.to_vec();
+    }
+    let mut out = Vec::with_capacity(frame.len() - VLAN_TAG_LEN);
+    out.extend_from_slice(&frame[..12]);
+    out.extend_from_slice(&frame[12 + VLAN_TAG_LEN..]);
+    out
+}
+
+/// Length of an untagged Ethernet II header (dst MAC, src MAC, EtherType).
+pub const ETHERNET_HEADER_LEN: usize = 14;
+/// Destination MAC for link-layer broadcast.
+pub const BROADCAST_MAC: [u8; 6] = [0xff; 6];
+
+/// Build an Ethernet II frame: 14-byte header followed by `payload`.
+pub fn build_ethernet_frame(
+    dst_mac: [u8; 6],
+    src_mac: [u8; 6],
+    ethertype: u16,
+    payload: &[u8],
+) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(ETHERNET_HEADER_LEN + payload.len());
+    frame.extend_from_slice(&dst_mac);
+    frame.extend_from_slice(&src_mac);
+    frame.extend_from_slice(&ethertype.to_be_bytes());
+    frame.extend_from_slice(payload);
+    frame
+}
+
 /// A small wrapper around pnet datalink Ethernet channel.
 pub struct RawSocket {
-    #[allow(dead_code)]
     iface_name: String,
     tx: Box<dyn DataLinkSender>,
     rx: Option<Box<dyn DataLinkReceiver + Send>>,
+    /// Optional capture sink; every received frame is copied here.
+    #[cfg(feature = "pcap")]
+    tee: Option<PcapWriter<Box<dyn std::io::Write + Send>>>,
 }
 
 impl RawSocket {
     /// Open a raw socket (datalink channel) on the named interface.
     pub fn open(name: &str) -> Result<Self, RawSocketError> {
+        Self::open_with_config(name, Config::default())
+    }
+
+    /// Like `open`, but `recv` gives up after `read_timeout` instead of
+    /// blocking until a frame arrives.
+    pub fn open_with_read_timeout(
+        name: &str,
+        read_timeout: Duration,
+    ) -> Result<Self, RawSocketError> {
+        let config = Config {
+            read_timeout: Some(read_timeout),
+            ..Config::default()
+        };
+        Self::open_with_config(name, config)
+    }
+
+    fn open_with_config(name: &str, config: Config) -> Result<Self, RawSocketError> {
         let interfaces = pnet_datalink::interfaces();
         let interface = interfaces
             .into_iter()
             .find(|i| i.name == name)
             .ok_or(RawSocketError::InterfaceNotFound)?;
-        let config = Config::default();
         match pnet_datalink::channel(&interface, config) {
             Ok(Channel::Ethernet(tx, rx)) => Ok(RawSocket {
                 iface_name: name.to_string(),
                 tx,
                 rx: Some(rx),
+                #[cfg(feature = "pcap")]
+                tee: None,
             }),
             Ok(_) => Err(RawSocketError::UnsupportedChannel),
             Err(e) => Err(RawSocketError::Io(e)),
         }
     }
 
+    /// Name of the interface the channel is bound to.
+    pub fn iface_name(&self) -> &str {
+        &self.iface_name
+    }
+
     /// Send a raw ethernet frame. `packet` should contain the full ethernet frame bytes.
     pub fn send(&mut self, packet: &[u8]) -> Result<(), RawSocketError> {
         match self.tx.send_to(packet, None) {
@@ -63,14 +208,75 @@ impl RawSocket {
         }
     }
 
-    /// Receive a single packet with a timeout. Returns Ok(Some(bytes)) if a packet
-    /// was received, Ok(None) on timeout, or Err on error. This performs the blocking
+    /// Send `payload` to `dst_mac`, building the Ethernet header from
+    /// `src_mac` and `ethertype`.
+    pub fn send_to_mac(
+        &mut self,
+        payload: &[u8],
+        dst_mac: [u8; 6],
+        src_mac: [u8; 6],
+        ethertype: u16,
+    ) -> Result<(), RawSocketError> {
+        self.send(&build_ethernet_frame(dst_mac, src_mac, ethertype, payload))
+    }
+
+    /// Send `payload` to the broadcast MAC (`ff:ff:ff:ff:ff:ff`).
+    pub fn broadcast(
+        &mut self,
+        payload: &[u8],
+        src_mac: [u8; 6],
+        ethertype: u16,
+    ) -> Result<(), RawSocketError> {
+        self.send_to_mac(payload, BROADCAST_MAC, src_mac, ethertype)
+    }
+
+    /// Tee every frame returned by `recv_with_timeout` into `writer`.
+    /// Replaces (and returns) any previously installed writer.
+    #[cfg(feature = "pcap")]
+    pub fn set_pcap_tee(
+        &mut self,
+        writer: PcapWriter<Box<dyn std::io::Write + Send>>,
+    ) -> Option<PcapWriter<Box<dyn std::io::Write + Send>>> {
+        self.tee.replace(writer)
+    }
+
+    /// Stop teeing frames and hand back the capture writer, if any.
+    #[cfg(feature = "pcap")]
+    pub fn take_pcap_tee(&mut self) -> Option<PcapWriter<Box<dyn std::io::Write + Send>>> {
+        self.tee.take()
+    }
+
+    /// Receive one frame on the calling thread. Returns Ok(None) when the
+    /// read timeout given to `open_with_read_timeout` passes first; a socket
+    /// from `open` blocks until a frame arrives.
+    pub fn recv(&mut self) -> Result<Option<RawFrame>, RawSocketError> {
+        let rx = self
+            .rx
+            .as_mut()
+            .ok_or(RawSocketError::RecvError("Receiver already taken".into()))?;
+        match rx.next() {
+            Ok(packet) => {
+                let vec = packet.to_vec();
+                #[cfg(feature = "pcap")]
+                if let Some(tee) = self.tee.as_mut() {
+                    tee.write_frame(&vec)?;
+                }
+                Ok(Some(RawFrame::new(vec)))
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::TimedOut => Ok(None),
+            Err(e) => Err(RawSocketError::RecvError(format!("recv error: {:?}", e))),
+        }
+    }
+
+    /// Receive a single packet with a timeout. Returns Ok(Some(frame)) if a packet
+    /// was received (the frame keeps any VLAN tag; see `RawFrame::vlan`),
+    /// Ok(None) on timeout, or Err on error. This performs the blocking
     /// receive in a short-lived thread so callers can use a timeout without blocking
     /// the thread that owns the socket.
     pub fn recv_with_timeout(
         &mut self,
         timeout: Duration,
-    ) -> Result<Option<Vec<u8>>, RawSocketError> {
+    ) -> Result<Option<RawFrame>, RawSocketError> {
         // Move the receiver out so the spawned thread owns it, then put it back afterwards.
         let mut rx = self
             .rx
@@ -100,7 +306,13 @@ impl RawSocket {
                 // Put receiver back
                 self.rx = maybe_rx;
                 match result {
-                    Ok(vec) => Ok(Some(vec)),
+                    Ok(vec) => {
+                        #[cfg(feature = "pcap")]
+                        if let Some(tee) = self.tee.as_mut() {
+                            tee.write_frame(&vec)?;
+                        }
+                        Ok(Some(RawFrame::new(vec)))
+                    }
                     Err(s) => Err(RawSocketError::RecvError(s)),
                 }
             }
@@ -135,10 +347,85 @@ mod tests {
         assert!(matches!(res, Err(RawSocketError::InterfaceNotFound)));
     }
 
+    // dst, src, 802.1Q tag (PCP 3, VLAN 100), EtherType ARP, payload
+    const TAGGED: [u8; 20] = [
+        0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x81, 0x00, 0x60,
+        0x64, 0x08, 0x06, 0x00, 0x01,
+    ];
+    const UNTAGGED: [u8; 16] = [
+        0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x08, 0x06, 0x00,
+        0x01,
+    ];
+
+    #[test]
+    fn parse_vlan_tag_reads_vid() {
+        assert_eq!(parse_vlan_tag(&TAGGED), Some(100));
+        assert_eq!(parse_vlan_tag(&UNTAGGED), None);
+        // truncated tag is not a VLAN frame
+        assert_eq!(parse_vlan_tag(&TAGGED[..15]), None);
+    }
+
+    #[test]
+    fn strip_vlan_tag_removes_header() {
+        assert_eq!(strip_vlan_tag(&TAGGED), UNTAGGED.to_vec());
+        assert_eq!(strip_vlan_tag(&UNTAGGED), UNTAGGED.to_vec());
+    }
+
+    #[test]
+    fn builds_ethernet_header() {
+        let src = [0x00, 0x11, 0x22, 0x33, 0x44, 0x55];
+        let frame = build_ethernet_frame(BROADCAST_MAC, src, 0x0806, &[0x00, 0x01]);
+        assert_eq!(frame, UNTAGGED.to_vec());
+        assert_eq!(&frame[12..14], &[0x08, 0x06]);
+
+        let dst = [0x02, 0, 0, 0, 0, 0x01];
+        let ipv4 = build_ethernet_frame(dst, src, 0x0800, &[]);
+        assert_eq!(ipv4.len(), ETHERNET_HEADER_LEN);
+        assert_eq!(&ipv4[..6], &dst);
+        assert_eq!(&ipv4[6..12], &src);
+        assert_eq!(u16::from_be_bytes([ipv4[12], ipv4[13]]), 0x0800);
+    }
+
+    #[test]
+    fn raw_frame_detects_vlan() {
+        let f = RawFrame::new(TAGGED.to_vec());
+        assert_eq!(f.vlan, Some(100));
+        assert_eq!(f.untagged(), UNTAGGED.to_vec());
+        assert_eq!(RawFrame::new(UNTAGGED.to_vec()).vlan, None);
+    }
+
+    #[cfg(feature = "pcap")]
+    #[test]
+    fn pcap_writer_roundtrips_frames() {
+        let frame = [
+            0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x08, 0x06,
+            0x00, 0x01,
+        ];
+        let mut w = PcapWriter::new(Vec::new()).expect("pcap header");
+        w.write_frame_at(&frame, Duration::from_secs(1_700_000_000))
+            .expect("write frame");
+        w.write_frame(&frame[..14]).expect("write frame");
+        let bytes = w.into_inner();
+
+        let mut rdr = pcap_file::pcap::PcapReader::new(&bytes[..]).expect("read header");
+        assert_eq!(
+            rdr.header().datalink,
+            pcap_file::DataLink::ETHERNET,
+            "captures must be Ethernet link type"
+        );
+        let first = rdr.next_packet().expect("first").expect("valid");
+        assert_eq!(first.data.as_ref(), &frame[..]);
+        assert_eq!(first.timestamp, Duration::from_secs(1_700_000_000));
+        let second = rdr.next_packet().expect("second").expect("valid");
+        assert_eq!(second.data.len(), 14);
+        assert!(rdr.next_packet().is_none());
+    }
+
     // Note: We avoid opening a real datalink channel in tests since that requires
     // elevated privileges on most systems. recv_with_timeout is exercised indirectly
     // in integration tests when running on allowed environments.
     #[test]
+    #[allow(clippy::assertions_on_constants)]
     fn recv_timeout_returns_none_on_no_packet() {
         // We can't create a real RawSocket without privileges; this test is a smoke test placeholder.
         // The behavior is implicitly validated in environments that allow datalink channels.
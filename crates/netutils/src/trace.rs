@@ -0,0 +1,59 @@
+//! Internal shims over `tracing` so instrumentation compiles away entirely
+//! when the `tracing` feature is off.
+
+/// Emit a debug-level event (no-op without the `tracing` feature).
+#[cfg(feature = "tracing")]
+macro_rules! trace_debug {
+    ($($arg:tt)*) => { tracing::debug!($($arg)*) };
+}
+#[cfg(not(feature = "tracing"))]
+macro_rules! trace_debug {
+    ($($arg:tt)*) => {{}};
+}
+
+/// Emit a trace-level event (no-op without the `tracing` feature).
+#[cfg(feature = "tracing")]
+macro_rules! trace_trace {
+    ($($arg:tt)*) => { tracing::trace!($($arg)*) };
+}
+#[cfg(not(feature = "tracing"))]
+macro_rules! trace_trace {
+    ($($arg:tt)*) => {{}};
+}
+
+/// Enter a debug-level span for the rest of the scope; bind the result
+/// (`let _span = trace_span!(...)`) to keep it open.
+#[cfg(feature = "tracing")]
+macro_rules! trace_span {
+    ($($arg:tt)*) => { tracing::debug_span!($($arg)*).entered() };
+}
+#[cfg(not(feature = "tracing"))]
+macro_rules! trace_span {
+    ($($arg:tt)*) => {
+        ()
+    };
+}
+
+pub(crate) use {trace_debug, trace_span, trace_trace};
+
+/// `tokio::spawn`, attaching the caller's span to the task when tracing.
+pub(crate) fn spawn_in_span<F>(fut: F) -> tokio::task::JoinHandle<F::Output>
+where
+    F: std::future::Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    #[cfg(feature = "tracing")]
+    let fut = tracing::Instrument::in_current_span(fut);
+    tokio::spawn(fut)
+}
+
+/// `JoinSet::spawn`, attaching the caller's span to the task when tracing.
+pub(crate) fn spawn_in_set<T, F>(set: &mut tokio::task::JoinSet<T>, fut: F)
+where
+    F: std::future::Future<Output = T> + Send + 'static,
+    T: Send + 'static,
+{
+    #[cfg(feature = "tracing")]
+    let fut = tracing::Instrument::in_current_span(fut);
+    set.spawn(fut);
+}
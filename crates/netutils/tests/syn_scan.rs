@@ -0,0 +1,36 @@
+//! Real-network SYN scan. Needs root / CAP_NET_RAW and a default route, so it
+//! is ignored by default:
+//!
+//! ```bash
+//! sudo -E cargo test --features syn-scan --test syn_scan -- --ignored
+//! ```
+#![cfg(feature = "syn-scan")]
+
+use netutils::iface;
+use netutils::portscan::{syn_scan, PortState, SynScanError};
+use std::time::Duration;
+
+#[test]
+#[ignore = "needs raw-socket privileges and a reachable default gateway"]
+fn syn_scan_of_default_gateway_answers_every_port() {
+    let iface = iface::get_default_interface().expect("default interface");
+    let gateway = iface::get_default_gateway_ipv4().expect("default gateway");
+    let ports = vec![22, 53, 80, 443];
+    match syn_scan(
+        gateway,
+        ports.clone(),
+        &iface.name,
+        Duration::from_secs(2),
+        100,
+    ) {
+        Ok(results) => {
+            assert_eq!(results.iter().map(|r| r.port).collect::<Vec<_>>(), ports);
+            for r in &results {
+                assert_eq!(r.open, r.state == PortState::Open);
+                assert_eq!(r.rtt_ms.is_some(), r.open);
+            }
+        }
+        Err(SynScanError::PermissionDenied) => panic!("run as root or with CAP_NET_RAW"),
+        Err(e) => panic!("syn scan failed: {}", e),
+    }
+}